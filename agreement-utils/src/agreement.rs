@@ -1,3 +1,4 @@
+use ya_client_model::market::proposal::State;
 use ya_client_model::market::Agreement;
 use ya_client_model::NodeId;
 
@@ -17,6 +18,17 @@ use std::path::PathBuf;
 pub const PROPERTY_TAG: &str = "@tag";
 const DEFAULT_FORMAT: &str = "json";
 
+/// Fields, which must be present in Agreement to split it into Proposals.
+const REQUIRED_FIELDS: &[&str] = &[
+    "/timestamp",
+    "/demand/demandId",
+    "/demand/requestorId",
+    "/demand/constraints",
+    "/offer/offerId",
+    "/offer/providerId",
+    "/offer/constraints",
+];
+
 // TODO: Consider different structure:
 //  - 2 fields for parsed properties (demand, offer) as ProposalView
 //  - other fields for agreement remain typed.
@@ -97,6 +109,52 @@ impl AgreementView {
     pub fn creation_timestamp(&self) -> Result<DateTime<Utc>, Error> {
         self.pointer_typed("/timestamp")
     }
+
+    /// Checks if all fields required by `as_proposal_views` are present.
+    /// Returns single error listing all missing fields.
+    pub fn validate_golem_schema(&self) -> Result<(), Error> {
+        let missing = REQUIRED_FIELDS
+            .iter()
+            .filter(|pointer| matches!(self.pointer(pointer), None | Some(Value::Null)))
+            .map(|pointer| pointer.to_string())
+            .collect::<Vec<_>>();
+        match missing.is_empty() {
+            true => Ok(()),
+            false => Err(Error::MissingFields(missing)),
+        }
+    }
+
+    /// Splits Agreement into Demand and Offer Proposals, both in `Accepted` state.
+    /// Missing properties are represented as `Null`.
+    pub fn as_proposal_views(&self) -> Result<(DemandView, OfferView), Error> {
+        self.validate_golem_schema()?;
+
+        let properties = |pointer: &str| self.pointer(pointer).cloned().unwrap_or(Value::Null);
+        let timestamp = self.creation_timestamp()?;
+
+        let demand = ProposalView {
+            content: OfferTemplate {
+                properties: properties("/demand/properties"),
+                constraints: self.pointer_typed("/demand/constraints")?,
+            },
+            id: self.pointer_typed("/demand/demandId")?,
+            issuer: self.requestor_id()?,
+            state: State::Accepted,
+            timestamp,
+        };
+
+        let offer = ProposalView {
+            content: OfferTemplate {
+                properties: properties("/offer/properties"),
+                constraints: self.pointer_typed("/offer/constraints")?,
+            },
+            id: self.pointer_typed("/offer/offerId")?,
+            issuer: self.provider_id()?,
+            state: State::Accepted,
+            timestamp,
+        };
+        Ok((demand, offer))
+    }
 }
 
 impl TryFrom<Value> for AgreementView {
@@ -164,8 +222,14 @@ pub enum Error {
     InvalidValue(String),
     #[error("Key '{0}' doesn't exist")]
     NoKey(String),
+    #[error("Missing required fields: {}", .0.join(", "))]
+    MissingFields(Vec<String>),
     #[error("Key '{0}' has invalid type. Error: {1}")]
     UnexpectedType(String, serde_json::Error),
+    #[error("Invalid constraints: {0}")]
+    InvalidConstraints(String),
+    #[error("Can't merge property '{0}': conflicting values {1} and {2}")]
+    MergeConflict(String, Value, Value),
 }
 
 pub trait TypedPointer {
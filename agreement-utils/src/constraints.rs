@@ -7,7 +7,62 @@ pub struct Constraints {
     pub operator: ClauseOperator,
 }
 
+/// Constraints can be built by chaining calls, for example:
+/// `Constraints::new().greater_than("golem.inf.mem.gib", 0.5).equals("golem.node.debug.subnet", "net-1")`
+/// renders to expression understood by `ParsedConstraints`.
 impl Constraints {
+    /// Empty constraints matching everything.
+    pub fn new() -> Constraints {
+        Constraints {
+            constraints: vec![],
+            operator: ClauseOperator::And,
+        }
+    }
+    pub fn equals(self, key: impl AsRef<str>, value: impl Into<serde_json::Value>) -> Constraints {
+        self.with(ConstraintKey::from(key).equal_to(ConstraintKey::new(value)))
+    }
+    pub fn not_equals(
+        self,
+        key: impl AsRef<str>,
+        value: impl Into<serde_json::Value>,
+    ) -> Constraints {
+        self.with(ConstraintKey::from(key).not_equal_to(ConstraintKey::new(value)))
+    }
+    pub fn greater_than(
+        self,
+        key: impl AsRef<str>,
+        value: impl Into<serde_json::Value>,
+    ) -> Constraints {
+        self.with(ConstraintKey::from(key).greater_than(ConstraintKey::new(value)))
+    }
+    pub fn less_than(
+        self,
+        key: impl AsRef<str>,
+        value: impl Into<serde_json::Value>,
+    ) -> Constraints {
+        self.with(ConstraintKey::from(key).less_than(ConstraintKey::new(value)))
+    }
+    pub fn greater_or_equal(
+        self,
+        key: impl AsRef<str>,
+        value: impl Into<serde_json::Value>,
+    ) -> Constraints {
+        self.with(ConstraintKey::from(key).greater_or_equal(ConstraintKey::new(value)))
+    }
+    pub fn less_or_equal(
+        self,
+        key: impl AsRef<str>,
+        value: impl Into<serde_json::Value>,
+    ) -> Constraints {
+        self.with(ConstraintKey::from(key).less_or_equal(ConstraintKey::new(value)))
+    }
+    /// Property must be present, regardless of its value.
+    pub fn exists(self, key: impl AsRef<str>) -> Constraints {
+        self.with(ConstraintKey::from(key).into())
+    }
+    fn with(self, expr: ConstraintExpr) -> Constraints {
+        self.and(Constraints::new_single(expr))
+    }
     pub fn new_clause<T: Into<ConstraintExpr>>(op: ClauseOperator, v: Vec<T>) -> Constraints {
         Constraints {
             constraints: v.into_iter().map(|x| x.into()).collect(),
@@ -27,10 +82,19 @@ impl Constraints {
         self.joined_with(c, ClauseOperator::And)
     }
     fn joined_with(self, c: Constraints, operator: ClauseOperator) -> Constraints {
-        if c.operator == operator && self.operator == operator {
+        // Empty constraints would render to empty clause.
+        if self.constraints.is_empty() {
+            return c;
+        } else if c.constraints.is_empty() {
+            return self;
+        }
+
+        // Operator of single expression doesn't matter, so it can be flattened.
+        let flattens = |c: &Constraints| c.operator == operator || c.constraints.len() == 1;
+        if flattens(&self) && flattens(&c) {
             Constraints {
                 constraints: [&self.constraints[..], &c.constraints[..]].concat(),
-                operator: self.operator,
+                operator,
             }
         } else {
             Constraints::new_clause(operator, vec![self, c])
@@ -69,6 +133,12 @@ impl Constraints {
     }
 }
 
+impl Default for Constraints {
+    fn default() -> Self {
+        Constraints::new()
+    }
+}
+
 impl fmt::Display for Constraints {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self.constraints.len() {
@@ -113,12 +183,14 @@ impl fmt::Display for ClauseOperator {
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum ConstraintOperator {
     Equal,
     NotEqual,
     LessThan,
     GreaterThan,
+    LessOrEqual,
+    GreaterOrEqual,
 }
 
 impl fmt::Display for ConstraintOperator {
@@ -131,6 +203,8 @@ impl fmt::Display for ConstraintOperator {
                 ConstraintOperator::NotEqual => "<>",
                 ConstraintOperator::LessThan => "<",
                 ConstraintOperator::GreaterThan => ">",
+                ConstraintOperator::LessOrEqual => "<=",
+                ConstraintOperator::GreaterOrEqual => ">=",
             }
         )
     }
@@ -217,6 +291,12 @@ impl ConstraintKey {
     pub fn less_than(self, value: ConstraintValue) -> ConstraintExpr {
         self.with_operator_value(ConstraintOperator::LessThan, value)
     }
+    pub fn greater_or_equal(self, value: ConstraintValue) -> ConstraintExpr {
+        self.with_operator_value(ConstraintOperator::GreaterOrEqual, value)
+    }
+    pub fn less_or_equal(self, value: ConstraintValue) -> ConstraintExpr {
+        self.with_operator_value(ConstraintOperator::LessOrEqual, value)
+    }
     pub fn equal_to(self, value: ConstraintValue) -> ConstraintExpr {
         self.with_operator_value(ConstraintOperator::Equal, value)
     }
@@ -236,6 +316,10 @@ macro_rules! constraints [
     ($key:tt < $value:expr , $($r:tt)*) => {{ Constraints::new_single(ConstraintKey::new($key).less_than(ConstraintKey::new($value))).and(constraints!( $($r)* )) }};
     ($key:tt > $value:expr $(,)*) => {{ Constraints::new_single(ConstraintKey::new($key).greater_than(ConstraintKey::new($value))) }};
     ($key:tt > $value:expr , $($r:tt)*) => {{ Constraints::new_single(ConstraintKey::new($key).greater_than(ConstraintKey::new($value))).and(constraints!( $($r)* )) }};
+    ($key:tt <= $value:expr $(,)*) => {{ Constraints::new_single(ConstraintKey::new($key).less_or_equal(ConstraintKey::new($value))) }};
+    ($key:tt <= $value:expr , $($r:tt)*) => {{ Constraints::new_single(ConstraintKey::new($key).less_or_equal(ConstraintKey::new($value))).and(constraints!( $($r)* )) }};
+    ($key:tt >= $value:expr $(,)*) => {{ Constraints::new_single(ConstraintKey::new($key).greater_or_equal(ConstraintKey::new($value))) }};
+    ($key:tt >= $value:expr , $($r:tt)*) => {{ Constraints::new_single(ConstraintKey::new($key).greater_or_equal(ConstraintKey::new($value))).and(constraints!( $($r)* )) }};
     ($key:tt $(,)*) => {{ Constraints::new_single(ConstraintKey::new($key)) }};
     ($key:tt , $($r:tt)*) => {{ Constraints::new_single(ConstraintKey::new($key)).and(constraints!( $($r)* )) }};
     ($t:expr $(,)*) => { $t };
@@ -243,3 +327,92 @@ macro_rules! constraints [
         $t.and(constraints!( $($r)* ))
     };
 ];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ParsedConstraints;
+    use serde_json::json;
+
+    fn evaluate(constraints: &Constraints, properties: serde_json::Value) -> bool {
+        crate::matches(&constraints.to_string(), &properties).unwrap()
+    }
+
+    #[test]
+    fn test_builder_renders_parsable_constraints() {
+        let constraints = Constraints::new()
+            .greater_than("golem.inf.mem.gib", 0.5)
+            .equals("golem.node.debug.subnet", "net-1");
+
+        assert_eq!(
+            ParsedConstraints::parse(&constraints.to_string()).unwrap(),
+            ParsedConstraints::And(vec![
+                ParsedConstraints::Clause {
+                    key: "golem.inf.mem.gib".to_string(),
+                    condition: Some((ConstraintOperator::GreaterThan, "0.5".to_string())),
+                },
+                ParsedConstraints::Clause {
+                    key: "golem.node.debug.subnet".to_string(),
+                    condition: Some((ConstraintOperator::Equal, "net-1".to_string())),
+                },
+            ])
+        );
+
+        assert!(evaluate(
+            &constraints,
+            json!({"golem.inf.mem.gib": 1.0, "golem.node.debug.subnet": "net-1"})
+        ));
+        assert!(!evaluate(
+            &constraints,
+            json!({"golem.inf.mem.gib": 0.5, "golem.node.debug.subnet": "net-1"})
+        ));
+        assert!(!evaluate(
+            &constraints,
+            json!({"golem.inf.mem.gib": 1.0, "golem.node.debug.subnet": "net-2"})
+        ));
+    }
+
+    #[test]
+    fn test_builder_alternatives() {
+        let constraints = Constraints::new()
+            .equals("golem.com.payment.chosen-platform", "erc20-polygon-glm")
+            .or(Constraints::new().exists("golem.com.payment.platform.erc20-polygon-glm.address"))
+            .and(Constraints::new().less_or_equal("golem.inf.cpu.threads", 4));
+
+        assert!(evaluate(
+            &constraints,
+            json!({
+                "golem.com.payment.chosen-platform": "erc20-polygon-glm",
+                "golem.inf.cpu.threads": 4,
+            })
+        ));
+        assert!(evaluate(
+            &constraints,
+            json!({
+                "golem.com.payment.platform.erc20-polygon-glm.address": "0x01",
+                "golem.inf.cpu.threads": 2,
+            })
+        ));
+        assert!(!evaluate(
+            &constraints,
+            json!({
+                "golem.com.payment.platform.erc20-polygon-glm.address": "0x01",
+                "golem.inf.cpu.threads": 8,
+            })
+        ));
+        assert!(!evaluate(&constraints, json!({"golem.inf.cpu.threads": 2})));
+    }
+
+    #[test]
+    fn test_empty_builder_matches_everything() {
+        let constraints = Constraints::new();
+        assert_eq!(constraints.to_string(), "");
+        assert!(evaluate(&constraints, json!({})));
+        assert_eq!(
+            Constraints::new()
+                .or(Constraints::new().exists("golem.inf.mem.gib"))
+                .to_string(),
+            "(golem.inf.mem.gib)"
+        );
+    }
+}
@@ -1,7 +1,12 @@
 pub mod agreement;
 mod constraints;
+mod matching;
 mod proposal;
+mod schema;
 mod template;
 
 pub use agreement::{AgreementView, DemandView, Error, OfferTemplate, OfferView, ProposalView};
 pub use constraints::*;
+pub use matching::{matches, unmatched_clauses, ParsedConstraints};
+pub use schema::{PricingModel, Resources};
+pub use template::PropertyChange;
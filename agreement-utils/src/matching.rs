@@ -0,0 +1,437 @@
+use serde_json::{Map, Value};
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::agreement::flatten;
+use crate::{ConstraintOperator, Error};
+
+/// Constraints parsed from golem LDAP-like syntax, for example:
+/// `(&(golem.inf.mem.gib>0.5)(golem.node.debug.subnet=net-1))`.
+/// Can be evaluated against properties of other party's Offer/Demand.
+///
+/// Supported operators: `=`, `<>`, `<`, `>`, `<=`, `>=`. Value in equality
+/// clauses can contain wildcard `*`, which matches any sequence of characters.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParsedConstraints {
+    And(Vec<ParsedConstraints>),
+    Or(Vec<ParsedConstraints>),
+    Not(Box<ParsedConstraints>),
+    /// Single clause. Clause without condition only checks if property exists.
+    Clause {
+        key: String,
+        condition: Option<(ConstraintOperator, String)>,
+    },
+}
+
+impl ParsedConstraints {
+    pub fn parse(constraints: &str) -> Result<ParsedConstraints, Error> {
+        let mut parser = Parser {
+            input: constraints,
+            position: 0,
+        };
+
+        parser.skip_whitespace();
+        if parser.peek().is_none() {
+            // Empty constraints match everything.
+            return Ok(ParsedConstraints::And(vec![]));
+        }
+
+        let parsed = parser.parse_expression()?;
+
+        parser.skip_whitespace();
+        match parser.peek() {
+            None => Ok(parsed),
+            Some(c) => Err(parser.error(format!("unexpected '{}' after expression", c))),
+        }
+    }
+
+    /// Evaluates constraints against flattened properties.
+    pub fn evaluate(&self, properties: &Map<String, Value>) -> bool {
+        match self {
+            ParsedConstraints::And(list) => list.iter().all(|c| c.evaluate(properties)),
+            ParsedConstraints::Or(list) => list.iter().any(|c| c.evaluate(properties)),
+            ParsedConstraints::Not(inner) => !inner.evaluate(properties),
+            ParsedConstraints::Clause { key, condition } => match properties.get(key) {
+                None => false,
+                Some(property) => match condition {
+                    None => true,
+                    Some((operator, value)) => compare(property, *operator, value),
+                },
+            },
+        }
+    }
+
+    /// Returns clauses responsible for constraints evaluating to false.
+    /// Empty list means, that constraints are matching.
+    pub fn unmatched(&self, properties: &Map<String, Value>) -> Vec<&ParsedConstraints> {
+        if self.evaluate(properties) {
+            return vec![];
+        }
+
+        match self {
+            ParsedConstraints::And(list) | ParsedConstraints::Or(list) => {
+                list.iter().flat_map(|c| c.unmatched(properties)).collect()
+            }
+            // Negation failed, because inner expression matched, so we can't
+            // point to any more specific clause.
+            ParsedConstraints::Not(_) | ParsedConstraints::Clause { .. } => vec![self],
+        }
+    }
+}
+
+/// Checks if properties satisfy constraints expression.
+/// Properties can be either in flat (`golem.inf.mem.gib`) or nested format.
+pub fn matches(constraints: &str, properties: &Value) -> Result<bool, Error> {
+    Ok(ParsedConstraints::parse(constraints)?.evaluate(&flatten(properties.clone())))
+}
+
+/// Returns textual representation of all clauses, that didn't match properties.
+pub fn unmatched_clauses(constraints: &str, properties: &Value) -> Result<Vec<String>, Error> {
+    let properties = flatten(properties.clone());
+    Ok(ParsedConstraints::parse(constraints)?
+        .unmatched(&properties)
+        .into_iter()
+        .map(|clause| clause.to_string())
+        .collect())
+}
+
+fn compare(property: &Value, operator: ConstraintOperator, value: &str) -> bool {
+    if value.contains('*') {
+        return match operator {
+            ConstraintOperator::Equal => wildcard_match(property, value),
+            ConstraintOperator::NotEqual => !wildcard_match(property, value),
+            _ => false,
+        };
+    }
+
+    match property {
+        // Array property matches if any of its elements matches.
+        Value::Array(items) => items.iter().any(|item| compare(item, operator, value)),
+        Value::Bool(flag) => match value.parse::<bool>() {
+            Ok(expected) => holds(operator, Some(flag.cmp(&expected))),
+            Err(_) => false,
+        },
+        Value::Number(number) => match (number.as_f64(), value.parse::<f64>()) {
+            (Some(property), Ok(value)) => holds(operator, property.partial_cmp(&value)),
+            _ => false,
+        },
+        Value::String(text) => match operator {
+            ConstraintOperator::Equal | ConstraintOperator::NotEqual => {
+                holds(operator, Some(text.as_str().cmp(value)))
+            }
+            _ => match (text.parse::<f64>(), value.parse::<f64>()) {
+                (Ok(property), Ok(value)) => holds(operator, property.partial_cmp(&value)),
+                _ => holds(operator, Some(text.as_str().cmp(value))),
+            },
+        },
+        _ => false,
+    }
+}
+
+fn wildcard_match(property: &Value, pattern: &str) -> bool {
+    let text = match property {
+        Value::Array(items) => return items.iter().any(|item| wildcard_match(item, pattern)),
+        Value::String(text) => text.clone(),
+        Value::Bool(_) | Value::Number(_) => property.to_string(),
+        _ => return false,
+    };
+
+    let parts = pattern.split('*').collect::<Vec<_>>();
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !text.starts_with(first) || text.len() < first.len() + last.len() {
+        return false;
+    }
+
+    // Each part between wildcards must appear in order, after previous one.
+    let mut rest = &text[first.len()..text.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(idx) => rest = &rest[idx + part.len()..],
+            None => return false,
+        }
+    }
+    text.ends_with(last)
+}
+
+fn holds(operator: ConstraintOperator, ordering: Option<Ordering>) -> bool {
+    let ordering = match ordering {
+        Some(ordering) => ordering,
+        None => return false,
+    };
+
+    match operator {
+        ConstraintOperator::Equal => ordering == Ordering::Equal,
+        ConstraintOperator::NotEqual => ordering != Ordering::Equal,
+        ConstraintOperator::LessThan => ordering == Ordering::Less,
+        ConstraintOperator::GreaterThan => ordering == Ordering::Greater,
+        ConstraintOperator::LessOrEqual => ordering != Ordering::Greater,
+        ConstraintOperator::GreaterOrEqual => ordering != Ordering::Less,
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<char> {
+        self.input[self.position..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.input[self.position..];
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), Error> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.position += c.len_utf8();
+                Ok(())
+            }
+            Some(c) => Err(self.error(format!("expected '{}', found '{}'", expected, c))),
+            None => Err(self.error(format!("expected '{}', found end of input", expected))),
+        }
+    }
+
+    fn error(&self, message: impl fmt::Display) -> Error {
+        Error::InvalidConstraints(format!(
+            "{} at position {} in '{}'",
+            message, self.position, self.input
+        ))
+    }
+
+    fn parse_expression(&mut self) -> Result<ParsedConstraints, Error> {
+        self.expect('(')?;
+        self.skip_whitespace();
+
+        let expression = match self.peek() {
+            Some('&') => {
+                self.position += 1;
+                ParsedConstraints::And(self.parse_list()?)
+            }
+            Some('|') => {
+                self.position += 1;
+                ParsedConstraints::Or(self.parse_list()?)
+            }
+            Some('!') => {
+                self.position += 1;
+                ParsedConstraints::Not(Box::new(self.parse_expression()?))
+            }
+            Some(')') => ParsedConstraints::And(vec![]),
+            Some(_) => self.parse_clause()?,
+            None => return Err(self.error("unexpected end of input")),
+        };
+
+        self.expect(')')?;
+        Ok(expression)
+    }
+
+    fn parse_list(&mut self) -> Result<Vec<ParsedConstraints>, Error> {
+        let mut list = vec![];
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('(') => list.push(self.parse_expression()?),
+                _ => return Ok(list),
+            }
+        }
+    }
+
+    fn parse_clause(&mut self) -> Result<ParsedConstraints, Error> {
+        let input = self.input;
+        let rest = &input[self.position..];
+        let end = match rest.find(['(', ')']) {
+            Some(end) if rest[end..].starts_with(')') => end,
+            Some(_) => return Err(self.error("unexpected '(' inside clause")),
+            None => return Err(self.error("unclosed clause")),
+        };
+
+        let clause = rest[..end].trim();
+        let parsed = match clause.find(['<', '>', '=']) {
+            None => ParsedConstraints::Clause {
+                key: clause.to_string(),
+                condition: None,
+            },
+            Some(idx) => {
+                let (key, condition) = clause.split_at(idx);
+                let (operator, value) = parse_condition(condition)
+                    .ok_or_else(|| self.error(format!("invalid clause '{}'", clause)))?;
+                ParsedConstraints::Clause {
+                    key: key.trim().to_string(),
+                    condition: Some((operator, value.trim().to_string())),
+                }
+            }
+        };
+
+        if let ParsedConstraints::Clause { key, .. } = &parsed {
+            if key.is_empty() {
+                return Err(self.error(format!("missing property name in clause '{}'", clause)));
+            }
+        }
+
+        self.position += end;
+        Ok(parsed)
+    }
+}
+
+fn parse_condition(condition: &str) -> Option<(ConstraintOperator, &str)> {
+    if let Some(value) = condition.strip_prefix("<>") {
+        Some((ConstraintOperator::NotEqual, value))
+    } else if let Some(value) = condition.strip_prefix("<=") {
+        Some((ConstraintOperator::LessOrEqual, value))
+    } else if let Some(value) = condition.strip_prefix(">=") {
+        Some((ConstraintOperator::GreaterOrEqual, value))
+    } else if let Some(value) = condition.strip_prefix('=') {
+        Some((ConstraintOperator::Equal, value))
+    } else if let Some(value) = condition.strip_prefix('<') {
+        Some((ConstraintOperator::LessThan, value))
+    } else if let Some(value) = condition.strip_prefix('>') {
+        Some((ConstraintOperator::GreaterThan, value))
+    } else {
+        None
+    }
+}
+
+impl fmt::Display for ParsedConstraints {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParsedConstraints::And(list) => {
+                write!(f, "(&")?;
+                for c in list {
+                    write!(f, "{}", c)?;
+                }
+                write!(f, ")")
+            }
+            ParsedConstraints::Or(list) => {
+                write!(f, "(|")?;
+                for c in list {
+                    write!(f, "{}", c)?;
+                }
+                write!(f, ")")
+            }
+            ParsedConstraints::Not(inner) => write!(f, "(!{})", inner),
+            ParsedConstraints::Clause {
+                key,
+                condition: None,
+            } => write!(f, "({})", key),
+            ParsedConstraints::Clause {
+                key,
+                condition: Some((operator, value)),
+            } => write!(f, "({}{}{})", key, operator, value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn properties() -> Value {
+        json!({
+            "golem.inf.mem.gib": 8.0,
+            "golem.inf.cpu.threads": 4,
+            "golem.node.debug.subnet": "net-1",
+            "golem.runtime.name": "wasmtime",
+            "golem.com.pricing.model": "linear",
+            "golem.srv.caps.multi-activity": true,
+            "golem.activity.caps.transfer.protocol": ["http", "https", "gftp"],
+        })
+    }
+
+    fn check(constraints: &str) -> bool {
+        matches(constraints, &properties()).unwrap()
+    }
+
+    #[test]
+    fn test_matches_equal() {
+        assert!(check("(golem.node.debug.subnet=net-1)"));
+        assert!(!check("(golem.node.debug.subnet=net-2)"));
+        assert!(check("(golem.inf.cpu.threads=4)"));
+        assert!(check("(golem.srv.caps.multi-activity=true)"));
+        assert!(check("(golem.activity.caps.transfer.protocol=gftp)"));
+    }
+
+    #[test]
+    fn test_matches_not_equal() {
+        assert!(check("(golem.node.debug.subnet<>net-2)"));
+        assert!(!check("(golem.node.debug.subnet<>net-1)"));
+    }
+
+    #[test]
+    fn test_matches_less_and_greater() {
+        assert!(check("(golem.inf.mem.gib>4)"));
+        assert!(!check("(golem.inf.mem.gib>8)"));
+        assert!(check("(golem.inf.mem.gib<16)"));
+        assert!(!check("(golem.inf.mem.gib<8)"));
+    }
+
+    #[test]
+    fn test_matches_less_or_equal_and_greater_or_equal() {
+        assert!(check("(golem.inf.mem.gib>=8)"));
+        assert!(!check("(golem.inf.mem.gib>=8.5)"));
+        assert!(check("(golem.inf.mem.gib<=8)"));
+        assert!(!check("(golem.inf.mem.gib<=7.5)"));
+    }
+
+    #[test]
+    fn test_matches_wildcard() {
+        assert!(check("(golem.runtime.name=wasm*)"));
+        assert!(check("(golem.runtime.name=*time)"));
+        assert!(check("(golem.runtime.name=w*m*e)"));
+        assert!(check("(golem.runtime.name=*)"));
+        assert!(!check("(golem.runtime.name=vm*)"));
+        assert!(check("(golem.runtime.name<>vm*)"));
+        assert!(check("(golem.activity.caps.transfer.protocol=gf*)"));
+        assert!(!check("(golem.missing.property=*)"));
+    }
+
+    #[test]
+    fn test_matches_property_presence() {
+        assert!(check("(golem.runtime.name)"));
+        assert!(!check("(golem.missing.property)"));
+    }
+
+    #[test]
+    fn test_matches_nested_groups() {
+        assert!(check(
+            "(&(golem.inf.mem.gib>4)(|(golem.runtime.name=vm)(golem.runtime.name=wasmtime)))"
+        ));
+        assert!(!check(
+            "(&(golem.inf.mem.gib>4)(|(golem.runtime.name=vm)(golem.runtime.name=docker)))"
+        ));
+        assert!(check("(!(golem.node.debug.subnet=net-2))"));
+        assert!(!check("(!(golem.node.debug.subnet=net-1))"));
+        assert!(check(
+            "(&(!(golem.com.pricing.model=fixed))(|(golem.inf.cpu.threads>=8)(golem.inf.mem.gib>=8)))"
+        ));
+        assert!(check(""));
+        assert!(check("()"));
+    }
+
+    #[test]
+    fn test_unmatched_clauses() {
+        let unmatched = unmatched_clauses(
+            "(&(golem.inf.mem.gib>=16)(golem.runtime.name=wasmtime)(!(golem.inf.cpu.threads=4)))",
+            &properties(),
+        )
+        .unwrap();
+        assert_eq!(
+            unmatched,
+            vec![
+                "(golem.inf.mem.gib>=16)".to_string(),
+                "(!(golem.inf.cpu.threads=4))".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_constraints() {
+        assert!(matches("(golem.inf.mem.gib>4", &properties()).is_err());
+        assert!(matches("(&(golem.inf.mem.gib>4)))", &properties()).is_err());
+        assert!(matches("(=4)", &properties()).is_err());
+    }
+}
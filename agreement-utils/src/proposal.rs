@@ -6,13 +6,15 @@ use crate::agreement::{expand, flatten, try_from_path, TypedPointer};
 use crate::template::property_to_pointer_paths;
 use crate::{Error, OfferTemplate};
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, TimeZone, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::path::PathBuf;
 
+pub const EXPIRATION_POINTER: &str = "/golem/srv/comp/expiration";
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct ProposalView {
     pub content: OfferTemplate,
@@ -50,6 +52,66 @@ impl ProposalView {
         }
     }
 
+    /// Time until which Requestor wants to compute, read from `/golem/srv/comp/expiration`
+    /// timestamp in milliseconds. Fails if timestamp is out of `DateTime` range.
+    pub fn expiration(&self) -> Result<DateTime<Utc>, Error> {
+        let timestamp: i64 = self.pointer_typed(EXPIRATION_POINTER)?;
+        match Utc.timestamp_millis_opt(timestamp) {
+            chrono::LocalResult::Single(expiration) => Ok(expiration),
+            _ => Err(Error::InvalidValue(format!(
+                "Cannot make DateTime from timestamp {}",
+                timestamp
+            ))),
+        }
+    }
+
+    /// Time left to expiration. Negative if Proposal already expired.
+    pub fn deadline_in(&self) -> Result<Duration, Error> {
+        Ok(self.expiration()? - Utc::now())
+    }
+
+    /// Pointers to properties, that were added, removed or changed in `other` Proposal.
+    pub fn changed_pointers(&self, other: &ProposalView) -> Vec<String> {
+        self.content
+            .diff(&other.content)
+            .into_iter()
+            .map(|change| change.pointer().to_string())
+            .collect()
+    }
+
+    /// Sets property under json pointer, for example `/golem/com/pricing/model`.
+    /// Missing intermediate objects are created. Fails if pointer goes
+    /// through a value, which is neither object nor array.
+    pub fn set_property(&mut self, pointer: &str, value: Value) -> Result<(), Error> {
+        let path: Vec<&str> = pointer.split('/').collect();
+        if path.len() < 2 || !path[0].is_empty() {
+            return Err(Error::InvalidValue(pointer.to_string()));
+        }
+
+        let mut current = &mut self.content.properties;
+        for name in &path[1..] {
+            current = match current {
+                Value::Object(object) => object.entry(name.to_string()).or_insert(Value::Null),
+                Value::Array(array) => name
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(move |idx| array.get_mut(idx))
+                    .ok_or_else(|| Error::NoKey(pointer.to_string()))?,
+                value @ Value::Null => {
+                    *value = Value::Object(Default::default());
+                    value
+                        .as_object_mut()
+                        .unwrap()
+                        .entry(name.to_string())
+                        .or_insert(Value::Null)
+                }
+                _ => return Err(Error::InvalidValue(pointer.to_string())),
+            };
+        }
+        *current = value;
+        Ok(())
+    }
+
     pub fn remove_property(&mut self, pointer: &str) -> Result<(), Error> {
         let path: Vec<&str> = pointer.split('/').collect();
 
@@ -174,3 +236,112 @@ impl TryFrom<&Proposal> for ProposalView {
         Self::try_from(expand(serde_json::to_value(proposal)?))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn proposal() -> ProposalView {
+        ProposalView {
+            content: OfferTemplate {
+                properties: json!({
+                    "golem": {
+                        "com": {
+                            "pricing": {"model": {"linear": {"coeffs": [0.1, 0.2, 1.0]}}},
+                            "scheme": "payu",
+                        }
+                    }
+                }),
+                constraints: String::new(),
+            },
+            id: "proposal-0".to_string(),
+            issuer: Default::default(),
+            state: State::Draft,
+            timestamp: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_set_property() {
+        let mut proposal = proposal();
+        proposal
+            .set_property("/golem/com/scheme", json!("payu-v2"))
+            .unwrap();
+        proposal
+            .set_property("/golem/com/pricing/model/linear/coeffs/1", json!(0.5))
+            .unwrap();
+        proposal
+            .set_property("/golem/inf/mem/gib", json!(4.0))
+            .unwrap();
+
+        assert_eq!(
+            proposal.pointer("/golem/com/scheme"),
+            Some(&json!("payu-v2"))
+        );
+        assert_eq!(
+            proposal.pointer("/golem/com/pricing/model/linear/coeffs"),
+            Some(&json!([0.1, 0.5, 1.0]))
+        );
+        assert_eq!(proposal.pointer("/golem/inf/mem/gib"), Some(&json!(4.0)));
+    }
+
+    #[test]
+    fn test_set_property_invalid_path() {
+        let original = proposal();
+        let mut proposal = original.clone();
+        assert!(proposal
+            .set_property("/golem/com/scheme/name", json!("payu"))
+            .is_err());
+        assert!(proposal
+            .set_property("/golem/com/pricing/model/linear/coeffs/5", json!(0.5))
+            .is_err());
+        assert!(proposal.set_property("golem", json!({})).is_err());
+        assert_eq!(proposal, original);
+    }
+
+    fn with_expiration(timestamp: i64) -> ProposalView {
+        let mut proposal = proposal();
+        proposal
+            .set_property(EXPIRATION_POINTER, json!(timestamp))
+            .unwrap();
+        proposal
+    }
+
+    #[test]
+    fn test_expiration() {
+        let expiration = Utc::now() + Duration::minutes(10);
+        let proposal = with_expiration(expiration.timestamp_millis());
+
+        assert_eq!(
+            proposal.expiration().unwrap().timestamp_millis(),
+            expiration.timestamp_millis()
+        );
+        let deadline = proposal.deadline_in().unwrap();
+        assert!(deadline <= Duration::minutes(10));
+        assert!(deadline > Duration::minutes(9));
+    }
+
+    #[test]
+    fn test_expiration_negative_timestamp() {
+        let proposal = with_expiration(-1000);
+
+        assert_eq!(
+            proposal.expiration().unwrap(),
+            Utc.timestamp_millis_opt(-1000).unwrap()
+        );
+        assert!(proposal.deadline_in().unwrap() < Duration::zero());
+    }
+
+    #[test]
+    fn test_expiration_out_of_range() {
+        let out_of_range = with_expiration(i64::MAX);
+        assert!(matches!(
+            out_of_range.expiration(),
+            Err(Error::InvalidValue(_))
+        ));
+        assert!(out_of_range.deadline_in().is_err());
+
+        assert!(matches!(proposal().expiration(), Err(Error::NoKey(_))));
+    }
+}
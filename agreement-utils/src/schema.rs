@@ -0,0 +1,174 @@
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+use ya_client_model::NodeId;
+
+use crate::{Error, ProposalView};
+
+pub const PRICING_MODEL_PROPERTY: &str = "golem.com.pricing.model";
+pub const LINEAR_COEFFS_PROPERTY: &str = "golem.com.pricing.model.linear.coeffs";
+pub const USAGE_VECTOR_PROPERTY: &str = "golem.com.usage.vector";
+pub const DEBIT_NOTE_ACCEPT_TIMEOUT_POINTER: &str =
+    "/golem/com/payment/debit-notes/accept-timeout?";
+pub const CPU_THREADS_POINTER: &str = "/golem/inf/cpu/threads";
+pub const MEM_GIB_POINTER: &str = "/golem/inf/mem/gib";
+pub const STORAGE_GIB_POINTER: &str = "/golem/inf/storage/gib";
+
+/// Pricing model declared by Provider in Offer.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum PricingModel {
+    /// Price is a sum of usage counters multiplied by coefficients. The last
+    /// coefficient is a constant price, not bound to any usage counter.
+    Linear {
+        coeffs: Vec<f64>,
+        usage_vector: Vec<String>,
+    },
+}
+
+/// Hardware resources described in Proposal. Resources not specified are `None`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Resources {
+    pub cpu_threads: Option<u32>,
+    pub mem_gib: Option<f64>,
+    pub storage_gib: Option<f64>,
+}
+
+/// Typed accessors for Golem properties. `OfferView` and `DemandView` are
+/// the same type, so accessors for both sides are available on each of them,
+/// but they make sense only for the side documented in their description.
+impl ProposalView {
+    /// Offer side. Id of Provider, who issued the Offer.
+    pub fn provider_id(&self) -> NodeId {
+        self.issuer
+    }
+
+    /// Demand side. Id of Requestor, who issued the Demand.
+    pub fn requestor_id(&self) -> NodeId {
+        self.issuer
+    }
+
+    /// Offer side. Fails if pricing model is missing or not supported.
+    pub fn pricing_model(&self) -> Result<PricingModel, Error> {
+        let model: String = self.get_property(PRICING_MODEL_PROPERTY)?;
+        match model.as_str() {
+            "linear" => Ok(PricingModel::Linear {
+                coeffs: self.get_property(LINEAR_COEFFS_PROPERTY)?,
+                usage_vector: self.get_property(USAGE_VECTOR_PROPERTY)?,
+            }),
+            _ => Err(Error::InvalidValue(format!(
+                "Unsupported pricing model: {}",
+                model
+            ))),
+        }
+    }
+
+    /// Demand side. Fails only if resource is specified with invalid type.
+    pub fn requested_resources(&self) -> Result<Resources, Error> {
+        Ok(Resources {
+            cpu_threads: optional(self.pointer_typed(CPU_THREADS_POINTER))?,
+            mem_gib: optional(self.pointer_typed(MEM_GIB_POINTER))?,
+            storage_gib: optional(self.pointer_typed(STORAGE_GIB_POINTER))?,
+        })
+    }
+
+    /// Demand side. Time for accepting Debit Notes demanded by Requestor.
+    /// None if Requestor doesn't support mid-agreement payments.
+    pub fn debit_note_accept_timeout(&self) -> Result<Option<Duration>, Error> {
+        Ok(optional(self.pointer_typed(DEBIT_NOTE_ACCEPT_TIMEOUT_POINTER))?.map(Duration::seconds))
+    }
+}
+
+/// Treats missing property as `None`, but keeps other errors.
+fn optional<T>(result: Result<T, Error>) -> Result<Option<T>, Error> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(Error::NoKey(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use serde_json::json;
+    use std::convert::TryFrom;
+    use std::str::FromStr;
+    use ya_client_model::market::proposal::State;
+    use ya_client_model::market::Proposal;
+
+    fn proposal(issuer: &str, properties: serde_json::Value) -> ProposalView {
+        ProposalView::try_from(&Proposal {
+            properties,
+            constraints: "()".to_string(),
+            proposal_id: "proposal-0".to_string(),
+            issuer_id: NodeId::from_str(issuer).unwrap(),
+            state: State::Initial,
+            timestamp: Utc::now(),
+            prev_proposal_id: None,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_offer_accessors() {
+        let provider = "0x33796f397a554a6c33675976683031774f637a37";
+        let offer = proposal(
+            provider,
+            json!({
+                "golem.com.pricing.model": "linear",
+                "golem.com.pricing.model.linear.coeffs": [0.1, 0.2, 1.0],
+                "golem.com.usage.vector": ["golem.usage.duration_sec", "golem.usage.cpu_sec"],
+                "golem.inf.mem.gib": 0.5,
+            }),
+        );
+
+        assert_eq!(offer.provider_id(), NodeId::from_str(provider).unwrap());
+        assert_eq!(
+            offer.pricing_model().unwrap(),
+            PricingModel::Linear {
+                coeffs: vec![0.1, 0.2, 1.0],
+                usage_vector: vec![
+                    "golem.usage.duration_sec".to_string(),
+                    "golem.usage.cpu_sec".to_string()
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn test_demand_accessors() {
+        let requestor = "0x4c684d736d3157416a6e494145776833584b4339";
+        let demand = proposal(
+            requestor,
+            json!({
+                "golem.com.payment.debit-notes.accept-timeout?": 240,
+                "golem.inf.cpu.threads": 4,
+                "golem.inf.mem.gib": 8.0,
+            }),
+        );
+
+        assert_eq!(demand.requestor_id(), NodeId::from_str(requestor).unwrap());
+        assert_eq!(
+            demand.debit_note_accept_timeout().unwrap(),
+            Some(Duration::seconds(240))
+        );
+        assert_eq!(
+            demand.requested_resources().unwrap(),
+            Resources {
+                cpu_threads: Some(4),
+                mem_gib: Some(8.0),
+                storage_gib: None,
+            }
+        );
+        assert!(demand.pricing_model().is_err());
+    }
+
+    #[test]
+    fn test_invalid_property_type() {
+        let demand = proposal(
+            "0x4c684d736d3157416a6e494145776833584b4339",
+            json!({ "golem.inf.mem.gib": "eight" }),
+        );
+        assert!(demand.requested_resources().is_err());
+    }
+}
@@ -3,7 +3,7 @@ use serde_json::{Map, Value};
 use std::collections::HashMap;
 use std::fmt::Formatter;
 
-use crate::agreement::{flatten, flatten_value, PROPERTY_TAG};
+use crate::agreement::{expand, flatten, flatten_value, PROPERTY_TAG};
 use crate::Error;
 
 /// TODO: Could we use Constraints instead of String?? This would require parsing string.
@@ -14,6 +14,35 @@ pub struct OfferTemplate {
     pub constraints: String,
 }
 
+/// Single property difference between two `OfferTemplates`.
+/// Properties are identified by json pointers, for example `/golem/inf/mem/gib`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum PropertyChange {
+    Added {
+        pointer: String,
+        value: Value,
+    },
+    Removed {
+        pointer: String,
+        value: Value,
+    },
+    Changed {
+        pointer: String,
+        old: Value,
+        new: Value,
+    },
+}
+
+impl PropertyChange {
+    pub fn pointer(&self) -> &str {
+        match self {
+            PropertyChange::Added { pointer, .. } => pointer,
+            PropertyChange::Removed { pointer, .. } => pointer,
+            PropertyChange::Changed { pointer, .. } => pointer,
+        }
+    }
+}
+
 impl Default for OfferTemplate {
     fn default() -> Self {
         OfferTemplate {
@@ -37,6 +66,63 @@ impl OfferTemplate {
         self
     }
 
+    /// Deep-merges properties of both templates and joins constraints with `&` operator.
+    /// Unlike `patch`, fails if the same property has different values in both templates.
+    /// Properties of resulting template are in nested format.
+    pub fn merge(mut self, other: OfferTemplate) -> Result<OfferTemplate, Error> {
+        let mut properties = flatten(self.properties);
+        for (key, value) in flatten(other.properties) {
+            match properties.get(&key) {
+                Some(current) if current != &value => {
+                    return Err(Error::MergeConflict(key, current.clone(), value))
+                }
+                _ => properties.insert(key, value),
+            };
+        }
+
+        self.properties = expand(Value::Object(properties));
+        if !other.constraints.is_empty() {
+            self.add_constraints(other.constraints);
+        }
+        Ok(self)
+    }
+
+    /// Lists properties that were added, removed or changed in `other` template
+    /// comparing to this one. Changes are sorted by pointer.
+    pub fn diff(&self, other: &OfferTemplate) -> Vec<PropertyChange> {
+        let ours = flatten(self.properties.clone());
+        let theirs = flatten(other.properties.clone());
+        let pointer = |key: &str| property_to_pointer_paths(key).path;
+
+        let mut changes = vec![];
+        for (key, old) in &ours {
+            match theirs.get(key) {
+                None => changes.push(PropertyChange::Removed {
+                    pointer: pointer(key),
+                    value: old.clone(),
+                }),
+                Some(new) if new != old => changes.push(PropertyChange::Changed {
+                    pointer: pointer(key),
+                    old: old.clone(),
+                    new: new.clone(),
+                }),
+                _ => (),
+            }
+        }
+
+        for (key, value) in &theirs {
+            if !ours.contains_key(key) {
+                changes.push(PropertyChange::Added {
+                    pointer: pointer(key),
+                    value: value.clone(),
+                })
+            }
+        }
+
+        changes.sort_by(|a, b| a.pointer().cmp(b.pointer()));
+        changes
+    }
+
     pub fn property(&self, property: &str) -> Option<&Value> {
         self.properties.as_object().unwrap().get(property)
     }
@@ -46,6 +132,21 @@ impl OfferTemplate {
         properties.insert(key.to_string(), value);
     }
 
+    /// Removes property set with `set_property`. Returns previous value.
+    pub fn remove_property(&mut self, key: &str) -> Option<Value> {
+        let properties = self.properties.as_object_mut().unwrap();
+        properties.remove(key)
+    }
+
+    /// All numeric values in flattened properties. Used to explain `Score`,
+    /// where each component keeps its values under its own namespace.
+    pub fn breakdown(&self) -> HashMap<String, f64> {
+        flatten(self.properties.clone())
+            .into_iter()
+            .filter_map(|(key, value)| value.as_f64().map(|value| (key, value)))
+            .collect()
+    }
+
     pub fn add_constraints(&mut self, constraints: String) {
         if self.constraints.is_empty() {
             self.constraints = constraints;
@@ -142,3 +243,87 @@ impl std::fmt::Debug for OfferTemplate {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_merge_templates() {
+        let first = OfferTemplate {
+            properties: json!({"golem": {"inf": {"mem.gib": 8.0}, "node.id.name": "dany"}}),
+            constraints: "(golem.srv.comp.expiration>0)".to_string(),
+        };
+        let second = OfferTemplate {
+            properties: json!({"golem.inf.storage.gib": 10.0, "golem.node.id.name": "dany"}),
+            constraints: "(golem.node.debug.subnet=net-1)".to_string(),
+        };
+
+        let merged = first.merge(second).unwrap();
+        assert_eq!(merged.pointer("/golem/inf/mem/gib"), Some(&json!(8.0)));
+        assert_eq!(merged.pointer("/golem/inf/storage/gib"), Some(&json!(10.0)));
+        assert_eq!(merged.pointer("/golem/node/id/name"), Some(&json!("dany")));
+        assert_eq!(
+            merged.constraints,
+            "(& (golem.srv.comp.expiration>0) (golem.node.debug.subnet=net-1))"
+        );
+    }
+
+    #[test]
+    fn test_merge_conflict() {
+        let first = OfferTemplate::new(json!({"golem.inf.mem.gib": 8.0}));
+        let second = OfferTemplate::new(json!({"golem": {"inf": {"mem": {"gib": 4.0}}}}));
+
+        match first.merge(second) {
+            Err(Error::MergeConflict(key, ours, theirs)) => {
+                assert_eq!(key, "golem.inf.mem.gib");
+                assert_eq!(ours, json!(8.0));
+                assert_eq!(theirs, json!(4.0));
+            }
+            result => panic!("Expected merge conflict, got: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_diff_nested_templates() {
+        let before = OfferTemplate {
+            properties: json!({
+                "golem": {
+                    "inf": {"mem.gib": 8.0, "storage.gib": 10.0},
+                    "com": {"pricing": {"model": "linear"}}
+                }
+            }),
+            constraints: String::new(),
+        };
+        let after = OfferTemplate {
+            properties: json!({
+                "golem": {
+                    "inf": {"mem.gib": 4.0},
+                    "com": {"pricing": {"model": "linear"}, "scheme": "payu"}
+                }
+            }),
+            constraints: String::new(),
+        };
+
+        assert_eq!(
+            before.diff(&after),
+            vec![
+                PropertyChange::Added {
+                    pointer: "/golem/com/scheme".to_string(),
+                    value: json!("payu"),
+                },
+                PropertyChange::Changed {
+                    pointer: "/golem/inf/mem/gib".to_string(),
+                    old: json!(8.0),
+                    new: json!(4.0),
+                },
+                PropertyChange::Removed {
+                    pointer: "/golem/inf/storage/gib".to_string(),
+                    value: json!(10.0),
+                },
+            ]
+        );
+        assert!(before.diff(&before).is_empty());
+    }
+}
@@ -1,7 +1,8 @@
 use std::convert::TryFrom;
 use std::fs;
 use tempdir::TempDir;
-use ya_agreement_utils::AgreementView;
+use ya_agreement_utils::{AgreementView, Error};
+use ya_client_model::market::proposal::State;
 
 #[test]
 fn test_parsing() -> anyhow::Result<()> {
@@ -14,3 +15,91 @@ fn test_parsing() -> anyhow::Result<()> {
     let _view = AgreementView::try_from(&file)?;
     Ok(())
 }
+
+fn agreement_with_timestamp() -> AgreementView {
+    let mut json: serde_json::Value =
+        serde_json::from_str(include_str!("agreement-9ce65424.json")).unwrap();
+    // Agreement asset was saved before timestamp field was introduced.
+    json["timestamp"] = serde_json::json!("2020-07-10T08:00:00Z");
+    AgreementView::try_from(json).unwrap()
+}
+
+#[test]
+fn test_as_proposal_views() {
+    let agreement = agreement_with_timestamp();
+    let (demand, offer) = agreement.as_proposal_views().unwrap();
+
+    assert_eq!(demand.id, "98513eff-defe-4e52-adf4-32c3e9cba6c8");
+    assert_eq!(demand.issuer, agreement.requestor_id().unwrap());
+    assert_eq!(demand.state, State::Accepted);
+    assert_eq!(
+        demand
+            .pointer_typed::<String>("/golem/node/debug/subnet")
+            .unwrap(),
+        "demo-1"
+    );
+    assert!(demand.content.constraints.contains("golem.runtime.name=vm"));
+
+    assert_eq!(offer.id, "4724b50d-493e-4f0c-85ab-30098f56c624");
+    assert_eq!(offer.issuer, agreement.provider_id().unwrap());
+    assert_eq!(offer.state, State::Accepted);
+    assert_eq!(
+        offer.pointer("/golem/com/pricing/model/@tag"),
+        Some(&serde_json::json!("linear"))
+    );
+    assert_eq!(offer.timestamp, demand.timestamp);
+}
+
+#[test]
+fn test_as_proposal_views_missing_properties() {
+    let mut agreement = agreement_with_timestamp();
+    agreement.remove_property("/offer/properties").unwrap();
+    agreement.remove_property("/demand/properties").unwrap();
+
+    let (demand, offer) = agreement.as_proposal_views().unwrap();
+    assert_eq!(demand.content.properties, serde_json::Value::Null);
+    assert_eq!(offer.content.properties, serde_json::Value::Null);
+    assert_eq!(offer.id, "4724b50d-493e-4f0c-85ab-30098f56c624");
+}
+
+#[test]
+fn test_as_proposal_views_missing_fields() {
+    let mut agreement = agreement_with_timestamp();
+    agreement.remove_property("/offer/offerId").unwrap();
+    assert!(agreement.as_proposal_views().is_err());
+
+    let mut json: serde_json::Value =
+        serde_json::from_str(include_str!("agreement-9ce65424.json")).unwrap();
+    json.as_object_mut().unwrap().remove("timestamp");
+    let agreement = AgreementView::try_from(json).unwrap();
+    assert!(agreement.as_proposal_views().is_err());
+}
+
+#[test]
+fn test_validate_golem_schema_reports_all_missing_fields() {
+    let mut agreement = agreement_with_timestamp();
+    agreement.validate_golem_schema().unwrap();
+
+    agreement.remove_property("/offer/providerId").unwrap();
+    agreement.remove_property("/demand/constraints").unwrap();
+
+    let error = agreement.validate_golem_schema().unwrap_err();
+    match &error {
+        Error::MissingFields(fields) => assert_eq!(
+            fields,
+            &vec![
+                "/demand/constraints".to_string(),
+                "/offer/providerId".to_string()
+            ]
+        ),
+        error => panic!("Expected MissingFields, got: {}", error),
+    }
+    assert!(error.to_string().contains("/offer/providerId"));
+    assert!(error.to_string().contains("/demand/constraints"));
+
+    // Splitting Agreement reports the same error.
+    assert!(matches!(
+        agreement.as_proposal_views(),
+        Err(Error::MissingFields(fields)) if fields.len() == 2
+    ));
+}
@@ -1,12 +1,71 @@
-use ya_negotiator_component::component::NegotiatorComponent;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use ya_agreement_utils::ProposalView;
+use ya_negotiator_component::component::{NegotiationResult, NegotiatorComponent, Score};
 
 /// Negotiator that accepts every incoming Proposal.
-pub struct AcceptAll {}
+/// Optionally sets `final-score`, which is useful for testing Proposals collection.
+pub struct AcceptAll {
+    score: Option<ScoreConfig>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Config {
+    /// Score set for every Proposal. Score is left untouched if not set,
+    /// which results in score 0.
+    #[serde(default)]
+    pub score: Option<ScoreConfig>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ScoreConfig {
+    Fixed(f64),
+    Random(RandomScore),
+}
+
+/// Score drawn uniformly from range [0.0, 1.0).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum RandomScore {
+    #[serde(rename = "random")]
+    Random,
+}
 
 impl AcceptAll {
-    pub fn new(_config: serde_yaml::Value) -> anyhow::Result<AcceptAll> {
-        Ok(AcceptAll {})
+    pub fn new(config: serde_yaml::Value) -> anyhow::Result<AcceptAll> {
+        let config: Config = match config {
+            serde_yaml::Value::Null => Config::default(),
+            config => serde_yaml::from_value(config)?,
+        };
+        Ok(AcceptAll {
+            score: config.score,
+        })
     }
 }
 
-impl NegotiatorComponent for AcceptAll {}
+impl NegotiatorComponent for AcceptAll {
+    fn negotiate_step(
+        &mut self,
+        _their: &ProposalView,
+        template: ProposalView,
+        mut score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        let value = match self.score {
+            Some(ScoreConfig::Fixed(value)) => value,
+            Some(ScoreConfig::Random(_)) => rand::thread_rng().gen::<f64>(),
+            None => {
+                return Ok(NegotiationResult::Ready {
+                    proposal: template,
+                    score,
+                })
+            }
+        };
+
+        score.set_property("final-score", serde_json::json!(value));
+        Ok(NegotiationResult::Ready {
+            proposal: template,
+            score,
+        })
+    }
+}
@@ -0,0 +1,294 @@
+use anyhow::{anyhow, bail, Result};
+use chrono::{
+    DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc, Weekday,
+};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+use ya_agreement_utils::{OfferTemplate, ProposalView};
+use ya_negotiator_component::component::{NegotiationResult, NegotiatorComponent, Score};
+use ya_negotiator_component::reason::RejectReason;
+
+/// Negotiator that accepts only computations, which will run entirely inside
+/// configured time windows, for example only at night or on weekends.
+pub struct AvailabilityWindow {
+    timezone: Tz,
+    windows: Vec<Window>,
+    duration_pointer: Option<String>,
+    /// Config advertised in Offer.
+    config: Config,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Config {
+    /// Timezone name from IANA database, for example `Europe/Warsaw`.
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+    pub windows: Vec<WindowConfig>,
+    /// Pointer to Demand property declaring expected computations duration in seconds.
+    /// If not set or Demand doesn't declare it, computations are expected to last
+    /// until Demand expiration.
+    #[serde(default)]
+    pub duration_pointer: Option<String>,
+}
+
+/// Time window in local time. Window with `end` before `start` crosses midnight.
+/// Window with `start` equal to `end` lasts whole day.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WindowConfig {
+    /// Days of week, when window starts, for example `[Sat, Sun]`. Every day if empty.
+    #[serde(default)]
+    pub days: Vec<String>,
+    /// Time in format `HH:MM`.
+    pub start: String,
+    pub end: String,
+}
+
+struct Window {
+    days: Vec<Weekday>,
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+fn default_timezone() -> String {
+    "UTC".to_string()
+}
+
+pub const OUTSIDE_AVAILABILITY_WINDOW: &str = "OUTSIDE_AVAILABILITY_WINDOW";
+
+impl AvailabilityWindow {
+    pub fn new(config: serde_yaml::Value) -> Result<AvailabilityWindow> {
+        let config: Config = serde_yaml::from_value(config)?;
+        let timezone = config
+            .timezone
+            .parse::<Tz>()
+            .map_err(|e| anyhow!("Invalid timezone '{}': {}", config.timezone, e))?;
+        let windows = config
+            .windows
+            .iter()
+            .map(Window::from_config)
+            .collect::<Result<Vec<_>>>()?;
+
+        if windows.is_empty() {
+            bail!("AvailabilityWindow requires at least one time window.");
+        }
+
+        Ok(AvailabilityWindow {
+            timezone,
+            windows,
+            duration_pointer: config.duration_pointer.clone(),
+            config,
+        })
+    }
+
+    /// Time range, during which computations will run.
+    fn computation_range(&self, demand: &ProposalView) -> Result<(DateTime<Utc>, DateTime<Utc>)> {
+        let now = Utc::now();
+        let expiration = demand.expiration()?;
+
+        let end = match self
+            .duration_pointer
+            .as_ref()
+            .and_then(|pointer| demand.pointer_typed::<i64>(pointer).ok())
+        {
+            Some(duration) => expiration.min(now + Duration::seconds(duration)),
+            None => expiration,
+        };
+        Ok((now, end))
+    }
+
+    /// Checks if range is covered by windows. Adjacent windows are treated
+    /// as a single window.
+    pub fn is_available(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> bool {
+        let mut covered_until = start;
+        // Each step moves forward by at least one window, so we won't loop forever.
+        for _ in 0..1000 {
+            match self.window_containing(covered_until) {
+                Some(window_end) if window_end >= end => return true,
+                Some(window_end) => covered_until = window_end,
+                None => return false,
+            }
+        }
+        false
+    }
+
+    /// Returns end of the longest window containing given moment.
+    fn window_containing(&self, moment: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let date = moment.with_timezone(&self.timezone).naive_local().date();
+        // Window crossing midnight could start on the previous day.
+        [date.pred_opt(), Some(date)]
+            .iter()
+            .flatten()
+            .flat_map(|date| {
+                self.windows
+                    .iter()
+                    .filter_map(move |window| window.occurrence(*date, &self.timezone))
+            })
+            .filter(|(start, end)| *start <= moment && moment < *end)
+            .map(|(_, end)| end)
+            .max()
+    }
+}
+
+impl Window {
+    fn from_config(config: &WindowConfig) -> Result<Window> {
+        let parse_time = |time: &str| {
+            NaiveTime::parse_from_str(time, "%H:%M")
+                .map_err(|e| anyhow!("Invalid time '{}': {}", time, e))
+        };
+        let days = config
+            .days
+            .iter()
+            .map(|day| {
+                day.parse::<Weekday>()
+                    .map_err(|_| anyhow!("Invalid day of week '{}'", day))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Window {
+            days,
+            start: parse_time(&config.start)?,
+            end: parse_time(&config.end)?,
+        })
+    }
+
+    /// Window occurrence starting on given local date.
+    fn occurrence(&self, date: NaiveDate, timezone: &Tz) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        if !self.days.is_empty() && !self.days.contains(&date.weekday()) {
+            return None;
+        }
+
+        let end_date = match self.end > self.start {
+            true => date,
+            false => date.succ_opt()?,
+        };
+
+        Some((
+            to_utc(date.and_time(self.start), timezone),
+            to_utc(end_date.and_time(self.end), timezone),
+        ))
+    }
+}
+
+/// Converts local time to UTC. Ambiguous times during DST transition resolve
+/// to the earlier moment and non-existent times are moved forward by the gap.
+fn to_utc(local: NaiveDateTime, timezone: &Tz) -> DateTime<Utc> {
+    let mut local = local;
+    loop {
+        if let Some(time) = timezone.from_local_datetime(&local).earliest() {
+            return time.with_timezone(&Utc);
+        }
+        local += Duration::minutes(30);
+    }
+}
+
+impl NegotiatorComponent for AvailabilityWindow {
+    fn negotiate_step(
+        &mut self,
+        demand: &ProposalView,
+        offer: ProposalView,
+        score: Score,
+    ) -> Result<NegotiationResult> {
+        let (start, end) = self.computation_range(demand)?;
+
+        let result = if self.is_available(start, end) {
+            NegotiationResult::Ready {
+                proposal: offer,
+                score,
+            }
+        } else {
+            log::info!(
+                "'AvailabilityWindow' negotiator: Reject proposal [{}] outside of availability windows.",
+                demand.id
+            );
+            NegotiationResult::Reject {
+                reason: RejectReason::new(format!(
+                    "Computations from {} to {} won't fit into availability windows",
+                    start, end
+                ))
+                .with_code(OUTSIDE_AVAILABILITY_WINDOW),
+                is_final: false,
+            }
+        };
+        Ok(result)
+    }
+
+    fn fill_template(&mut self, template: OfferTemplate) -> Result<OfferTemplate> {
+        Ok(template.patch(OfferTemplate::new(serde_json::json!({
+            "golem": {
+                "node": {
+                    "availability": {
+                        "timezone": self.config.timezone,
+                        "windows": self.config.windows,
+                    }
+                }
+            }
+        }))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn availability_window(timezone: &str, windows: serde_json::Value) -> AvailabilityWindow {
+        AvailabilityWindow::new(
+            serde_yaml::to_value(serde_json::json!({
+                "timezone": timezone,
+                "windows": windows,
+            }))
+            .unwrap(),
+        )
+        .unwrap()
+    }
+
+    fn utc(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(time)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_availability_window_crossing_midnight() {
+        let component = availability_window(
+            "Europe/Warsaw",
+            serde_json::json!([{"start": "22:00", "end": "06:00"}]),
+        );
+
+        // 23:00 - 05:00 local time.
+        assert!(component.is_available(utc("2023-01-10T22:00:00Z"), utc("2023-01-11T04:00:00Z")));
+        // Computations ending after window.
+        assert!(!component.is_available(utc("2023-01-10T22:00:00Z"), utc("2023-01-11T06:00:00Z")));
+        // Computations in the middle of the day.
+        assert!(!component.is_available(utc("2023-01-10T11:00:00Z"), utc("2023-01-10T12:00:00Z")));
+    }
+
+    #[test]
+    fn test_availability_window_weekdays() {
+        let component = availability_window(
+            "UTC",
+            serde_json::json!([
+                {"days": ["Sat"], "start": "00:00", "end": "00:00"},
+                {"days": ["Sun"], "start": "00:00", "end": "12:00"},
+            ]),
+        );
+
+        // 2023-01-14 is Saturday. Adjacent windows are joined together.
+        assert!(component.is_available(utc("2023-01-14T10:00:00Z"), utc("2023-01-15T11:00:00Z")));
+        assert!(!component.is_available(utc("2023-01-14T10:00:00Z"), utc("2023-01-15T13:00:00Z")));
+        assert!(!component.is_available(utc("2023-01-13T10:00:00Z"), utc("2023-01-13T11:00:00Z")));
+    }
+
+    #[test]
+    fn test_availability_window_dst_transition() {
+        let component = availability_window(
+            "Europe/Warsaw",
+            serde_json::json!([{"start": "22:00", "end": "06:00"}]),
+        );
+
+        // Clocks are moved forward at 2:00 on 2023-03-26, so window ends at 04:00 UTC
+        // instead of 05:00 UTC.
+        assert!(component.is_available(utc("2023-03-25T21:30:00Z"), utc("2023-03-26T03:59:00Z")));
+        assert!(!component.is_available(utc("2023-03-25T21:30:00Z"), utc("2023-03-26T04:30:00Z")));
+    }
+}
@@ -1,8 +1,9 @@
-use anyhow::{anyhow, Result};
-use chrono::{DateTime, Duration, TimeZone, Utc};
+use anyhow::Result;
+use chrono::{Duration, Utc};
 use serde::{Deserialize, Serialize};
 
 use ya_agreement_utils::ProposalView;
+use ya_client_model::market::proposal::State;
 use ya_negotiator_component::component::{NegotiationResult, NegotiatorComponent, Score};
 use ya_negotiator_component::reason::RejectReason;
 
@@ -10,6 +11,8 @@ use ya_negotiator_component::reason::RejectReason;
 pub struct LimitExpiration {
     min_expiration: Duration,
     max_expiration: Duration,
+    max_debit_note_accept_timeout: Option<Duration>,
+    min_agreement_expiration: Option<Duration>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -18,6 +21,23 @@ pub struct Config {
     pub min_expiration: std::time::Duration,
     #[serde(with = "humantime_serde")]
     pub max_expiration: std::time::Duration,
+    /// Maximal time for accepting Debit Notes, that Requestor can demand.
+    /// Not checked, if not set.
+    #[serde(
+        with = "humantime_serde",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_debit_note_accept_timeout: Option<std::time::Duration>,
+    /// Minimal time left to expiration, when Agreement is proposed. Negotiations
+    /// can take long, so time left for computations can be too short at this point.
+    /// Not checked, if not set.
+    #[serde(
+        with = "humantime_serde",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub min_agreement_expiration: Option<std::time::Duration>,
 }
 
 impl LimitExpiration {
@@ -26,23 +46,79 @@ impl LimitExpiration {
         Ok(LimitExpiration {
             min_expiration: chrono::Duration::from_std(config.min_expiration)?,
             max_expiration: chrono::Duration::from_std(config.max_expiration)?,
+            max_debit_note_accept_timeout: config
+                .max_debit_note_accept_timeout
+                .map(chrono::Duration::from_std)
+                .transpose()?,
+            min_agreement_expiration: config
+                .min_agreement_expiration
+                .map(chrono::Duration::from_std)
+                .transpose()?,
         })
     }
-}
 
-fn proposal_expiration_from(proposal: &ProposalView) -> Result<DateTime<Utc>> {
-    let expiration_key_str = "/golem/srv/comp/expiration";
-    let value = proposal
-        .pointer(expiration_key_str)
-        .ok_or_else(|| anyhow::anyhow!("Missing expiration key in Proposal"))?
-        .clone();
-    let timestamp: i64 = serde_json::from_value(value)?;
-    match Utc.timestamp_millis_opt(timestamp) {
-        chrono::LocalResult::Single(t) => Ok(t),
-        _ => Err(anyhow!("Cannot make DateTime from timestamp {timestamp}")),
+    fn check_expiration(&self, demand: &ProposalView) -> Result<Option<RejectReason>> {
+        let min_expiration = Utc::now() + self.min_expiration;
+        let max_expiration = Utc::now() + self.max_expiration;
+
+        let expiration = demand.expiration()?;
+
+        if expiration > max_expiration || expiration < min_expiration {
+            return Ok(Some(
+                RejectReason::new(format!(
+                    "Proposal expires at: {} which is less than {} or more than {} from now",
+                    expiration, self.min_expiration, self.max_expiration
+                ))
+                .with_code(EXPIRATION_OUT_OF_RANGE),
+            ));
+        }
+
+        // Agreement is the last moment to check, if we will have enough time for computations.
+        if let (State::Accepted, Some(min_agreement_expiration)) =
+            (&demand.state, self.min_agreement_expiration)
+        {
+            if demand.deadline_in()? < min_agreement_expiration {
+                return Ok(Some(
+                    RejectReason::new(format!(
+                        "Agreement expires at: {} which is less than {} from now",
+                        expiration, min_agreement_expiration
+                    ))
+                    .with_code(AGREEMENT_EXPIRATION_TOO_SHORT),
+                ));
+            }
+        }
+        Ok(None)
+    }
+
+    fn check_debit_note_timeout(&self, demand: &ProposalView) -> Result<Option<RejectReason>> {
+        let max_timeout = match self.max_debit_note_accept_timeout {
+            Some(max_timeout) => max_timeout,
+            None => return Ok(None),
+        };
+
+        // Requestor doesn't support mid-agreement payments, so there is nothing to check.
+        let timeout = match demand.debit_note_accept_timeout()? {
+            Some(timeout) => timeout,
+            None => return Ok(None),
+        };
+
+        if timeout > max_timeout {
+            return Ok(Some(
+                RejectReason::new(format!(
+                    "Debit Note accept timeout {} is greater than {}",
+                    timeout, max_timeout
+                ))
+                .with_code(DEBIT_NOTE_TIMEOUT_TOO_LONG),
+            ));
+        }
+        Ok(None)
     }
 }
 
+pub const EXPIRATION_OUT_OF_RANGE: &str = "EXPIRATION_OUT_OF_RANGE";
+pub const AGREEMENT_EXPIRATION_TOO_SHORT: &str = "AGREEMENT_EXPIRATION_TOO_SHORT";
+pub const DEBIT_NOTE_TIMEOUT_TOO_LONG: &str = "DEBIT_NOTE_TIMEOUT_TOO_LONG";
+
 impl NegotiatorComponent for LimitExpiration {
     fn negotiate_step(
         &mut self,
@@ -50,29 +126,152 @@ impl NegotiatorComponent for LimitExpiration {
         offer: ProposalView,
         score: Score,
     ) -> anyhow::Result<NegotiationResult> {
-        let min_expiration = Utc::now() + self.min_expiration;
-        let max_expiration = Utc::now() + self.max_expiration;
-
-        let expiration = proposal_expiration_from(&demand)?;
+        let rejection = match self.check_expiration(demand)? {
+            Some(reason) => Some(reason),
+            None => self.check_debit_note_timeout(demand)?,
+        };
 
-        let result = if expiration > max_expiration || expiration < min_expiration {
-            log::info!(
-                "Negotiator: Reject proposal [{}] due to expiration limits.",
-                demand.id
-            );
-            NegotiationResult::Reject {
-                reason: RejectReason::new(format!(
-                    "Proposal expires at: {} which is less than {} or more than {} from now",
-                    expiration, self.min_expiration, self.max_expiration
-                )),
-                is_final: true,
+        let result = match rejection {
+            Some(reason) => {
+                log::info!(
+                    "Negotiator: Reject proposal [{}] due to expiration limits. {}",
+                    demand.id,
+                    reason
+                );
+                NegotiationResult::Reject {
+                    reason,
+                    is_final: true,
+                }
             }
-        } else {
-            NegotiationResult::Ready {
+            None => NegotiationResult::Ready {
                 proposal: offer,
                 score,
-            }
+            },
         };
         Ok(result)
     }
+
+    fn reconfigure(&mut self, config: serde_yaml::Value) -> anyhow::Result<()> {
+        // Negotiator is stateless, so we can just replace it.
+        *self = LimitExpiration::new(config)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{demand, negotiate};
+
+    fn limit_expiration(
+        max_debit_note_accept_timeout: Option<u64>,
+        min_agreement_expiration: Option<u64>,
+    ) -> LimitExpiration {
+        LimitExpiration::new(
+            serde_yaml::to_value(Config {
+                min_expiration: std::time::Duration::from_secs(30),
+                max_expiration: std::time::Duration::from_secs(300),
+                max_debit_note_accept_timeout: max_debit_note_accept_timeout
+                    .map(std::time::Duration::from_secs),
+                min_agreement_expiration: min_agreement_expiration
+                    .map(std::time::Duration::from_secs),
+            })
+            .unwrap(),
+        )
+        .unwrap()
+    }
+
+    fn expiration_demand(
+        expires_in: i64,
+        debit_note_timeout: Option<u64>,
+        state: State,
+    ) -> ProposalView {
+        let properties = match debit_note_timeout {
+            Some(timeout) => serde_json::json!({
+                "golem.com.payment.debit-notes.accept-timeout?": timeout
+            }),
+            None => serde_json::json!({}),
+        };
+        demand("demand", expires_in, properties, state)
+    }
+
+    /// Returns code of violated limit or None, if Proposal was accepted.
+    fn violated_limit(component: &mut LimitExpiration, demand: &ProposalView) -> Option<String> {
+        match negotiate(component, demand) {
+            NegotiationResult::Reject { reason, is_final } => {
+                assert!(is_final);
+                reason.code
+            }
+            NegotiationResult::Ready { .. } => None,
+            result => panic!("Unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_limit_expiration_debit_note_timeout() {
+        let mut component = limit_expiration(Some(120), None);
+
+        let demand = expiration_demand(100, Some(60), State::Draft);
+        assert_eq!(violated_limit(&mut component, &demand), None);
+
+        let demand = expiration_demand(100, Some(240), State::Draft);
+        assert_eq!(
+            violated_limit(&mut component, &demand),
+            Some(DEBIT_NOTE_TIMEOUT_TOO_LONG.to_string())
+        );
+
+        // Requestor without mid-agreement payments.
+        let demand = expiration_demand(100, None, State::Draft);
+        assert_eq!(violated_limit(&mut component, &demand), None);
+
+        // Limit not configured.
+        let mut component = limit_expiration(None, None);
+        let demand = expiration_demand(100, Some(240), State::Draft);
+        assert_eq!(violated_limit(&mut component, &demand), None);
+    }
+
+    #[test]
+    fn test_limit_expiration_agreement_expiration() {
+        let mut component = limit_expiration(None, Some(120));
+
+        // Proposals are checked only against `min_expiration`.
+        let demand = expiration_demand(100, None, State::Draft);
+        assert_eq!(violated_limit(&mut component, &demand), None);
+
+        let demand = expiration_demand(100, None, State::Accepted);
+        assert_eq!(
+            violated_limit(&mut component, &demand),
+            Some(AGREEMENT_EXPIRATION_TOO_SHORT.to_string())
+        );
+
+        let demand = expiration_demand(200, None, State::Accepted);
+        assert_eq!(violated_limit(&mut component, &demand), None);
+    }
+
+    #[test]
+    fn test_limit_expiration_combined_limits() {
+        let mut component = limit_expiration(Some(120), Some(120));
+
+        let demand = expiration_demand(200, Some(60), State::Accepted);
+        assert_eq!(violated_limit(&mut component, &demand), None);
+
+        // Expiration limit is checked first.
+        let demand = expiration_demand(900, Some(240), State::Accepted);
+        assert_eq!(
+            violated_limit(&mut component, &demand),
+            Some(EXPIRATION_OUT_OF_RANGE.to_string())
+        );
+
+        let demand = expiration_demand(100, Some(240), State::Accepted);
+        assert_eq!(
+            violated_limit(&mut component, &demand),
+            Some(AGREEMENT_EXPIRATION_TOO_SHORT.to_string())
+        );
+
+        let demand = expiration_demand(200, Some(240), State::Accepted);
+        assert_eq!(
+            violated_limit(&mut component, &demand),
+            Some(DEBIT_NOTE_TIMEOUT_TOO_LONG.to_string())
+        );
+    }
 }
@@ -0,0 +1,406 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use ya_agreement_utils::{AgreementView, ProposalView};
+use ya_client_model::market::proposal::State;
+use ya_negotiator_component::component::{
+    is_diagnostics_query, AgreementResult, NegotiationResult, NegotiatorComponent, Score,
+};
+use ya_negotiator_component::reason::RejectReason;
+
+use crate::reservations::Reservations;
+
+/// Resource names and pointers to Demand properties requesting them.
+const RESOURCES: [(&str, &str); 3] = [
+    ("mem", "/golem/inf/mem/gib"),
+    ("storage", "/golem/inf/storage/gib"),
+    ("cpu-threads", "/golem/inf/cpu/threads"),
+];
+
+pub const INSUFFICIENT_RESOURCES: &str = "INSUFFICIENT_RESOURCES";
+
+/// Amounts of resources in the same order as in `RESOURCES`.
+#[derive(Clone, Copy, Debug, Default)]
+struct Resources([f64; 3]);
+
+/// Negotiator that rejects Demands requesting more resources, than Provider has.
+/// Resources used by running Agreements and Agreements waiting for approval are
+/// subtracted from capacity, so Provider won't overcommit.
+pub struct HardwareLimits {
+    capacity: [Option<f64>; 3],
+    /// Resources used by approved Agreements, keyed by Agreement id.
+    active: HashMap<String, Resources>,
+    /// Resources reserved by Agreements, that weren't approved yet.
+    reservations: Reservations<Resources>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Config {
+    #[serde(default)]
+    pub mem_gib: Option<f64>,
+    #[serde(default)]
+    pub storage_gib: Option<f64>,
+    #[serde(default)]
+    pub cpu_threads: Option<u32>,
+    /// Time after which reserved resources are released, if Agreement wasn't approved.
+    #[serde(with = "humantime_serde", default = "default_reservation_timeout")]
+    pub reservation_timeout: Duration,
+}
+
+fn default_reservation_timeout() -> Duration {
+    Duration::from_secs(60)
+}
+
+impl Resources {
+    fn from_proposal(proposal: &ProposalView) -> Resources {
+        let mut resources = Resources::default();
+        for (idx, (_, pointer)) in RESOURCES.iter().enumerate() {
+            resources.0[idx] = proposal.pointer_typed::<f64>(pointer).unwrap_or(0.0);
+        }
+        resources
+    }
+
+    /// Resources requested in Demand, from which Agreement was created.
+    fn from_agreement(agreement: &AgreementView) -> Resources {
+        let mut resources = Resources::default();
+        for (idx, (_, pointer)) in RESOURCES.iter().enumerate() {
+            resources.0[idx] = agreement
+                .pointer_typed::<f64>(&format!("/demand/properties{}", pointer))
+                .unwrap_or(0.0);
+        }
+        resources
+    }
+
+    fn add(mut self, other: &Resources) -> Resources {
+        for idx in 0..RESOURCES.len() {
+            self.0[idx] += other.0[idx];
+        }
+        self
+    }
+}
+
+impl HardwareLimits {
+    pub fn new(config: serde_yaml::Value) -> anyhow::Result<HardwareLimits> {
+        let config: Config = serde_yaml::from_value(config)?;
+        Ok(HardwareLimits {
+            capacity: [
+                config.mem_gib,
+                config.storage_gib,
+                config.cpu_threads.map(|threads| threads as f64),
+            ],
+            active: HashMap::new(),
+            reservations: Reservations::new(config.reservation_timeout),
+        })
+    }
+
+    /// Resources used by active Agreements and not expired reservations.
+    fn used(&self) -> Resources {
+        self.active
+            .values()
+            .chain(self.reservations.active())
+            .fold(Resources::default(), |sum, resources| sum.add(resources))
+    }
+
+    /// Reserves requested resources, unless it is `dry_run`.
+    fn evaluate(
+        &mut self,
+        demand: &ProposalView,
+        offer: ProposalView,
+        score: Score,
+        dry_run: bool,
+    ) -> NegotiationResult {
+        self.reservations.release_expired("HardwareLimits");
+
+        // Agreement can be re-evaluated, so we shouldn't count our own reservation.
+        let reserved = self.reservations.take(&demand.id);
+
+        let requested = Resources::from_proposal(demand);
+        let (exceeded, exceeds_capacity) = self.exceeded(&requested);
+        let since = reserved.map(|(_, since)| since);
+        if dry_run {
+            // Dry run can evaluate different request, than the reserved one.
+            if let Some((resources, since)) = reserved {
+                self.reservations
+                    .reserve(demand.id.clone(), resources, Some(since));
+            }
+        }
+
+        if exceeded.is_empty() {
+            // Agreement phase. Reserve resources until Agreement will be approved.
+            if demand.state == State::Accepted && !dry_run {
+                self.reservations
+                    .reserve(demand.id.clone(), requested, since);
+            }
+            NegotiationResult::Ready {
+                proposal: offer,
+                score,
+            }
+        } else {
+            log::info!(
+                "'HardwareLimits' negotiator: Reject proposal [{}]. Exceeded resources: {:?}",
+                demand.id,
+                exceeded
+            );
+            NegotiationResult::Reject {
+                reason: RejectReason::new(format!("Not enough resources: {}", exceeded.join(", ")))
+                    .with_code(INSUFFICIENT_RESOURCES)
+                    .entry("exceeded", exceeded),
+                is_final: exceeds_capacity,
+            }
+        }
+    }
+
+    /// Returns names of exceeded resources and information, if they exceed
+    /// total capacity or only currently available resources.
+    fn exceeded(&self, requested: &Resources) -> (Vec<&'static str>, bool) {
+        let used = self.used();
+        let mut exceeded = vec![];
+        let mut exceeds_capacity = false;
+
+        for (idx, (name, _)) in RESOURCES.iter().enumerate() {
+            if let Some(capacity) = self.capacity[idx] {
+                if requested.0[idx] > capacity {
+                    exceeds_capacity = true;
+                    exceeded.push(*name);
+                } else if requested.0[idx] + used.0[idx] > capacity {
+                    exceeded.push(*name);
+                }
+            }
+        }
+        (exceeded, exceeds_capacity)
+    }
+}
+
+impl NegotiatorComponent for HardwareLimits {
+    fn negotiate_step(
+        &mut self,
+        demand: &ProposalView,
+        offer: ProposalView,
+        score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        Ok(self.evaluate(demand, offer, score, false))
+    }
+
+    /// Only expired reservations are released.
+    fn dry_run_step(
+        &mut self,
+        demand: &ProposalView,
+        _history: &[ProposalView],
+        offer: ProposalView,
+        score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        Ok(self.evaluate(demand, offer, score, true))
+    }
+
+    fn on_agreement_terminated(
+        &mut self,
+        agreement_id: &str,
+        _result: &AgreementResult,
+    ) -> anyhow::Result<()> {
+        self.active.remove(agreement_id);
+        Ok(())
+    }
+
+    fn on_agreement_approved(&mut self, agreement: &AgreementView) -> anyhow::Result<()> {
+        // Reservation turns into resources used by active Agreement. Reservation
+        // could expire before approval, but Agreement uses resources anyway.
+        let resources = self.reservations.approve(agreement).unwrap_or_else(|| {
+            log::debug!(
+                "'HardwareLimits' negotiator: No reservation for Agreement [{}]. Using resources from Agreement.",
+                agreement.id
+            );
+            Resources::from_agreement(agreement)
+        });
+        self.active.insert(agreement.id.clone(), resources);
+        Ok(())
+    }
+
+    fn on_proposal_rejected(&mut self, proposal_id: &str) -> anyhow::Result<()> {
+        self.reservations.release(proposal_id);
+        Ok(())
+    }
+
+    fn control_event(
+        &mut self,
+        _component: &str,
+        params: serde_json::Value,
+    ) -> anyhow::Result<serde_json::Value> {
+        if !is_diagnostics_query(&params) {
+            return Ok(serde_json::Value::Null);
+        }
+
+        let used = self.used();
+        let used = RESOURCES
+            .iter()
+            .enumerate()
+            .map(|(idx, (name, _))| (name.to_string(), serde_json::json!(used.0[idx])))
+            .collect::<serde_json::Map<_, _>>();
+
+        Ok(serde_json::json!({
+            "active-agreements": self.active.len(),
+            "reservations": self.reservations.active().count(),
+            "used": used,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{agreement, demand, negotiate};
+
+    fn hardware_limits() -> HardwareLimits {
+        HardwareLimits::new(
+            serde_yaml::to_value(Config {
+                mem_gib: Some(8.0),
+                storage_gib: Some(100.0),
+                cpu_threads: Some(4),
+                reservation_timeout: Duration::from_secs(60),
+            })
+            .unwrap(),
+        )
+        .unwrap()
+    }
+
+    /// Returns exceeded resources or empty list, if Proposal was accepted.
+    fn exceeded_resources(component: &mut HardwareLimits, demand: &ProposalView) -> Vec<String> {
+        match negotiate(component, demand) {
+            NegotiationResult::Ready { .. } => vec![],
+            NegotiationResult::Reject { reason, .. } => {
+                serde_json::from_value(reason.extra["exceeded"].clone()).unwrap()
+            }
+            result => panic!("Unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_hardware_limits_each_resource() {
+        let mut component = hardware_limits();
+
+        let cases = vec![
+            ("golem.inf.mem.gib", 16.0, "mem"),
+            ("golem.inf.storage.gib", 200.0, "storage"),
+            ("golem.inf.cpu.threads", 8.0, "cpu-threads"),
+        ];
+
+        for (property, amount, resource) in cases {
+            let requested = demand(
+                "id",
+                50,
+                serde_json::json!({ property: amount }),
+                State::Draft,
+            );
+            assert_eq!(
+                exceeded_resources(&mut component, &requested),
+                vec![resource]
+            );
+
+            let requested = demand("id", 50, serde_json::json!({ property: 1.0 }), State::Draft);
+            assert!(exceeded_resources(&mut component, &requested).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_hardware_limits_combined() {
+        let mut component = hardware_limits();
+
+        let requested = demand(
+            "id",
+            50,
+            serde_json::json!({
+                "golem.inf.mem.gib": 16.0,
+                "golem.inf.storage.gib": 50.0,
+                "golem.inf.cpu.threads": 8,
+            }),
+            State::Draft,
+        );
+        assert_eq!(
+            exceeded_resources(&mut component, &requested),
+            vec!["mem", "cpu-threads"]
+        );
+    }
+
+    /// Resources reserved by Agreements waiting for approval can't be used by other Agreements.
+    #[test]
+    fn test_hardware_limits_prevent_overcommit() {
+        let mut component = hardware_limits();
+        let resources = serde_json::json!({"golem.inf.mem.gib": 6.0, "golem.inf.cpu.threads": 2});
+
+        let first = demand("agreement-1", 50, resources.clone(), State::Accepted);
+        let second = demand("agreement-2", 50, resources.clone(), State::Accepted);
+
+        assert!(exceeded_resources(&mut component, &first).is_empty());
+        // Only memory is exhausted.
+        assert_eq!(exceeded_resources(&mut component, &second), vec!["mem"]);
+        // Rejection isn't final, since resources can be freed later.
+        match component
+            .negotiate_step(&second, second.clone(), Score::default())
+            .unwrap()
+        {
+            NegotiationResult::Reject { is_final, .. } => assert!(!is_final),
+            result => panic!("Expected Reject, got: {:?}", result),
+        }
+
+        component.on_proposal_rejected("agreement-1").unwrap();
+        assert!(exceeded_resources(&mut component, &second).is_empty());
+    }
+
+    /// Dry run of reserved Agreement shouldn't change amount of reserved resources.
+    #[test]
+    fn test_hardware_limits_dry_run_keeps_reservation() {
+        let mut component = hardware_limits();
+
+        let first = demand(
+            "agreement-1",
+            50,
+            serde_json::json!({"golem.inf.mem.gib": 6.0}),
+            State::Accepted,
+        );
+        assert!(exceeded_resources(&mut component, &first).is_empty());
+
+        let smaller = demand(
+            "agreement-1",
+            50,
+            serde_json::json!({"golem.inf.mem.gib": 1.0}),
+            State::Accepted,
+        );
+        component
+            .dry_run_step(&smaller, &[], smaller.clone(), Score::default())
+            .unwrap();
+
+        let second = demand(
+            "agreement-2",
+            50,
+            serde_json::json!({"golem.inf.mem.gib": 4.0}),
+            State::Accepted,
+        );
+        assert_eq!(exceeded_resources(&mut component, &second), vec!["mem"]);
+
+        // Our own rejection of the first Agreement releases its resources.
+        component.on_proposal_rejected("agreement-1").unwrap();
+        assert!(exceeded_resources(&mut component, &second).is_empty());
+    }
+
+    /// Approved Agreement uses resources, even if its reservation expired or was never made.
+    #[test]
+    fn test_hardware_limits_approval_without_reservation() {
+        let mut component = hardware_limits();
+
+        let agreement = agreement("agreement-1", serde_json::json!({"golem.inf.mem.gib": 6.0}));
+        component.on_agreement_approved(&agreement).unwrap();
+
+        let second = demand(
+            "agreement-2",
+            50,
+            serde_json::json!({"golem.inf.mem.gib": 6.0}),
+            State::Accepted,
+        );
+        assert_eq!(exceeded_resources(&mut component, &second), vec!["mem"]);
+
+        component
+            .on_agreement_terminated("agreement-1", &AgreementResult::ClosedByUs)
+            .unwrap();
+        assert!(exceeded_resources(&mut component, &second).is_empty());
+    }
+}
@@ -1,32 +1,94 @@
 pub mod accept_all;
+pub mod availability;
 pub mod expiration;
+pub mod hardware;
 pub mod max_agreements;
+pub mod payment_platform;
+pub mod rate_limit;
+mod reservations;
+pub mod tap;
+pub mod template_env;
+#[cfg(test)]
+mod test_utils;
 
 pub use accept_all::AcceptAll;
+pub use availability::AvailabilityWindow;
 pub use expiration::LimitExpiration;
+pub use hardware::HardwareLimits;
 pub use max_agreements::MaxAgreements;
+pub use payment_platform::PaymentPlatform;
+pub use rate_limit::RateLimit;
+pub use tap::Tap;
+pub use template_env::TemplateEnv;
 
-use ya_negotiator_component::static_lib::register_negotiator;
+use ya_negotiator_component::static_lib::{register_negotiator, ConstructorFunction};
 use ya_negotiator_component::NegotiatorComponent;
 
+/// Library name, under which `register_negotiators` registers negotiators.
+pub const LIBRARY: &str = "golem-negotiators";
+
+/// Registers all negotiators from this crate as `golem-negotiators` static library.
 pub fn register_negotiators() {
-    register_negotiator(
-        "golem-negotiators",
+    register_negotiators_with(|name, constructor| {
+        register_negotiator(LIBRARY, name, constructor);
+    });
+}
+
+/// Passes name and constructor of each negotiator from this crate to `register`,
+/// so they can be registered under any library name.
+pub fn register_negotiators_with(mut register: impl FnMut(&str, ConstructorFunction)) {
+    register(
         "AcceptAll",
-        Box::new(|config, _| Ok(Box::new(AcceptAll::new(config)?) as Box<dyn NegotiatorComponent>)),
+        Box::new(|config, _, _| {
+            Ok(Box::new(AcceptAll::new(config)?) as Box<dyn NegotiatorComponent>)
+        }),
     );
-    register_negotiator(
-        "golem-negotiators",
+    register(
+        "AvailabilityWindow",
+        Box::new(|config, _, _| {
+            Ok(Box::new(AvailabilityWindow::new(config)?) as Box<dyn NegotiatorComponent>)
+        }),
+    );
+    register(
         "LimitExpiration",
-        Box::new(|config, _| {
+        Box::new(|config, _, _| {
             Ok(Box::new(LimitExpiration::new(config)?) as Box<dyn NegotiatorComponent>)
         }),
     );
-    register_negotiator(
-        "golem-negotiators",
+    register(
         "LimitAgreements",
-        Box::new(|config, _| {
+        Box::new(|config, _, _| {
             Ok(Box::new(MaxAgreements::new(config)?) as Box<dyn NegotiatorComponent>)
         }),
     );
+    register(
+        "RateLimit",
+        Box::new(|config, _, _| {
+            Ok(Box::new(RateLimit::new(config)?) as Box<dyn NegotiatorComponent>)
+        }),
+    );
+    register(
+        "PaymentPlatform",
+        Box::new(|config, _, _| {
+            Ok(Box::new(PaymentPlatform::new(config)?) as Box<dyn NegotiatorComponent>)
+        }),
+    );
+    register(
+        "HardwareLimits",
+        Box::new(|config, _, _| {
+            Ok(Box::new(HardwareLimits::new(config)?) as Box<dyn NegotiatorComponent>)
+        }),
+    );
+    register(
+        "TemplateEnv",
+        Box::new(|config, agent_env, _| {
+            Ok(Box::new(TemplateEnv::new(config, agent_env)?) as Box<dyn NegotiatorComponent>)
+        }),
+    );
+    register(
+        "Tap",
+        Box::new(|config, _, working_dir| {
+            Ok(Box::new(Tap::new(config, working_dir)?) as Box<dyn NegotiatorComponent>)
+        }),
+    );
 }
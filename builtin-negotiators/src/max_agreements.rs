@@ -1,22 +1,56 @@
 use anyhow::bail;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
 
 use ya_agreement_utils::{AgreementView, ProposalView};
+use ya_client_model::market::proposal::State;
 use ya_negotiator_component::component::{
-    AgreementResult, NegotiationResult, NegotiatorComponent, Score,
+    is_diagnostics_query, AgreementResult, ComponentDescription, NegotiationResult,
+    NegotiatorComponent, ReevaluationHandle, Score,
 };
 use ya_negotiator_component::reason::RejectReason;
 
+use crate::reservations::Reservations;
+
+pub const NO_CAPACITY: &str = "NO_CAPACITY";
+
+/// Maximal number of parked Proposals. Proposals rejected, when limit is reached,
+/// won't be re-evaluated.
+const MAX_PARKED: usize = 1000;
+
 /// Negotiator that can limit number of running agreements.
 pub struct MaxAgreements {
     active_agreements: HashSet<String>,
+    /// Agreements accepted by negotiator, but not approved yet. They occupy slots,
+    /// so we don't accept more Agreements than we can handle.
+    reservations: Reservations<()>,
     max_agreements: u32,
+    /// Proposals rejected due to lack of capacity with expiration of Agreement they
+    /// propose. They will be re-evaluated, when slot is freed, unless they expire first.
+    parked: HashMap<String, Option<DateTime<Utc>>>,
+    reevaluation: Option<ReevaluationHandle>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Config {
     pub max_agreements: u32,
+    /// Time after which reserved slot is released, if Agreement wasn't approved.
+    /// Prevents leaking slots in case of abandoned negotiations.
+    #[serde(with = "humantime_serde", default = "default_reservation_timeout")]
+    pub reservation_timeout: Duration,
+}
+
+/// Only active Agreements are persisted. Reservations are short-lived and
+/// can be recreated by other party.
+#[derive(Default, Serialize, Deserialize)]
+struct PersistentState {
+    active_agreements: HashSet<String>,
+}
+
+fn default_reservation_timeout() -> Duration {
+    Duration::from_secs(60)
 }
 
 impl MaxAgreements {
@@ -25,22 +59,46 @@ impl MaxAgreements {
         Ok(MaxAgreements {
             max_agreements: config.max_agreements,
             active_agreements: HashSet::new(),
+            reservations: Reservations::new(config.reservation_timeout),
+            parked: HashMap::new(),
+            reevaluation: None,
         })
     }
 
     pub fn has_free_slot(&self) -> bool {
-        self.active_agreements.len() < self.max_agreements as usize
+        self.active_agreements.len() + self.reserved_slots() < self.max_agreements as usize
     }
-}
 
-impl NegotiatorComponent for MaxAgreements {
-    fn negotiate_step(
+    fn reserved_slots(&self) -> usize {
+        self.reservations.active().count()
+    }
+
+    /// Reserves slot or parks rejected Proposal, unless it is `dry_run`.
+    fn evaluate(
         &mut self,
         demand: &ProposalView,
         offer: ProposalView,
         score: Score,
-    ) -> anyhow::Result<NegotiationResult> {
-        let result = if self.has_free_slot() {
+        dry_run: bool,
+    ) -> NegotiationResult {
+        self.reservations.release_expired("MaxAgreements");
+
+        // Agreement can be re-evaluated, so we shouldn't count our own reservation.
+        let reserved = self.reservations.take(&demand.id);
+        let has_free_slot = self.has_free_slot();
+        let since = reserved.map(|(_, since)| since);
+        if dry_run {
+            if let Some(since) = since {
+                self.reservations
+                    .reserve(demand.id.clone(), (), Some(since));
+            }
+        }
+
+        if has_free_slot {
+            // Agreement phase. Reserve slot until Agreement will be approved.
+            if demand.state == State::Accepted && !dry_run {
+                self.reservations.reserve(demand.id.clone(), (), since);
+            }
             NegotiationResult::Ready {
                 proposal: offer,
                 score,
@@ -50,15 +108,62 @@ impl NegotiatorComponent for MaxAgreements {
                 "'MaxAgreements' negotiator: Reject proposal [{}] due to limit.",
                 demand.id, // TODO: Should be just `id`, but I reuse AgreementView struct.
             );
+            if demand.state != State::Accepted && self.reevaluation.is_some() && !dry_run {
+                self.park(demand);
+            }
             NegotiationResult::Reject {
                 reason: RejectReason::new(format!(
                     "No capacity available. Reached Agreements limit: {}",
                     self.max_agreements
-                )),
+                ))
+                .with_code(NO_CAPACITY),
                 is_final: false,
             }
-        };
-        Ok(result)
+        }
+    }
+
+    fn park(&mut self, demand: &ProposalView) {
+        self.release_expired_parked();
+        if self.parked.len() >= MAX_PARKED && !self.parked.contains_key(&demand.id) {
+            log::debug!(
+                "'MaxAgreements' negotiator: Too many parked Proposals. Proposal [{}] won't be re-evaluated.",
+                demand.id
+            );
+            return;
+        }
+        self.parked
+            .insert(demand.id.clone(), demand.expiration().ok());
+    }
+
+    fn release_expired_parked(&mut self) {
+        let now = Utc::now();
+        self.parked.retain(|_, expiration| {
+            expiration
+                .map(|expiration| expiration > now)
+                .unwrap_or(true)
+        });
+    }
+}
+
+impl NegotiatorComponent for MaxAgreements {
+    fn negotiate_step(
+        &mut self,
+        demand: &ProposalView,
+        offer: ProposalView,
+        score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        Ok(self.evaluate(demand, offer, score, false))
+    }
+
+    /// Only expired reservations are released.
+    fn dry_run_step(
+        &mut self,
+        demand: &ProposalView,
+        _history: &[ProposalView],
+        offer: ProposalView,
+        score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        Ok(self.evaluate(demand, offer, score, true))
     }
 
     fn on_agreement_terminated(
@@ -68,12 +173,23 @@ impl NegotiatorComponent for MaxAgreements {
     ) -> anyhow::Result<()> {
         self.active_agreements.remove(agreement_id);
 
-        let free_slots = self.max_agreements as usize - self.active_agreements.len();
+        let free_slots =
+            (self.max_agreements as usize).saturating_sub(self.active_agreements.len());
         log::info!("Negotiator: {} free slot(s) for agreements.", free_slots);
+
+        self.release_expired_parked();
+        if let Some(reevaluation) = &self.reevaluation {
+            if self.has_free_slot() && !self.parked.is_empty() {
+                reevaluation.reevaluate(self.parked.drain().map(|(id, _)| id).collect());
+            }
+        }
         Ok(())
     }
 
     fn on_agreement_approved(&mut self, agreement: &AgreementView) -> anyhow::Result<()> {
+        // Reservation turns into active Agreement.
+        self.reservations.approve(agreement);
+
         if self.has_free_slot() {
             self.active_agreements.insert(agreement.id.clone());
             Ok(())
@@ -85,4 +201,66 @@ impl NegotiatorComponent for MaxAgreements {
             )
         }
     }
+
+    fn on_proposal_rejected(&mut self, proposal_id: &str) -> anyhow::Result<()> {
+        self.reservations.release(proposal_id);
+        Ok(())
+    }
+
+    fn set_reevaluation_handle(&mut self, handle: ReevaluationHandle) {
+        self.reevaluation = Some(handle);
+    }
+
+    /// Changes limits, but keeps active Agreements and reservations. Lowering limit
+    /// below number of active Agreements won't break them, but new Proposals will be
+    /// rejected until enough Agreements are terminated.
+    fn reconfigure(&mut self, config: serde_yaml::Value) -> anyhow::Result<()> {
+        let config: Config = serde_yaml::from_value(config)?;
+        self.max_agreements = config.max_agreements;
+        self.reservations.set_timeout(config.reservation_timeout);
+        Ok(())
+    }
+
+    fn serialize_state(&self) -> anyhow::Result<serde_json::Value> {
+        Ok(serde_json::to_value(PersistentState {
+            active_agreements: self.active_agreements.clone(),
+        })?)
+    }
+
+    fn restore_state(&mut self, state: serde_json::Value) -> anyhow::Result<()> {
+        let state: PersistentState = serde_json::from_value(state)?;
+        self.active_agreements = state.active_agreements;
+        Ok(())
+    }
+
+    fn describe(&self) -> Option<ComponentDescription> {
+        Some(ComponentDescription {
+            description: "Limits number of simultaneously running Agreements.".to_string(),
+            config_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "max_agreements": {"type": "integer", "minimum": 0},
+                    "reservation_timeout": {"type": "string", "format": "duration"},
+                },
+                "required": ["max_agreements"],
+            }),
+        })
+    }
+
+    fn control_event(
+        &mut self,
+        _component: &str,
+        params: serde_json::Value,
+    ) -> anyhow::Result<serde_json::Value> {
+        if !is_diagnostics_query(&params) {
+            return Ok(serde_json::Value::Null);
+        }
+
+        Ok(serde_json::json!({
+            "active-agreements": self.active_agreements.len(),
+            "reserved-agreements": self.reserved_slots(),
+            "parked-proposals": self.parked.len(),
+            "max-agreements": self.max_agreements,
+        }))
+    }
 }
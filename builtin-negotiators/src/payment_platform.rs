@@ -0,0 +1,235 @@
+use serde::{Deserialize, Serialize};
+
+use ya_agreement_utils::{Constraints, OfferTemplate, ProposalView};
+use ya_negotiator_component::component::{NegotiationResult, NegotiatorComponent, Score};
+use ya_negotiator_component::reason::RejectReason;
+
+/// Negotiator that accepts only Demands using allowed payment platforms.
+pub struct PaymentPlatform {
+    platforms: Vec<String>,
+    accept_missing: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Config {
+    /// Allowed payment platforms, for example `erc20-polygon-glm`.
+    pub platforms: Vec<String>,
+    /// Accept Demands, that don't specify any payment platform.
+    #[serde(default)]
+    pub accept_missing: bool,
+}
+
+pub const PAYMENT_PLATFORM_MISSING: &str = "PAYMENT_PLATFORM_MISSING";
+pub const PAYMENT_PLATFORM_NOT_SUPPORTED: &str = "PAYMENT_PLATFORM_NOT_SUPPORTED";
+
+const CHOSEN_PLATFORM_POINTER: &str = "/golem/com/payment/chosen-platform";
+const PLATFORMS_POINTER: &str = "/golem/com/payment/platform";
+const CHOSEN_PLATFORM_PROPERTY: &str = "golem.com.payment.chosen-platform";
+const PLATFORMS_PROPERTY: &str = "golem.com.payment.platform";
+
+impl PaymentPlatform {
+    pub fn new(config: serde_yaml::Value) -> anyhow::Result<PaymentPlatform> {
+        let config: Config = serde_yaml::from_value(config)?;
+        Ok(PaymentPlatform {
+            platforms: config.platforms,
+            accept_missing: config.accept_missing,
+        })
+    }
+
+    /// Platforms, that Requestor is able to use. If Requestor already chose
+    /// platform, only this one is returned.
+    fn demand_platforms(demand: &ProposalView) -> Vec<String> {
+        if let Some(platform) = demand
+            .pointer(CHOSEN_PLATFORM_POINTER)
+            .and_then(|value| value.as_str())
+        {
+            return vec![platform.to_string()];
+        }
+
+        demand
+            .pointer(PLATFORMS_POINTER)
+            .and_then(|value| value.as_object())
+            .map(|platforms| platforms.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Demands, which don't support any of accepted platforms, won't match our Offer.
+    /// Demands without platforms can't be filtered out without negation, so constraints
+    /// aren't used, if they should be accepted.
+    fn constraints(&self) -> Constraints {
+        if self.accept_missing {
+            return Constraints::new();
+        }
+
+        self.platforms
+            .iter()
+            .fold(Constraints::new(), |constraints, platform| {
+                constraints
+                    .or(Constraints::new().equals(CHOSEN_PLATFORM_PROPERTY, platform.as_str()))
+                    .or(Constraints::new()
+                        .exists(format!("{}.{}.address", PLATFORMS_PROPERTY, platform)))
+            })
+    }
+
+    fn rejection(&self, demand: &ProposalView) -> Option<RejectReason> {
+        let platforms = Self::demand_platforms(demand);
+        if platforms.is_empty() {
+            return match self.accept_missing {
+                true => None,
+                false => Some(
+                    RejectReason::new("Payment platform not specified")
+                        .with_code(PAYMENT_PLATFORM_MISSING),
+                ),
+            };
+        }
+
+        match platforms
+            .iter()
+            .any(|platform| self.platforms.contains(platform))
+        {
+            true => None,
+            false => Some(
+                RejectReason::new(format!(
+                    "Payment platforms {:?} not supported. Accepted platforms: {:?}",
+                    platforms, self.platforms
+                ))
+                .with_code(PAYMENT_PLATFORM_NOT_SUPPORTED),
+            ),
+        }
+    }
+}
+
+impl NegotiatorComponent for PaymentPlatform {
+    fn negotiate_step(
+        &mut self,
+        demand: &ProposalView,
+        offer: ProposalView,
+        score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        let result = match self.rejection(demand) {
+            Some(reason) => {
+                log::info!(
+                    "'PaymentPlatform' negotiator: Reject proposal [{}]. {}",
+                    demand.id,
+                    reason
+                );
+                NegotiationResult::Reject {
+                    reason,
+                    is_final: true,
+                }
+            }
+            None => NegotiationResult::Ready {
+                proposal: offer,
+                score,
+            },
+        };
+        Ok(result)
+    }
+
+    fn fill_template(&mut self, template: OfferTemplate) -> anyhow::Result<OfferTemplate> {
+        let mut offer = OfferTemplate::new(serde_json::json!({
+            "golem": {
+                "com": {
+                    "payment": {
+                        "accepted-platforms": self.platforms,
+                    }
+                }
+            }
+        }));
+        offer.constraints = self.constraints().to_string();
+        Ok(template.patch(offer))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{demand, negotiate};
+    use ya_client_model::market::proposal::State;
+
+    fn payment_platform_accepts(accept_missing: bool, properties: serde_json::Value) -> bool {
+        let mut component = PaymentPlatform::new(
+            serde_yaml::to_value(Config {
+                platforms: vec!["erc20-polygon-glm".to_string()],
+                accept_missing,
+            })
+            .unwrap(),
+        )
+        .unwrap();
+
+        let their = demand("demand", 50, properties, State::Draft);
+        match negotiate(&mut component, &their) {
+            NegotiationResult::Ready { .. } => true,
+            NegotiationResult::Reject { .. } => false,
+            result => panic!("Unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_payment_platform() {
+        let allowed = serde_json::json!({"golem.com.payment.chosen-platform": "erc20-polygon-glm"});
+        let disallowed =
+            serde_json::json!({"golem.com.payment.chosen-platform": "erc20-goerli-tglm"});
+
+        assert!(payment_platform_accepts(false, allowed));
+        assert!(!payment_platform_accepts(false, disallowed));
+
+        // Requestor didn't choose platform yet, but supports allowed one.
+        assert!(payment_platform_accepts(
+            false,
+            serde_json::json!({
+                "golem.com.payment.platform.erc20-goerli-tglm.address": "0x01",
+                "golem.com.payment.platform.erc20-polygon-glm.address": "0x01",
+            })
+        ));
+        assert!(!payment_platform_accepts(
+            false,
+            serde_json::json!({"golem.com.payment.platform.erc20-goerli-tglm.address": "0x01"})
+        ));
+    }
+
+    #[test]
+    fn test_payment_platform_missing() {
+        assert!(!payment_platform_accepts(false, serde_json::json!({})));
+        assert!(payment_platform_accepts(true, serde_json::json!({})));
+
+        let mut component =
+            PaymentPlatform::new(serde_yaml::from_str("platforms: [erc20-polygon-glm]").unwrap())
+                .unwrap();
+        let offer = component.fill_template(OfferTemplate::default()).unwrap();
+        assert_eq!(
+            offer.property("golem.com.payment.accepted-platforms"),
+            Some(&serde_json::json!(["erc20-polygon-glm"]))
+        );
+    }
+
+    /// Demands without accepted platforms shouldn't match Offer at all.
+    #[test]
+    fn test_payment_platform_constraints() {
+        let mut component =
+            PaymentPlatform::new(serde_yaml::from_str("platforms: [erc20-polygon-glm]").unwrap())
+                .unwrap();
+        let offer = component.fill_template(OfferTemplate::default()).unwrap();
+
+        let demand_matches = |properties: serde_json::Value| {
+            ya_agreement_utils::matches(&offer.constraints, &properties).unwrap()
+        };
+        assert!(demand_matches(
+            serde_json::json!({"golem.com.payment.chosen-platform": "erc20-polygon-glm"})
+        ));
+        assert!(demand_matches(serde_json::json!({
+            "golem.com.payment.platform.erc20-polygon-glm.address": "0x01"
+        })));
+        assert!(!demand_matches(serde_json::json!({
+            "golem.com.payment.platform.erc20-goerli-tglm.address": "0x01"
+        })));
+        assert!(!demand_matches(serde_json::json!({})));
+
+        let mut component = PaymentPlatform::new(
+            serde_yaml::from_str("{platforms: [erc20-polygon-glm], accept_missing: true}").unwrap(),
+        )
+        .unwrap();
+        let offer = component.fill_template(OfferTemplate::default()).unwrap();
+        assert_eq!(offer.constraints, "");
+    }
+}
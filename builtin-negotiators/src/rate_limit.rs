@@ -0,0 +1,225 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use ya_agreement_utils::ProposalView;
+use ya_client_model::market::proposal::State;
+use ya_client_model::NodeId;
+use ya_negotiator_component::component::{
+    is_diagnostics_query, NegotiationResult, NegotiatorComponent, Score,
+};
+use ya_negotiator_component::reason::RejectReason;
+
+pub const RATE_LIMITED: &str = "RATE_LIMITED";
+
+/// Negotiator that limits number of Proposals accepted from single Requestor
+/// in sliding time window. Prevents single node from monopolizing negotiations.
+///
+/// Only Proposals starting negotiations (in `Initial` state) are counted. Further
+/// rounds of accepted negotiations and re-evaluations of the same Proposal don't
+/// count against the limit.
+pub struct RateLimit {
+    max_per_window: usize,
+    window: Duration,
+    /// Moments, when Proposals from each node were accepted, with their ids.
+    accepted: HashMap<NodeId, VecDeque<(Instant, String)>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Config {
+    pub max_per_window: usize,
+    #[serde(with = "humantime_serde")]
+    pub window: Duration,
+}
+
+impl RateLimit {
+    pub fn new(config: serde_yaml::Value) -> anyhow::Result<RateLimit> {
+        let config: Config = serde_yaml::from_value(config)?;
+        Ok(RateLimit {
+            max_per_window: config.max_per_window,
+            window: config.window,
+            accepted: HashMap::new(),
+        })
+    }
+
+    /// Counts Proposal against the limit, unless it is `dry_run`.
+    fn evaluate(
+        &mut self,
+        their: &ProposalView,
+        template: ProposalView,
+        score: Score,
+        dry_run: bool,
+    ) -> NegotiationResult {
+        self.prune();
+
+        let counted = self
+            .accepted
+            .get(&their.issuer)
+            .map(|timestamps| timestamps.iter().any(|(_, id)| id == &their.id))
+            .unwrap_or(false);
+        if their.state != State::Initial || counted {
+            return NegotiationResult::Ready {
+                proposal: template,
+                score,
+            };
+        }
+
+        let accepted = self
+            .accepted
+            .get(&their.issuer)
+            .map(|timestamps| timestamps.len())
+            .unwrap_or(0);
+        if accepted < self.max_per_window {
+            if !dry_run {
+                self.accepted
+                    .entry(their.issuer)
+                    .or_default()
+                    .push_back((Instant::now(), their.id.clone()));
+            }
+            NegotiationResult::Ready {
+                proposal: template,
+                score,
+            }
+        } else {
+            log::info!(
+                "'RateLimit' negotiator: Reject proposal [{}] from [{}] due to rate limit.",
+                their.id,
+                their.issuer
+            );
+            NegotiationResult::Reject {
+                reason: RejectReason::new(format!(
+                    "Too many Proposals. Limit: {} per {}",
+                    self.max_per_window,
+                    humantime::format_duration(self.window)
+                ))
+                .with_code(RATE_LIMITED),
+                is_final: false,
+            }
+        }
+    }
+
+    /// Removes timestamps outside of window. Nodes without recent Proposals
+    /// are forgotten to bound memory usage.
+    fn prune(&mut self) {
+        let window = self.window;
+        self.accepted.retain(|_, timestamps| {
+            while let Some((timestamp, _)) = timestamps.front() {
+                if timestamp.elapsed() < window {
+                    break;
+                }
+                timestamps.pop_front();
+            }
+            !timestamps.is_empty()
+        });
+    }
+}
+
+impl NegotiatorComponent for RateLimit {
+    fn negotiate_step(
+        &mut self,
+        their: &ProposalView,
+        template: ProposalView,
+        score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        Ok(self.evaluate(their, template, score, false))
+    }
+
+    /// Only timestamps outside of window are forgotten.
+    fn dry_run_step(
+        &mut self,
+        their: &ProposalView,
+        _history: &[ProposalView],
+        template: ProposalView,
+        score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        Ok(self.evaluate(their, template, score, true))
+    }
+
+    fn control_event(
+        &mut self,
+        _component: &str,
+        params: serde_json::Value,
+    ) -> anyhow::Result<serde_json::Value> {
+        if !is_diagnostics_query(&params) {
+            return Ok(serde_json::Value::Null);
+        }
+
+        self.prune();
+        Ok(serde_json::json!({
+            "tracked-nodes": self.accepted.len(),
+            "max-per-window": self.max_per_window,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{demand, negotiate};
+    use ya_negotiator_component::component::diagnostics_query;
+
+    fn from_node(node_id: &str, proposal_id: &str) -> ProposalView {
+        let mut proposal = demand(proposal_id, 50, serde_json::json!({}), State::Initial);
+        proposal.issuer = node_id.parse().unwrap();
+        proposal
+    }
+
+    fn rate_limit_accepts(component: &mut RateLimit, their: &ProposalView) -> bool {
+        match negotiate(component, their) {
+            NegotiationResult::Ready { .. } => true,
+            NegotiationResult::Reject { is_final, .. } => {
+                assert!(!is_final);
+                false
+            }
+            result => panic!("Unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let mut component = RateLimit::new(
+            serde_yaml::to_value(Config {
+                max_per_window: 2,
+                window: Duration::from_millis(300),
+            })
+            .unwrap(),
+        )
+        .unwrap();
+
+        let requestor = "0x0000000000000000000000000000000000000001";
+        let other = from_node("0x0000000000000000000000000000000000000002", "other-1");
+
+        let first = from_node(requestor, "requestor-1");
+        assert!(rate_limit_accepts(&mut component, &first));
+        assert!(rate_limit_accepts(
+            &mut component,
+            &from_node(requestor, "requestor-2")
+        ));
+        assert!(!rate_limit_accepts(
+            &mut component,
+            &from_node(requestor, "requestor-3")
+        ));
+
+        // Re-evaluation of counted Proposal and further negotiation rounds aren't limited.
+        assert!(rate_limit_accepts(&mut component, &first));
+        let mut counter = from_node(requestor, "requestor-4");
+        counter.state = State::Draft;
+        assert!(rate_limit_accepts(&mut component, &counter));
+
+        // Limit is counted separately for each node.
+        assert!(rate_limit_accepts(&mut component, &other));
+
+        // Requestor can negotiate again after window elapsed.
+        std::thread::sleep(Duration::from_millis(400));
+        assert!(rate_limit_accepts(
+            &mut component,
+            &from_node(requestor, "requestor-5")
+        ));
+
+        // Other node was forgotten, since all his Proposals are outside window.
+        let state = component
+            .control_event("RateLimit", diagnostics_query())
+            .unwrap();
+        assert_eq!(state["tracked-nodes"], serde_json::json!(1));
+    }
+}
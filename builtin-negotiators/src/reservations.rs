@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use ya_agreement_utils::AgreementView;
+
+/// Pointers to ids of Proposals, from which Agreement was created.
+const PROPOSAL_ID_POINTERS: [&str; 2] = ["/demand/demandId", "/offer/offerId"];
+
+/// Slots reserved for Agreements accepted by negotiator, but not approved yet,
+/// keyed by id of other party's Proposal. Reservation is released, if Agreement
+/// isn't approved before timeout, so abandoned negotiations don't leak resources.
+pub(crate) struct Reservations<T> {
+    reservations: HashMap<String, (T, Instant)>,
+    timeout: Duration,
+}
+
+impl<T> Reservations<T> {
+    pub fn new(timeout: Duration) -> Reservations<T> {
+        Reservations {
+            reservations: HashMap::new(),
+            timeout,
+        }
+    }
+
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Values of reservations, that didn't expire yet.
+    pub fn active(&self) -> impl Iterator<Item = &T> {
+        let timeout = self.timeout;
+        self.reservations
+            .values()
+            .filter(move |(_, reserved)| reserved.elapsed() < timeout)
+            .map(|(value, _)| value)
+    }
+
+    pub fn release_expired(&mut self, negotiator: &str) {
+        let timeout = self.timeout;
+        self.reservations.retain(|id, (_, reserved)| {
+            let expired = reserved.elapsed() >= timeout;
+            if expired {
+                log::info!(
+                    "'{}' negotiator: Reservation for [{}] expired. Releasing slot.",
+                    negotiator,
+                    id
+                );
+            }
+            !expired
+        });
+    }
+
+    /// Removes reservation of Proposal, which is evaluated again, so it won't
+    /// count against itself. Returns reserved value and moment of reservation, which
+    /// should be passed to `reserve`, so re-evaluation doesn't prolong reservation.
+    pub fn take(&mut self, proposal_id: &str) -> Option<(T, Instant)> {
+        self.reservations.remove(proposal_id)
+    }
+
+    pub fn reserve(&mut self, proposal_id: String, value: T, since: Option<Instant>) {
+        self.reservations
+            .insert(proposal_id, (value, since.unwrap_or_else(Instant::now)));
+    }
+
+    pub fn release(&mut self, proposal_id: &str) {
+        self.reservations.remove(proposal_id);
+    }
+
+    /// Removes reservation turning into approved Agreement. Returns reserved value,
+    /// if reservation still existed.
+    pub fn approve(&mut self, agreement: &AgreementView) -> Option<T> {
+        PROPOSAL_ID_POINTERS
+            .iter()
+            .filter_map(|pointer| agreement.pointer_typed::<String>(pointer).ok())
+            .filter_map(|id| self.reservations.remove(&id))
+            .map(|(value, _)| value)
+            .last()
+    }
+}
@@ -0,0 +1,124 @@
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+use ya_agreement_utils::ProposalView;
+use ya_negotiator_component::component::{NegotiationResult, NegotiatorComponent, Score};
+
+/// Name of file in working dir, where `Tap` appends JSON lines.
+pub const TAP_FILE: &str = "tap.jsonl";
+
+/// Negotiator that never changes decision. Logs chosen properties of incoming
+/// Proposals and current score, so it can be inserted between other components
+/// to debug negotiation chain.
+pub struct Tap {
+    pointers: Vec<String>,
+    level: log::Level,
+    file: Option<File>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Config {
+    /// Json pointers to Proposal properties, for example `/golem/inf/mem/gib`.
+    #[serde(default)]
+    pub pointers: Vec<String>,
+    /// Log level name: `error`, `warn`, `info`, `debug` or `trace`.
+    #[serde(default = "default_level")]
+    pub level: String,
+    /// Append each record as JSON line to `TAP_FILE` in working dir.
+    #[serde(default)]
+    pub write_file: bool,
+}
+
+fn default_level() -> String {
+    "info".to_string()
+}
+
+impl Tap {
+    pub fn new(config: serde_yaml::Value, working_dir: PathBuf) -> anyhow::Result<Tap> {
+        let config: Config = serde_yaml::from_value(config)?;
+        let level = config
+            .level
+            .parse()
+            .map_err(|_| anyhow!("Invalid log level: {}", config.level))?;
+
+        let file = match config.write_file {
+            true => {
+                let path = working_dir.join(TAP_FILE);
+                Some(
+                    OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(&path)
+                        .with_context(|| format!("Can't open tap file {}", path.display()))?,
+                )
+            }
+            false => None,
+        };
+
+        Ok(Tap {
+            pointers: config.pointers,
+            level,
+            file,
+        })
+    }
+
+    fn record(&self, their: &ProposalView, score: &Score) -> Value {
+        let properties = self
+            .pointers
+            .iter()
+            .map(|pointer| {
+                let value = their.pointer(pointer).cloned().unwrap_or(Value::Null);
+                (pointer.clone(), value)
+            })
+            .collect::<Map<_, _>>();
+
+        serde_json::json!({
+            "proposal-id": their.id,
+            "issuer": their.issuer,
+            "properties": properties,
+            "score": score.properties,
+        })
+    }
+}
+
+impl NegotiatorComponent for Tap {
+    fn negotiate_step(
+        &mut self,
+        their: &ProposalView,
+        template: ProposalView,
+        score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        let record = self.record(their, &score);
+        log::log!(self.level, "'Tap' negotiator: {}", record);
+
+        if let Some(file) = &mut self.file {
+            // Debugging aid shouldn't break negotiations.
+            writeln!(file, "{}", record)
+                .map_err(|e| log::warn!("'Tap' negotiator: Failed to write record. {e}"))
+                .ok();
+        }
+
+        Ok(NegotiationResult::Ready {
+            proposal: template,
+            score,
+        })
+    }
+
+    /// Dry runs aren't recorded.
+    fn dry_run_step(
+        &mut self,
+        _their: &ProposalView,
+        _history: &[ProposalView],
+        template: ProposalView,
+        score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        Ok(NegotiationResult::Ready {
+            proposal: template,
+            score,
+        })
+    }
+}
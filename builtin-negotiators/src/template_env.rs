@@ -0,0 +1,178 @@
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+use ya_agreement_utils::{OfferTemplate, ProposalView};
+use ya_negotiator_component::component::{NegotiationResult, NegotiatorComponent, Score};
+
+/// Negotiator substituting `${env.key}` tokens in string properties of Offer
+/// template with values from Agent environment and config. Values from config
+/// take precedence over Agent environment. It doesn't take part in
+/// negotiations. Put it after components adding templated properties, since
+/// templates are filled in the order of components.
+pub struct TemplateEnv {
+    env: HashMap<String, String>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Config {
+    /// Values available for substitution, for example `node-name: dany`
+    /// can be referenced as `${env.node-name}`.
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+}
+
+const TOKEN_PREFIX: &str = "${env.";
+const TOKEN_SUFFIX: &str = "}";
+
+impl TemplateEnv {
+    pub fn new(config: serde_yaml::Value, agent_env: serde_yaml::Value) -> Result<TemplateEnv> {
+        let config: Config = match config {
+            serde_yaml::Value::Null => Config::default(),
+            config => serde_yaml::from_value(config)?,
+        };
+
+        let mut env = scalar_values(agent_env)?;
+        env.extend(config.env);
+        Ok(TemplateEnv { env })
+    }
+
+    /// Replaces all tokens in `text`. Fails on first token without value in environment.
+    pub fn substitute(&self, text: &str) -> Result<String> {
+        let mut result = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find(TOKEN_PREFIX) {
+            let after_prefix = &rest[start + TOKEN_PREFIX.len()..];
+            let end = match after_prefix.find(TOKEN_SUFFIX) {
+                Some(end) => end,
+                None => bail!("Unterminated template token in '{}'.", text),
+            };
+            let key = &after_prefix[..end];
+            let value = self
+                .env
+                .get(key)
+                .ok_or_else(|| anyhow!("Missing environment value for key '{}'.", key))?;
+
+            result.push_str(&rest[..start]);
+            result.push_str(value);
+            rest = &after_prefix[end + TOKEN_SUFFIX.len()..];
+        }
+        result.push_str(rest);
+        Ok(result)
+    }
+
+    fn substitute_value(&self, value: &mut Value) -> Result<()> {
+        match value {
+            Value::String(text) => *text = self.substitute(text)?,
+            Value::Array(values) => {
+                for value in values.iter_mut() {
+                    self.substitute_value(value)?;
+                }
+            }
+            Value::Object(map) => {
+                for value in map.values_mut() {
+                    self.substitute_value(value)?;
+                }
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+}
+
+/// Converts scalar values of Agent environment mapping to strings.
+/// Nested values can't be substituted, so they are skipped.
+fn scalar_values(agent_env: serde_yaml::Value) -> Result<HashMap<String, String>> {
+    let mapping = match agent_env {
+        serde_yaml::Value::Null => return Ok(HashMap::new()),
+        serde_yaml::Value::Mapping(mapping) => mapping,
+        _ => bail!("Agent environment should be a mapping."),
+    };
+
+    Ok(mapping
+        .into_iter()
+        .filter_map(|(key, value)| {
+            let key = key.as_str()?.to_string();
+            let value = match value {
+                serde_yaml::Value::String(value) => value,
+                serde_yaml::Value::Number(value) => value.to_string(),
+                serde_yaml::Value::Bool(value) => value.to_string(),
+                _ => return None,
+            };
+            Some((key, value))
+        })
+        .collect())
+}
+
+impl NegotiatorComponent for TemplateEnv {
+    fn negotiate_step(
+        &mut self,
+        _their: &ProposalView,
+        template: ProposalView,
+        score: Score,
+    ) -> Result<NegotiationResult> {
+        Ok(NegotiationResult::Ready {
+            proposal: template,
+            score,
+        })
+    }
+
+    fn fill_template(&mut self, mut template: OfferTemplate) -> Result<OfferTemplate> {
+        self.substitute_value(&mut template.properties)?;
+        Ok(template)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template_env() -> TemplateEnv {
+        TemplateEnv::new(
+            serde_yaml::from_str(
+                r#"
+env:
+  node-name: dany
+  subnet: net-1
+"#,
+            )
+            .unwrap(),
+            serde_yaml::Value::Null,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_template_env_substitution() {
+        let template = OfferTemplate::new(serde_json::json!({
+            "golem.node.id.name": "${env.node-name}",
+            "golem.node.debug.subnet": "subnet-${env.subnet}",
+            "golem.inf.mem.gib": 8.0,
+        }));
+
+        let template = template_env().fill_template(template).unwrap();
+        assert_eq!(
+            template.property("golem.node.id.name"),
+            Some(&serde_json::json!("dany"))
+        );
+        assert_eq!(
+            template.property("golem.node.debug.subnet"),
+            Some(&serde_json::json!("subnet-net-1"))
+        );
+        assert_eq!(
+            template.property("golem.inf.mem.gib"),
+            Some(&serde_json::json!(8.0))
+        );
+    }
+
+    #[test]
+    fn test_template_env_missing_key() {
+        let template = OfferTemplate::new(serde_json::json!({
+            "golem.node.id.name": "${env.node-id}",
+        }));
+
+        let error = template_env().fill_template(template).unwrap_err();
+        assert!(error.to_string().contains("'node-id'"), "{}", error);
+    }
+}
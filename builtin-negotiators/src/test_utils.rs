@@ -0,0 +1,80 @@
+use chrono::Utc;
+use serde_json::Value;
+use std::convert::TryFrom;
+
+use ya_agreement_utils::{AgreementView, ProposalView};
+use ya_client_model::market::agreement::State as AgreementState;
+use ya_client_model::market::proposal::State;
+use ya_client_model::market::{Agreement, Demand, Offer, Proposal};
+use ya_negotiator_component::component::{NegotiationResult, NegotiatorComponent, Score};
+
+/// Demand properties expiring in `expires_in` seconds. `properties` are added
+/// to default ones or replace them.
+fn demand_properties(expires_in: i64, properties: Value) -> Value {
+    let expiration = Utc::now() + chrono::Duration::seconds(expires_in);
+    let mut demand = serde_json::json!({
+        "golem.node.id.name": "example-node",
+        "golem.node.debug.subnet": "net-1",
+        "golem.srv.comp.task_package": "package",
+        "golem.srv.comp.expiration": expiration.timestamp_millis(),
+    });
+    for (key, value) in properties.as_object().unwrap() {
+        demand[key] = value.clone();
+    }
+    demand
+}
+
+/// Demand expiring in `expires_in` seconds. See `demand_properties`.
+pub fn demand(id: &str, expires_in: i64, properties: Value, state: State) -> ProposalView {
+    let proposal = Proposal {
+        properties: demand_properties(expires_in, properties),
+        constraints: String::new(),
+        proposal_id: id.to_string(),
+        issuer_id: Default::default(),
+        state,
+        timestamp: Utc::now(),
+        prev_proposal_id: None,
+    };
+    ProposalView::try_from(&proposal).unwrap()
+}
+
+/// Agreement with Demand and Offer having the same properties. See `demand_properties`.
+pub fn agreement(id: &str, properties: Value) -> AgreementView {
+    let properties = demand_properties(50, properties);
+    let agreement = Agreement {
+        agreement_id: id.to_string(),
+        demand: Demand {
+            properties: properties.clone(),
+            constraints: String::new(),
+            demand_id: format!("{}-demand", id),
+            requestor_id: Default::default(),
+            timestamp: Utc::now(),
+        },
+        offer: Offer {
+            properties,
+            constraints: String::new(),
+            offer_id: format!("{}-offer", id),
+            provider_id: Default::default(),
+            timestamp: Utc::now(),
+        },
+        valid_to: Utc::now() + chrono::Duration::minutes(20),
+        approved_date: None,
+        state: AgreementState::Proposal,
+        timestamp: Utc::now(),
+        app_session_id: None,
+        proposed_signature: None,
+        approved_signature: None,
+        committed_signature: None,
+    };
+    AgreementView::try_from(&agreement).unwrap()
+}
+
+/// Evaluates `their` Proposal in the first negotiation round, using it as template.
+pub fn negotiate(
+    component: &mut dyn NegotiatorComponent,
+    their: &ProposalView,
+) -> NegotiationResult {
+    component
+        .negotiate_step(their, their.clone(), Score::default())
+        .unwrap()
+}
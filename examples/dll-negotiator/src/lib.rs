@@ -1,14 +1,17 @@
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::time::Duration;
 
 use crate::ya_negotiator_component::reason::RejectReason;
 use ya_negotiator_shared_lib_interface::plugin::{
-    NegotiationResult, NegotiatorComponent, NegotiatorConstructor, ProposalView, Score,
+    CounterOffer, NegotiationResult, NegotiatorComponent, NegotiatorConstructor, ProposalView,
+    Score,
 };
 use ya_negotiator_shared_lib_interface::*;
 
 pub struct FilterNodes {
     names: Vec<String>,
+    working_dir: PathBuf,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -16,15 +19,29 @@ pub struct FilterNodesConfig {
     pub names: Vec<String>,
 }
 
+/// Part of Agent environment used by `FilterNodes`. Agent can share
+/// rejection list between all negotiators this way.
+#[derive(Default, Serialize, Deserialize)]
+pub struct FilterNodesEnv {
+    #[serde(default, rename = "rejected-nodes")]
+    pub rejected_nodes: Vec<String>,
+}
+
 impl NegotiatorConstructor<FilterNodes> for FilterNodes {
-    fn new(
+    fn create(
         _name: &str,
         config: serde_yaml::Value,
-        _working_dir: PathBuf,
+        agent_env: serde_yaml::Value,
+        working_dir: PathBuf,
     ) -> anyhow::Result<FilterNodes> {
         let config: FilterNodesConfig = serde_yaml::from_value(config)?;
+        let env: FilterNodesEnv = match agent_env {
+            serde_yaml::Value::Null => FilterNodesEnv::default(),
+            agent_env => serde_yaml::from_value(agent_env)?,
+        };
         Ok(FilterNodes {
-            names: config.names,
+            names: config.names.into_iter().chain(env.rejected_nodes).collect(),
+            working_dir,
         })
     }
 }
@@ -40,7 +57,8 @@ impl NegotiatorComponent for FilterNodes {
             Ok(node_name) => {
                 if self.names.contains(&node_name) {
                     NegotiationResult::Reject {
-                        reason: RejectReason::new("Node on rejection list."),
+                        reason: RejectReason::new("Node on rejection list.")
+                            .with_code("NODE_BLACKLISTED"),
                         is_final: true,
                     }
                 } else {
@@ -51,11 +69,60 @@ impl NegotiatorComponent for FilterNodes {
                 }
             }
             Err(_) => NegotiationResult::Reject {
-                reason: RejectReason::new("Unnamed Node"),
+                reason: RejectReason::new("Unnamed Node").with_code("NODE_UNNAMED"),
                 is_final: true,
             },
         })
     }
+
+    fn shutdown(&mut self, _timeout: Duration) -> anyhow::Result<()> {
+        // Leave trace, that negotiator was shutdown correctly.
+        std::fs::write(self.working_dir.join("shutdown"), "")?;
+        Ok(())
+    }
+}
+
+/// Counters Proposals with price higher than configured maximum by proposing
+/// maximum price instead.
+pub struct PriceCounter {
+    pointer: String,
+    max_price: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PriceCounterConfig {
+    /// Pointer to price property, for example `/golem/com/pricing/model/linear/coeffs/0`.
+    pub pointer: String,
+    pub max_price: f64,
+}
+
+impl NegotiatorConstructor<PriceCounter> for PriceCounter {
+    fn create(
+        _name: &str,
+        config: serde_yaml::Value,
+        _agent_env: serde_yaml::Value,
+        _working_dir: PathBuf,
+    ) -> anyhow::Result<PriceCounter> {
+        let config: PriceCounterConfig = serde_yaml::from_value(config)?;
+        Ok(PriceCounter {
+            pointer: config.pointer,
+            max_price: config.max_price,
+        })
+    }
+}
+
+impl NegotiatorComponent for PriceCounter {
+    fn negotiate_step(
+        &mut self,
+        their: &ProposalView,
+        template: ProposalView,
+        score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        let price: f64 = their.pointer_typed(&self.pointer)?;
+        CounterOffer::new(template, score)
+            .set(&self.pointer, price.min(self.max_price).into())
+            .build()
+    }
 }
 
-register_negotiators!(FilterNodes);
+register_negotiators!(FilterNodes, PriceCounter);
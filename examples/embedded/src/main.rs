@@ -0,0 +1,83 @@
+//! Negotiator embedded in the same process, assembled only from builtin components.
+//! Shows the whole path from config to the first negotiation decision.
+
+use chrono::{Duration, Utc};
+
+use ya_agreement_utils::OfferTemplate;
+use ya_client_model::market::proposal::State;
+use ya_client_model::market::Proposal;
+use ya_negotiators::factory::{
+    create_negotiator, CompositeNegotiatorConfig, NegotiatorsConfigBuilder,
+};
+
+fn offer_template() -> OfferTemplate {
+    OfferTemplate::new(serde_json::json!({
+        "golem.node.id.name": "embedded-provider",
+    }))
+}
+
+fn demand() -> Proposal {
+    let expiration = Utc::now() + Duration::minutes(10);
+    Proposal {
+        properties: serde_json::json!({
+            "golem.node.id.name": "embedded-requestor",
+            "golem.srv.comp.expiration": expiration.timestamp_millis(),
+        }),
+        constraints: "".to_string(),
+        proposal_id: "demand-1".to_string(),
+        issuer_id: Default::default(),
+        state: State::Initial,
+        timestamp: Utc::now(),
+        prev_proposal_id: None,
+    }
+}
+
+#[actix_rt::main]
+async fn main() -> anyhow::Result<()> {
+    // Params are the same, as would be written in yaml config file.
+    let config = NegotiatorsConfigBuilder::new()
+        .builtin(
+            "LimitExpiration",
+            serde_json::json!({
+                "min_expiration": "5min",
+                "max_expiration": "30min",
+            }),
+        )?
+        .builtin(
+            "LimitAgreements",
+            serde_json::json!({ "max_agreements": 1 }),
+        )?
+        .composite(CompositeNegotiatorConfig::default_provider())
+        .build();
+
+    let working_dir = std::env::temp_dir().join("embedded-negotiator");
+    let (negotiator, mut callbacks) = create_negotiator(
+        config,
+        serde_yaml::Value::Null,
+        working_dir.clone(),
+        working_dir,
+    )?;
+
+    let offer = negotiator.create_offer(&offer_template()).await?;
+    let offer = Proposal {
+        properties: offer.properties,
+        constraints: offer.constraints,
+        proposal_id: "offer-1".to_string(),
+        issuer_id: Default::default(),
+        state: State::Initial,
+        timestamp: Utc::now(),
+        prev_proposal_id: None,
+    };
+
+    // Decisions aren't returned directly, but sent through callbacks channel.
+    negotiator
+        .react_to_proposal("subscription-1", &demand(), &offer)
+        .await?;
+    let action = callbacks.proposal_channel.recv().await;
+    println!("{:#?}", action);
+
+    negotiator
+        .shutdown(std::time::Duration::from_secs(5))
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,289 @@
+use anyhow::anyhow;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use ya_agreement_utils::{AgreementView, OfferTemplate, ProposalView};
+use ya_negotiator_component::component::{
+    reconfigure_request, AgreementEvent, AgreementResult, NegotiationResult, NegotiatorComponent,
+    ProposalScore, Score,
+};
+
+use crate::message::{
+    NegotiationMessage, NegotiationOutcome, NegotiationRequest, NegotiationResponse,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum JsonRpcError {
+    #[error("[Negotiator Error] Connection with JSON-RPC negotiator failed. {0}")]
+    Io(#[from] std::io::Error),
+    #[error("[Negotiator Error] Failed to serialize/deserialize JSON-RPC message. {0}")]
+    Serialization(#[from] serde_json::Error),
+    #[error("[Negotiator Error] JSON-RPC negotiator closed connection.")]
+    Disconnected,
+    #[error("[Negotiator Error] JSON-RPC connection unusable, previous call panicked.")]
+    Poisoned,
+    #[error("[Negotiator Error] Unexpected response id {got:?}, expected {expected}.")]
+    UnexpectedId { expected: u64, got: Option<u64> },
+    #[error("{message} (code: {code})")]
+    Remote { code: i64, message: String },
+}
+
+/// Used, if config doesn't specify how long to wait for remote negotiator.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    next_id: u64,
+}
+
+/// Negotiator running in external process, reachable with JSON-RPC over TCP.
+/// Calls are blocking, so remote negotiator should respond quickly. Calls not
+/// completed within timeout fail with `JsonRpcError::Io`. Negotiators created
+/// with JSON-RPC components run on dedicated Arbiter, so blocking calls don't
+/// stall other actors.
+pub struct JsonRpcComponent {
+    name: String,
+    /// `serialize_state` takes `&self`, so connection must be mutable behind reference.
+    connection: Mutex<Connection>,
+}
+
+impl JsonRpcComponent {
+    /// Connects to JSON-RPC server and creates negotiator `name` on the remote side.
+    /// Reading and writing messages fails after `timeout` (`DEFAULT_TIMEOUT` if not set).
+    pub fn connect(
+        address: impl ToSocketAddrs,
+        timeout: Option<Duration>,
+        name: &str,
+        config: serde_yaml::Value,
+        agent_env: serde_yaml::Value,
+        working_dir: PathBuf,
+    ) -> anyhow::Result<Box<dyn NegotiatorComponent>> {
+        let timeout = timeout.unwrap_or(DEFAULT_TIMEOUT);
+        let stream = TcpStream::connect(address).map_err(JsonRpcError::from)?;
+        stream
+            .set_read_timeout(Some(timeout))
+            .map_err(JsonRpcError::from)?;
+        stream
+            .set_write_timeout(Some(timeout))
+            .map_err(JsonRpcError::from)?;
+        let component = JsonRpcComponent {
+            name: name.to_string(),
+            connection: Mutex::new(Connection {
+                reader: BufReader::new(stream.try_clone().map_err(JsonRpcError::from)?),
+                writer: stream,
+                next_id: 0,
+            }),
+        };
+
+        component.call::<()>(NegotiationMessage::Create {
+            name: name.to_string(),
+            config,
+            agent_env,
+            working_dir,
+        })?;
+        Ok(Box::new(component))
+    }
+
+    fn call<T: DeserializeOwned>(&self, message: NegotiationMessage) -> Result<T, JsonRpcError> {
+        let mut connection = self.connection.lock().map_err(|_| JsonRpcError::Poisoned)?;
+        let id = connection.next_id;
+        connection.next_id += 1;
+
+        let mut request = serde_json::to_string(&NegotiationRequest::new(id, message))?;
+        request.push('\n');
+        connection.writer.write_all(request.as_bytes())?;
+        connection.writer.flush()?;
+
+        // Responses to previous calls, which timed out, can arrive later.
+        let response = loop {
+            let mut line = String::new();
+            if connection.reader.read_line(&mut line)? == 0 {
+                return Err(JsonRpcError::Disconnected);
+            }
+
+            let response: NegotiationResponse = serde_json::from_str(&line)?;
+            match response.id {
+                Some(got) if got < id => {
+                    log::debug!("Skipping late JSON-RPC response [{got}] of {}.", self.name)
+                }
+                _ => break response,
+            }
+        };
+        if response.id != Some(id) {
+            return Err(JsonRpcError::UnexpectedId {
+                expected: id,
+                got: response.id,
+            });
+        }
+
+        match response.outcome {
+            NegotiationOutcome::Result(result) => Ok(serde_json::from_value(result)?),
+            NegotiationOutcome::Error(error) => Err(JsonRpcError::Remote {
+                code: error.code,
+                message: error.message,
+            }),
+        }
+    }
+}
+
+impl NegotiatorComponent for JsonRpcComponent {
+    fn negotiate_step(
+        &mut self,
+        their: &ProposalView,
+        template: ProposalView,
+        score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        self.negotiate_step_with_history(their, &[], template, score)
+    }
+
+    fn negotiate_step_with_history(
+        &mut self,
+        their: &ProposalView,
+        history: &[ProposalView],
+        template: ProposalView,
+        score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        Ok(self.call(NegotiationMessage::NegotiateStep {
+            their: their.clone(),
+            template,
+            score,
+            history: history.to_vec(),
+        })?)
+    }
+
+    fn dry_run_step(
+        &mut self,
+        their: &ProposalView,
+        history: &[ProposalView],
+        template: ProposalView,
+        score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        Ok(self.call(NegotiationMessage::DryRunStep {
+            their: their.clone(),
+            template,
+            score,
+            history: history.to_vec(),
+        })?)
+    }
+
+    fn negotiate_batch(
+        &mut self,
+        items: Vec<(ProposalView, Vec<ProposalView>, ProposalView, Score)>,
+    ) -> Vec<anyhow::Result<NegotiationResult>> {
+        let count = items.len();
+        let results = self
+            .call::<Vec<Result<NegotiationResult, String>>>(NegotiationMessage::NegotiateBatch {
+                items,
+            })
+            .map_err(anyhow::Error::from)
+            .and_then(|results| match results.len() == count {
+                true => Ok(results),
+                false => Err(anyhow!(
+                    "Expected {} negotiation results, got {}.",
+                    count,
+                    results.len()
+                )),
+            });
+
+        match results {
+            Ok(results) => results
+                .into_iter()
+                .map(|result| result.map_err(|e| anyhow!(e)))
+                .collect(),
+            // Whole batch failed, so error applies to all items.
+            Err(e) => (0..count).map(|_| Err(anyhow!(e.to_string()))).collect(),
+        }
+    }
+
+    fn rescore_batch(&mut self, proposals: &mut [ProposalScore]) -> anyhow::Result<()> {
+        let rescored: Vec<ProposalScore> = self.call(NegotiationMessage::RescoreBatch {
+            proposals: proposals.to_vec(),
+        })?;
+        if rescored.len() != proposals.len() {
+            anyhow::bail!(
+                "Expected {} rescored Proposals, got {}.",
+                proposals.len(),
+                rescored.len()
+            );
+        }
+        proposals.clone_from_slice(&rescored);
+        Ok(())
+    }
+
+    fn fill_template(&mut self, template: OfferTemplate) -> anyhow::Result<OfferTemplate> {
+        Ok(self.call(NegotiationMessage::FillTemplate { template })?)
+    }
+
+    fn on_offer_published(&mut self, offer: &OfferTemplate) -> anyhow::Result<()> {
+        Ok(self.call(NegotiationMessage::OnOfferPublished {
+            offer: offer.clone(),
+        })?)
+    }
+
+    fn on_agreement_terminated(
+        &mut self,
+        agreement_id: &str,
+        result: &AgreementResult,
+    ) -> anyhow::Result<()> {
+        Ok(self.call(NegotiationMessage::OnAgreementTerminated {
+            agreement_id: agreement_id.to_string(),
+            result: result.clone(),
+        })?)
+    }
+
+    fn on_agreement_approved(&mut self, agreement: &AgreementView) -> anyhow::Result<()> {
+        Ok(self.call(NegotiationMessage::OnAgreementApproved {
+            agreement: agreement.clone(),
+        })?)
+    }
+
+    fn on_proposal_rejected(&mut self, proposal_id: &str) -> anyhow::Result<()> {
+        Ok(self.call(NegotiationMessage::OnProposalRejected {
+            proposal_id: proposal_id.to_string(),
+        })?)
+    }
+
+    fn on_agreement_event(
+        &mut self,
+        agreement_id: &str,
+        event: &AgreementEvent,
+    ) -> anyhow::Result<()> {
+        Ok(self.call(NegotiationMessage::OnAgreementEvent {
+            agreement_id: agreement_id.to_string(),
+            event: event.clone(),
+        })?)
+    }
+
+    fn control_event(&mut self, component: &str, params: Value) -> anyhow::Result<Value> {
+        Ok(self.call(NegotiationMessage::ControlEvent {
+            component: component.to_string(),
+            params,
+        })?)
+    }
+
+    fn serialize_state(&self) -> anyhow::Result<Value> {
+        Ok(self.call(NegotiationMessage::SerializeState)?)
+    }
+
+    fn restore_state(&mut self, state: Value) -> anyhow::Result<()> {
+        Ok(self.call(NegotiationMessage::RestoreState { state })?)
+    }
+
+    /// Reconfigure request is passed through `control_event`, the same way
+    /// as for shared library negotiators. Server side unpacks it.
+    fn reconfigure(&mut self, config: serde_yaml::Value) -> anyhow::Result<()> {
+        let name = self.name.clone();
+        self.control_event(&name, reconfigure_request(config)?)?;
+        Ok(())
+    }
+
+    fn shutdown(&mut self, timeout: Duration) -> anyhow::Result<()> {
+        Ok(self.call(NegotiationMessage::Shutdown { timeout })?)
+    }
+}
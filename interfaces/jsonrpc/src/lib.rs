@@ -0,0 +1,9 @@
+//! Transport for negotiators running in external process. Messages are
+//! JSON-RPC 2.0 requests and responses sent as newline-delimited JSON over TCP.
+//! Each connection hosts single negotiator component.
+mod component;
+pub mod message;
+mod server;
+
+pub use component::{JsonRpcComponent, JsonRpcError};
+pub use server::{run_server, serve_connection, ConstructorFunction};
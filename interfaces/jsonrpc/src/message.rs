@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use ya_agreement_utils::{AgreementView, OfferTemplate, ProposalView};
+use ya_negotiator_component::component::{AgreementEvent, AgreementResult, ProposalScore, Score};
+
+pub const JSONRPC_VERSION: &str = "2.0";
+
+/// Invalid JSON was received by the server.
+pub const PARSE_ERROR: i64 = -32700;
+/// First message on connection wasn't `Create`, or `Create` was sent twice.
+pub const INVALID_REQUEST: i64 = -32600;
+/// Negotiator component returned error.
+pub const COMPONENT_ERROR: i64 = -32000;
+
+/// Calls of `NegotiatorComponent` functions. Method name is snake case name of variant.
+/// `Create` must be the first message sent on each connection.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "method", content = "params", rename_all = "snake_case")]
+pub enum NegotiationMessage {
+    Create {
+        name: String,
+        config: serde_yaml::Value,
+        agent_env: serde_yaml::Value,
+        working_dir: PathBuf,
+    },
+    NegotiateStep {
+        their: ProposalView,
+        template: ProposalView,
+        score: Score,
+        /// Their Proposals from previous negotiation rounds, the oldest first.
+        #[serde(default)]
+        history: Vec<ProposalView>,
+    },
+    /// The same as `NegotiateStep`, but mustn't change component state.
+    DryRunStep {
+        their: ProposalView,
+        template: ProposalView,
+        score: Score,
+        #[serde(default)]
+        history: Vec<ProposalView>,
+    },
+    /// Evaluates many Proposals in single round-trip. Each item is (their, history, template,
+    /// score) tuple. Result is list of results in the same order, each either result or error
+    /// message.
+    NegotiateBatch {
+        items: Vec<(ProposalView, Vec<ProposalView>, ProposalView, Score)>,
+    },
+    RescoreBatch {
+        proposals: Vec<ProposalScore>,
+    },
+    FillTemplate {
+        template: OfferTemplate,
+    },
+    OnOfferPublished {
+        offer: OfferTemplate,
+    },
+    OnAgreementTerminated {
+        agreement_id: String,
+        result: AgreementResult,
+    },
+    OnAgreementApproved {
+        agreement: AgreementView,
+    },
+    OnProposalRejected {
+        proposal_id: String,
+    },
+    OnAgreementEvent {
+        agreement_id: String,
+        event: AgreementEvent,
+    },
+    ControlEvent {
+        component: String,
+        params: Value,
+    },
+    SerializeState,
+    RestoreState {
+        state: Value,
+    },
+    Shutdown {
+        timeout: Duration,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NegotiationRequest {
+    pub jsonrpc: String,
+    pub id: u64,
+    #[serde(flatten)]
+    pub message: NegotiationMessage,
+}
+
+/// Either `result` or `error` field of JSON-RPC response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NegotiationOutcome {
+    Result(Value),
+    Error(ErrorObject),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorObject {
+    pub code: i64,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NegotiationResponse {
+    pub jsonrpc: String,
+    /// `None` only if request couldn't be parsed.
+    pub id: Option<u64>,
+    #[serde(flatten)]
+    pub outcome: NegotiationOutcome,
+}
+
+impl NegotiationRequest {
+    pub fn new(id: u64, message: NegotiationMessage) -> NegotiationRequest {
+        NegotiationRequest {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id,
+            message,
+        }
+    }
+}
+
+impl NegotiationResponse {
+    pub fn result(id: u64, result: Value) -> NegotiationResponse {
+        NegotiationResponse {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id: Some(id),
+            outcome: NegotiationOutcome::Result(result),
+        }
+    }
+
+    pub fn error(id: Option<u64>, code: i64, message: impl ToString) -> NegotiationResponse {
+        NegotiationResponse {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id,
+            outcome: NegotiationOutcome::Error(ErrorObject {
+                code,
+                message: message.to_string(),
+            }),
+        }
+    }
+}
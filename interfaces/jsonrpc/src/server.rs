@@ -0,0 +1,429 @@
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use ya_negotiator_component::component::{
+    reconfigure_config, NegotiationResult, NegotiatorComponent,
+};
+
+use crate::message::{
+    NegotiationMessage, NegotiationRequest, NegotiationResponse, COMPONENT_ERROR, INVALID_REQUEST,
+    PARSE_ERROR,
+};
+
+/// Creates negotiator by name. Called for each `Create` message.
+pub type ConstructorFunction = Arc<
+    dyn Fn(
+            &str,
+            serde_yaml::Value,
+            serde_yaml::Value,
+            PathBuf,
+        ) -> anyhow::Result<Box<dyn NegotiatorComponent>>
+        + Send
+        + Sync,
+>;
+
+/// Accepts connections and serves each of them in separate thread.
+/// Returns only if listening fails.
+pub fn run_server(listener: TcpListener, constructor: ConstructorFunction) -> std::io::Result<()> {
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let constructor = constructor.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = serve_connection(stream, constructor) {
+                log::warn!("JSON-RPC negotiator connection failed. {e}");
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Handles requests from single connection until client disconnects.
+pub fn serve_connection(stream: TcpStream, constructor: ConstructorFunction) -> anyhow::Result<()> {
+    let mut writer = stream.try_clone()?;
+    let reader = BufReader::new(stream);
+    let mut component: Option<Box<dyn NegotiatorComponent>> = None;
+
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<NegotiationRequest>(&line) {
+            Ok(request) => handle_request(request, &mut component, &constructor),
+            Err(e) => NegotiationResponse::error(None, PARSE_ERROR, e),
+        };
+
+        let mut response = serde_json::to_string(&response)?;
+        response.push('\n');
+        writer.write_all(response.as_bytes())?;
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+fn handle_request(
+    request: NegotiationRequest,
+    component: &mut Option<Box<dyn NegotiatorComponent>>,
+    constructor: &ConstructorFunction,
+) -> NegotiationResponse {
+    let id = request.id;
+    let result = match request.message {
+        NegotiationMessage::Create {
+            name,
+            config,
+            agent_env,
+            working_dir,
+        } => {
+            if component.is_some() {
+                return NegotiationResponse::error(
+                    Some(id),
+                    INVALID_REQUEST,
+                    "Negotiator already created on this connection.",
+                );
+            }
+            constructor(&name, config, agent_env, working_dir).map(|created| {
+                *component = Some(created);
+                Value::Null
+            })
+        }
+        message => match component {
+            Some(component) => call_component(message, component.as_mut()),
+            None => {
+                return NegotiationResponse::error(
+                    Some(id),
+                    INVALID_REQUEST,
+                    "Negotiator must be created before calling other methods.",
+                )
+            }
+        },
+    };
+
+    match result {
+        Ok(result) => NegotiationResponse::result(id, result),
+        Err(e) => NegotiationResponse::error(Some(id), COMPONENT_ERROR, e),
+    }
+}
+
+fn call_component(
+    message: NegotiationMessage,
+    component: &mut dyn NegotiatorComponent,
+) -> anyhow::Result<Value> {
+    let result = match message {
+        NegotiationMessage::Create { .. } => unreachable!("Create is handled separately."),
+        NegotiationMessage::NegotiateStep {
+            their,
+            template,
+            score,
+            history,
+        } => serde_json::to_value(
+            component.negotiate_step_with_history(&their, &history, template, score)?,
+        )?,
+        NegotiationMessage::DryRunStep {
+            their,
+            template,
+            score,
+            history,
+        } => serde_json::to_value(component.dry_run_step(&their, &history, template, score)?)?,
+        NegotiationMessage::NegotiateBatch { items } => serde_json::to_value(
+            component
+                .negotiate_batch(items)
+                .into_iter()
+                .map(|result| result.map_err(|e| e.to_string()))
+                .collect::<Vec<Result<NegotiationResult, String>>>(),
+        )?,
+        NegotiationMessage::RescoreBatch { mut proposals } => {
+            component.rescore_batch(&mut proposals)?;
+            serde_json::to_value(proposals)?
+        }
+        NegotiationMessage::FillTemplate { template } => {
+            serde_json::to_value(component.fill_template(template)?)?
+        }
+        NegotiationMessage::OnOfferPublished { offer } => {
+            component.on_offer_published(&offer)?;
+            Value::Null
+        }
+        NegotiationMessage::OnAgreementTerminated {
+            agreement_id,
+            result,
+        } => {
+            component.on_agreement_terminated(&agreement_id, &result)?;
+            Value::Null
+        }
+        NegotiationMessage::OnAgreementApproved { agreement } => {
+            component.on_agreement_approved(&agreement)?;
+            Value::Null
+        }
+        NegotiationMessage::OnProposalRejected { proposal_id } => {
+            component.on_proposal_rejected(&proposal_id)?;
+            Value::Null
+        }
+        NegotiationMessage::OnAgreementEvent {
+            agreement_id,
+            event,
+        } => {
+            component.on_agreement_event(&agreement_id, &event)?;
+            Value::Null
+        }
+        NegotiationMessage::ControlEvent {
+            component: name,
+            params,
+        } => match reconfigure_config(&params) {
+            Some(config) => {
+                component.reconfigure(config)?;
+                Value::Null
+            }
+            None => component.control_event(&name, params)?,
+        },
+        NegotiationMessage::SerializeState => component.serialize_state()?,
+        NegotiationMessage::RestoreState { state } => {
+            component.restore_state(state)?;
+            Value::Null
+        }
+        NegotiationMessage::Shutdown { timeout } => {
+            component.shutdown(timeout)?;
+            Value::Null
+        }
+    };
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::JsonRpcComponent;
+
+    use ya_agreement_utils::{OfferTemplate, ProposalView};
+    use ya_negotiator_component::component::{NegotiationResult, Score};
+    use ya_negotiator_component::reason::RejectReason;
+
+    /// Rejects Proposals without `test.accept` property and marks Offers.
+    struct AcceptMarked;
+
+    impl NegotiatorComponent for AcceptMarked {
+        fn negotiate_step(
+            &mut self,
+            their: &ProposalView,
+            template: ProposalView,
+            score: Score,
+        ) -> anyhow::Result<NegotiationResult> {
+            Ok(match their.pointer("/test/accept") {
+                Some(_) => NegotiationResult::Ready {
+                    proposal: template,
+                    score,
+                },
+                None => NegotiationResult::Reject {
+                    reason: RejectReason::new("Not marked."),
+                    is_final: true,
+                },
+            })
+        }
+
+        fn fill_template(&mut self, mut template: OfferTemplate) -> anyhow::Result<OfferTemplate> {
+            template.set_property("test.marked", serde_json::json!(true));
+            Ok(template)
+        }
+
+        fn control_event(&mut self, _component: &str, _params: Value) -> anyhow::Result<Value> {
+            anyhow::bail!("Control events not supported.")
+        }
+    }
+
+    /// Counts negotiated Proposals, but dry runs don't change the counter.
+    struct CountSteps(u32);
+
+    impl NegotiatorComponent for CountSteps {
+        fn negotiate_step(
+            &mut self,
+            _their: &ProposalView,
+            template: ProposalView,
+            score: Score,
+        ) -> anyhow::Result<NegotiationResult> {
+            self.0 += 1;
+            Ok(NegotiationResult::Ready {
+                proposal: template,
+                score,
+            })
+        }
+
+        fn dry_run_step(
+            &mut self,
+            _their: &ProposalView,
+            _history: &[ProposalView],
+            template: ProposalView,
+            score: Score,
+        ) -> anyhow::Result<NegotiationResult> {
+            Ok(NegotiationResult::Ready {
+                proposal: template,
+                score,
+            })
+        }
+
+        fn serialize_state(&self) -> anyhow::Result<Value> {
+            Ok(serde_json::json!(self.0))
+        }
+    }
+
+    fn start_server() -> std::net::SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let constructor: ConstructorFunction = Arc::new(|name, _, _, _| match name {
+            "AcceptMarked" => Ok(Box::new(AcceptMarked) as Box<dyn NegotiatorComponent>),
+            "CountSteps" => Ok(Box::new(CountSteps(0)) as Box<dyn NegotiatorComponent>),
+            _ => anyhow::bail!("Unknown negotiator {name}"),
+        });
+        std::thread::spawn(move || run_server(listener, constructor));
+        address
+    }
+
+    fn proposal(properties: Value) -> ProposalView {
+        ProposalView {
+            content: OfferTemplate {
+                properties,
+                constraints: String::new(),
+            },
+            id: "proposal-1".to_string(),
+            issuer: Default::default(),
+            state: ya_client_model::market::proposal::State::Draft,
+            timestamp: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_jsonrpc_round_trip() {
+        let address = start_server();
+        let mut component = JsonRpcComponent::connect(
+            address,
+            None,
+            "AcceptMarked",
+            serde_yaml::Value::Null,
+            serde_yaml::Value::Null,
+            PathBuf::new(),
+        )
+        .unwrap();
+
+        let template = component.fill_template(OfferTemplate::default()).unwrap();
+        assert_eq!(
+            template.property("test.marked"),
+            Some(&serde_json::json!(true))
+        );
+
+        let marked = proposal(serde_json::json!({ "test": { "accept": true } }));
+        match component
+            .negotiate_step(&marked, marked.clone(), Score::default())
+            .unwrap()
+        {
+            NegotiationResult::Ready { proposal, .. } => assert_eq!(proposal, marked),
+            result => panic!("Expected Ready, got: {:?}", result),
+        }
+
+        let unmarked = proposal(serde_json::json!({}));
+        match component
+            .negotiate_step(&unmarked, unmarked.clone(), Score::default())
+            .unwrap()
+        {
+            NegotiationResult::Reject { reason, is_final } => {
+                assert_eq!(reason.message, "Not marked.");
+                assert!(is_final);
+            }
+            result => panic!("Expected Reject, got: {:?}", result),
+        }
+
+        // Component errors are passed to the client.
+        let error = component
+            .control_event("AcceptMarked", Value::Null)
+            .unwrap_err();
+        assert!(error.to_string().contains("Control events not supported."));
+
+        // Connection is still usable after error.
+        component.on_proposal_rejected("proposal-1").unwrap();
+    }
+
+    #[test]
+    fn test_jsonrpc_dry_run() {
+        let address = start_server();
+        let mut component = JsonRpcComponent::connect(
+            address,
+            None,
+            "CountSteps",
+            serde_yaml::Value::Null,
+            serde_yaml::Value::Null,
+            PathBuf::new(),
+        )
+        .unwrap();
+
+        let their = proposal(serde_json::json!({}));
+        component
+            .dry_run_step(&their, &[], their.clone(), Score::default())
+            .unwrap();
+        assert_eq!(component.serialize_state().unwrap(), serde_json::json!(0));
+
+        component
+            .negotiate_step(&their, their.clone(), Score::default())
+            .unwrap();
+        assert_eq!(component.serialize_state().unwrap(), serde_json::json!(1));
+    }
+
+    /// Batch evaluated in single round-trip should give the same results as
+    /// evaluating Proposals one by one.
+    #[test]
+    fn test_jsonrpc_negotiate_batch() {
+        let address = start_server();
+        let mut component = JsonRpcComponent::connect(
+            address,
+            None,
+            "AcceptMarked",
+            serde_yaml::Value::Null,
+            serde_yaml::Value::Null,
+            PathBuf::new(),
+        )
+        .unwrap();
+
+        let items = vec![
+            serde_json::json!({ "test": { "accept": true } }),
+            serde_json::json!({}),
+        ]
+        .into_iter()
+        .map(|properties| {
+            let their = proposal(properties);
+            (their.clone(), vec![], their, Score::default())
+        })
+        .collect::<Vec<_>>();
+
+        let expected = items
+            .clone()
+            .into_iter()
+            .map(|(their, history, template, score)| {
+                component.negotiate_step_with_history(&their, &history, template, score)
+            })
+            .collect::<anyhow::Result<Vec<_>>>()
+            .unwrap();
+        let results = component
+            .negotiate_batch(items)
+            .into_iter()
+            .collect::<anyhow::Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(results, expected);
+        assert!(matches!(results[1], NegotiationResult::Reject { .. }));
+    }
+
+    #[test]
+    fn test_jsonrpc_unknown_negotiator() {
+        let address = start_server();
+        let error = JsonRpcComponent::connect(
+            address,
+            None,
+            "Unknown",
+            serde_yaml::Value::Null,
+            serde_yaml::Value::Null,
+            PathBuf::new(),
+        )
+        .err()
+        .unwrap();
+        assert!(error.to_string().contains("Unknown negotiator Unknown"));
+    }
+}
@@ -1,13 +1,15 @@
 use abi_stable::std_types::RStr;
 use anyhow::anyhow;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use crate::interface::{load_library, BoxedSharedNegotiatorAPI};
+use crate::interface::{load_library, BoxedSharedNegotiatorAPI, API_VERSION};
 
 use serde_json::Value;
 use ya_agreement_utils::{AgreementView, OfferTemplate, ProposalView};
 use ya_negotiator_component::component::{
-    AgreementEvent, AgreementResult, NegotiationResult, NegotiatorComponent, Score,
+    reconfigure_request, AgreementEvent, AgreementResult, NegotiationResult, NegotiatorComponent,
+    ProposalScore, Score,
 };
 
 #[derive(thiserror::Error, Debug)]
@@ -24,6 +26,7 @@ pub enum SharedLibError {
 
 /// Negotiator loaded from shared library.
 pub struct SharedLibNegotiator {
+    name: String,
     negotiator: BoxedSharedNegotiatorAPI,
 }
 
@@ -32,9 +35,11 @@ impl SharedLibNegotiator {
         path: &Path,
         negotiator_name: &str,
         config: serde_yaml::Value,
+        agent_env: serde_yaml::Value,
         working_dir: PathBuf,
     ) -> anyhow::Result<Box<dyn NegotiatorComponent>> {
         let config = serde_yaml::to_string(&config).map_err(SharedLibError::from)?;
+        let agent_env = serde_yaml::to_string(&agent_env).map_err(SharedLibError::from)?;
         let working_dir = working_dir
             .to_path_buf()
             .to_str()
@@ -45,9 +50,12 @@ impl SharedLibNegotiator {
             .to_string();
 
         let library = load_library(path)?;
+        check_api_version(negotiator_name, library.api_version())?;
+
         let negotiator = library.create_negotiator()(
             RStr::from_str(negotiator_name),
             RStr::from_str(&config),
+            RStr::from_str(&agent_env),
             RStr::from_str(&working_dir),
         )
         .into_result()
@@ -55,7 +63,28 @@ impl SharedLibNegotiator {
             SharedLibError::Initialization(negotiator_name.to_string(), e.into_string())
         })?;
 
-        Ok(Box::new(SharedLibNegotiator { negotiator }))
+        Ok(Box::new(SharedLibNegotiator {
+            name: negotiator_name.to_string(),
+            negotiator,
+        }))
+    }
+}
+
+/// Library built against different version of negotiator API can't be used safely.
+/// `None` means, that library was built before API versioning was introduced.
+fn check_api_version(negotiator_name: &str, version: Option<u32>) -> Result<(), SharedLibError> {
+    match version {
+        Some(version) if version == API_VERSION => Ok(()),
+        version => Err(SharedLibError::Initialization(
+            negotiator_name.to_string(),
+            format!(
+                "Library negotiator API version: {}, expected version: {}.",
+                version
+                    .map(|version| version.to_string())
+                    .unwrap_or("unknown".to_string()),
+                API_VERSION
+            ),
+        )),
     }
 }
 
@@ -83,6 +112,107 @@ impl NegotiatorComponent for SharedLibNegotiator {
         Ok(serde_json::from_str(&result).map_err(SharedLibError::from)?)
     }
 
+    fn negotiate_step_with_history(
+        &mut self,
+        demand: &ProposalView,
+        history: &[ProposalView],
+        offer: ProposalView,
+        score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        let demand = serde_json::to_string(&demand).map_err(SharedLibError::from)?;
+        let history = serde_json::to_string(&history).map_err(SharedLibError::from)?;
+        let offer = serde_json::to_string(&offer).map_err(SharedLibError::from)?;
+        let score = serde_json::to_string(&score).map_err(SharedLibError::from)?;
+
+        let result = self
+            .negotiator
+            .negotiate_step_with_history(
+                &RStr::from_str(&demand),
+                &RStr::from_str(&history),
+                &RStr::from_str(&offer),
+                &RStr::from_str(&score),
+            )
+            .into_result()
+            .map_err(|e| SharedLibError::Negotiation(e.into_string()))?;
+
+        Ok(serde_json::from_str(&result).map_err(SharedLibError::from)?)
+    }
+
+    fn dry_run_step(
+        &mut self,
+        demand: &ProposalView,
+        history: &[ProposalView],
+        offer: ProposalView,
+        score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        let demand = serde_json::to_string(&demand).map_err(SharedLibError::from)?;
+        let history = serde_json::to_string(&history).map_err(SharedLibError::from)?;
+        let offer = serde_json::to_string(&offer).map_err(SharedLibError::from)?;
+        let score = serde_json::to_string(&score).map_err(SharedLibError::from)?;
+
+        let result = self
+            .negotiator
+            .dry_run_step(
+                &RStr::from_str(&demand),
+                &RStr::from_str(&history),
+                &RStr::from_str(&offer),
+                &RStr::from_str(&score),
+            )
+            .into_result()
+            .map_err(|e| SharedLibError::Negotiation(e.into_string()))?;
+
+        Ok(serde_json::from_str(&result).map_err(SharedLibError::from)?)
+    }
+
+    fn negotiate_batch(
+        &mut self,
+        items: Vec<(ProposalView, Vec<ProposalView>, ProposalView, Score)>,
+    ) -> Vec<anyhow::Result<NegotiationResult>> {
+        let count = items.len();
+        let results = (|| {
+            let items = serde_json::to_string(&items).map_err(SharedLibError::from)?;
+            let results = self
+                .negotiator
+                .negotiate_batch(&RStr::from_str(&items))
+                .into_result()
+                .map_err(|e| SharedLibError::Negotiation(e.into_string()))?;
+
+            let results: Vec<Result<NegotiationResult, String>> =
+                serde_json::from_str(&results).map_err(SharedLibError::from)?;
+            Result::<_, SharedLibError>::Ok(results)
+        })();
+
+        match results {
+            Ok(results) => results
+                .into_iter()
+                .map(|result| result.map_err(|e| anyhow!(SharedLibError::Negotiation(e))))
+                .collect(),
+            // Whole batch failed, so error applies to all items.
+            Err(e) => (0..count).map(|_| Err(anyhow!(e.to_string()))).collect(),
+        }
+    }
+
+    fn rescore_batch(&mut self, proposals: &mut [ProposalScore]) -> anyhow::Result<()> {
+        let serialized = serde_json::to_string(&*proposals).map_err(SharedLibError::from)?;
+        let result = self
+            .negotiator
+            .rescore_batch(&RStr::from_str(&serialized))
+            .into_result()
+            .map_err(|e| SharedLibError::Negotiation(e.into_string()))?;
+
+        let rescored: Vec<ProposalScore> =
+            serde_json::from_str(result.as_str()).map_err(SharedLibError::from)?;
+        if rescored.len() != proposals.len() {
+            return Err(anyhow!(
+                "Expected {} rescored Proposals, got {}.",
+                proposals.len(),
+                rescored.len()
+            ));
+        }
+        proposals.clone_from_slice(&rescored);
+        Ok(())
+    }
+
     fn fill_template(&mut self, offer_template: OfferTemplate) -> anyhow::Result<OfferTemplate> {
         let constraints = offer_template.constraints;
         let properties =
@@ -96,6 +226,16 @@ impl NegotiatorComponent for SharedLibNegotiator {
         Ok(serde_json::from_str(result.as_str()).map_err(SharedLibError::from)?)
     }
 
+    fn on_offer_published(&mut self, offer: &OfferTemplate) -> anyhow::Result<()> {
+        let offer = serde_json::to_string(&offer).map_err(SharedLibError::from)?;
+
+        Ok(self
+            .negotiator
+            .on_offer_published(&RStr::from_str(&offer))
+            .into_result()
+            .map_err(|e| SharedLibError::Negotiation(e.into_string()))?)
+    }
+
     fn on_agreement_terminated(
         &mut self,
         agreement_id: &str,
@@ -155,4 +295,61 @@ impl NegotiatorComponent for SharedLibNegotiator {
 
         Ok(serde_json::from_str(result.as_str()).map_err(SharedLibError::from)?)
     }
+
+    fn serialize_state(&self) -> anyhow::Result<serde_json::Value> {
+        let state = self
+            .negotiator
+            .serialize_state()
+            .into_result()
+            .map_err(|e| SharedLibError::Negotiation(e.into_string()))?;
+        Ok(serde_json::from_str(state.as_str()).map_err(SharedLibError::from)?)
+    }
+
+    fn restore_state(&mut self, state: serde_json::Value) -> anyhow::Result<()> {
+        let state = serde_json::to_string(&state).map_err(SharedLibError::from)?;
+        Ok(self
+            .negotiator
+            .restore_state(&RStr::from_str(&state))
+            .into_result()
+            .map_err(|e| SharedLibError::Negotiation(e.into_string()))?)
+    }
+
+    /// Reconfigure request is passed through `control_event`, since `SharedNegotiatorAPI`
+    /// doesn't have separate function for it. Library side unpacks it.
+    fn reconfigure(&mut self, config: serde_yaml::Value) -> anyhow::Result<()> {
+        let name = self.name.clone();
+        self.control_event(&name, reconfigure_request(config)?)?;
+        Ok(())
+    }
+
+    fn shutdown(&mut self, timeout: Duration) -> anyhow::Result<()> {
+        let timeout = serde_json::to_string(&timeout).map_err(SharedLibError::from)?;
+        Ok(self
+            .negotiator
+            .shutdown(&RStr::from_str(&timeout))
+            .into_result()
+            .map_err(|e| SharedLibError::Negotiation(e.into_string()))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_version_mismatch() {
+        assert!(check_api_version("FilterNodes", Some(API_VERSION)).is_ok());
+
+        let error = check_api_version("FilterNodes", Some(API_VERSION + 1)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Failed to initialize negotiator 'FilterNodes'. Library negotiator API version: {}, expected version: {}.",
+                API_VERSION + 1,
+                API_VERSION
+            )
+        );
+
+        assert!(check_api_version("FilterNodes", None).is_err());
+    }
 }
@@ -14,6 +14,12 @@ use abi_stable::{
     StableAbi,
 };
 
+/// Version of negotiator API exposed by shared libraries. Must be bumped each time
+/// `NegotiatorLib` or `SharedNegotiatorAPI` change, or types serialized across this
+/// boundary (like `NegotiationResult`) change, so libraries built against older
+/// interface will be rejected on load.
+pub const API_VERSION: u32 = 11;
+
 #[repr(C)]
 #[derive(StableAbi)]
 #[sabi(kind(Prefix(prefix_ref = "NegotiatorLib_Ref")))]
@@ -32,9 +38,14 @@ pub struct NegotiatorLib {
     */
     #[sabi(last_prefix_field)]
     /// Create negotiator. First parameter is name. Second parameter is negotiator config.
-    /// Third parameter is path which component can use to store it's data.
+    /// Third parameter is serialized Agent environment. Fourth parameter is path
+    /// which component can use to store it's data.
     pub create_negotiator:
-        extern "C" fn(RStr, RStr, RStr) -> RResult<BoxedSharedNegotiatorAPI, RString>,
+        extern "C" fn(RStr, RStr, RStr, RStr) -> RResult<BoxedSharedNegotiatorAPI, RString>,
+    /// `API_VERSION` library was built with. Libraries built before versioning
+    /// was introduced, don't have this field.
+    #[sabi(missing_field(option))]
+    pub api_version: u32,
 }
 
 /// The RootModule trait defines how to load the root module of a library.
@@ -67,6 +78,34 @@ pub trait SharedNegotiatorAPI {
         score: &RStr,
     ) -> RResult<RString, RString>;
 
+    /// The same as `negotiate_step`. `history` is serialized list of Proposals
+    /// sent by other party in previous rounds of this negotiation.
+    fn negotiate_step_with_history(
+        &mut self,
+        demand: &RStr,
+        history: &RStr,
+        offer: &RStr,
+        score: &RStr,
+    ) -> RResult<RString, RString>;
+
+    /// The same as `negotiate_step_with_history`, but mustn't change component state.
+    /// Used for dry runs, which can't influence real negotiations.
+    fn dry_run_step(
+        &mut self,
+        demand: &RStr,
+        history: &RStr,
+        offer: &RStr,
+        score: &RStr,
+    ) -> RResult<RString, RString>;
+
+    /// Evaluates many Proposals in single call. `items` is serialized list
+    /// of (demand, offer, score) tuples. Returns serialized list of results.
+    fn negotiate_batch(&mut self, items: &RStr) -> RResult<RString, RString>;
+
+    /// Rescores serialized list of collected `ProposalScore`s before decision.
+    /// Returns serialized list of the same length.
+    fn rescore_batch(&mut self, proposals: &RStr) -> RResult<RString, RString>;
+
     /// Called during Offer creation. `NegotiatorComponent` should add properties
     /// and constraints for which it is responsible during future negotiations.
     /// TODO: Make API generic enough to work with Requestor.
@@ -76,6 +115,9 @@ pub trait SharedNegotiatorAPI {
         template_constraints: &RStr,
     ) -> RResult<RString, RString>;
 
+    /// Called with serialized Offer after all components filled template.
+    fn on_offer_published(&mut self, offer: &RStr) -> RResult<(), RString>;
+
     /// Called when Agreement was finished. `NegotiatorComponent` can use termination
     /// result to adjust his future negotiation strategy.
     fn on_agreement_terminated(
@@ -93,6 +135,15 @@ pub trait SharedNegotiatorAPI {
     fn on_agreement_event(&mut self, agreement_id: &RStr, event: &RStr) -> RResult<(), RString>;
 
     fn control_event(&mut self, component: &RStr, params: &RStr) -> RResult<RString, RString>;
+
+    /// Returns serialized component state, which should survive restart.
+    fn serialize_state(&self) -> RResult<RString, RString>;
+
+    /// Restores state returned by `serialize_state`.
+    fn restore_state(&mut self, state: &RStr) -> RResult<(), RString>;
+
+    /// Called before Negotiator is destroyed. Timeout is serialized `Duration`.
+    fn shutdown(&mut self, timeout: &RStr) -> RResult<(), RString>;
 }
 
 pub type BoxedSharedNegotiatorAPI = SharedNegotiatorAPI_TO<'static, RBox<()>>;
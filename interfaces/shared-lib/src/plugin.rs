@@ -12,12 +12,18 @@ use crate::SharedLibError;
 use std::str::FromStr;
 pub use ya_agreement_utils::{AgreementView, OfferTemplate, ProposalView};
 pub use ya_client_model::market::Reason;
+use ya_negotiator_component::component::reconfigure_config;
 pub use ya_negotiator_component::component::{
-    AgreementResult, NegotiationResult, NegotiatorComponent, Score,
+    AgreementResult, CounterOffer, NegotiationResult, NegotiatorComponent, ProposalScore, Score,
 };
 
 pub trait NegotiatorConstructor<T: NegotiatorComponent + Sync + Send + Sized>: Sync + Send {
-    fn new(name: &str, config: serde_yaml::Value, working_dir: PathBuf) -> anyhow::Result<T>;
+    fn create(
+        name: &str,
+        config: serde_yaml::Value,
+        agent_env: serde_yaml::Value,
+        working_dir: PathBuf,
+    ) -> anyhow::Result<T>;
 }
 
 /// Wraps `NegotiatorComponent` inside shared library and translates communication
@@ -35,9 +41,10 @@ where
     pub fn new(
         name: RStr,
         config: RStr,
+        agent_env: RStr,
         working_dir: RStr,
     ) -> RResult<BoxedSharedNegotiatorAPI, RString> {
-        match Self::new_impl(name, config, working_dir) {
+        match Self::new_impl(name, config, agent_env, working_dir) {
             Ok(nagotiator) => ROk(nagotiator),
             Err(e) => RErr(RString::from(e.to_string())),
         }
@@ -46,11 +53,13 @@ where
     fn new_impl(
         name: RStr,
         config: RStr,
+        agent_env: RStr,
         working_dir: RStr,
     ) -> anyhow::Result<BoxedSharedNegotiatorAPI> {
         let working_dir = PathBuf::from_str(working_dir.as_str())?;
         let config = serde_yaml::from_str(config.as_str())?;
-        let component = T::new(name.as_str(), config, working_dir)?;
+        let agent_env = serde_yaml::from_str(agent_env.as_str())?;
+        let component = T::create(name.as_str(), config, agent_env, working_dir)?;
 
         Ok(BoxedSharedNegotiatorAPI::from_value(
             NegotiatorWrapper { component },
@@ -88,6 +97,90 @@ where
         }
     }
 
+    fn negotiate_step_with_history(
+        &mut self,
+        demand: &RStr,
+        history: &RStr,
+        offer: &RStr,
+        score: &RStr,
+    ) -> RResult<RString, RString> {
+        match (|| {
+            let demand = serde_json::from_str(demand.as_str()).map_err(SharedLibError::from)?;
+            let history: Vec<ProposalView> =
+                serde_json::from_str(history.as_str()).map_err(SharedLibError::from)?;
+            let offer = serde_json::from_str(offer.as_str()).map_err(SharedLibError::from)?;
+            let score = serde_json::from_str(score.as_str()).map_err(SharedLibError::from)?;
+
+            let result = self
+                .component
+                .negotiate_step_with_history(&demand, &history, offer, score)
+                .map_err(|e| SharedLibError::Negotiation(e.to_string()))?;
+
+            serde_json::to_string(&result).map_err(SharedLibError::from)
+        })() {
+            Ok(result) => ROk(RString::from(result)),
+            Err(e) => RResult::RErr(RString::from(e.to_string())),
+        }
+    }
+
+    fn dry_run_step(
+        &mut self,
+        demand: &RStr,
+        history: &RStr,
+        offer: &RStr,
+        score: &RStr,
+    ) -> RResult<RString, RString> {
+        match (|| {
+            let demand = serde_json::from_str(demand.as_str()).map_err(SharedLibError::from)?;
+            let history: Vec<ProposalView> =
+                serde_json::from_str(history.as_str()).map_err(SharedLibError::from)?;
+            let offer = serde_json::from_str(offer.as_str()).map_err(SharedLibError::from)?;
+            let score = serde_json::from_str(score.as_str()).map_err(SharedLibError::from)?;
+
+            let result = self
+                .component
+                .dry_run_step(&demand, &history, offer, score)
+                .map_err(|e| SharedLibError::Negotiation(e.to_string()))?;
+
+            serde_json::to_string(&result).map_err(SharedLibError::from)
+        })() {
+            Ok(result) => ROk(RString::from(result)),
+            Err(e) => RResult::RErr(RString::from(e.to_string())),
+        }
+    }
+
+    fn negotiate_batch(&mut self, items: &RStr) -> RResult<RString, RString> {
+        match (|| {
+            let items = serde_json::from_str(items.as_str()).map_err(SharedLibError::from)?;
+            let results = self
+                .component
+                .negotiate_batch(items)
+                .into_iter()
+                .map(|result| result.map_err(|e| e.to_string()))
+                .collect::<Vec<Result<NegotiationResult, String>>>();
+
+            serde_json::to_string(&results).map_err(SharedLibError::from)
+        })() {
+            Ok(result) => ROk(RString::from(result)),
+            Err(e) => RResult::RErr(RString::from(e.to_string())),
+        }
+    }
+
+    fn rescore_batch(&mut self, proposals: &RStr) -> RResult<RString, RString> {
+        match (|| {
+            let mut proposals: Vec<ProposalScore> =
+                serde_json::from_str(proposals.as_str()).map_err(SharedLibError::from)?;
+            self.component
+                .rescore_batch(&mut proposals)
+                .map_err(|e| SharedLibError::Negotiation(e.to_string()))?;
+
+            serde_json::to_string(&proposals).map_err(SharedLibError::from)
+        })() {
+            Ok(result) => ROk(RString::from(result)),
+            Err(e) => RResult::RErr(RString::from(e.to_string())),
+        }
+    }
+
     fn fill_template(
         &mut self,
         template_props: &RStr,
@@ -117,6 +210,19 @@ where
         }
     }
 
+    fn on_offer_published(&mut self, offer: &RStr) -> RResult<(), RString> {
+        match (|| {
+            let offer = serde_json::from_str(offer.as_str()).map_err(SharedLibError::from)?;
+            self.component
+                .on_offer_published(&offer)
+                .map_err(|e| SharedLibError::Negotiation(e.to_string()))?;
+            Result::<(), SharedLibError>::Ok(())
+        })() {
+            Ok(_) => ROk(()),
+            Err(e) => RResult::RErr(RString::from(e.to_string())),
+        }
+    }
+
     fn on_agreement_terminated(
         &mut self,
         agreement_id: &RStr,
@@ -175,10 +281,14 @@ where
     fn control_event(&mut self, component: &RStr, params: &RStr) -> RResult<RString, RString> {
         match (|| {
             let params = serde_json::from_str(params.as_str()).map_err(SharedLibError::from)?;
-            let response = self
-                .component
-                .control_event(component.as_str(), params)
-                .map_err(|e| SharedLibError::Negotiation(e.to_string()))?;
+            let response = match reconfigure_config(&params) {
+                Some(config) => self
+                    .component
+                    .reconfigure(config)
+                    .map(|_| serde_json::Value::Null),
+                None => self.component.control_event(component.as_str(), params),
+            }
+            .map_err(|e| SharedLibError::Negotiation(e.to_string()))?;
 
             Result::<String, SharedLibError>::Ok(
                 serde_json::to_string(&response).map_err(SharedLibError::from)?,
@@ -188,10 +298,50 @@ where
             Err(e) => RResult::RErr(RString::from(e.to_string())),
         }
     }
+
+    fn serialize_state(&self) -> RResult<RString, RString> {
+        match (|| {
+            let state = self
+                .component
+                .serialize_state()
+                .map_err(|e| SharedLibError::Negotiation(e.to_string()))?;
+
+            serde_json::to_string(&state).map_err(SharedLibError::from)
+        })() {
+            Ok(state) => ROk(RString::from(state)),
+            Err(e) => RResult::RErr(RString::from(e.to_string())),
+        }
+    }
+
+    fn restore_state(&mut self, state: &RStr) -> RResult<(), RString> {
+        match (|| {
+            let state = serde_json::from_str(state.as_str()).map_err(SharedLibError::from)?;
+            self.component
+                .restore_state(state)
+                .map_err(|e| SharedLibError::Negotiation(e.to_string()))?;
+            Result::<(), SharedLibError>::Ok(())
+        })() {
+            Ok(_) => ROk(()),
+            Err(e) => RResult::RErr(RString::from(e.to_string())),
+        }
+    }
+
+    fn shutdown(&mut self, timeout: &RStr) -> RResult<(), RString> {
+        match (|| {
+            let timeout = serde_json::from_str(timeout.as_str()).map_err(SharedLibError::from)?;
+            self.component
+                .shutdown(timeout)
+                .map_err(|e| SharedLibError::Negotiation(e.to_string()))?;
+            Result::<(), SharedLibError>::Ok(())
+        })() {
+            Ok(_) => ROk(()),
+            Err(e) => RResult::RErr(RString::from(e.to_string())),
+        }
+    }
 }
 
 type ConstructorFunction =
-    Box<dyn Fn(RStr, RStr, RStr) -> RResult<BoxedSharedNegotiatorAPI, RString> + Send + Sync>;
+    Box<dyn Fn(RStr, RStr, RStr, RStr) -> RResult<BoxedSharedNegotiatorAPI, RString> + Send + Sync>;
 
 lazy_static! {
     /// Contains functions that can create negotiators by name.
@@ -210,6 +360,7 @@ pub fn register_negotiator_impl(
 pub fn create_negotiator(
     name: RStr,
     config: RStr,
+    agent_env: RStr,
     working_dir: RStr,
 ) -> RResult<BoxedSharedNegotiatorAPI, RString> {
     let map = match (*CONSTRUCTORS).lock() {
@@ -218,7 +369,7 @@ pub fn create_negotiator(
     };
 
     match map.get(name.as_str()) {
-        Some(constructor) => constructor(name, config, working_dir),
+        Some(constructor) => constructor(name, config, agent_env, working_dir),
         None => RErr(RString::from(format!("Negotiator '{}' not found.", name))),
     }
 }
@@ -226,13 +377,13 @@ pub fn create_negotiator(
 #[macro_export]
 macro_rules! register_negotiators_inner {
     ($NegotiatorType:ty) => {{
-        ya_negotiator_shared_lib_interface::plugin::register_negotiator_impl(stringify!($NegotiatorType), Box::new(|name, config, working_dir| {
-            ya_negotiator_shared_lib_interface::plugin::NegotiatorWrapper::<$NegotiatorType>::new(name, config, working_dir)
+        ya_negotiator_shared_lib_interface::plugin::register_negotiator_impl(stringify!($NegotiatorType), Box::new(|name, config, agent_env, working_dir| {
+            ya_negotiator_shared_lib_interface::plugin::NegotiatorWrapper::<$NegotiatorType>::new(name, config, agent_env, working_dir)
         })).unwrap();
     }};
     ($NegotiatorType:ty, $($Rest:ty),+) => {
-        ya_negotiator_shared_lib_interface::register_negotiators_inner!($NegotiatorType)
-        ya_negotiator_shared_lib_interface::register_negotiators_inner!($($Rest),+)
+        ya_negotiator_shared_lib_interface::register_negotiators_inner!($NegotiatorType);
+        ya_negotiator_shared_lib_interface::register_negotiators_inner!($($Rest),+);
     };
 }
 
@@ -244,7 +395,8 @@ macro_rules! register_negotiators {
             ya_negotiator_shared_lib_interface::register_negotiators_inner!($($NegotiatorTypes),+);
 
             ya_negotiator_shared_lib_interface::interface::NegotiatorLib {
-                create_negotiator: ya_negotiator_shared_lib_interface::plugin::create_negotiator
+                create_negotiator: ya_negotiator_shared_lib_interface::plugin::create_negotiator,
+                api_version: ya_negotiator_shared_lib_interface::interface::API_VERSION,
             }.leak_into_prefix()
         }
     };
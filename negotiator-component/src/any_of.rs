@@ -0,0 +1,314 @@
+use std::time::Duration;
+
+use ya_agreement_utils::{AgreementView, OfferTemplate, ProposalView};
+
+use crate::component::{
+    AgreementEvent, AgreementResult, ComponentDescription, NegotiationResult, NegotiatorComponent,
+    ProposalScore, ReevaluationHandle, Score,
+};
+use crate::correlated_log;
+use crate::reason::RejectReason;
+
+/// Accepts Proposal, if any of alternative components accepts it. Contrary to
+/// `NegotiatorsPack`, which requires all components to agree, `AnyOf` returns
+/// result of the first child returning `Ready` or `Accept`, together with its score.
+///
+/// If no child is ready, result of first child still negotiating is returned.
+/// Proposal is rejected only if all children reject it. Rejection is final only,
+/// if all children rejected Proposal finally. Child failing to evaluate Proposal
+/// is treated as non-finally rejecting alternative.
+pub struct AnyOf {
+    children: Vec<Box<dyn NegotiatorComponent>>,
+}
+
+impl AnyOf {
+    pub fn new(children: Vec<Box<dyn NegotiatorComponent>>) -> AnyOf {
+        AnyOf { children }
+    }
+
+    fn step(
+        &mut self,
+        their: &ProposalView,
+        history: &[ProposalView],
+        template: ProposalView,
+        score: Score,
+        dry_run: bool,
+    ) -> anyhow::Result<NegotiationResult> {
+        let mut negotiating = None;
+        let mut reasons = vec![];
+        let mut all_final = true;
+
+        for child in &mut self.children {
+            let result = match dry_run {
+                true => child.dry_run_step(their, history, template.clone(), score.clone()),
+                false => child.negotiate_step_with_history(
+                    their,
+                    history,
+                    template.clone(),
+                    score.clone(),
+                ),
+            };
+            let result = match result {
+                Ok(result) => result,
+                Err(e) => {
+                    correlated_log!(
+                        log::Level::Warn,
+                        "AnyOf child failed evaluating Proposal [{}]. {e}",
+                        their.id,
+                    );
+                    all_final = false;
+                    reasons.push(RejectReason::new(format!("Alternative failed: {e}")));
+                    continue;
+                }
+            };
+            match result {
+                result @ NegotiationResult::Ready { .. }
+                | result @ NegotiationResult::Accept { .. } => return Ok(result),
+                result @ NegotiationResult::Negotiating { .. } => {
+                    negotiating.get_or_insert(result);
+                }
+                NegotiationResult::Reject { reason, is_final } => {
+                    all_final &= is_final;
+                    reasons.push(reason);
+                }
+            }
+        }
+
+        Ok(match negotiating {
+            Some(result) => result,
+            None => NegotiationResult::Reject {
+                reason: combine_reasons(reasons),
+                is_final: all_final,
+            },
+        })
+    }
+}
+
+/// Combines rejections of all children into single reason. Individual reasons
+/// are available under `reasons` key.
+fn combine_reasons(reasons: Vec<RejectReason>) -> RejectReason {
+    let message = reasons
+        .iter()
+        .map(|reason| reason.message.as_str())
+        .collect::<Vec<_>>()
+        .join("; ");
+    RejectReason::new(format!("All alternatives rejected Proposal: {}", message)).entry(
+        "reasons",
+        serde_json::to_value(&reasons).unwrap_or(serde_json::Value::Null),
+    )
+}
+
+impl NegotiatorComponent for AnyOf {
+    fn negotiate_step(
+        &mut self,
+        their: &ProposalView,
+        template: ProposalView,
+        score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        self.negotiate_step_with_history(their, &[], template, score)
+    }
+
+    fn negotiate_step_with_history(
+        &mut self,
+        their: &ProposalView,
+        history: &[ProposalView],
+        template: ProposalView,
+        score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        self.step(their, history, template, score, false)
+    }
+
+    fn dry_run_step(
+        &mut self,
+        their: &ProposalView,
+        history: &[ProposalView],
+        template: ProposalView,
+        score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        self.step(their, history, template, score, true)
+    }
+
+    fn rescore_batch(&mut self, proposals: &mut [ProposalScore]) -> anyhow::Result<()> {
+        for child in &mut self.children {
+            child.rescore_batch(proposals)?;
+        }
+        Ok(())
+    }
+
+    /// All alternatives must be able to negotiate, so each of them fills template.
+    fn fill_template(&mut self, mut template: OfferTemplate) -> anyhow::Result<OfferTemplate> {
+        for child in &mut self.children {
+            template = child.fill_template(template)?;
+        }
+        Ok(template)
+    }
+
+    fn on_offer_published(&mut self, offer: &OfferTemplate) -> anyhow::Result<()> {
+        for child in &mut self.children {
+            child
+                .on_offer_published(offer)
+                .map_err(|e| {
+                    correlated_log!(
+                        log::Level::Warn,
+                        "AnyOf child failed handling published Offer. {e}"
+                    )
+                })
+                .ok();
+        }
+        Ok(())
+    }
+
+    fn on_agreement_terminated(
+        &mut self,
+        agreement_id: &str,
+        result: &AgreementResult,
+    ) -> anyhow::Result<()> {
+        for child in &mut self.children {
+            child
+                .on_agreement_terminated(agreement_id, result)
+                .map_err(|e| {
+                    correlated_log!(
+                        log::Level::Warn,
+                        "AnyOf child failed handling Agreement [{agreement_id}] termination. {e}"
+                    )
+                })
+                .ok();
+        }
+        Ok(())
+    }
+
+    fn on_agreement_approved(&mut self, agreement: &AgreementView) -> anyhow::Result<()> {
+        for child in &mut self.children {
+            child
+                .on_agreement_approved(agreement)
+                .map_err(|e| {
+                    correlated_log!(
+                        log::Level::Warn,
+                        "AnyOf child failed handling Agreement [{}] approval. {e}",
+                        agreement.id,
+                    )
+                })
+                .ok();
+        }
+        Ok(())
+    }
+
+    fn on_proposal_rejected(&mut self, proposal_id: &str) -> anyhow::Result<()> {
+        for child in &mut self.children {
+            child
+                .on_proposal_rejected(proposal_id)
+                .map_err(|e| {
+                    correlated_log!(
+                        log::Level::Warn,
+                        "AnyOf child failed handling Proposal [{proposal_id}] rejection. {e}",
+                    )
+                })
+                .ok();
+        }
+        Ok(())
+    }
+
+    fn on_agreement_event(
+        &mut self,
+        agreement_id: &str,
+        event: &AgreementEvent,
+    ) -> anyhow::Result<()> {
+        for child in &mut self.children {
+            child
+                .on_agreement_event(agreement_id, event)
+                .map_err(|e| {
+                    correlated_log!(
+                        log::Level::Warn,
+                        "AnyOf child failed handling post Terminate event [{agreement_id}]. {e}",
+                    )
+                })
+                .ok();
+        }
+        Ok(())
+    }
+
+    /// Event is forwarded to all children. Their responses are returned in order
+    /// of alternatives.
+    fn control_event(
+        &mut self,
+        component: &str,
+        params: serde_json::Value,
+    ) -> anyhow::Result<serde_json::Value> {
+        Ok(serde_json::Value::Array(
+            self.children
+                .iter_mut()
+                .map(|child| child.control_event(component, params.clone()))
+                .collect::<anyhow::Result<_>>()?,
+        ))
+    }
+
+    /// Combines descriptions of children. Schemas of their configs are listed
+    /// under `anyOf` in order of alternatives.
+    fn describe(&self) -> Option<ComponentDescription> {
+        let descriptions = self
+            .children
+            .iter()
+            .map(|child| child.describe())
+            .collect::<Vec<_>>();
+        if descriptions.iter().all(Option::is_none) {
+            return None;
+        }
+
+        let description = descriptions
+            .iter()
+            .flatten()
+            .map(|description| description.description.as_str())
+            .collect::<Vec<_>>()
+            .join("; ");
+        let schemas = descriptions
+            .iter()
+            .map(|description| {
+                description
+                    .as_ref()
+                    .map(|description| description.config_schema.clone())
+                    .unwrap_or(serde_json::Value::Null)
+            })
+            .collect::<Vec<_>>();
+        Some(ComponentDescription {
+            description: format!("Any of alternatives: {description}"),
+            config_schema: serde_json::json!({ "anyOf": schemas }),
+        })
+    }
+
+    /// States of children are kept in order of alternatives.
+    fn serialize_state(&self) -> anyhow::Result<serde_json::Value> {
+        Ok(serde_json::Value::Array(
+            self.children
+                .iter()
+                .map(|child| child.serialize_state())
+                .collect::<anyhow::Result<_>>()?,
+        ))
+    }
+
+    fn restore_state(&mut self, state: serde_json::Value) -> anyhow::Result<()> {
+        let states = match state {
+            serde_json::Value::Array(states) => states,
+            _ => return Ok(()),
+        };
+        for (child, state) in self.children.iter_mut().zip(states) {
+            child.restore_state(state)?;
+        }
+        Ok(())
+    }
+
+    fn set_reevaluation_handle(&mut self, handle: ReevaluationHandle) {
+        for child in &mut self.children {
+            child.set_reevaluation_handle(handle.clone());
+        }
+    }
+
+    fn shutdown(&mut self, timeout: Duration) -> anyhow::Result<()> {
+        for child in &mut self.children {
+            child
+                .shutdown(timeout)
+                .map_err(|e| log::warn!("AnyOf child failed to shutdown. {e}"))
+                .ok();
+        }
+        Ok(())
+    }
+}
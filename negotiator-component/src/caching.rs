@@ -0,0 +1,307 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use serde_json::{Map, Value};
+
+use ya_agreement_utils::agreement::flatten;
+use ya_agreement_utils::{AgreementView, OfferTemplate, PropertyChange, ProposalView};
+
+use crate::component::{
+    AgreementEvent, AgreementResult, ComponentDescription, NegotiationResult, NegotiatorComponent,
+    ProposalScore, ReevaluationHandle, Score,
+};
+use crate::reason::RejectReason;
+
+/// Caches results of wrapped component, which decision depends only on chosen
+/// properties of incoming Proposal, for example subnet or node name. Useful for
+/// components communicating with external process or shared library, when many
+/// Proposals have the same values of these properties.
+///
+/// Cache is keyed by values under `pointers` in their Proposal. Cached result keeps
+/// only changes, that wrapped component made to our template and to the score, so
+/// the same changes are applied to any template on cache hit.
+/// Entries expire after `ttl` and expired entries are swept on each insert.
+/// The whole cache is dropped, when new Offer is published,
+/// Agreement is approved or terminated, or component is reconfigured, since wrapped
+/// component's decisions could change afterwards.
+pub struct CachingComponent {
+    inner: Box<dyn NegotiatorComponent>,
+    pointers: Vec<String>,
+    ttl: Duration,
+    cache: HashMap<String, (Instant, CachedResult)>,
+}
+
+#[derive(Clone, Copy)]
+enum ResultKind {
+    Ready,
+    Accept,
+    Negotiating,
+}
+
+#[derive(Clone)]
+enum CachedResult {
+    Proposal {
+        changes: Vec<PropertyChange>,
+        score: Map<String, Value>,
+        removed_score: Vec<String>,
+        kind: ResultKind,
+    },
+    Reject {
+        reason: RejectReason,
+        is_final: bool,
+    },
+}
+
+impl CachingComponent {
+    pub fn new(
+        inner: Box<dyn NegotiatorComponent>,
+        pointers: Vec<String>,
+        ttl: Duration,
+    ) -> CachingComponent {
+        CachingComponent {
+            inner,
+            pointers,
+            ttl,
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Drops all cached results.
+    pub fn invalidate(&mut self) {
+        self.cache.clear();
+    }
+
+    fn cache_key(&self, their: &ProposalView) -> String {
+        Value::Array(
+            self.pointers
+                .iter()
+                .map(|pointer| their.pointer(pointer).cloned().unwrap_or(Value::Null))
+                .collect(),
+        )
+        .to_string()
+    }
+
+    fn insert(&mut self, key: String, result: CachedResult) {
+        let ttl = self.ttl;
+        self.cache
+            .retain(|_, (inserted, _)| inserted.elapsed() < ttl);
+        self.cache.insert(key, (Instant::now(), result));
+    }
+
+    fn cached(&mut self, key: &str) -> Option<CachedResult> {
+        match self.cache.get(key) {
+            Some((inserted, result)) if inserted.elapsed() < self.ttl => Some(result.clone()),
+            Some(_) => {
+                self.cache.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+}
+
+impl CachedResult {
+    fn new(template: &ProposalView, score: &Score, result: &NegotiationResult) -> CachedResult {
+        let (proposal, new_score, kind) = match result {
+            NegotiationResult::Ready { proposal, score } => (proposal, score, ResultKind::Ready),
+            NegotiationResult::Accept { proposal, score } => (proposal, score, ResultKind::Accept),
+            NegotiationResult::Negotiating { proposal, score } => {
+                (proposal, score, ResultKind::Negotiating)
+            }
+            NegotiationResult::Reject { reason, is_final } => {
+                return CachedResult::Reject {
+                    reason: reason.clone(),
+                    is_final: *is_final,
+                }
+            }
+        };
+
+        let previous = flatten(score.properties.clone());
+        let new_score = flatten(new_score.properties.clone());
+        CachedResult::Proposal {
+            changes: template.content.diff(&proposal.content),
+            removed_score: previous
+                .keys()
+                .filter(|key| !new_score.contains_key(*key))
+                .cloned()
+                .collect(),
+            score: new_score
+                .into_iter()
+                .filter(|(key, value)| previous.get(key) != Some(value))
+                .collect(),
+            kind,
+        }
+    }
+
+    fn apply(
+        self,
+        mut template: ProposalView,
+        mut score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        let (changes, score_changes, removed_score, kind) = match self {
+            CachedResult::Reject { reason, is_final } => {
+                return Ok(NegotiationResult::Reject { reason, is_final })
+            }
+            CachedResult::Proposal {
+                changes,
+                score,
+                removed_score,
+                kind,
+            } => (changes, score, removed_score, kind),
+        };
+
+        for change in changes {
+            match change {
+                PropertyChange::Added { pointer, value }
+                | PropertyChange::Changed {
+                    pointer,
+                    new: value,
+                    ..
+                } => template.set_property(&pointer, value)?,
+                PropertyChange::Removed { pointer, .. } => {
+                    template.remove_property(&pointer).ok();
+                }
+            }
+        }
+        for key in removed_score {
+            score.remove_property(&key);
+        }
+        for (key, value) in score_changes {
+            score.set_property(key, value);
+        }
+
+        Ok(match kind {
+            ResultKind::Ready => NegotiationResult::Ready {
+                proposal: template,
+                score,
+            },
+            ResultKind::Accept => NegotiationResult::Accept {
+                proposal: template,
+                score,
+            },
+            ResultKind::Negotiating => NegotiationResult::Negotiating {
+                proposal: template,
+                score,
+            },
+        })
+    }
+}
+
+impl NegotiatorComponent for CachingComponent {
+    fn negotiate_step(
+        &mut self,
+        their: &ProposalView,
+        template: ProposalView,
+        score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        self.negotiate_step_with_history(their, &[], template, score)
+    }
+
+    /// Wrapped component shouldn't depend on `history`, otherwise cached results
+    /// would be wrong.
+    fn negotiate_step_with_history(
+        &mut self,
+        their: &ProposalView,
+        history: &[ProposalView],
+        template: ProposalView,
+        score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        let key = self.cache_key(their);
+        if let Some(cached) = self.cached(&key) {
+            return cached.apply(template, score);
+        }
+
+        let result = self.inner.negotiate_step_with_history(
+            their,
+            history,
+            template.clone(),
+            score.clone(),
+        )?;
+        self.insert(key, CachedResult::new(&template, &score, &result));
+        Ok(result)
+    }
+
+    /// Uses cached result, but doesn't cache result of wrapped component.
+    fn dry_run_step(
+        &mut self,
+        their: &ProposalView,
+        history: &[ProposalView],
+        template: ProposalView,
+        score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        let key = self.cache_key(their);
+        if let Some(cached) = self.cached(&key) {
+            return cached.apply(template, score);
+        }
+        self.inner.dry_run_step(their, history, template, score)
+    }
+
+    fn rescore_batch(&mut self, proposals: &mut [ProposalScore]) -> anyhow::Result<()> {
+        self.inner.rescore_batch(proposals)
+    }
+
+    fn fill_template(&mut self, template: OfferTemplate) -> anyhow::Result<OfferTemplate> {
+        self.inner.fill_template(template)
+    }
+
+    fn on_offer_published(&mut self, offer: &OfferTemplate) -> anyhow::Result<()> {
+        self.invalidate();
+        self.inner.on_offer_published(offer)
+    }
+
+    fn on_agreement_terminated(
+        &mut self,
+        agreement_id: &str,
+        result: &AgreementResult,
+    ) -> anyhow::Result<()> {
+        self.invalidate();
+        self.inner.on_agreement_terminated(agreement_id, result)
+    }
+
+    fn on_agreement_approved(&mut self, agreement: &AgreementView) -> anyhow::Result<()> {
+        self.invalidate();
+        self.inner.on_agreement_approved(agreement)
+    }
+
+    fn on_proposal_rejected(&mut self, proposal_id: &str) -> anyhow::Result<()> {
+        self.inner.on_proposal_rejected(proposal_id)
+    }
+
+    fn on_agreement_event(
+        &mut self,
+        agreement_id: &str,
+        event: &AgreementEvent,
+    ) -> anyhow::Result<()> {
+        self.inner.on_agreement_event(agreement_id, event)
+    }
+
+    fn control_event(&mut self, component: &str, params: Value) -> anyhow::Result<Value> {
+        self.inner.control_event(component, params)
+    }
+
+    fn reconfigure(&mut self, config: serde_yaml::Value) -> anyhow::Result<()> {
+        self.invalidate();
+        self.inner.reconfigure(config)
+    }
+
+    fn describe(&self) -> Option<ComponentDescription> {
+        self.inner.describe()
+    }
+
+    fn serialize_state(&self) -> anyhow::Result<Value> {
+        self.inner.serialize_state()
+    }
+
+    fn restore_state(&mut self, state: Value) -> anyhow::Result<()> {
+        self.invalidate();
+        self.inner.restore_state(state)
+    }
+
+    fn set_reevaluation_handle(&mut self, handle: ReevaluationHandle) {
+        self.inner.set_reevaluation_handle(handle)
+    }
+
+    fn shutdown(&mut self, timeout: Duration) -> anyhow::Result<()> {
+        self.inner.shutdown(timeout)
+    }
+}
@@ -1,4 +1,7 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
 
 use crate::reason::RejectReason;
 use ya_agreement_utils::{AgreementView, OfferTemplate, ProposalView};
@@ -14,13 +17,93 @@ use ya_client_model::market::Reason;
 /// as he wants. Each component defines himself under what names these scores will be placed in
 /// `Score` structure (so namespacing is encouraged).
 /// Property `final-score` has special meaning and will be used by `CompositeNegotiator` to
-/// choose between Proposals and Agreements.
+/// choose between Proposals and Agreements. Property used for each of them can be changed
+/// with `CollectionConfig::score_pointer`. Agreements are evaluated with `Accepted` Proposals,
+/// so components can check `their.state` to compute separate Agreement-phase score.
 ///
 /// We use the same structure for scoring Proposals as for negotiating
 /// them. We don't need constraints part here, but this structure has many utils
 /// useful for properties manipulation, that I don't want to duplicate its functionality.
 pub type Score = OfferTemplate;
 
+/// Fully negotiated Proposal waiting in collection for decision.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProposalScore {
+    pub their: ProposalView,
+    pub our: ProposalView,
+    /// Value read from `Score` with collection score pointer. Proposals with
+    /// the highest values are chosen.
+    pub score: f64,
+    /// Numeric values set by scoring components. See `Score::breakdown`.
+    pub breakdown: HashMap<String, f64>,
+}
+
+/// `control_event` params asking component to describe its internal state.
+/// Components, which don't handle this query, return `Null`.
+pub fn diagnostics_query() -> serde_json::Value {
+    serde_json::json!({ "query": "diagnostics" })
+}
+
+/// Checks if `control_event` params are diagnostics query. See `diagnostics_query`.
+pub fn is_diagnostics_query(params: &serde_json::Value) -> bool {
+    params.get("query").and_then(|query| query.as_str()) == Some("diagnostics")
+}
+
+/// `control_event` params asking component to replace its config with `config`.
+/// `NegotiatorsPack` handles them by calling `NegotiatorComponent::reconfigure`.
+pub fn reconfigure_request(config: serde_yaml::Value) -> anyhow::Result<serde_json::Value> {
+    Ok(serde_json::json!({
+        "action": "reconfigure",
+        "config": serde_json::to_value(config)?,
+    }))
+}
+
+/// Extracts new config from `control_event` params, if they are reconfigure
+/// request. See `reconfigure_request`.
+pub fn reconfigure_config(params: &serde_json::Value) -> Option<serde_yaml::Value> {
+    if params.get("action").and_then(|action| action.as_str()) != Some("reconfigure") {
+        return None;
+    }
+    params
+        .get("config")
+        .and_then(|config| serde_yaml::to_value(config).ok())
+}
+
+/// Human-readable information about component, returned by `NegotiatorComponent::describe`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ComponentDescription {
+    pub description: String,
+    /// JSON schema of component config.
+    pub config_schema: serde_json::Value,
+}
+
+/// Asks Negotiator to evaluate Proposals again. Component can send it, when Proposals
+/// rejected by it with `is_final` set to false could be accepted now. Only Proposals
+/// parked by Negotiator after such rejection are re-evaluated, other ids are ignored.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ReevaluationRequest {
+    pub proposal_ids: Vec<String>,
+}
+
+/// Sends `ReevaluationRequest` to Negotiator. Negotiator passes handle to components
+/// after creating them. See `NegotiatorComponent::set_reevaluation_handle`.
+#[derive(Clone)]
+pub struct ReevaluationHandle {
+    sender: Arc<dyn Fn(ReevaluationRequest) + Send + Sync>,
+}
+
+impl ReevaluationHandle {
+    pub fn new(sender: impl Fn(ReevaluationRequest) + Send + Sync + 'static) -> ReevaluationHandle {
+        ReevaluationHandle {
+            sender: Arc::new(sender),
+        }
+    }
+
+    pub fn reevaluate(&self, proposal_ids: Vec<String>) {
+        (self.sender)(ReevaluationRequest { proposal_ids })
+    }
+}
+
 /// Result returned by `NegotiatorComponent` during Proposals evaluation.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub enum NegotiationResult {
@@ -54,6 +137,72 @@ pub enum NegotiationResult {
         reason: RejectReason,
         is_final: bool,
     },
+    /// The same as `Ready`, but additionally `NegotiatorComponent` explicitly asks
+    /// to promote Proposal to Agreement, instead of leaving this decision to Negotiator.
+    /// Without `explicit_accept` enabled in Negotiator config, there is no difference
+    /// between `Accept` and `Ready`. Component can't change Proposal, when returning it.
+    Accept {
+        proposal: ProposalView,
+        score: Score,
+    },
+}
+
+/// Builds `NegotiationResult` from our previous Proposal and changes, that we want
+/// to make in counter Proposal, for example:
+/// ```ignore
+/// CounterOffer::new(template, score)
+///     .set("/golem/com/pricing/model/linear/coeffs/0", json!(0.01))
+///     .build()
+/// ```
+/// Result is `Negotiating` if changes modified the template, otherwise `Ready`.
+pub struct CounterOffer {
+    template: ProposalView,
+    score: Score,
+    changes: Vec<(String, Option<serde_json::Value>)>,
+}
+
+impl CounterOffer {
+    pub fn new(template: ProposalView, score: Score) -> CounterOffer {
+        CounterOffer {
+            template,
+            score,
+            changes: vec![],
+        }
+    }
+
+    pub fn set(mut self, pointer: &str, value: serde_json::Value) -> CounterOffer {
+        self.changes.push((pointer.to_string(), Some(value)));
+        self
+    }
+
+    pub fn remove(mut self, pointer: &str) -> CounterOffer {
+        self.changes.push((pointer.to_string(), None));
+        self
+    }
+
+    pub fn build(self) -> anyhow::Result<NegotiationResult> {
+        let mut proposal = self.template.clone();
+        for (pointer, value) in self.changes {
+            match value {
+                Some(value) => proposal.set_property(&pointer, value)?,
+                // Removing not existing property doesn't change anything.
+                None => {
+                    proposal.remove_property(&pointer).ok();
+                }
+            }
+        }
+
+        Ok(match proposal == self.template {
+            true => NegotiationResult::Ready {
+                proposal,
+                score: self.score,
+            },
+            false => NegotiationResult::Negotiating {
+                proposal,
+                score: self.score,
+            },
+        })
+    }
 }
 
 /// Result of agreement execution.
@@ -84,6 +233,17 @@ pub enum AgreementEvent {
     /// Could be partially paid??
     InvoicePaid,
     InvoiceRejected,
+    /// Debit Note with total `amount` due was accepted.
+    DebitNoteAccepted {
+        amount: f64,
+    },
+    DebitNoteRejected,
+    /// Part of the Invoice was paid. `amount` is the sum paid so far
+    /// and `total` is the whole amount due.
+    PartialPayment {
+        amount: f64,
+        total: f64,
+    },
     /// Provider/Requestor is unreachable, so we can't send terminate Agreement.
     UnableToTerminate,
     ComputationFailure(serde_json::Value),
@@ -120,12 +280,73 @@ pub trait NegotiatorComponent {
         })
     }
 
+    /// The same as `negotiate_step`, but additionally gets `history` of Proposals, that
+    /// other party sent us in previous rounds of this negotiation, the oldest first.
+    /// Components, which need to know how negotiations progress (for example to detect,
+    /// that other party keeps lowering the price), should override this function.
+    /// By default history is ignored and `negotiate_step` is called.
+    fn negotiate_step_with_history(
+        &mut self,
+        their: &ProposalView,
+        _history: &[ProposalView],
+        template: ProposalView,
+        score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        self.negotiate_step(their, template, score)
+    }
+
+    /// Evaluates Proposal the same way as `negotiate_step_with_history`, but without
+    /// changing component state, for example counters or reservations. Used for dry
+    /// runs, which mustn't influence real negotiations. Stateful components should
+    /// override this function. By default `negotiate_step_with_history` is called.
+    fn dry_run_step(
+        &mut self,
+        their: &ProposalView,
+        history: &[ProposalView],
+        template: ProposalView,
+        score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        self.negotiate_step_with_history(their, history, template, score)
+    }
+
+    /// Evaluates many Proposals at once. Each item consists of Proposal that we got
+    /// from other party, its history, our template and score, the same as in
+    /// `negotiate_step_with_history`. Results are returned in the same order as items.
+    ///
+    /// Components communicating with negotiation logic outside of this process
+    /// should override this function to handle all items in a single round-trip.
+    fn negotiate_batch(
+        &mut self,
+        items: Vec<(ProposalView, Vec<ProposalView>, ProposalView, Score)>,
+    ) -> Vec<anyhow::Result<NegotiationResult>> {
+        items
+            .into_iter()
+            .map(|(their, history, template, score)| {
+                self.negotiate_step_with_history(&their, &history, template, score)
+            })
+            .collect()
+    }
+
+    /// Called with all Proposals collected in current period, just before choosing
+    /// the best of them. Allows scoring relative to the whole batch, for example
+    /// normalizing prices to percentiles. Proposals are sorted again afterwards.
+    fn rescore_batch(&mut self, _proposals: &mut [ProposalScore]) -> anyhow::Result<()> {
+        Ok(())
+    }
+
     /// Called during Offer/Demand creation. `NegotiatorComponent` should add properties
     /// and constraints for which it is responsible during future negotiations.
     fn fill_template(&mut self, template: OfferTemplate) -> anyhow::Result<OfferTemplate> {
         Ok(template)
     }
 
+    /// Called after all components filled template, with Offer/Demand, that will be
+    /// published. `NegotiatorComponent` can remember it as a baseline for future
+    /// negotiations. It's only notification, Offer can't be changed anymore.
+    fn on_offer_published(&mut self, _offer: &OfferTemplate) -> anyhow::Result<()> {
+        Ok(())
+    }
+
     /// Called when Agreement was finished. `NegotiatorComponent` can use termination
     /// result to adjust his future negotiation strategy.
     fn on_agreement_terminated(
@@ -143,7 +364,9 @@ pub trait NegotiatorComponent {
         Ok(())
     }
 
-    /// Called when other party rejects our Proposal.
+    /// Called when other party rejects our Proposal. Also called with id of their Proposal,
+    /// when Negotiator rejects Agreement created from it, so components can release
+    /// resources reserved for this Agreement.
     /// TODO: We should call this, if any of our components rejected Proposal either.
     ///       Add flag that will indicate who rejected.
     /// TODO: Add Reason parameter.
@@ -164,6 +387,9 @@ pub trait NegotiatorComponent {
     /// Allows to control `NegotiatorComponent's` behavior or query any information
     /// from it. Thanks to this event Requestor/Provider implementation can interact with
     /// `NegotiatorComponents`.
+    ///
+    /// Components should respond to `diagnostics_query()` params with description
+    /// of their internal state, which will be included in Negotiator diagnostic dump.
     fn control_event(
         &mut self,
         _component: &str,
@@ -171,4 +397,39 @@ pub trait NegotiatorComponent {
     ) -> anyhow::Result<serde_json::Value> {
         Ok(serde_json::Value::Null)
     }
+
+    /// Replaces component config at runtime, without restarting Negotiator.
+    /// Called, when `control_event` with `reconfigure_request` params is sent
+    /// to this component. Components aren't reconfigurable by default.
+    fn reconfigure(&mut self, _config: serde_yaml::Value) -> anyhow::Result<()> {
+        anyhow::bail!("Negotiator component is not reconfigurable.")
+    }
+
+    /// Describes component for agents inspecting loaded negotiators at runtime.
+    /// Components aren't obliged to describe themselves.
+    fn describe(&self) -> Option<ComponentDescription> {
+        None
+    }
+
+    /// Returns component state, which should survive Negotiator restart.
+    /// Stateless components return `Null`.
+    fn serialize_state(&self) -> anyhow::Result<serde_json::Value> {
+        Ok(serde_json::Value::Null)
+    }
+
+    /// Restores state returned by `serialize_state` of component with the same config.
+    /// Called on freshly created component, before any negotiations.
+    fn restore_state(&mut self, _state: serde_json::Value) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Called once, when Negotiator is created. Components rejecting Proposals only
+    /// temporarily, can keep handle to request their re-evaluation later.
+    fn set_reevaluation_handle(&mut self, _handle: ReevaluationHandle) {}
+
+    /// Called before Negotiator is destroyed. `NegotiatorComponent` should flush
+    /// its state and free resources. It shouldn't take longer than `timeout`.
+    fn shutdown(&mut self, _timeout: Duration) -> anyhow::Result<()> {
+        Ok(())
+    }
 }
@@ -0,0 +1,43 @@
+use std::cell::RefCell;
+
+thread_local! {
+    static CORRELATION_ID: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Sets correlation id for current thread until dropped. Previous id
+/// is restored afterwards, so scopes can be nested.
+#[must_use]
+pub struct CorrelationScope {
+    prev: Option<String>,
+}
+
+/// Marks all messages logged with `correlated_log!` until returned scope is dropped
+/// with Proposal or Agreement id. This allows to filter logs of single negotiation
+/// passing through Negotiator, collections and components.
+pub fn correlate(id: &str) -> CorrelationScope {
+    let prev = CORRELATION_ID.with(|current| current.replace(Some(id.to_string())));
+    CorrelationScope { prev }
+}
+
+/// Id of Proposal or Agreement processed by current thread.
+pub fn correlation_id() -> Option<String> {
+    CORRELATION_ID.with(|current| current.borrow().clone())
+}
+
+impl Drop for CorrelationScope {
+    fn drop(&mut self) {
+        let prev = self.prev.take();
+        CORRELATION_ID.with(|current| *current.borrow_mut() = prev);
+    }
+}
+
+/// Works like `log::log!`, but prefixes message with current correlation id.
+#[macro_export]
+macro_rules! correlated_log {
+    ($level:expr, $($arg:tt)+) => {
+        match $crate::correlation::correlation_id() {
+            Some(id) => ::log::log!($level, "[correlation-id={}] {}", id, format_args!($($arg)+)),
+            None => ::log::log!($level, $($arg)+),
+        }
+    };
+}
@@ -1,10 +1,22 @@
+mod any_of;
+mod caching;
 pub mod component;
+pub mod correlation;
+pub mod metrics;
 mod pack;
 pub mod reason;
+pub mod scoring;
 pub mod static_lib;
 
+pub use any_of::AnyOf;
+pub use caching::CachingComponent;
 pub use component::{
-    AgreementEvent, AgreementResult, NegotiationResult, NegotiatorComponent, Score,
+    AgreementEvent, AgreementResult, ComponentDescription, CounterOffer, NegotiationResult,
+    NegotiatorComponent, ProposalScore, ReevaluationHandle, ReevaluationRequest, Score,
+};
+pub use pack::{
+    unique_name, ErrorPolicy, NegotiatorsPack, RejectPolicy, TemplateConflictPolicy,
+    COMPONENT_ERROR, PACK_COMPONENT,
 };
-pub use pack::NegotiatorsPack;
 pub use reason::RejectReason;
+pub use scoring::{namespaced_score, ScoringAdapter, ScoringComponent};
@@ -0,0 +1,65 @@
+use std::time::Duration;
+
+use crate::component::NegotiationResult;
+
+/// Outcome of single `negotiate_step` call of component.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepOutcome {
+    Ready,
+    Accepted,
+    Negotiating,
+    Rejected,
+    Failed,
+}
+
+impl StepOutcome {
+    pub fn from_result(result: &anyhow::Result<NegotiationResult>) -> StepOutcome {
+        match result {
+            Ok(NegotiationResult::Ready { .. }) => StepOutcome::Ready,
+            Ok(NegotiationResult::Accept { .. }) => StepOutcome::Accepted,
+            Ok(NegotiationResult::Negotiating { .. }) => StepOutcome::Negotiating,
+            Ok(NegotiationResult::Reject { .. }) => StepOutcome::Rejected,
+            Err(_) => StepOutcome::Failed,
+        }
+    }
+}
+
+/// Sink for negotiation metrics. Implement it to pass negotiator throughput
+/// and latency to monitoring system. All functions do nothing by default.
+pub trait Metrics: Send + Sync {
+    /// Called after each component finished evaluating Proposal.
+    fn on_negotiate_step_done(&self, _component: &str, _duration: Duration, _outcome: StepOutcome) {
+    }
+
+    /// Called when collection decided, which Proposals or Agreements to accept.
+    /// `collection` is either `Proposal` or `Agreement`.
+    fn on_decision(&self, _collection: &str, _accepted: usize, _rejected: usize) {}
+}
+
+/// Ignores all metrics.
+pub struct NoMetrics;
+
+impl Metrics for NoMetrics {}
+
+/// Writes metrics to log on debug level.
+pub struct LogMetrics;
+
+impl Metrics for LogMetrics {
+    fn on_negotiate_step_done(&self, component: &str, duration: Duration, outcome: StepOutcome) {
+        log::debug!(
+            "Negotiator component '{}' finished step in {:?} with outcome: {:?}.",
+            component,
+            duration,
+            outcome
+        );
+    }
+
+    fn on_decision(&self, collection: &str, accepted: usize, rejected: usize) {
+        log::debug!(
+            "{} collection decision: accepted {}, rejected {}.",
+            collection,
+            accepted,
+            rejected
+        );
+    }
+}
@@ -1,98 +1,647 @@
-use anyhow::anyhow;
+use anyhow::{anyhow, bail};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use ya_agreement_utils::{AgreementView, OfferTemplate, ProposalView};
+use ya_agreement_utils::{AgreementView, OfferTemplate, PropertyChange, ProposalView};
 
 use crate::component::{
-    AgreementEvent, AgreementResult, NegotiationResult, NegotiatorComponent, Score,
+    reconfigure_config, AgreementEvent, AgreementResult, NegotiationResult, NegotiatorComponent,
+    ProposalScore, ReevaluationHandle, Score,
 };
+use crate::correlated_log;
+use crate::metrics::{Metrics, NoMetrics, StepOutcome};
+use crate::reason::RejectReason;
 
+/// Reserved component name. `control_event` sent to it returns list of components
+/// in the pack together with their descriptions.
+pub const PACK_COMPONENT: &str = "__chain";
+
+/// Code of rejection caused by component error with `ErrorPolicy::RejectOnError`.
+pub const COMPONENT_ERROR: &str = "COMPONENT_ERROR";
+
+/// Decides what happens, when component returns error from `negotiate_step`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    /// Error is returned from pack and Proposal isn't evaluated further.
+    FailFast,
+    /// Proposal is rejected with reason containing component name and error.
+    RejectOnError,
+    /// Error is logged and Proposal is passed unchanged to next component.
+    SkipComponent,
+}
+
+/// Decides what happens, when component in `fill_template` changes or removes
+/// property written by one of previous components. Properties present in template
+/// before calling the pack aren't tracked, so components can always replace them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplateConflictPolicy {
+    /// Value written by the last component is published.
+    LastWins,
+    /// `fill_template` fails, unless component was allowed to override values
+    /// with `NegotiatorsPack::allow_override`.
+    ErrorOnConflict,
+}
+
+/// Decides what happens, when component rejects Proposal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectPolicy {
+    /// Reason of the first rejecting component is returned. Next components
+    /// aren't called.
+    FirstReject,
+    /// Remaining components are still called, so all rejection reasons are
+    /// reported together. Rejection is final, if any of them was final.
+    /// After the first rejection components are called with `dry_run_step`,
+    /// so stateful components don't count or reserve anything for rejected Proposal.
+    CollectAnyFinal,
+    /// Like `CollectAnyFinal`, but rejection is final only if all of them were final.
+    CollectAllFinal,
+}
+
+/// Passes Proposals through all components. Components are always called
+/// in the order, in which they were added, since each of them gets Proposal
+/// and score modified by the previous ones.
 pub struct NegotiatorsPack {
-    components: HashMap<String, Box<dyn NegotiatorComponent>>,
+    components: Vec<(String, Box<dyn NegotiatorComponent>)>,
+    /// Reject Proposals, if any component returned `Ready`, but changed
+    /// Proposal at the same time. Otherwise such situation is only logged.
+    strict_ready: bool,
+    error_policy: ErrorPolicy,
+    reject_policy: RejectPolicy,
+    conflict_policy: TemplateConflictPolicy,
+    /// Components allowed to replace properties written by previous components.
+    overrides: HashSet<String>,
+    metrics: Arc<dyn Metrics>,
 }
 
 impl NegotiatorsPack {
     pub fn new() -> NegotiatorsPack {
         NegotiatorsPack {
-            components: HashMap::new(),
+            components: Vec::new(),
+            strict_ready: false,
+            error_policy: ErrorPolicy::FailFast,
+            reject_policy: RejectPolicy::FirstReject,
+            conflict_policy: TemplateConflictPolicy::LastWins,
+            overrides: HashSet::new(),
+            metrics: Arc::new(NoMetrics),
         }
     }
 
+    pub fn strict_ready(mut self, strict: bool) -> NegotiatorsPack {
+        self.strict_ready = strict;
+        self
+    }
+
+    pub fn error_policy(mut self, policy: ErrorPolicy) -> NegotiatorsPack {
+        self.error_policy = policy;
+        self
+    }
+
+    pub fn reject_policy(mut self, policy: RejectPolicy) -> NegotiatorsPack {
+        self.reject_policy = policy;
+        self
+    }
+
+    pub fn conflict_policy(mut self, policy: TemplateConflictPolicy) -> NegotiatorsPack {
+        self.conflict_policy = policy;
+        self
+    }
+
+    /// Allows component to replace properties written by previous components
+    /// in `fill_template` regardless of `TemplateConflictPolicy`.
+    pub fn allow_override(mut self, name: &str) -> NegotiatorsPack {
+        self.overrides.insert(name.to_string());
+        self
+    }
+
+    /// Sink, which will be notified about each component `negotiate_step` call.
+    pub fn with_metrics(mut self, metrics: Arc<dyn Metrics>) -> NegotiatorsPack {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Adds component at the end of the pack. If component with the same name
+    /// already exists, name gets `#N` suffix, for example `AcceptAll#1`.
     pub fn add_component(
         mut self,
         name: &str,
         component: Box<dyn NegotiatorComponent>,
     ) -> NegotiatorsPack {
-        self.components.insert(name.to_string(), component);
+        let name = self.unique_name(name);
+        self.components.push((name, component));
         self
     }
-}
 
-impl NegotiatorComponent for NegotiatorsPack {
-    fn negotiate_step(
+    /// Names of all components in this pack in order of calling them.
+    pub fn component_names(&self) -> Vec<String> {
+        self.components
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// Empty pack accepts all Proposals unchanged.
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty()
+    }
+
+    fn contains(&self, name: &str) -> bool {
+        self.components.iter().any(|(existing, _)| existing == name)
+    }
+
+    /// Name, that component would get if added to pack with `name`.
+    pub fn unique_name(&self, name: &str) -> String {
+        unique_name(name, |candidate| self.contains(candidate))
+    }
+
+    /// Passes Proposal through all components. In `dry_run` and after Proposal
+    /// was rejected, components are only asked for dry run, so they don't change state.
+    fn step(
         &mut self,
         incoming_proposal: &ProposalView,
-        mut template: ProposalView,
-        mut score: Score,
+        history: &[ProposalView],
+        template: ProposalView,
+        score: Score,
+        dry_run: bool,
     ) -> anyhow::Result<NegotiationResult> {
-        let mut all_ready = true;
+        let mut step = PackStep::new(template, score, self.reject_policy);
         for (name, component) in &mut self.components {
-            let result = component.negotiate_step(incoming_proposal, template, score)?;
-            match result {
-                NegotiationResult::Ready {
-                    proposal: offer,
-                    score: new_score,
-                } => {
-                    template = offer;
-                    score = new_score;
-                }
-                NegotiationResult::Negotiating {
-                    proposal: offer,
-                    score: new_score,
-                } => {
-                    log::info!(
-                        "Negotiator component '{}' is still negotiating Proposal [{}].",
+            let result = if dry_run || step.is_rejected() {
+                component.dry_run_step(
+                    incoming_proposal,
+                    history,
+                    step.template.clone(),
+                    step.score.clone(),
+                )
+            } else {
+                let start = Instant::now();
+                let result = component.negotiate_step_with_history(
+                    incoming_proposal,
+                    history,
+                    step.template.clone(),
+                    step.score.clone(),
+                );
+                self.metrics.on_negotiate_step_done(
+                    name,
+                    start.elapsed(),
+                    StepOutcome::from_result(&result),
+                );
+                result
+            };
+
+            let result = match result {
+                Ok(result) => step
+                    .apply(name, incoming_proposal, result, self.strict_ready)
+                    .map(Ok),
+                Err(e) => handle_error(self.error_policy, name, incoming_proposal, e),
+            };
+            if let Some(result) = step.end(name, result) {
+                return result;
+            }
+        }
+        Ok(step.finish())
+    }
+
+    /// Response to `control_event` sent to `PACK_COMPONENT`.
+    fn describe_components(&self) -> Value {
+        let components = self
+            .components
+            .iter()
+            .map(|(name, component)| {
+                let description = component.describe();
+                serde_json::json!({
+                    "name": name,
+                    "description": description.as_ref().map(|d| &d.description),
+                    "config_schema": description.as_ref().map(|d| &d.config_schema),
+                })
+            })
+            .collect::<Vec<_>>();
+        serde_json::json!({
+            "components": components,
+            "empty": self.is_empty(),
+        })
+    }
+}
+
+/// Returns `name` or, if it is already taken, the first free `{name}#{n}`.
+/// `NegotiatorsPack` uses this scheme to distinguish multiple instances of
+/// the same negotiator.
+pub fn unique_name(name: &str, is_taken: impl Fn(&str) -> bool) -> String {
+    if !is_taken(name) {
+        return name.to_string();
+    }
+    (1..)
+        .map(|n| format!("{name}#{n}"))
+        .find(|candidate| !is_taken(candidate))
+        .unwrap()
+}
+
+/// Negotiation state of single Proposal passed through subsequent components.
+struct PackStep {
+    template: ProposalView,
+    score: Score,
+    all_ready: bool,
+    /// Any component explicitly asked to promote Proposal to Agreement.
+    accepted: bool,
+    reject_policy: RejectPolicy,
+    /// Rejections collected according to `RejectPolicy` with names of components.
+    rejections: Vec<(String, RejectReason, bool)>,
+}
+
+impl PackStep {
+    fn new(template: ProposalView, score: Score, reject_policy: RejectPolicy) -> PackStep {
+        PackStep {
+            template,
+            score,
+            all_ready: true,
+            accepted: false,
+            reject_policy,
+            rejections: Vec::new(),
+        }
+    }
+
+    /// Applies result returned by component. Returns final result, if component
+    /// ended negotiations of this Proposal.
+    fn apply(
+        &mut self,
+        name: &str,
+        incoming_proposal: &ProposalView,
+        result: NegotiationResult,
+        strict_ready: bool,
+    ) -> Option<NegotiationResult> {
+        let accept = matches!(result, NegotiationResult::Accept { .. });
+        match result {
+            NegotiationResult::Ready {
+                proposal: offer,
+                score: new_score,
+            }
+            | NegotiationResult::Accept {
+                proposal: offer,
+                score: new_score,
+            } => {
+                // Component is not allowed to change Proposal, when returning Ready.
+                let changed = self.template.changed_pointers(&offer);
+                if !changed.is_empty() {
+                    correlated_log!(
+                        log::Level::Warn,
+                        "Negotiator component '{}' returned Ready for Proposal [{}], but changed properties: {}.",
                         name,
-                        incoming_proposal.id
+                        incoming_proposal.id,
+                        changed.join(", ")
                     );
 
-                    all_ready = false;
-                    template = offer;
-                    score = new_score;
-                }
-                NegotiationResult::Reject { reason, is_final } => {
-                    return Ok(NegotiationResult::Reject { reason, is_final })
+                    if strict_ready {
+                        return Some(NegotiationResult::Reject {
+                            reason: RejectReason::new(format!(
+                                "Negotiator component '{}' changed Proposal without continuing negotiations.",
+                                name
+                            ))
+                            .with_component(name),
+                            is_final: false,
+                        });
+                    }
                 }
+
+                self.accepted |= accept;
+                self.template = offer;
+                self.score = new_score;
+            }
+            NegotiationResult::Negotiating {
+                proposal: offer,
+                score: new_score,
+            } => {
+                correlated_log!(
+                    log::Level::Info,
+                    "Negotiator component '{}' is still negotiating Proposal [{}].",
+                    name,
+                    incoming_proposal.id
+                );
+
+                self.all_ready = false;
+                self.template = offer;
+                self.score = new_score;
             }
+            NegotiationResult::Reject { reason, is_final } => {
+                return Some(NegotiationResult::Reject {
+                    reason: reason.with_component(name),
+                    is_final,
+                })
+            }
+        }
+        None
+    }
+
+    /// Proposal was rejected, but `RejectPolicy` still collects reasons.
+    fn is_rejected(&self) -> bool {
+        !self.rejections.is_empty()
+    }
+
+    /// Collects rejection, if `RejectPolicy` allows calling next components.
+    /// Returns result, which ends negotiations of this Proposal.
+    fn end(
+        &mut self,
+        name: &str,
+        result: Option<anyhow::Result<NegotiationResult>>,
+    ) -> Option<anyhow::Result<NegotiationResult>> {
+        match (self.reject_policy, result) {
+            (RejectPolicy::FirstReject, result) => result,
+            (_, Some(Ok(NegotiationResult::Reject { reason, is_final }))) => {
+                self.rejections.push((name.to_string(), reason, is_final));
+                None
+            }
+            (_, result) => result,
+        }
+    }
+
+    /// Single rejection is returned unchanged. Multiple rejections are combined
+    /// into single reason. Individual reasons are available under `reasons` key.
+    fn collected_rejection(&mut self) -> Option<NegotiationResult> {
+        if self.rejections.len() <= 1 {
+            return self
+                .rejections
+                .pop()
+                .map(|(_, reason, is_final)| NegotiationResult::Reject { reason, is_final });
+        }
+
+        let is_final = match self.reject_policy {
+            RejectPolicy::CollectAllFinal => self.rejections.iter().all(|(_, _, f)| *f),
+            _ => self.rejections.iter().any(|(_, _, f)| *f),
+        };
+        let message = self
+            .rejections
+            .iter()
+            .map(|(name, reason, _)| format!("'{}': {}", name, reason.message))
+            .collect::<Vec<_>>()
+            .join("; ");
+        let reasons = self
+            .rejections
+            .drain(..)
+            .map(|(name, reason, is_final)| reason.with_component(&name).final_flag(is_final))
+            .collect::<Vec<_>>();
+
+        Some(NegotiationResult::Reject {
+            reason: RejectReason::new(format!(
+                "Negotiator components rejected Proposal: {}",
+                message
+            ))
+            .entry(
+                "reasons",
+                serde_json::to_value(&reasons).unwrap_or(serde_json::Value::Null),
+            ),
+            is_final,
+        })
+    }
+
+    fn finish(mut self) -> NegotiationResult {
+        if let Some(rejection) = self.collected_rejection() {
+            return rejection;
         }
 
         // Full negotiations is ready only, if all `NegotiatorComponent` returned
         // ready state. Otherwise we must still continue negotiations.
-        Ok(match all_ready {
-            true => NegotiationResult::Ready {
-                proposal: template,
-                score,
+        // Single component explicitly accepting is enough to promote ready Proposal.
+        match (self.all_ready, self.accepted) {
+            (true, true) => NegotiationResult::Accept {
+                proposal: self.template,
+                score: self.score,
             },
-            false => NegotiationResult::Negotiating {
-                proposal: template,
-                score,
+            (true, false) => NegotiationResult::Ready {
+                proposal: self.template,
+                score: self.score,
             },
-        })
+            (false, _) => NegotiationResult::Negotiating {
+                proposal: self.template,
+                score: self.score,
+            },
+        }
+    }
+}
+
+/// Returns final result for Proposal according to policy or `None`,
+/// if component should be skipped.
+fn handle_error(
+    policy: ErrorPolicy,
+    name: &str,
+    incoming_proposal: &ProposalView,
+    error: anyhow::Error,
+) -> Option<anyhow::Result<NegotiationResult>> {
+    match policy {
+        ErrorPolicy::FailFast => Some(Err(error)),
+        ErrorPolicy::RejectOnError => Some(Ok(NegotiationResult::Reject {
+            reason: RejectReason::new(format!("Negotiator component '{}' failed. {}", name, error))
+                .with_code(COMPONENT_ERROR)
+                .with_component(name),
+            is_final: false,
+        })),
+        ErrorPolicy::SkipComponent => {
+            correlated_log!(
+                log::Level::Warn,
+                "Negotiator component '{}' failed evaluating Proposal [{}], skipping it. {}",
+                name,
+                incoming_proposal.id,
+                error
+            );
+            None
+        }
+    }
+}
+
+impl NegotiatorComponent for NegotiatorsPack {
+    fn negotiate_step(
+        &mut self,
+        incoming_proposal: &ProposalView,
+        template: ProposalView,
+        score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        self.negotiate_step_with_history(incoming_proposal, &[], template, score)
+    }
+
+    fn negotiate_step_with_history(
+        &mut self,
+        incoming_proposal: &ProposalView,
+        history: &[ProposalView],
+        template: ProposalView,
+        score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        self.step(incoming_proposal, history, template, score, false)
+    }
+
+    /// Dry run isn't reported to metrics.
+    fn dry_run_step(
+        &mut self,
+        incoming_proposal: &ProposalView,
+        history: &[ProposalView],
+        template: ProposalView,
+        score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        self.step(incoming_proposal, history, template, score, true)
+    }
+
+    /// Passes all Proposals, which are still negotiated, to each component
+    /// in single `negotiate_batch` call.
+    fn negotiate_batch(
+        &mut self,
+        items: Vec<(ProposalView, Vec<ProposalView>, ProposalView, Score)>,
+    ) -> Vec<anyhow::Result<NegotiationResult>> {
+        let (theirs, mut steps): (Vec<_>, Vec<_>) = items
+            .into_iter()
+            .map(|(their, history, template, score)| {
+                (
+                    (their, history),
+                    PackStep::new(template, score, self.reject_policy),
+                )
+            })
+            .unzip();
+        let mut finished: Vec<Option<anyhow::Result<NegotiationResult>>> =
+            theirs.iter().map(|_| None).collect();
+
+        for (name, component) in &mut self.components {
+            let (rejected, pending): (Vec<_>, Vec<_>) = (0..steps.len())
+                .filter(|idx| finished[*idx].is_none())
+                .partition(|idx| steps[*idx].is_rejected());
+            if pending.is_empty() && rejected.is_empty() {
+                break;
+            }
+
+            for idx in rejected {
+                let step = &mut steps[idx];
+                let (their, history) = &theirs[idx];
+                let result = match component.dry_run_step(
+                    their,
+                    history,
+                    step.template.clone(),
+                    step.score.clone(),
+                ) {
+                    Ok(result) => step.apply(name, their, result, self.strict_ready).map(Ok),
+                    Err(e) => handle_error(self.error_policy, name, their, e),
+                };
+                finished[idx] = step.end(name, result);
+            }
+            if pending.is_empty() {
+                continue;
+            }
+
+            let inputs = pending
+                .iter()
+                .map(|idx| {
+                    let step = &steps[*idx];
+                    let (their, history) = &theirs[*idx];
+                    (
+                        their.clone(),
+                        history.clone(),
+                        step.template.clone(),
+                        step.score.clone(),
+                    )
+                })
+                .collect();
+
+            let start = Instant::now();
+            let mut results = component.negotiate_batch(inputs);
+            // We can't measure items separately, so each one gets average duration.
+            let duration = start.elapsed() / pending.len() as u32;
+
+            // We can't tell, which Proposals are missing results, so none of them is trusted.
+            if results.len() != pending.len() {
+                let returned = results.len();
+                results = pending
+                    .iter()
+                    .map(|_| {
+                        Err(anyhow!(
+                            "Negotiator component '{name}' returned {returned} results for batch of {} Proposals.",
+                            pending.len()
+                        ))
+                    })
+                    .collect();
+            }
+
+            for (idx, result) in pending.into_iter().zip(results) {
+                self.metrics.on_negotiate_step_done(
+                    name,
+                    duration,
+                    StepOutcome::from_result(&result),
+                );
+                let their = &theirs[idx].0;
+                let result = match result {
+                    Ok(result) => steps[idx]
+                        .apply(name, their, result, self.strict_ready)
+                        .map(Ok),
+                    Err(e) => handle_error(self.error_policy, name, their, e),
+                };
+                finished[idx] = steps[idx].end(name, result);
+            }
+        }
+
+        steps
+            .into_iter()
+            .zip(finished)
+            .map(|(step, finished)| finished.unwrap_or_else(|| Ok(step.finish())))
+            .collect()
+    }
+
+    /// Components rescore Proposals in order, so each of them sees scores
+    /// changed by the previous ones.
+    fn rescore_batch(&mut self, proposals: &mut [ProposalScore]) -> anyhow::Result<()> {
+        for (name, component) in &mut self.components {
+            component.rescore_batch(proposals).map_err(|e| {
+                anyhow!("Negotiator component '{name}' failed rescoring Proposals. {e}")
+            })?;
+        }
+        Ok(())
     }
 
     fn fill_template(
         &mut self,
         mut offer_template: OfferTemplate,
     ) -> anyhow::Result<OfferTemplate> {
+        // Component, that wrote each property, keyed by pointer.
+        let mut writers = HashMap::<String, String>::new();
         for (name, component) in &mut self.components {
+            let previous = match self.conflict_policy {
+                TemplateConflictPolicy::LastWins => None,
+                TemplateConflictPolicy::ErrorOnConflict => Some(offer_template.clone()),
+            };
+
             offer_template = component.fill_template(offer_template).map_err(|e| {
                 anyhow!("Negotiator component '{name}' failed filling Offer template. {e}")
             })?;
+
+            if let Some(previous) = previous {
+                for change in previous.diff(&offer_template) {
+                    let pointer = change.pointer().to_string();
+                    if let Some(writer) = writers.get(&pointer) {
+                        if !self.overrides.contains(name.as_str()) {
+                            bail!(
+                                "Negotiator component '{name}' changed property {pointer} \
+                                 written by component '{writer}'."
+                            );
+                        }
+                    }
+                    match change {
+                        PropertyChange::Removed { .. } => writers.remove(&pointer),
+                        _ => writers.insert(pointer, name.clone()),
+                    };
+                }
+            }
         }
         Ok(offer_template)
     }
 
+    fn on_offer_published(&mut self, offer: &OfferTemplate) -> anyhow::Result<()> {
+        for (name, component) in &mut self.components {
+            component
+                .on_offer_published(offer)
+                .map_err(|e| {
+                    correlated_log!(
+                        log::Level::Warn,
+                        "Negotiator component '{name}' failed handling published Offer. {e}"
+                    )
+                })
+                .ok();
+        }
+        Ok(())
+    }
+
     fn on_agreement_terminated(
         &mut self,
         agreement_id: &str,
@@ -102,7 +651,8 @@ impl NegotiatorComponent for NegotiatorsPack {
             component
                 .on_agreement_terminated(agreement_id, result)
                 .map_err(|e| {
-                    log::warn!(
+                    correlated_log!(
+                        log::Level::Warn,
                         "Negotiator component '{name}' failed handling Agreement [{agreement_id}] termination. {e}"
                     )
                 })
@@ -116,7 +666,8 @@ impl NegotiatorComponent for NegotiatorsPack {
             component
                 .on_agreement_approved(agreement)
                 .map_err(|e| {
-                    log::warn!(
+                    correlated_log!(
+                        log::Level::Warn,
                         "Negotiator component '{name}' failed handling Agreement [{}] approval. {e}",
                         agreement.id,
                     )
@@ -131,7 +682,8 @@ impl NegotiatorComponent for NegotiatorsPack {
             component
                 .on_proposal_rejected(proposal_id)
                 .map_err(|e| {
-                    log::warn!(
+                    correlated_log!(
+                        log::Level::Warn,
                         "Negotiator component '{name}' failed handling Proposal [{proposal_id}] rejection. {e}",
                     )
                 })
@@ -149,7 +701,8 @@ impl NegotiatorComponent for NegotiatorsPack {
             component
                 .on_agreement_event(agreement_id, event)
                 .map_err(|e| {
-                    log::warn!(
+                    correlated_log!(
+                        log::Level::Warn,
                         "Negotiator component '{name}' failed handling post Terminate event [{agreement_id}]. {e}",
                     )
                 })
@@ -163,9 +716,193 @@ impl NegotiatorComponent for NegotiatorsPack {
         component: &str,
         params: Value,
     ) -> anyhow::Result<serde_json::Value> {
-        match self.components.get_mut(component) {
-            None => Ok(serde_json::Value::Null),
-            Some(negotiator) => negotiator.control_event(component, params),
+        if component == PACK_COMPONENT {
+            return Ok(self.describe_components());
+        }
+
+        let reconfigure = reconfigure_config(&params);
+        let negotiator = self
+            .components
+            .iter_mut()
+            .find(|(name, _)| name == component)
+            .map(|(_, negotiator)| negotiator);
+        match (negotiator, reconfigure) {
+            (None, Some(_)) => Err(anyhow!("Negotiator component '{component}' not found.")),
+            (None, None) => Ok(serde_json::Value::Null),
+            (Some(negotiator), Some(config)) => {
+                negotiator
+                    .reconfigure(config)
+                    .map_err(|e| anyhow!("Failed to reconfigure '{component}'. {e}"))?;
+                log::info!("Negotiator component '{component}' reconfigured.");
+                Ok(serde_json::Value::Null)
+            }
+            (Some(negotiator), None) => negotiator.control_event(component, params),
+        }
+    }
+
+    /// State of components is keyed by their names. Stateless components are skipped.
+    fn serialize_state(&self) -> anyhow::Result<Value> {
+        let mut states = serde_json::Map::new();
+        for (name, component) in &self.components {
+            let state = component.serialize_state().map_err(|e| {
+                anyhow!("Negotiator component '{name}' failed serializing state. {e}")
+            })?;
+            if !state.is_null() {
+                states.insert(name.clone(), state);
+            }
+        }
+        Ok(Value::Object(states))
+    }
+
+    fn restore_state(&mut self, mut state: Value) -> anyhow::Result<()> {
+        for (name, component) in &mut self.components {
+            let component_state = match state.get_mut(name.as_str()) {
+                Some(component_state) => component_state.take(),
+                None => continue,
+            };
+            component.restore_state(component_state).map_err(|e| {
+                anyhow!("Negotiator component '{name}' failed restoring state. {e}")
+            })?;
+        }
+        Ok(())
+    }
+
+    fn set_reevaluation_handle(&mut self, handle: ReevaluationHandle) {
+        for (_, component) in &mut self.components {
+            component.set_reevaluation_handle(handle.clone());
+        }
+    }
+
+    fn shutdown(&mut self, timeout: Duration) -> anyhow::Result<()> {
+        for (name, component) in &mut self.components {
+            component
+                .shutdown(timeout)
+                .map_err(|e| {
+                    correlated_log!(
+                        log::Level::Warn,
+                        "Negotiator component '{name}' failed to shutdown. {e}"
+                    )
+                })
+                .ok();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::convert::TryFrom;
+
+    fn proposal(id: &str) -> ProposalView {
+        ProposalView::try_from(json!({
+            "properties": {"golem": {"inf": {"mem": {"gib": 1.0}}}},
+            "constraints": "",
+            "proposalId": id,
+            "issuerId": "0x0000000000000000000000000000000000000001",
+            "state": "Draft",
+            "timestamp": "2023-01-10T22:00:00Z",
+        }))
+        .unwrap()
+    }
+
+    fn batch(ids: &[&str]) -> Vec<(ProposalView, Vec<ProposalView>, ProposalView, Score)> {
+        ids.iter()
+            .map(|id| {
+                let their = proposal(id);
+                (their.clone(), vec![], their, Score::default())
+            })
+            .collect()
+    }
+
+    /// Returns result only for the first Proposal in batch.
+    struct ShortBatch;
+
+    impl NegotiatorComponent for ShortBatch {
+        fn negotiate_batch(
+            &mut self,
+            items: Vec<(ProposalView, Vec<ProposalView>, ProposalView, Score)>,
+        ) -> Vec<anyhow::Result<NegotiationResult>> {
+            items
+                .into_iter()
+                .take(1)
+                .map(|(their, history, template, score)| {
+                    self.negotiate_step_with_history(&their, &history, template, score)
+                })
+                .collect()
         }
     }
+
+    /// Proposals without result from component can't be treated as accepted by it.
+    #[test]
+    fn test_negotiate_batch_short_results() {
+        let mut pack = NegotiatorsPack::new().add_component("ShortBatch", Box::new(ShortBatch));
+        let results = pack.negotiate_batch(batch(&["proposal-1", "proposal-2"]));
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|result| result.is_err()));
+
+        let mut pack = NegotiatorsPack::new()
+            .error_policy(ErrorPolicy::RejectOnError)
+            .add_component("ShortBatch", Box::new(ShortBatch));
+        let results = pack.negotiate_batch(batch(&["proposal-1", "proposal-2"]));
+        for result in results {
+            match result.unwrap() {
+                NegotiationResult::Reject { reason, .. } => {
+                    assert_eq!(reason.code.as_deref(), Some(COMPONENT_ERROR))
+                }
+                result => panic!("Expected Reject, got: {:?}", result),
+            }
+        }
+    }
+
+    /// Appends its name to list of visited components in template.
+    struct AppendName(String);
+
+    impl NegotiatorComponent for AppendName {
+        fn fill_template(&mut self, mut template: OfferTemplate) -> anyhow::Result<OfferTemplate> {
+            let mut visited = template
+                .property("test.visited")
+                .cloned()
+                .unwrap_or_else(|| json!([]));
+            visited.as_array_mut().unwrap().push(self.0.clone().into());
+            template.set_property("test.visited", visited);
+            Ok(template)
+        }
+    }
+
+    /// Components should be called in order of adding them, regardless of their names.
+    #[test]
+    fn test_pack_preserves_order() {
+        let names = [
+            "Zeta", "Alpha", "Mu", "Beta", "Omega", "Gamma", "Kappa", "Delta",
+        ];
+        let pack = names.iter().fold(NegotiatorsPack::new(), |pack, name| {
+            pack.add_component(name, Box::new(AppendName(name.to_string())))
+        });
+        assert_eq!(pack.component_names(), names);
+
+        let mut pack = pack;
+        let template = pack.fill_template(OfferTemplate::default()).unwrap();
+        assert_eq!(template.property("test.visited"), Some(&json!(names)));
+    }
+
+    /// Components with the same name shouldn't replace each other.
+    #[test]
+    fn test_pack_deduplicates_names() {
+        let mut pack = NegotiatorsPack::new()
+            .add_component("AppendName", Box::new(AppendName("first".to_string())))
+            .add_component("AppendName", Box::new(AppendName("second".to_string())))
+            .add_component("AppendName", Box::new(AppendName("third".to_string())));
+        assert_eq!(
+            pack.component_names(),
+            vec!["AppendName", "AppendName#1", "AppendName#2"]
+        );
+
+        let template = pack.fill_template(OfferTemplate::default()).unwrap();
+        assert_eq!(
+            template.property("test.visited"),
+            Some(&json!(["first", "second", "third"]))
+        );
+    }
 }
@@ -10,18 +10,44 @@ use ya_client_model::market::Reason;
 #[display(fmt = "'{}'", message)]
 pub struct RejectReason {
     pub message: String,
+    /// Machine readable code allowing to distinguish rejection reasons
+    /// without parsing message, for example `EXPIRATION_OUT_OF_RANGE`.
+    #[serde(rename = "golem.proposal.rejection.code", default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
     #[serde(flatten)]
     pub extra: serde_json::Value,
 }
 
+/// Key under which `RejectReason` code is placed in `Reason`.
+pub const REJECTION_CODE_KEY: &str = "golem.proposal.rejection.code";
+/// Key of boolean flag in `Reason` indicating, that rejection ends negotiations.
+pub const FINAL_FLAG_KEY: &str = "golem.proposal.rejection.is-final";
+/// Key under which `NegotiatorsPack` places name of component, that rejected Proposal.
+pub const COMPONENT_KEY: &str = "golem.proposal.rejection.component";
+
 impl RejectReason {
     pub fn new(message: impl ToString) -> RejectReason {
         RejectReason {
             message: message.to_string(),
+            code: None,
             extra: serde_json::json!({}),
         }
     }
 
+    pub fn with_code(mut self, code: impl ToString) -> RejectReason {
+        self.code = Some(code.to_string());
+        self
+    }
+
+    /// Adds all fields of `properties` object to reason. Non-object values are ignored.
+    pub fn properties(mut self, properties: serde_json::Value) -> RejectReason {
+        if let serde_json::Value::Object(properties) = properties {
+            self.extra.as_object_mut().unwrap().extend(properties);
+        }
+        self
+    }
+
     pub fn entry<T: Into<serde_json::Value>>(
         mut self,
         key: impl ToString,
@@ -35,12 +61,43 @@ impl RejectReason {
     }
 
     pub fn final_flag(self, flag: bool) -> Self {
-        self.entry("golem.proposal.rejection.is-final".to_string(), flag)
+        self.entry(FINAL_FLAG_KEY, flag)
+    }
+
+    /// Sets name of rejecting component, unless it was already set. This way
+    /// in nested packs the innermost component is reported.
+    pub fn with_component(self, name: &str) -> RejectReason {
+        match self.component() {
+            Some(_) => self,
+            None => self.entry(COMPONENT_KEY, name),
+        }
+    }
+
+    /// Name of component, that rejected Proposal. See `with_component`.
+    pub fn component(&self) -> Option<&str> {
+        self.extra
+            .get(COMPONENT_KEY)
+            .and_then(|component| component.as_str())
+    }
+
+    /// Reads flag set by `final_flag`. Returns None, if rejecting party didn't
+    /// specify, whether negotiations can be continued.
+    pub fn is_final(&self) -> Option<bool> {
+        self.extra
+            .get(FINAL_FLAG_KEY)
+            .and_then(|flag| flag.as_bool())
     }
 }
 
 impl Into<Reason> for RejectReason {
-    fn into(self) -> Reason {
+    fn into(mut self) -> Reason {
+        if let Some(code) = self.code {
+            self.extra
+                .as_object_mut()
+                .unwrap()
+                .insert(REJECTION_CODE_KEY.to_string(), code.into());
+        }
+
         Reason {
             message: self.message,
             extra: self.extra,
@@ -48,6 +105,24 @@ impl Into<Reason> for RejectReason {
     }
 }
 
+impl From<Reason> for RejectReason {
+    fn from(reason: Reason) -> RejectReason {
+        let mut extra = match reason.extra {
+            serde_json::Value::Object(extra) => extra,
+            _ => serde_json::Map::new(),
+        };
+        let code = extra
+            .remove(REJECTION_CODE_KEY)
+            .and_then(|code| code.as_str().map(ToString::to_string));
+
+        RejectReason {
+            message: reason.message,
+            code,
+            extra: serde_json::Value::Object(extra),
+        }
+    }
+}
+
 impl Into<Option<Reason>> for RejectReason {
     fn into(self) -> Option<Reason> {
         Some(self.into())
@@ -0,0 +1,49 @@
+use ya_agreement_utils::ProposalView;
+
+use crate::component::{NegotiationResult, NegotiatorComponent, Score};
+
+/// Component, which only evaluates Proposals without negotiating them.
+/// Use `ScoringAdapter` to put it into `NegotiatorsPack`.
+///
+/// Scorers should place their values under their own namespace, since scores
+/// returned by all components are merged together. Use `namespaced_score` to follow
+/// this convention, so contributions can be retrieved with `Score::breakdown`.
+/// Note that `final-score` will be overwritten by each scorer setting it.
+pub trait ScoringComponent {
+    /// Evaluates Proposal. `our` is Proposal already negotiated by previous components.
+    fn score(&self, their: &ProposalView, our: &ProposalView) -> anyhow::Result<Score>;
+}
+
+/// Creates `Score` with `value` placed under `<namespace>.score` key.
+pub fn namespaced_score(namespace: &str, value: f64) -> Score {
+    let mut score = Score::default();
+    score.set_property(format!("{}.score", namespace), serde_json::json!(value));
+    score
+}
+
+/// Wraps `ScoringComponent` into `NegotiatorComponent`. Adapter never changes
+/// Proposals and always returns `Ready`, so it doesn't influence negotiations.
+pub struct ScoringAdapter<S: ScoringComponent> {
+    scorer: S,
+}
+
+impl<S: ScoringComponent> ScoringAdapter<S> {
+    pub fn new(scorer: S) -> ScoringAdapter<S> {
+        ScoringAdapter { scorer }
+    }
+}
+
+impl<S: ScoringComponent> NegotiatorComponent for ScoringAdapter<S> {
+    fn negotiate_step(
+        &mut self,
+        their: &ProposalView,
+        template: ProposalView,
+        score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        let new_score = self.scorer.score(their, &template)?;
+        Ok(NegotiationResult::Ready {
+            proposal: template,
+            score: score.patch(new_score),
+        })
+    }
+}
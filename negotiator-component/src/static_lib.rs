@@ -6,29 +6,89 @@ use std::sync::{Arc, Mutex};
 
 use crate::component::NegotiatorComponent;
 
+/// Creates negotiator from its config, Agent environment and working directory.
 pub type ConstructorFunction = Box<
-    dyn Fn(serde_yaml::Value, PathBuf) -> anyhow::Result<Box<dyn NegotiatorComponent>>
+    dyn Fn(
+            serde_yaml::Value,
+            serde_yaml::Value,
+            PathBuf,
+        ) -> anyhow::Result<Box<dyn NegotiatorComponent>>
         + Send
         + Sync,
 >;
 
+/// Library name, under which builtin negotiators are registered.
+pub const BUILTIN_LIBRARY: &str = "builtin";
+
 lazy_static! {
     /// Contains functions that can create negotiators by name.
     static ref CONSTRUCTORS: Arc<Mutex<HashMap<String, ConstructorFunction>>> = Arc::new(Mutex::new(HashMap::new()));
 }
 
-pub fn register_negotiator(library: &str, name: &str, constructor: ConstructorFunction) {
-    let negotiator = format!("{}::{}", library, name);
-    println!("Registering: {}", negotiator);
+/// Name identifying negotiator in registry: `library::name`.
+pub fn qualified_name(library: &str, name: &str) -> String {
+    format!("{}::{}", library, name)
+}
+
+/// Registers negotiator constructor under `library::name`. Returns true, if it
+/// replaced constructor registered previously under the same name.
+pub fn register_negotiator(library: &str, name: &str, constructor: ConstructorFunction) -> bool {
+    let negotiator = qualified_name(library, name);
+    log::debug!("Registering: {}", negotiator);
+    (*CONSTRUCTORS)
+        .lock()
+        .unwrap()
+        .insert(negotiator, constructor)
+        .is_some()
+}
+
+/// Registers negotiator constructor under `library::name`, unless other constructor
+/// is registered there already. Returns true, if constructor was registered.
+pub fn register_negotiator_if_missing(
+    library: &str,
+    name: &str,
+    constructor: ConstructorFunction,
+) -> bool {
+    let negotiator = qualified_name(library, name);
+    let mut constructors = (*CONSTRUCTORS).lock().unwrap();
+    if constructors.contains_key(&negotiator) {
+        return false;
+    }
+    log::debug!("Registering: {}", negotiator);
+    constructors.insert(negotiator, constructor);
+    true
+}
+
+/// Removes negotiator constructor registered under `library::name`. Negotiators
+/// created already aren't affected. Returns false, if negotiator wasn't registered.
+pub fn deregister_negotiator(library: &str, name: &str) -> bool {
+    let negotiator = qualified_name(library, name);
+    log::debug!("Deregistering: {}", negotiator);
     (*CONSTRUCTORS)
         .lock()
         .unwrap()
-        .insert(negotiator, constructor);
+        .remove(&negotiator)
+        .is_some()
+}
+
+/// Checks, if negotiator constructor is registered under `library::name`.
+pub fn is_registered(library: &str, name: &str) -> bool {
+    (*CONSTRUCTORS)
+        .lock()
+        .unwrap()
+        .contains_key(&qualified_name(library, name))
+}
+
+/// Removes all registered negotiator constructors. Builtin negotiators are
+/// registered again, when next Negotiator is created.
+pub fn clear_negotiators() {
+    (*CONSTRUCTORS).lock().unwrap().clear();
 }
 
 pub fn create_static_negotiator(
     name_path: &str,
     config: serde_yaml::Value,
+    agent_env: serde_yaml::Value,
     working_dir: PathBuf,
 ) -> anyhow::Result<Box<dyn NegotiatorComponent>> {
     let map = (*CONSTRUCTORS)
@@ -36,7 +96,7 @@ pub fn create_static_negotiator(
         .map_err(|e| anyhow!("Failed to acquire static Negotiator creation lock: {}", e))?;
 
     match map.get(name_path) {
-        Some(constructor) => constructor(config, working_dir),
+        Some(constructor) => constructor(config, agent_env, working_dir),
         None => Err(anyhow!("Negotiator '{}' not found.", name_path)),
     }
 }
@@ -0,0 +1,121 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::{TryRecvError, TrySendError};
+
+/// Actions, which didn't fit into bounded channel, the oldest first. Shared by both
+/// sides of channel, so receiver can take them after draining the channel.
+type Overflow<T> = Arc<Mutex<VecDeque<T>>>;
+
+/// Receiving side of channel with actions produced by Negotiator.
+/// Depending on `CompositeNegotiatorConfig::channel_capacity` channel can be bounded or not.
+pub enum ActionReceiver<T> {
+    Bounded {
+        receiver: mpsc::Receiver<T>,
+        overflow: Overflow<T>,
+    },
+    Unbounded(mpsc::UnboundedReceiver<T>),
+}
+
+pub(crate) enum ActionSender<T> {
+    Bounded {
+        sender: mpsc::Sender<T>,
+        overflow: Overflow<T>,
+    },
+    Unbounded(mpsc::UnboundedSender<T>),
+}
+
+pub(crate) fn action_channel<T>(capacity: Option<usize>) -> (ActionSender<T>, ActionReceiver<T>) {
+    match capacity {
+        Some(capacity) => {
+            let (sender, receiver) = mpsc::channel(capacity);
+            let overflow = Overflow::default();
+            (
+                ActionSender::Bounded {
+                    sender,
+                    overflow: overflow.clone(),
+                },
+                ActionReceiver::Bounded { receiver, overflow },
+            )
+        }
+        None => {
+            let (sender, receiver) = mpsc::unbounded_channel();
+            (
+                ActionSender::Unbounded(sender),
+                ActionReceiver::Unbounded(receiver),
+            )
+        }
+    }
+}
+
+impl<T> ActionReceiver<T> {
+    pub async fn recv(&mut self) -> Option<T> {
+        match self {
+            ActionReceiver::Bounded { receiver, overflow } => {
+                if let Ok(action) = receiver.try_recv() {
+                    return Some(action);
+                }
+                // Sender puts actions into the channel only, if overflow is empty,
+                // so we can wait for the channel, when both are empty.
+                if let Some(action) = overflow.lock().unwrap().pop_front() {
+                    return Some(action);
+                }
+                receiver.recv().await
+            }
+            ActionReceiver::Unbounded(receiver) => receiver.recv().await,
+        }
+    }
+
+    /// Returns action without waiting. Fails if channel is empty or closed.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        match self {
+            ActionReceiver::Bounded { receiver, overflow } => match receiver.try_recv() {
+                Ok(action) => Ok(action),
+                Err(e) => overflow.lock().unwrap().pop_front().ok_or(e),
+            },
+            ActionReceiver::Unbounded(receiver) => receiver.try_recv(),
+        }
+    }
+}
+
+impl<T> ActionSender<T> {
+    /// Never waits for free space in channel. Actions, which don't fit into bounded
+    /// channel, wait in overflow queue until receiver drains the channel, so it fails
+    /// only if receiver was dropped.
+    pub fn send(&self, action: T) -> Result<(), TrySendError<T>> {
+        match self {
+            ActionSender::Bounded { sender, overflow } => {
+                if sender.is_closed() {
+                    return Err(TrySendError::Closed(action));
+                }
+
+                // Lock is held during sending, so actions can't be reordered.
+                let mut overflow = overflow.lock().unwrap();
+                if !overflow.is_empty() {
+                    overflow.push_back(action);
+                    return Ok(());
+                }
+                match sender.try_send(action) {
+                    Err(TrySendError::Full(action)) => {
+                        overflow.push_back(action);
+                        Ok(())
+                    }
+                    result => result,
+                }
+            }
+            ActionSender::Unbounded(sender) => {
+                sender.send(action).map_err(|e| TrySendError::Closed(e.0))
+            }
+        }
+    }
+
+    /// Channel is full, if it has no free space or any action waits in overflow queue.
+    pub fn is_full(&self) -> bool {
+        match self {
+            ActionSender::Bounded { sender, overflow } => {
+                sender.capacity() == 0 || !overflow.lock().unwrap().is_empty()
+            }
+            ActionSender::Unbounded(_) => false,
+        }
+    }
+}
@@ -1,22 +1,24 @@
 use actix::prelude::*;
-use anyhow::{anyhow, bail};
+use anyhow::anyhow;
 use derive_more::Display;
 use futures::future::{AbortHandle, Abortable};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 use std::cmp::min;
-use std::time::Duration;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 
-use crate::component::ProposalView;
-
+pub use ya_negotiator_component::component::ProposalScore;
+use ya_negotiator_component::correlated_log;
+use ya_negotiator_component::metrics::{Metrics, NoMetrics};
 use ya_negotiator_component::reason::RejectReason;
 
-#[derive(Debug)]
-pub struct ProposalScore {
-    pub their: ProposalView,
-    pub our: ProposalView,
-    pub score: f64,
-}
+/// Code of rejection sent to Proposals, that weren't chosen, because of low score.
+pub const NODE_BUSY: &str = "NODE_BUSY";
+/// Code of rejection sent to Proposals with NaN or infinite score.
+pub const INVALID_SCORE: &str = "INVALID_SCORE";
 
 #[derive(Debug)]
 pub enum DecideReason {
@@ -24,8 +26,17 @@ pub enum DecideReason {
     GoalReached,
 }
 
+/// Reason, why decision didn't choose any Proposal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, Serialize, Deserialize)]
+pub enum NoDecisionReason {
+    /// No Proposals were collected in this period.
+    NothingCollected,
+    /// Proposals were collected, but goal doesn't allow choosing any of them.
+    GoalExhausted,
+}
+
 /// Decision making mode.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum DecideGoal {
     /// ProposalsCollection is expected to provide limited number of Proposals.
     /// After goal is reached, no new Proposals will be chosen. Someone must
@@ -36,7 +47,7 @@ pub enum DecideGoal {
     Batch(usize),
 }
 
-#[derive(Debug, Copy, Clone, Display)]
+#[derive(Debug, Copy, Clone, Display, PartialEq, Eq)]
 pub enum CollectionType {
     Agreement,
     Proposal,
@@ -53,17 +64,105 @@ pub enum FeedbackAction {
         reason: RejectReason,
         is_final: bool,
     },
+    /// Decision was made, but no Proposal was chosen.
+    NoDecision {
+        reason: NoDecisionReason,
+    },
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct CollectionConfig {
     /// Time period before making decision, which Proposals to choose.
     #[serde(with = "humantime_serde")]
     pub collect_period: Option<Duration>,
+    /// Each collect period is extended by random duration from range `[0, jitter]`,
+    /// so Negotiators started at the same time don't make decisions simultaneously.
+    #[serde(with = "humantime_serde", default)]
+    pub collect_period_jitter: Option<Duration>,
     /// Number of Proposals to collect, after which best of them will be accepted.
     pub collect_amount: Option<usize>,
+    /// Decision triggered by `collect_amount` is postponed, until at least this
+    /// much time elapsed since the beginning of collect period.
+    #[serde(with = "humantime_serde", default)]
+    pub min_collect_time: Option<Duration>,
     /// Expected number of Proposals to choose or batch size. See DecideGoal description.
     pub goal: DecideGoal,
+    /// What to do with Proposals, which got NaN or infinite score.
+    #[serde(default)]
+    pub invalid_score: InvalidScorePolicy,
+    /// Reason sent to Proposals, which weren't chosen during decision.
+    #[serde(default)]
+    pub busy_reason: BusyReasonConfig,
+    /// Pointer to `Score` property used to compare Proposals.
+    #[serde(default = "default_score_pointer")]
+    pub score_pointer: String,
+}
+
+pub fn default_score_pointer() -> String {
+    "/final-score".to_string()
+}
+
+/// Reason of rejecting Proposals, which lost to better ones. Such Proposals
+/// are always rejected without `final` flag.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BusyReasonConfig {
+    pub message: String,
+    pub code: String,
+    /// Attaches score of rejected Proposal, the lowest score of chosen Proposals
+    /// and difference between them, so other party knows how far it was from
+    /// being chosen.
+    #[serde(default)]
+    pub include_scores: bool,
+}
+
+impl Default for BusyReasonConfig {
+    fn default() -> Self {
+        BusyReasonConfig {
+            message: "Node is busy.".to_string(),
+            code: NODE_BUSY.to_string(),
+            include_scores: false,
+        }
+    }
+}
+
+/// Handling of Proposals with NaN or infinite score. Such Proposal never
+/// affects other Proposals in collection.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum InvalidScorePolicy {
+    /// Proposal is rejected immediately without `final` flag.
+    #[default]
+    Reject,
+    /// Proposal gets the lowest possible score, so it will be chosen only,
+    /// if there are no better candidates.
+    LowestPriority,
+}
+
+/// Proposal id together with its score.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScoredId {
+    pub id: String,
+    pub score: f64,
+    pub breakdown: HashMap<String, f64>,
+}
+
+/// Snapshot of collection state for diagnostic purposes.
+#[derive(Debug, Clone, Serialize)]
+pub struct CollectionSummary {
+    pub goal: DecideGoal,
+    /// Proposals waiting for decision, ordered from the best.
+    pub awaiting: Vec<ScoredId>,
+    /// Proposals rejected without final flag, ordered from the best.
+    pub rejected: Vec<ScoredId>,
+}
+
+/// Full collection state, which allows to recreate it after restart.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollectionState {
+    pub goal: DecideGoal,
+    pub awaiting: Vec<ProposalScore>,
+    pub rejected: Vec<ProposalScore>,
+    #[serde(default)]
+    pub pending_approvals: usize,
 }
 
 #[derive(Message, Debug)]
@@ -84,41 +183,94 @@ pub struct ProposalsCollection {
 
     /// Expected number of Proposals to choose or batch size. See DecideGoal description.
     goal: DecideGoal,
+    /// Number of chosen Agreements waiting for approval result. Agreements not chosen
+    /// meanwhile are kept pending instead of rejecting them, because we can't approve
+    /// Agreement, which we already rejected, when approval fails.
+    pending_approvals: usize,
 
     /// Time period before making decision, which Proposals to choose.
     collect_period: Duration,
+    /// Maximal random extension of collect period.
+    collect_period_jitter: Duration,
     /// Number of Proposals to collect, after which best of them will be accepted.
     collect_amount: usize,
-
+    min_collect_time: Duration,
+    /// Beginning of current collect period.
+    period_start: Instant,
+    invalid_score: InvalidScorePolicy,
+    busy_reason: BusyReasonConfig,
+    score_pointer: String,
+
+    /// Decisions are triggered by spawned timers. Otherwise owner must call `poll_timers`.
+    timers: bool,
+    /// End of current collect period. `None` if collect period is unlimited.
+    collect_deadline: Option<Instant>,
+    /// Decision postponed until `min_collect_time` elapses.
+    postponed_deadline: Option<Instant>,
     collect_timeout_handle: Option<AbortHandle>,
+    postponed_decision_handle: Option<AbortHandle>,
 
     /// This collection handles Agreements or Proposals.
     collection_type: CollectionType,
 
     feedback_channel: mpsc::UnboundedSender<Feedback>,
     pub feedback_receiver: Option<mpsc::UnboundedReceiver<Feedback>>,
+
+    metrics: Arc<dyn Metrics>,
 }
 
 impl ProposalsCollection {
     pub fn new(collection_type: CollectionType, config: CollectionConfig) -> ProposalsCollection {
+        let mut collection = ProposalsCollection::without_timers(collection_type, config);
+        collection.timers = true;
+        collection.start_collect_period();
+        collection
+    }
+
+    /// Collection, which doesn't spawn timers, so it can be used without async runtime.
+    /// Owner must call `poll_timers` to make decisions after collect period elapses.
+    pub fn without_timers(
+        collection_type: CollectionType,
+        config: CollectionConfig,
+    ) -> ProposalsCollection {
         let (feedback_sender, feedback_receiver) = mpsc::unbounded_channel();
 
         let mut collection = ProposalsCollection {
             awaiting: vec![],
             rejected: vec![],
             collect_period: config.collect_period.unwrap_or(Duration::MAX),
+            collect_period_jitter: config.collect_period_jitter.unwrap_or(Duration::ZERO),
             collect_amount: config.collect_amount.unwrap_or(usize::MAX),
+            min_collect_time: config.min_collect_time.unwrap_or(Duration::ZERO),
+            period_start: Instant::now(),
+            invalid_score: config.invalid_score,
+            busy_reason: config.busy_reason,
+            score_pointer: config.score_pointer,
+            timers: false,
+            collect_deadline: None,
+            postponed_deadline: None,
             collect_timeout_handle: None,
+            postponed_decision_handle: None,
             feedback_channel: feedback_sender,
             feedback_receiver: Some(feedback_receiver),
             collection_type,
             goal: config.goal,
+            pending_approvals: 0,
+            metrics: Arc::new(NoMetrics),
         };
 
-        collection.spawn_collect_period();
+        collection.start_collect_period();
         collection
     }
 
+    pub fn score_pointer(&self) -> &str {
+        &self.score_pointer
+    }
+
+    pub fn set_metrics(&mut self, metrics: Arc<dyn Metrics>) {
+        self.metrics = metrics;
+    }
+
     pub fn set_goal(&mut self, goal: DecideGoal) {
         match self.goal {
             DecideGoal::Limit(current) => match goal {
@@ -133,40 +285,129 @@ impl ProposalsCollection {
         }
     }
 
+    pub fn summary(&self) -> CollectionSummary {
+        let scored_ids = |list: &Vec<ProposalScore>| {
+            list.iter()
+                .map(|proposal| ScoredId {
+                    id: proposal.their.id.clone(),
+                    score: proposal.score,
+                    breakdown: proposal.breakdown.clone(),
+                })
+                .collect()
+        };
+
+        CollectionSummary {
+            goal: self.goal.clone(),
+            awaiting: scored_ids(&self.awaiting),
+            rejected: scored_ids(&self.rejected),
+        }
+    }
+
+    pub fn snapshot(&self) -> CollectionState {
+        CollectionState {
+            goal: self.goal.clone(),
+            awaiting: self.awaiting.clone(),
+            rejected: self.rejected.clone(),
+            pending_approvals: self.pending_approvals,
+        }
+    }
+
+    /// Replaces collection content with `state`. Collect period isn't restarted,
+    /// but decision is made immediately, if enough Proposals were collected.
+    pub fn restore(&mut self, state: CollectionState) -> anyhow::Result<()> {
+        self.goal = state.goal;
+        self.awaiting = state.awaiting;
+        self.rejected = state.rejected;
+        self.pending_approvals = state.pending_approvals;
+
+        if !self.awaiting.is_empty() && self.awaiting.len() >= self.collect_amount {
+            self.send_feedback(FeedbackAction::Decide(DecideReason::GoalReached))?;
+        }
+        Ok(())
+    }
+
     /// Collects Proposals, that were already fully negotiated and score
     /// for them was computed.
     /// Note: id is dirty hack to display Agreement id instead of Proposal id here.
     /// ProposalViews don't contain Agreement id.
-    pub fn new_scored(&mut self, new: ProposalScore, id: &str) -> anyhow::Result<()> {
-        log::info!("Adding {} [{}] to choose later.", self.collection_type, id);
+    pub fn new_scored(&mut self, mut new: ProposalScore, id: &str) -> anyhow::Result<()> {
+        correlated_log!(
+            log::Level::Info,
+            "Adding {} [{}] to choose later.",
+            self.collection_type,
+            id
+        );
 
-        if new.score.is_nan() {
-            bail!("{} [{}] score was set to NaN.", self.collection_type, id);
-        }
+        if !new.score.is_finite() {
+            correlated_log!(
+                log::Level::Warn,
+                "{} [{}] score was set to {}. Handling it with policy: {:?}.",
+                self.collection_type,
+                id,
+                new.score,
+                self.invalid_score
+            );
 
-        // Keep vector sorted.
-        let idx = match self
-            .awaiting
-            .binary_search_by(|proposal| new.score.partial_cmp(&proposal.score).unwrap())
-        {
-            Ok(idx) => idx + 1,
-            Err(idx) => idx,
-        };
+            match self.invalid_score {
+                InvalidScorePolicy::Reject => {
+                    return self.send_feedback(FeedbackAction::Reject {
+                        id: new.their.id.clone(),
+                        reason: RejectReason::new(format!("Invalid score: {}.", new.score))
+                            .with_code(INVALID_SCORE),
+                        is_final: false,
+                    });
+                }
+                InvalidScorePolicy::LowestPriority => new.score = f64::MIN,
+            }
+        }
 
-        self.awaiting.insert(idx, new);
+        insert_sorted(&mut self.awaiting, new);
 
         // Check if we reached number of Proposals, by which we should make
-        // decision immediately without waiting `collect_period`.
+        // decision without waiting `collect_period`.
         if self.awaiting.len() >= self.collect_amount {
-            self.send_feedback(FeedbackAction::Decide(DecideReason::GoalReached))?;
+            let elapsed = self.period_start.elapsed();
+            if elapsed >= self.min_collect_time {
+                self.send_feedback(FeedbackAction::Decide(DecideReason::GoalReached))?;
+            } else if self.postponed_deadline.is_none() {
+                self.postpone_decision(self.min_collect_time - elapsed);
+            }
         }
 
         Ok(())
     }
 
+    /// Lets `rescore` change scores of awaiting Proposals before decision.
+    /// Previous scores are kept, if it fails. Invalid scores get the lowest priority.
+    pub fn rescore(&mut self, rescore: impl FnOnce(&mut [ProposalScore]) -> anyhow::Result<()>) {
+        if self.awaiting.is_empty() {
+            return;
+        }
+
+        let previous = self.awaiting.clone();
+        if let Err(e) = rescore(&mut self.awaiting) {
+            log::warn!(
+                "Failed to rescore {}s. Using previous scores. {e}",
+                self.collection_type
+            );
+            self.awaiting = previous;
+            return;
+        }
+
+        for proposal in self.awaiting.iter_mut() {
+            if !proposal.score.is_finite() {
+                proposal.score = f64::MIN;
+            }
+        }
+        for proposal in std::mem::take(&mut self.awaiting) {
+            insert_sorted(&mut self.awaiting, proposal);
+        }
+    }
+
     /// Makes decision, which Proposals should be responded to.
     /// Rest of the Proposals is rejected and they are all placed in queue
-    /// for future, in case not enough Agreements will be signed.
+    /// for future, in case not enough Agreements will be signed. Agreements are
+    /// kept pending instead, until all chosen Agreements get approval result.
     pub fn decide(&mut self) -> anyhow::Result<()> {
         let goal = match self.goal {
             DecideGoal::Limit(expected_goal) => {
@@ -185,12 +426,34 @@ impl ProposalsCollection {
 
         // Vector is sorted so the best elements are on the beginning.
         let accepted = self.awaiting.drain(0..goal).collect::<Vec<_>>();
-        let rejected = self.awaiting.drain(..).collect::<Vec<_>>();
+        if self.collection_type == CollectionType::Agreement {
+            self.pending_approvals += accepted.len();
+        }
+
+        let rejected = match self.pending_approvals {
+            0 => self.awaiting.drain(..).collect::<Vec<_>>(),
+            _ => vec![],
+        };
+        // The worst of chosen Proposals sets score required to win.
+        let winning_score = accepted.last().map(|proposal| proposal.score);
 
         if goal != 0 {
             log::info!("Decided to accept {} {}(s).", goal, self.collection_type);
+        } else {
+            let reason = match rejected.is_empty() && self.awaiting.is_empty() {
+                true => NoDecisionReason::NothingCollected,
+                false => NoDecisionReason::GoalExhausted,
+            };
+            self.send_feedback(FeedbackAction::NoDecision { reason })
+                .ok();
         }
 
+        self.metrics.on_decision(
+            &self.collection_type.to_string(),
+            accepted.len(),
+            rejected.len(),
+        );
+
         for proposal in accepted {
             self.send_feedback(FeedbackAction::Accept {
                 id: proposal.their.id,
@@ -201,55 +464,109 @@ impl ProposalsCollection {
         for proposal in rejected {
             self.send_feedback(FeedbackAction::Reject {
                 id: proposal.their.id.clone(),
-                reason: RejectReason::new("Node is busy.").into(),
+                reason: self.busy_reason(&proposal, winning_score),
                 is_final: false,
             })
             .ok();
 
             // We collect Proposals with too low score.
-            // Proposals with invalid score won't be added.
-            self.add_rejected(proposal).ok();
+            insert_sorted(&mut self.rejected, proposal);
         }
 
         // If decide call was called because of collect period timeout, we must
         // start waiting for new period. If we just reached expected number of
-        // collected Proposals, we can spawn collect period anyway.
-        self.spawn_collect_period();
+        // collected Proposals, we can start collect period anyway.
+        self.start_collect_period();
         Ok(())
     }
 
-    fn add_rejected(&mut self, new: ProposalScore) -> anyhow::Result<()> {
-        if new.score.is_nan() {
-            bail!(
-                "{} [{}] score was set to NaN.",
-                self.collection_type,
-                new.their.id
-            );
+    fn busy_reason(&self, proposal: &ProposalScore, winning_score: Option<f64>) -> RejectReason {
+        let reason = RejectReason::new(&self.busy_reason.message).with_code(&self.busy_reason.code);
+        match winning_score {
+            Some(winning_score) if self.busy_reason.include_scores => reason
+                .entry("score", proposal.score)
+                .entry("winning-score", winning_score)
+                .entry("score-delta", winning_score - proposal.score),
+            _ => reason,
         }
+    }
 
-        // Keep vector sorted.
-        let idx = match self
-            .rejected
-            .binary_search_by(|proposal| new.score.partial_cmp(&proposal.score).unwrap())
-        {
-            Ok(idx) => idx + 1,
-            Err(idx) => idx,
-        };
+    /// Called when chosen Agreement was signed. Returns true, if Agreements kept
+    /// pending aren't waiting for any other approval and should be decided now.
+    pub fn approved(&mut self) -> bool {
+        self.pending_approvals = self.pending_approvals.saturating_sub(1);
+        self.pending_approvals == 0 && !self.awaiting.is_empty()
+    }
 
-        self.rejected.insert(idx, new);
+    /// Called when chosen Agreement wasn't signed. Frees its slot in `DecideGoal::Limit`,
+    /// so one of Agreements kept pending can be chosen in next decision.
+    pub fn reconsider(&mut self) {
+        self.pending_approvals = self.pending_approvals.saturating_sub(1);
+        if let DecideGoal::Limit(goal) = self.goal {
+            self.goal = DecideGoal::Limit(goal + 1);
+        }
+    }
+
+    /// Sends decision feedback, if collect period elapsed or postponed decision
+    /// is due. Used by collections created `without_timers`.
+    pub fn poll_timers(&mut self) -> anyhow::Result<()> {
+        let now = Instant::now();
+        let elapsed = |deadline: Option<Instant>| matches!(deadline, Some(end) if end <= now);
+
+        if elapsed(self.postponed_deadline) {
+            self.postponed_deadline = None;
+            self.send_feedback(FeedbackAction::Decide(DecideReason::GoalReached))?;
+        } else if elapsed(self.collect_deadline) {
+            self.collect_deadline = None;
+            self.send_feedback(FeedbackAction::Decide(DecideReason::TimeElapsed))?;
+        }
         Ok(())
     }
 
-    fn spawn_collect_period(&mut self) {
+    /// Decision is made after `delay`, unless new collect period starts earlier.
+    fn postpone_decision(&mut self, delay: Duration) {
+        self.postponed_deadline = Instant::now().checked_add(delay);
+        if !self.timers {
+            return;
+        }
+
+        let (abort_handle, abort_registration) = AbortHandle::new_pair();
+        let feedback = self.feedback_channel.clone();
+        let collection_type = self.collection_type;
+
+        let future = async move {
+            tokio::time::sleep(delay).await;
+            feedback
+                .send(Feedback {
+                    action: FeedbackAction::Decide(DecideReason::GoalReached),
+                    collection_type,
+                })
+                .ok();
+        };
+
+        tokio::spawn(Abortable::new(future, abort_registration));
+        self.postponed_decision_handle = Some(abort_handle);
+    }
+
+    fn start_collect_period(&mut self) {
         // Cancel previous future notifying about collect period.
         if let Some(handle) = self.collect_timeout_handle.take() {
             handle.abort();
             self.collect_timeout_handle = None;
         }
+        if let Some(handle) = self.postponed_decision_handle.take() {
+            handle.abort();
+        }
+        self.postponed_deadline = None;
+        self.period_start = Instant::now();
 
-        let (abort_handle, abort_registration) = AbortHandle::new_pair();
+        let timeout = self.next_collect_period();
+        self.collect_deadline = self.period_start.checked_add(timeout);
+        if !self.timers {
+            return;
+        }
 
-        let timeout = self.collect_period.clone();
+        let (abort_handle, abort_registration) = AbortHandle::new_pair();
         let feedback = self.feedback_channel.clone();
         let collection_type = self.collection_type;
 
@@ -268,6 +585,16 @@ impl ProposalsCollection {
         self.collect_timeout_handle = Some(abort_handle);
     }
 
+    /// Jitter is sampled again for each collect period.
+    fn next_collect_period(&self) -> Duration {
+        if self.collect_period_jitter.is_zero() {
+            return self.collect_period;
+        }
+
+        let jitter = rand::thread_rng().gen_range(Duration::ZERO..=self.collect_period_jitter);
+        self.collect_period.saturating_add(jitter)
+    }
+
     fn send_feedback(&self, action: FeedbackAction) -> anyhow::Result<()> {
         Ok(self
             .feedback_channel
@@ -278,3 +605,224 @@ impl ProposalsCollection {
             .map_err(|_| anyhow!("Feedback channel closed."))?)
     }
 }
+
+/// Keeps vector sorted from the highest score.
+fn insert_sorted(list: &mut Vec<ProposalScore>, new: ProposalScore) {
+    let idx =
+        match list.binary_search_by(|proposal| new.score.partial_cmp(&proposal.score).unwrap()) {
+            Ok(idx) => idx + 1,
+            Err(idx) => idx,
+        };
+    list.insert(idx, new);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::ProposalView;
+    use std::time::Instant;
+    use ya_agreement_utils::OfferTemplate;
+    use ya_client_model::market::proposal::State;
+
+    fn config(period_ms: u64, jitter_ms: u64) -> CollectionConfig {
+        CollectionConfig {
+            collect_period: Some(Duration::from_millis(period_ms)),
+            collect_period_jitter: Some(Duration::from_millis(jitter_ms)),
+            collect_amount: None,
+            min_collect_time: None,
+            goal: DecideGoal::Batch(1),
+            invalid_score: InvalidScorePolicy::Reject,
+            busy_reason: BusyReasonConfig::default(),
+            score_pointer: default_score_pointer(),
+        }
+    }
+
+    fn collection(period_ms: u64, jitter_ms: u64) -> ProposalsCollection {
+        ProposalsCollection::new(CollectionType::Proposal, config(period_ms, jitter_ms))
+    }
+
+    fn scored(id: &str, score: f64) -> ProposalScore {
+        let view = ProposalView {
+            content: OfferTemplate::default(),
+            id: id.to_string(),
+            issuer: Default::default(),
+            state: State::Draft,
+            timestamp: chrono::Utc::now(),
+        };
+        ProposalScore {
+            their: view.clone(),
+            our: view,
+            score,
+            breakdown: HashMap::new(),
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_collect_period_jitter_range() {
+        let collection = collection(100, 50);
+        let periods = (0..100)
+            .map(|_| collection.next_collect_period())
+            .collect::<Vec<_>>();
+
+        for period in &periods {
+            assert!(*period >= Duration::from_millis(100), "{:?}", period);
+            assert!(*period <= Duration::from_millis(150), "{:?}", period);
+        }
+        // Jitter should be sampled for each period separately.
+        assert!(periods.iter().any(|period| *period != periods[0]));
+    }
+
+    #[actix_rt::test]
+    async fn test_collect_period_with_jitter_elapses() {
+        let start = Instant::now();
+        let mut collection = collection(100, 100);
+        let mut feedback = collection.feedback_receiver.take().unwrap();
+
+        match feedback.recv().await.unwrap().action {
+            FeedbackAction::Decide(DecideReason::TimeElapsed) => {}
+            action => panic!("Unexpected feedback: {:?}", action),
+        }
+
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(100), "{:?}", elapsed);
+        // Leave margin for slow test environments.
+        assert!(elapsed < Duration::from_millis(500), "{:?}", elapsed);
+    }
+
+    #[actix_rt::test]
+    async fn test_min_collect_time_postpones_decision() {
+        let start = Instant::now();
+        let mut collection = collection(60000, 0);
+        collection.collect_amount = 2;
+        collection.min_collect_time = Duration::from_millis(200);
+        let mut feedback = collection.feedback_receiver.take().unwrap();
+
+        for id in ["first", "second", "third"] {
+            collection.new_scored(scored(id, 1.0), id).unwrap();
+        }
+
+        match feedback.recv().await.unwrap().action {
+            FeedbackAction::Decide(DecideReason::GoalReached) => {}
+            action => panic!("Unexpected feedback: {:?}", action),
+        }
+
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(200), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(1000), "{:?}", elapsed);
+        // Decision is postponed only once per collect period.
+        assert!(feedback.try_recv().is_err());
+    }
+
+    /// Collection without timers should decide only when polled after collect period.
+    #[test]
+    fn test_poll_timers_without_runtime() {
+        let mut collection =
+            ProposalsCollection::without_timers(CollectionType::Proposal, config(60000, 0));
+        let mut feedback = collection.feedback_receiver.take().unwrap();
+
+        collection.collect_deadline = Some(Instant::now() + Duration::from_secs(60));
+        collection.poll_timers().unwrap();
+        assert!(feedback.try_recv().is_err());
+
+        collection.collect_deadline = Some(Instant::now());
+        collection.poll_timers().unwrap();
+        match feedback.try_recv().unwrap().action {
+            FeedbackAction::Decide(DecideReason::TimeElapsed) => {}
+            action => panic!("Unexpected feedback: {:?}", action),
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_invalid_score_rejected() {
+        let mut collection = collection(60000, 0);
+        let mut feedback = collection.feedback_receiver.take().unwrap();
+
+        collection
+            .new_scored(scored("nan", f64::NAN), "nan")
+            .unwrap();
+        collection.new_scored(scored("low", 0.5), "low").unwrap();
+        collection.new_scored(scored("high", 1.0), "high").unwrap();
+
+        match feedback.recv().await.unwrap().action {
+            FeedbackAction::Reject {
+                id,
+                reason,
+                is_final,
+            } => {
+                assert_eq!(id, "nan");
+                assert_eq!(reason.code.as_deref(), Some(INVALID_SCORE));
+                assert!(!is_final);
+            }
+            action => panic!("Unexpected feedback: {:?}", action),
+        }
+
+        // Invalid score doesn't break ordering of valid Proposals.
+        collection.decide().unwrap();
+        match feedback.recv().await.unwrap().action {
+            FeedbackAction::Accept { id } => assert_eq!(id, "high"),
+            action => panic!("Unexpected feedback: {:?}", action),
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_empty_decision_reported() {
+        let mut collection = collection(60000, 0);
+        let mut feedback = collection.feedback_receiver.take().unwrap();
+
+        collection.decide().unwrap();
+        let item = feedback.recv().await.unwrap();
+        assert_eq!(item.collection_type, CollectionType::Proposal);
+        match item.action {
+            FeedbackAction::NoDecision { reason } => {
+                assert_eq!(reason, NoDecisionReason::NothingCollected)
+            }
+            action => panic!("Unexpected feedback: {:?}", action),
+        }
+        assert!(feedback.try_recv().is_err());
+    }
+
+    /// Collect period elapsing without any Proposal should be reported exactly once.
+    #[actix_rt::test]
+    async fn test_empty_period_single_no_decision() {
+        let mut collection = collection(100, 0);
+        collection.collection_type = CollectionType::Agreement;
+        let mut feedback = collection.feedback_receiver.take().unwrap();
+
+        match feedback.recv().await.unwrap().action {
+            FeedbackAction::Decide(DecideReason::TimeElapsed) => {}
+            action => panic!("Unexpected feedback: {:?}", action),
+        }
+        collection.decide().unwrap();
+
+        let no_decisions = std::iter::from_fn(|| feedback.try_recv().ok())
+            .filter(|feedback| {
+                feedback.collection_type == CollectionType::Agreement
+                    && matches!(
+                        feedback.action,
+                        FeedbackAction::NoDecision {
+                            reason: NoDecisionReason::NothingCollected,
+                        }
+                    )
+            })
+            .count();
+        assert_eq!(no_decisions, 1);
+    }
+
+    #[actix_rt::test]
+    async fn test_invalid_score_lowest_priority() {
+        let mut collection = collection(60000, 0);
+        collection.invalid_score = InvalidScorePolicy::LowestPriority;
+
+        collection
+            .new_scored(scored("inf", f64::INFINITY), "inf")
+            .unwrap();
+        collection.new_scored(scored("low", -10.0), "low").unwrap();
+
+        let order = collection
+            .awaiting
+            .iter()
+            .map(|proposal| proposal.their.id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(order, vec!["low", "inf"]);
+    }
+}
@@ -1,38 +1,107 @@
-use actix::{Actor, Context, Handler, StreamHandler};
+use actix::{Actor, Arbiter, Context, Handler, StreamHandler};
 use anyhow::anyhow;
 use futures::stream::select;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::convert::TryFrom;
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
 use tokio_stream::wrappers::UnboundedReceiverStream;
 
-use ya_client_model::market::proposal::State;
-use ya_client_model::market::NewOffer;
+use ya_client_model::market::{NewOffer, Proposal};
+use ya_client_model::NodeId;
 
-use crate::component::{NegotiationResult, NegotiatorComponent, ProposalView, Score};
+use crate::channel::{action_channel, ActionReceiver, ActionSender};
+use crate::component::{
+    diagnostics_query, AgreementResult, NegotiationResult, NegotiatorComponent, ProposalView,
+    ReevaluationHandle, ReevaluationRequest, Score,
+};
+use crate::decision::{
+    AgreementDecision, DecisionRules, FeedbackDecision, Negotiations, ProposalDecision,
+};
 use crate::negotiators::{
-    AgreementAction, AgreementRejected, AgreementSigned, ControlEvent, PostAgreementEvent,
-    ProposalAction, ProposalRejected, RequestAgreements,
+    AgreementAction, AgreementRejected, AgreementSigned, ControlEvent, DiagnosticDump,
+    DryRunProposal, NegotiationEvent, PostAgreementEvent, ProposalAction, ProposalRejected,
+    RequestAgreements, Restore, SetEventSink, Shutdown, Snapshot,
 };
 use crate::negotiators::{AgreementFinalized, CreateOffer, ReactToAgreement, ReactToProposal};
+use crate::rounds::{agreement_proposals, LatestProposals, NegotiationRounds};
 use crate::{NegotiatorsPack, ProposalsCollection};
 
 use crate::collection::{
-    CollectionConfig, CollectionType, DecideGoal, DecideReason, Feedback, FeedbackAction,
-    ProposalScore,
+    default_score_pointer, BusyReasonConfig, CollectionConfig, CollectionState, CollectionType,
+    DecideGoal, Feedback, InvalidScorePolicy, NoDecisionReason,
 };
 
 use ya_agreement_utils::agreement::expand;
-use ya_agreement_utils::{AgreementView, OfferTemplate};
-use ya_negotiator_component::reason::RejectReason;
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use ya_agreement_utils::OfferTemplate;
+use ya_negotiator_component::correlated_log;
+use ya_negotiator_component::correlation::correlate;
+use ya_negotiator_component::metrics::{Metrics, NoMetrics};
+
+/// Number of recent decisions kept for diagnostic purposes.
+const MAX_RECENT_DECISIONS: usize = 50;
+const MAX_PARKED_PROPOSALS: usize = 100;
+
+/// Code of rejection of `Ready` Proposal with `final-score` below `min_final_score`.
+pub const SCORE_BELOW_MINIMUM: &str = "SCORE_BELOW_MINIMUM";
+/// Code of rejection of Proposals and Agreements issued by our own node.
+pub const SELF_NEGOTIATION: &str = "SELF_NEGOTIATION";
+/// Code of rejection of Draft Proposals, which we would counter with unchanged Proposal.
+pub const NO_PROGRESS: &str = "NO_PROGRESS";
+/// Code of rejection of Proposals exceeding `CompositeNegotiatorConfig::max_rounds`.
+pub const TOO_MANY_ROUNDS: &str = "TOO_MANY_ROUNDS";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct CompositeNegotiatorConfig {
     pub proposals: CollectionConfig,
     pub agreements: CollectionConfig,
+    /// `Ready` Proposals with score (`final-score` by default, see `score_pointer`) below
+    /// this value are rejected immediately with final flag, before they are added to Proposals
+    /// collection. Proposals without score are treated as scored 0.0. No limit, if not set.
+    #[serde(default)]
+    pub min_final_score: Option<f64>,
+    /// Capacity of channels with Proposal and Agreement actions. When channel is full,
+    /// Negotiator refuses to react to new Proposals and Agreements, so caller should
+    /// retry after consuming pending actions. Actions decided by collections meanwhile
+    /// are queued and delivered in order, as receiver drains the channel.
+    /// Channels are unbounded, if not set.
+    #[serde(default)]
+    pub channel_capacity: Option<usize>,
+    /// Id of our node. If set, Proposals and Agreements issued by this node are
+    /// rejected, so Negotiator never negotiates with itself.
+    #[serde(default)]
+    pub node_id: Option<NodeId>,
+    /// Remove components working directories on `Shutdown`. Useful for components,
+    /// which persist state that shouldn't outlive the Negotiator.
+    #[serde(default)]
+    pub cleanup_working_dirs: bool,
+    /// Fail creating Negotiator, if there are no components to create. Pack without
+    /// components accepts all Proposals, so otherwise this situation is only logged.
+    #[serde(default)]
+    pub require_non_empty: bool,
+    /// Reject Draft Proposals instead of countering them with Proposal identical
+    /// to our previous one. Such negotiations would never end, since other party
+    /// has nothing new to respond to.
+    #[serde(default)]
+    pub reject_no_progress: bool,
+    /// Maximal number of Proposals from other party in single negotiation. Proposal
+    /// exceeding it is rejected with final flag, so buggy counterparty can't keep
+    /// negotiating forever. No limit, if not set.
+    #[serde(default)]
+    pub max_rounds: Option<u32>,
+    /// Promote Draft Proposals to Agreement only, if any component returned `Accept`.
+    /// `Ready` Draft Proposals are countered instead. By default `Ready` and `Accept`
+    /// are treated the same and Draft Proposals are promoted in both cases.
+    /// Note: At least one component must return `Accept`, otherwise no Proposal is ever
+    /// promoted. None of builtin negotiators does. Use it with `reject_no_progress` or
+    /// `max_rounds`, so negotiations countered with unchanged Proposals end.
+    #[serde(default)]
+    pub explicit_accept: bool,
 }
 
 /// Actor implementing Negotiation logic.
@@ -54,25 +123,62 @@ pub struct CompositeNegotiatorConfig {
 pub struct Negotiator {
     components: NegotiatorsPack,
 
-    proposal_channel: mpsc::UnboundedSender<ProposalAction>,
-    agreement_channel: mpsc::UnboundedSender<AgreementAction>,
+    proposal_channel: ActionSender<ProposalAction>,
+    agreement_channel: ActionSender<AgreementAction>,
 
     proposals: ProposalsCollection,
     agreements: ProposalsCollection,
 
-    /// Mapping between Proposal Ids and Agreements.
-    /// ProposalCollection stores only ProposalIds, so we must retrieve
-    /// Agreement id somehow.
-    proposal_agreement: HashMap<String, String>,
-    /// Mapping between Proposals/Agreements and related subscription Ids.
-    /// Note: In theory it is possible to have conflict between Agreement and Proposal
-    /// Ids, but in practise probability is very low.
-    subscriptions: HashMap<String, String>,
+    /// Subscriptions of negotiated Proposals and Agreements. ProposalCollection
+    /// stores only Proposal ids, so we must retrieve Agreement id somehow.
+    negotiations: Negotiations,
+    /// State of negotiations, that we countered.
+    rounds: NegotiationRounds,
+    /// Proposals received by `NegotiatorAddr`, used to drop results of superseded ones.
+    latest: LatestProposals,
+    /// Last decisions sent to Requestor/Provider, the oldest first.
+    decisions: VecDeque<Decision>,
+    rules: DecisionRules,
+    /// Proposals rejected with `is_final` set to false, the oldest first.
+    /// Components can request their re-evaluation with `ReevaluationRequest`.
+    parked: VecDeque<ReactToProposal>,
+    reevaluation_receiver: Option<mpsc::UnboundedReceiver<ReevaluationRequest>>,
+    event_sink: Option<mpsc::UnboundedSender<NegotiationEvent>>,
+    /// Directories removed on `Shutdown`.
+    cleanup_dirs: Vec<PathBuf>,
+    /// Negotiator runs on Arbiter created only for it, which must be stopped with it.
+    dedicated_arbiter: bool,
+}
+
+/// State of Negotiator, that allows to continue negotiations after process restart.
+/// Parked Proposals aren't included.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NegotiatorState {
+    pub proposals: CollectionState,
+    pub agreements: CollectionState,
+    pub proposal_agreement: HashMap<String, String>,
+    pub subscriptions: HashMap<String, String>,
+    pub pending_approval: HashSet<String>,
+    pub scores: HashMap<String, Score>,
+    #[serde(default)]
+    pub rounds: HashMap<String, u32>,
+    #[serde(default)]
+    pub histories: HashMap<String, Vec<ProposalView>>,
+    /// State of components keyed by their names. See `NegotiatorComponent::serialize_state`.
+    #[serde(default)]
+    pub components: Value,
+}
+
+/// Decision made by Negotiator about Proposal or Agreement.
+#[derive(Debug, Clone, Serialize)]
+struct Decision {
+    id: String,
+    action: String,
 }
 
 pub struct NegotiatorCallbacks {
-    pub proposal_channel: mpsc::UnboundedReceiver<ProposalAction>,
-    pub agreement_channel: mpsc::UnboundedReceiver<AgreementAction>,
+    pub proposal_channel: ActionReceiver<ProposalAction>,
+    pub agreement_channel: ActionReceiver<AgreementAction>,
 }
 
 impl Negotiator {
@@ -80,17 +186,47 @@ impl Negotiator {
         components: NegotiatorsPack,
         config: CompositeNegotiatorConfig,
     ) -> (Negotiator, NegotiatorCallbacks) {
-        let (proposal_sender, proposal_receiver) = mpsc::unbounded_channel();
-        let (agreement_sender, agreement_receiver) = mpsc::unbounded_channel();
+        Negotiator::with_metrics(components, config, Arc::new(NoMetrics))
+    }
+
+    /// Creates Negotiator reporting components steps and collections
+    /// decisions to `metrics` sink.
+    pub fn with_metrics(
+        components: NegotiatorsPack,
+        config: CompositeNegotiatorConfig,
+        metrics: Arc<dyn Metrics>,
+    ) -> (Negotiator, NegotiatorCallbacks) {
+        let (proposal_sender, proposal_receiver) = action_channel(config.channel_capacity);
+        let (agreement_sender, agreement_receiver) = action_channel(config.channel_capacity);
+
+        let rules = DecisionRules::new(&config);
+        let mut proposals = ProposalsCollection::new(CollectionType::Proposal, config.proposals);
+        let mut agreements = ProposalsCollection::new(CollectionType::Agreement, config.agreements);
+        proposals.set_metrics(metrics.clone());
+        agreements.set_metrics(metrics.clone());
+
+        let (reevaluation_sender, reevaluation_receiver) = mpsc::unbounded_channel();
+        let mut components = components.with_metrics(metrics);
+        components.set_reevaluation_handle(ReevaluationHandle::new(move |request| {
+            reevaluation_sender.send(request).ok();
+        }));
 
         let negotiator = Negotiator {
             components,
-            proposal_channel: proposal_sender.clone(),
+            proposal_channel: proposal_sender,
             agreement_channel: agreement_sender,
-            proposals: ProposalsCollection::new(CollectionType::Proposal, config.proposals),
-            agreements: ProposalsCollection::new(CollectionType::Agreement, config.agreements),
-            proposal_agreement: Default::default(),
-            subscriptions: Default::default(),
+            proposals,
+            agreements,
+            negotiations: Default::default(),
+            rounds: Default::default(),
+            latest: Default::default(),
+            decisions: Default::default(),
+            rules,
+            parked: Default::default(),
+            reevaluation_receiver: Some(reevaluation_receiver),
+            event_sink: None,
+            cleanup_dirs: vec![],
+            dedicated_arbiter: false,
         };
 
         let callbacks = NegotiatorCallbacks {
@@ -100,6 +236,179 @@ impl Negotiator {
 
         return (negotiator, callbacks);
     }
+
+    /// Negotiator will send `NegotiationEvent` for each decision and lifecycle
+    /// notification to `sink`.
+    pub fn with_event_sink(mut self, sink: mpsc::UnboundedSender<NegotiationEvent>) -> Negotiator {
+        self.event_sink = Some(sink);
+        self
+    }
+
+    /// Negotiator will remove `dirs` after shutting down components.
+    pub fn with_cleanup_dirs(mut self, dirs: Vec<PathBuf>) -> Negotiator {
+        self.cleanup_dirs = dirs;
+        self
+    }
+
+    /// Negotiator will stop Arbiter it was started on, when it stops itself.
+    pub(crate) fn with_dedicated_arbiter(mut self) -> Negotiator {
+        self.dedicated_arbiter = true;
+        self
+    }
+
+    pub(crate) fn latest_proposals(&self) -> LatestProposals {
+        self.latest.clone()
+    }
+
+    /// Event sink is optional, so failing to notify it isn't an error.
+    fn emit(&mut self, event: NegotiationEvent) {
+        let closed = match &self.event_sink {
+            Some(sink) => sink.send(event).is_err(),
+            None => false,
+        };
+        if closed {
+            log::debug!("Negotiation events receiver dropped. Stop sending events.");
+            self.event_sink = None;
+        }
+    }
+
+    pub fn snapshot(&self) -> anyhow::Result<NegotiatorState> {
+        Ok(NegotiatorState {
+            proposals: self.proposals.snapshot(),
+            agreements: self.agreements.snapshot(),
+            proposal_agreement: self.negotiations.proposal_agreement.clone(),
+            subscriptions: self.negotiations.subscriptions.clone(),
+            pending_approval: self.negotiations.pending_approval.clone(),
+            scores: self.rounds.scores.clone(),
+            rounds: self.rounds.rounds.clone(),
+            histories: self.rounds.histories.clone(),
+            components: self.components.serialize_state()?,
+        })
+    }
+
+    /// Replaces negotiations state with `state` taken from other Negotiator.
+    /// Should be called on freshly created Negotiator.
+    pub fn restore(&mut self, state: NegotiatorState) -> anyhow::Result<()> {
+        self.proposals.restore(state.proposals)?;
+        self.agreements.restore(state.agreements)?;
+        self.negotiations = Negotiations {
+            proposal_agreement: state.proposal_agreement,
+            subscriptions: state.subscriptions,
+            pending_approval: state.pending_approval,
+        };
+        self.rounds = NegotiationRounds {
+            scores: state.scores,
+            rounds: state.rounds,
+            histories: state.histories,
+        };
+        self.components.restore_state(state.components)
+    }
+
+    fn no_decision(&mut self, collection_type: CollectionType, reason: NoDecisionReason) {
+        self.emit(NegotiationEvent::NoDecision {
+            collection: collection_type.to_string(),
+            reason,
+        });
+    }
+
+    fn send_proposal_action(
+        &mut self,
+        action: ProposalAction,
+    ) -> Result<(), TrySendError<ProposalAction>> {
+        self.remember_decision(action.id(), action.to_string());
+        self.emit(NegotiationEvent::ProposalDecision(action.clone()));
+        if let ProposalAction::RejectProposal { id, .. } = &action {
+            self.forget_proposal(id);
+        }
+        self.proposal_channel.send(action)
+    }
+
+    fn send_agreement_action(
+        &mut self,
+        action: AgreementAction,
+    ) -> Result<(), TrySendError<AgreementAction>> {
+        self.remember_decision(action.id(), action.to_string());
+        self.emit(NegotiationEvent::AgreementDecision(action.clone()));
+        self.agreement_channel.send(action)
+    }
+
+    /// Agreements kept pending during approval can be rejected or chosen now,
+    /// if no other approval is outstanding.
+    fn approval_succeeded(&mut self, agreement_id: &str) -> anyhow::Result<()> {
+        if !self.negotiations.pending_approval.remove(agreement_id) || !self.agreements.approved() {
+            return Ok(());
+        }
+        self.decide(CollectionType::Agreement)
+    }
+
+    /// Agreement approved by us wasn't signed, so we can choose another
+    /// one in it's place.
+    fn approval_failed(&mut self, agreement_id: &str) -> anyhow::Result<()> {
+        if !self.negotiations.pending_approval.remove(agreement_id) {
+            return Ok(());
+        }
+
+        correlated_log!(
+            log::Level::Info,
+            "Approved Agreement [{}] wasn't signed. Choosing another Agreement.",
+            agreement_id
+        );
+        self.agreements.reconsider();
+        self.decide(CollectionType::Agreement)
+    }
+
+    /// Lets components rescore collected batch, before choosing best of them.
+    fn decide(&mut self, collection_type: CollectionType) -> anyhow::Result<()> {
+        let collection = match collection_type {
+            CollectionType::Agreement => &mut self.agreements,
+            CollectionType::Proposal => &mut self.proposals,
+        };
+        self.rules
+            .decide_collection(&mut self.components, collection)
+    }
+
+    /// Negotiation won't be continued, so we don't need its state anymore.
+    fn forget_proposal(&mut self, proposal_id: &str) {
+        self.rounds.forget(proposal_id);
+        self.negotiations.subscriptions.remove(proposal_id);
+    }
+
+    fn forget_expired(&mut self) {
+        for id in self.rounds.forget_expired() {
+            log::debug!("Negotiation of Proposal [{}] expired.", id);
+            self.negotiations.subscriptions.remove(&id);
+        }
+    }
+
+    /// Agreement was signed or terminated, so neither Agreement nor Proposals,
+    /// it was created from, will be negotiated anymore.
+    fn forget_agreement(&mut self, agreement_id: &str) {
+        for proposal_id in self.negotiations.forget_agreement(agreement_id) {
+            self.rounds.forget(&proposal_id);
+        }
+    }
+
+    fn park(&mut self, msg: ReactToProposal) {
+        if self.parked.len() >= MAX_PARKED_PROPOSALS {
+            self.parked.pop_front();
+        }
+        self.parked.push_back(msg);
+    }
+
+    fn unpark(&mut self, proposal_id: &str) -> Option<ReactToProposal> {
+        let idx = self
+            .parked
+            .iter()
+            .position(|msg| msg.incoming_proposal.proposal_id == proposal_id)?;
+        self.parked.remove(idx)
+    }
+
+    fn remember_decision(&mut self, id: String, action: String) {
+        if self.decisions.len() >= MAX_RECENT_DECISIONS {
+            self.decisions.pop_front();
+        }
+        self.decisions.push_back(Decision { id, action });
+    }
 }
 
 impl Handler<CreateOffer> for Negotiator {
@@ -107,6 +416,13 @@ impl Handler<CreateOffer> for Negotiator {
 
     fn handle(&mut self, msg: CreateOffer, _: &mut Context<Self>) -> Self::Result {
         let offer_template = self.components.fill_template(msg.offer_template)?;
+        self.components
+            .on_offer_published(&offer_template)
+            .map_err(|e| log::warn!("Failed to notify components about published Offer. {e}"))
+            .ok();
+        self.emit(NegotiationEvent::OfferPublished {
+            offer: offer_template.clone(),
+        });
         Ok(NewOffer::new(
             offer_template.properties,
             offer_template.constraints,
@@ -114,185 +430,248 @@ impl Handler<CreateOffer> for Negotiator {
     }
 }
 
-impl Handler<ReactToProposal> for Negotiator {
-    type Result = anyhow::Result<()>;
+/// Our previous Proposal is a template for changes made by components.
+pub(crate) fn template_from(our_prev_proposal: Proposal) -> ProposalView {
+    ProposalView {
+        content: OfferTemplate {
+            properties: expand(our_prev_proposal.properties),
+            constraints: our_prev_proposal.constraints,
+        },
+        id: our_prev_proposal.proposal_id,
+        issuer: our_prev_proposal.issuer_id,
+        state: our_prev_proposal.state,
+        timestamp: our_prev_proposal.timestamp,
+    }
+}
 
-    fn handle(&mut self, msg: ReactToProposal, _: &mut Context<Self>) -> Self::Result {
-        log::debug!(
+impl Handler<DryRunProposal> for Negotiator {
+    type Result = anyhow::Result<NegotiationResult>;
+
+    fn handle(&mut self, msg: DryRunProposal, _: &mut Context<Self>) -> Self::Result {
+        let _correlation = correlate(&msg.incoming_proposal.proposal_id);
+        correlated_log!(
+            log::Level::Debug,
+            "Dry run of Proposal [{}] from [{}]",
+            msg.incoming_proposal.proposal_id,
+            msg.incoming_proposal.issuer_id
+        );
+
+        // Score from previous round must stay available for real negotiations.
+        let prev_score = msg
+            .our_prev_proposal
+            .prev_proposal_id
+            .as_ref()
+            .and_then(|prev_id| self.rounds.scores.get(prev_id).cloned())
+            .unwrap_or_default();
+
+        let history = msg
+            .our_prev_proposal
+            .prev_proposal_id
+            .as_ref()
+            .and_then(|prev_id| self.rounds.histories.get(prev_id).cloned())
+            .unwrap_or_default();
+
+        let their = ProposalView::try_from(&msg.incoming_proposal)?;
+        let template = template_from(msg.our_prev_proposal);
+
+        self.components
+            .dry_run_step(&their, &history, template, prev_score)
+    }
+}
+
+impl Negotiator {
+    fn react_to_proposal(&mut self, msg: ReactToProposal) -> anyhow::Result<()> {
+        let our_id = msg.incoming_proposal.prev_proposal_id.clone();
+        if self.proposal_channel.is_full() {
+            // Caller will send Proposal again, so it shouldn't be tracked meanwhile.
+            self.latest
+                .finish(our_id.as_ref(), &msg.incoming_proposal.proposal_id);
+            return Err(anyhow!(
+                "Negotiator overloaded. Can't react to Proposal [{}], until pending actions are consumed.",
+                msg.incoming_proposal.proposal_id
+            ));
+        }
+
+        let _correlation = correlate(&msg.incoming_proposal.proposal_id);
+        correlated_log!(
+            log::Level::Debug,
             "Reacting to Proposal [{}] from [{}]",
             msg.incoming_proposal.proposal_id,
             msg.incoming_proposal.issuer_id
         );
 
-        self.subscriptions.insert(
+        // Other party sent newer Proposal in this negotiation, while this one was
+        // waiting in mailbox. Responding to outdated Proposal makes no sense.
+        if let Some(newer_id) = self
+            .latest
+            .superseded(our_id.as_ref(), &msg.incoming_proposal.proposal_id)
+        {
+            correlated_log!(
+                log::Level::Info,
+                "Proposal [{}] was superseded by Proposal [{}]. Skipping it.",
+                msg.incoming_proposal.proposal_id,
+                newer_id
+            );
+            return Ok(());
+        }
+
+        let their = ProposalView::try_from(&msg.incoming_proposal).map_err(|e| {
+            self.latest
+                .finish(our_id.as_ref(), &msg.incoming_proposal.proposal_id);
+            e
+        })?;
+
+        self.negotiations.subscriptions.insert(
             msg.incoming_proposal.proposal_id.clone(),
             msg.subscription_id.clone(),
         );
 
-        let their = ProposalView::try_from(&msg.incoming_proposal)?;
-        let template = ProposalView {
-            content: OfferTemplate {
-                properties: expand(msg.our_prev_proposal.properties),
-                constraints: msg.our_prev_proposal.constraints,
-            },
-            id: msg.our_prev_proposal.proposal_id,
-            issuer: msg.our_prev_proposal.issuer_id,
-            state: msg.our_prev_proposal.state,
-            timestamp: msg.our_prev_proposal.timestamp,
-        };
-
-        let result = self
-            .components
-            .negotiate_step(&their, template, Score::default())?;
+        self.forget_expired();
+
+        // Our previous Proposal was a response to their previous Proposal, so we can
+        // find Score computed in previous round of this negotiation.
+        let prev = self
+            .rounds
+            .take(msg.our_prev_proposal.prev_proposal_id.as_ref());
+
+        let template = template_from(msg.our_prev_proposal.clone());
+
+        let decision = self
+            .rules
+            .decide_proposal(&mut self.components, &their, &prev, template);
+
+        // Newer Proposal could arrive during evaluation as well. Components could
+        // reserve something for this one, so they must release it.
+        if let Some(newer_id) = self.latest.finish(our_id.as_ref(), &their.id) {
+            correlated_log!(
+                log::Level::Info,
+                "Proposal [{}] was superseded by Proposal [{}] during evaluation. Dropping result.",
+                their.id,
+                newer_id
+            );
+            self.components.on_proposal_rejected(&their.id).ok();
+            self.rounds
+                .put_back(msg.our_prev_proposal.prev_proposal_id.as_ref(), prev);
+            self.negotiations.subscriptions.remove(&their.id);
+            return Ok(());
+        }
 
-        match result {
-            NegotiationResult::Reject { reason, is_final } => {
-                self.proposal_channel.send(ProposalAction::RejectProposal {
+        match decision? {
+            ProposalDecision::Reject { reason, reevaluate } => {
+                self.send_proposal_action(ProposalAction::RejectProposal {
+                    subscription_id: msg.subscription_id.clone(),
+                    id: their.id,
+                    reason,
+                })?;
+                if reevaluate {
+                    self.park(msg);
+                }
+            }
+            ProposalDecision::Counter {
+                proposal: our,
+                score,
+            } => {
+                self.rounds.countered(&their, prev, score);
+                self.send_proposal_action(ProposalAction::CounterProposal {
                     subscription_id: msg.subscription_id,
-                    id: their.id.clone(),
-                    reason: reason.final_flag(is_final).into(),
+                    id: their.id,
+                    proposal: our.into(),
                 })?;
             }
-            NegotiationResult::Ready {
+            ProposalDecision::Ready {
                 proposal: our,
                 score,
-            } => match their.state {
-                State::Initial => {
-                    // We must counter Initial Proposal even, if it is ready to promote to Agreement.
-                    // ProposalsCollection should store only fully negotiated Proposals.
-                    self.proposal_channel
-                        .send(ProposalAction::CounterProposal {
-                            subscription_id: msg.subscription_id,
-                            id: their.id.clone(),
-                            proposal: our.into(),
-                        })?;
-                }
-                State::Draft => {
-                    let id = their.id.clone();
-                    self.proposals.new_scored(
-                        ProposalScore {
-                            their,
-                            our,
-                            score: score.pointer_typed("/final-score").unwrap_or(0.0),
-                        },
-                        &id,
-                    )?;
-                }
-                _ => {
-                    log::warn!("Invalid Proposal [{}] state {:?}", their.id, their.state)
-                }
-            },
-
-            NegotiationResult::Negotiating { proposal: our, .. } => {
-                self.proposal_channel
-                    .send(ProposalAction::CounterProposal {
-                        subscription_id: msg.subscription_id,
-                        id: their.id.clone(),
-                        proposal: our.into(),
-                    })?;
+            } => {
+                let id = their.id.clone();
+                self.rules
+                    .collect(&mut self.proposals, &id, their, our, &score)?;
             }
         }
         Ok(())
     }
 }
 
-pub fn to_proposal_views(
-    mut agreement: AgreementView,
-) -> anyhow::Result<(ProposalView, ProposalView)> {
-    // Dispatch Agreement into separate Demand-Offer Proposal pair.
-    let offer_id = agreement.pointer_typed("/offer/offerId")?;
-    let demand_id = agreement.pointer_typed("/demand/demandId")?;
-    let offer_proposal = agreement
-        .json
-        .pointer_mut("/offer/properties")
-        .map(Value::take)
-        .unwrap_or(Value::Null);
-
-    let demand_proposal = agreement
-        .json
-        .pointer_mut("/demand/properties")
-        .map(Value::take)
-        .unwrap_or(Value::Null);
-
-    let offer_proposal = ProposalView {
-        content: OfferTemplate {
-            properties: offer_proposal,
-            constraints: agreement.pointer_typed("/offer/constraints")?,
-        },
-        id: offer_id,
-        issuer: agreement.pointer_typed("/offer/providerId")?,
-        state: State::Accepted,
-        timestamp: agreement.creation_timestamp()?,
-    };
+impl Handler<ReactToProposal> for Negotiator {
+    type Result = anyhow::Result<()>;
 
-    let demand_proposal = ProposalView {
-        content: OfferTemplate {
-            properties: demand_proposal,
-            constraints: agreement.pointer_typed("/demand/constraints")?,
-        },
-        id: demand_id,
-        issuer: agreement.pointer_typed("/demand/requestorId")?,
-        state: State::Accepted,
-        timestamp: agreement.creation_timestamp()?,
-    };
-    Ok((demand_proposal, offer_proposal))
+    fn handle(&mut self, msg: ReactToProposal, _: &mut Context<Self>) -> Self::Result {
+        self.react_to_proposal(msg)
+    }
+}
+
+/// Re-evaluates parked Proposals on components request. Proposals, which aren't
+/// parked anymore, are ignored.
+impl StreamHandler<ReevaluationRequest> for Negotiator {
+    fn handle(&mut self, item: ReevaluationRequest, _ctx: &mut Context<Self>) {
+        for id in item.proposal_ids {
+            let msg = match self.unpark(&id) {
+                Some(msg) => msg,
+                None => continue,
+            };
+
+            log::info!("Re-evaluating parked Proposal [{}].", id);
+            self.react_to_proposal(msg)
+                .map_err(|e| log::warn!("Failed to re-evaluate Proposal [{}]. {}", id, e))
+                .ok();
+        }
+    }
+
+    /// Components keep handle to this stream, so it is never finished while
+    /// Negotiator is alive. We don't want to stop the actor anyway.
+    fn finished(&mut self, _ctx: &mut Context<Self>) {}
 }
 
 impl Handler<ReactToAgreement> for Negotiator {
     type Result = anyhow::Result<()>;
 
     fn handle(&mut self, msg: ReactToAgreement, _: &mut Context<Self>) -> Self::Result {
-        log::debug!("Reacting to Agreement [{}]", msg.agreement.id);
+        if self.agreement_channel.is_full() {
+            return Err(anyhow!(
+                "Negotiator overloaded. Can't react to Agreement [{}], until pending actions are consumed.",
+                msg.agreement.id
+            ));
+        }
+
+        let _correlation = correlate(&msg.agreement.id);
+        correlated_log!(
+            log::Level::Debug,
+            "Reacting to Agreement [{}]",
+            msg.agreement.id
+        );
 
         let agreement_id = msg.agreement.id.clone();
-        let (their, our) = to_proposal_views(msg.agreement.clone()).map_err(|e| {
+        let (their, our) = msg.agreement.as_proposal_views().map_err(|e| {
             anyhow!(
                 "Negotiator failed to extract Proposals from Agreement. {}",
                 e
             )
         })?;
 
-        self.proposal_agreement
-            .insert(their.id.clone(), agreement_id.clone());
-        self.proposal_agreement
-            .insert(our.id.clone(), agreement_id.clone());
-
-        self.subscriptions
-            .insert(msg.agreement.id.clone(), msg.subscription_id.clone());
-
         // We expect that all `NegotiatorComponents` should return ready state.
         // Otherwise we must reject Agreement proposals, because negotiations weren't finished.
-        match self
-            .components
-            .negotiate_step(&their, our, Score::default())?
-        {
-            NegotiationResult::Ready { proposal, score } => {
-                self.agreements.new_scored(
-                    ProposalScore {
-                        their,
-                        our: proposal,
-                        score: score.pointer_typed("/final-score").unwrap_or(0.0),
-                    },
+        match self.rules.decide_agreement(
+            &mut self.components,
+            &agreement_id,
+            &their,
+            our.clone(),
+        )? {
+            AgreementDecision::Ready { proposal, score } => {
+                self.negotiations.collect_agreement(
                     &agreement_id,
-                )?;
-            }
-            NegotiationResult::Reject { reason, is_final } => {
-                self.agreement_channel
-                    .send(AgreementAction::RejectAgreement {
-                        id: agreement_id,
-                        subscription_id: msg.subscription_id,
-                        reason: reason.final_flag(is_final).into(),
-                    })?;
+                    &their,
+                    &our,
+                    msg.subscription_id.clone(),
+                );
+
+                self.rules
+                    .collect(&mut self.agreements, &agreement_id, their, proposal, &score)?;
             }
-            NegotiationResult::Negotiating { .. } => {
-                self.agreement_channel
-                    .send(AgreementAction::RejectAgreement {
-                        id: agreement_id,
-                        subscription_id: msg.subscription_id,
-                        reason: RejectReason::new("Negotiations aren't finished.")
-                            .final_flag(true)
-                            .into(),
-                    })?;
+            AgreementDecision::Reject { reason } => {
+                self.send_agreement_action(AgreementAction::RejectAgreement {
+                    id: agreement_id,
+                    subscription_id: msg.subscription_id,
+                    reason,
+                })?;
             }
         }
         Ok(())
@@ -303,6 +682,15 @@ impl Handler<AgreementSigned> for Negotiator {
     type Result = anyhow::Result<()>;
 
     fn handle(&mut self, msg: AgreementSigned, _: &mut Context<Self>) -> Self::Result {
+        let _correlation = correlate(&msg.agreement.id);
+        self.approval_succeeded(&msg.agreement.id)?;
+        self.forget_agreement(&msg.agreement.id);
+        for proposal_id in agreement_proposals(&msg.agreement) {
+            self.forget_proposal(&proposal_id);
+        }
+        self.emit(NegotiationEvent::AgreementSigned {
+            agreement_id: msg.agreement.id.clone(),
+        });
         self.components.on_agreement_approved(&msg.agreement)
     }
 }
@@ -311,6 +699,19 @@ impl Handler<AgreementFinalized> for Negotiator {
     type Result = anyhow::Result<()>;
 
     fn handle(&mut self, msg: AgreementFinalized, _: &mut Context<Self>) -> Self::Result {
+        let _correlation = correlate(&msg.agreement_id);
+        self.emit(NegotiationEvent::AgreementFinalized {
+            agreement_id: msg.agreement_id.clone(),
+            result: msg.result.clone(),
+        });
+        if matches!(
+            msg.result,
+            AgreementResult::BrokenByUs { .. } | AgreementResult::BrokenByThem { .. }
+        ) {
+            self.approval_failed(&msg.agreement_id)?;
+        }
+        self.forget_agreement(&msg.agreement_id);
+
         self.components
             .on_agreement_terminated(&msg.agreement_id, &msg.result)
     }
@@ -320,12 +721,13 @@ impl Handler<AgreementRejected> for Negotiator {
     type Result = anyhow::Result<()>;
 
     fn handle(&mut self, msg: AgreementRejected, _: &mut Context<Self>) -> Self::Result {
-        let id = msg.agreement_id;
-        log::debug!(
-            "AgreementRejected for [{id}] ignored, since event isn't supported by negotiators yet."
-        );
+        let _correlation = correlate(&msg.agreement_id);
+        self.emit(NegotiationEvent::AgreementRejected {
+            agreement_id: msg.agreement_id.clone(),
+        });
         //self.components.on_agreement_rejected(&msg.agreement_id)
-        Ok(())
+        self.forget_agreement(&msg.agreement_id);
+        self.approval_failed(&msg.agreement_id)
     }
 }
 
@@ -334,6 +736,12 @@ impl Handler<ProposalRejected> for Negotiator {
 
     fn handle(&mut self, msg: ProposalRejected, _: &mut Context<Self>) -> Self::Result {
         // TODO: Pass reason to components.
+        let _correlation = correlate(&msg.proposal_id);
+        self.emit(NegotiationEvent::ProposalRejected {
+            proposal_id: msg.proposal_id.clone(),
+            reason: msg.reason.clone(),
+        });
+        self.forget_proposal(&msg.proposal_id);
         self.components.on_proposal_rejected(&msg.proposal_id)
     }
 }
@@ -342,6 +750,11 @@ impl Handler<PostAgreementEvent> for Negotiator {
     type Result = anyhow::Result<()>;
 
     fn handle(&mut self, msg: PostAgreementEvent, _: &mut Context<Self>) -> Self::Result {
+        let _correlation = correlate(&msg.agreement_id);
+        self.emit(NegotiationEvent::PostAgreementEvent {
+            agreement_id: msg.agreement_id.clone(),
+            event: msg.event.clone(),
+        });
         self.components
             .on_agreement_event(&msg.agreement_id, &msg.event)
     }
@@ -355,6 +768,79 @@ impl Handler<ControlEvent> for Negotiator {
     }
 }
 
+impl Handler<SetEventSink> for Negotiator {
+    type Result = ();
+
+    fn handle(&mut self, msg: SetEventSink, _: &mut Context<Self>) -> Self::Result {
+        self.event_sink = Some(msg.0);
+    }
+}
+
+impl Handler<Snapshot> for Negotiator {
+    type Result = anyhow::Result<NegotiatorState>;
+
+    fn handle(&mut self, _msg: Snapshot, _: &mut Context<Self>) -> Self::Result {
+        self.snapshot()
+    }
+}
+
+impl Handler<Restore> for Negotiator {
+    type Result = anyhow::Result<()>;
+
+    fn handle(&mut self, msg: Restore, _: &mut Context<Self>) -> Self::Result {
+        self.restore(msg.0)
+    }
+}
+
+impl Handler<DiagnosticDump> for Negotiator {
+    type Result = anyhow::Result<serde_json::Value>;
+
+    fn handle(&mut self, _msg: DiagnosticDump, _: &mut Context<Self>) -> Self::Result {
+        let names = self.components.component_names();
+        let components_state = names
+            .iter()
+            .map(|name| {
+                let state = self
+                    .components
+                    .control_event(name, diagnostics_query())
+                    .unwrap_or_else(|e| serde_json::json!({ "error": e.to_string() }));
+                (name.clone(), state)
+            })
+            .collect::<serde_json::Map<_, _>>();
+
+        let proposals = self.proposals.summary();
+        let agreements = self.agreements.summary();
+
+        Ok(serde_json::json!({
+            "components": names,
+            "goals": {
+                "proposals": proposals.goal,
+                "agreements": agreements.goal,
+            },
+            "collections": {
+                "proposals": proposals,
+                "agreements": agreements,
+            },
+            "components-state": components_state,
+            "recent-decisions": self.decisions,
+        }))
+    }
+}
+
+impl Handler<Shutdown> for Negotiator {
+    type Result = anyhow::Result<()>;
+
+    fn handle(&mut self, msg: Shutdown, _: &mut Context<Self>) -> Self::Result {
+        let result = self.components.shutdown(msg.timeout);
+        for dir in self.cleanup_dirs.drain(..) {
+            if let Err(e) = std::fs::remove_dir_all(&dir) {
+                log::warn!("Failed to remove working dir {}. {e}", dir.display());
+            }
+        }
+        result
+    }
+}
+
 impl Handler<RequestAgreements> for Negotiator {
     type Result = ();
 
@@ -368,129 +854,35 @@ impl Handler<RequestAgreements> for Negotiator {
 /// number of artifacts collected, timeouts etc.
 impl StreamHandler<Feedback> for Negotiator {
     fn handle(&mut self, item: Feedback, _ctx: &mut Context<Self>) {
-        match item.collection_type {
-            CollectionType::Agreement => match item.action {
-                FeedbackAction::Decide(reason) => {
-                    match reason {
-                        DecideReason::TimeElapsed => {
-                            log::debug!("Choosing Agreements, because collect period elapsed.")
-                        }
-                        DecideReason::GoalReached => log::debug!(
-                            "Choosing Agreements, because collected expected number of them."
-                        ),
-                    };
-                    self.agreements.decide()
-                }
-                FeedbackAction::Accept { id } => {
-                    let proposal_id = id.clone();
-                    let id = match self.proposal_agreement.get(&proposal_id) {
-                        Some(id) => id.clone(),
-                        None => {
-                            log::warn!(
-                                "Accepted Proposal [{}] with no matching Agreement.",
-                                proposal_id
-                            );
-                            return;
-                        }
-                    };
-                    let subscription_id = match self.subscriptions.get(&id) {
-                        None => return,
-                        Some(id) => id.to_string(),
-                    };
-
-                    log::info!("Accepting Agreement [{}]", id);
-
-                    self.proposal_agreement.remove(&proposal_id);
-                    self.agreement_channel
-                        .send(AgreementAction::ApproveAgreement {
-                            id: id.clone(),
-                            subscription_id,
-                        })
-                        .map_err(|_| anyhow!("Failed to send AcceptAgreement for {}", id))
-                }
-                FeedbackAction::Reject {
-                    id,
-                    reason,
-                    is_final,
-                } => {
-                    let proposal_id = id.clone();
-                    let agreement_id = match self.proposal_agreement.get(&proposal_id) {
-                        Some(id) => id.clone(),
-                        None => {
-                            log::warn!(
-                                "Rejected Proposal [{}] with no matching Agreement.",
-                                proposal_id
-                            );
-                            return;
-                        }
-                    };
-
-                    let subscription_id = match self.subscriptions.get(&id) {
-                        None => return,
-                        Some(id) => id.to_string(),
-                    };
-
-                    log::info!("Rejecting Agreement [{}]", agreement_id);
-
-                    if is_final {
-                        self.proposal_agreement.remove(&proposal_id);
+        match self
+            .negotiations
+            .feedback(item, &mut self.rounds, &mut self.components)
+        {
+            FeedbackDecision::Decide(collection_type) => self.decide(collection_type),
+            FeedbackDecision::Proposal(action) => {
+                let id = action.id();
+                self.send_proposal_action(action).map_err(|e| {
+                    self.forget_proposal(&id);
+                    anyhow!("Failed to send decision about Proposal [{}]. {}", id, e)
+                })
+            }
+            FeedbackDecision::Agreement(action) => {
+                let id = action.id();
+                self.send_agreement_action(action).map_err(|e| {
+                    if self.negotiations.pending_approval.remove(&id) {
+                        // Approval, which wasn't sent, will never be signed, so its slot is
+                        // freed for next decision.
+                        self.agreements.reconsider();
+                        self.forget_agreement(&id);
                     }
-
-                    self.agreement_channel
-                        .send(AgreementAction::RejectAgreement {
-                            id: agreement_id.clone(),
-                            subscription_id,
-                            reason: reason.into(),
-                        })
-                        .map_err(|_| {
-                            anyhow!("Failed to send RejectAgreement for [{}]", agreement_id)
-                        })
-                }
-            },
-            CollectionType::Proposal => match item.action {
-                FeedbackAction::Decide(reason) => {
-                    match reason {
-                        DecideReason::TimeElapsed => {
-                            log::debug!("Choosing Proposals, because collect period elapsed.")
-                        }
-                        DecideReason::GoalReached => log::debug!(
-                            "Choosing Proposals, because collected expected number of them."
-                        ),
-                    };
-                    self.proposals.decide()
-                }
-                FeedbackAction::Accept { id } => {
-                    log::info!("Accepting Proposal [{}]", id);
-
-                    let subscription_id = match self.subscriptions.get(&id) {
-                        None => return,
-                        Some(id) => id.to_string(),
-                    };
-
-                    self.proposal_channel
-                        .send(ProposalAction::AcceptProposal {
-                            id: id.clone(),
-                            subscription_id,
-                        })
-                        .map_err(|_| anyhow!("Failed to send AcceptProposal for [{}]", id))
-                }
-                FeedbackAction::Reject { id, reason, .. } => {
-                    log::info!("Rejecting Proposal {}", id);
-
-                    let subscription_id = match self.subscriptions.get(&id) {
-                        None => return,
-                        Some(id) => id.to_string(),
-                    };
-
-                    self.proposal_channel
-                        .send(ProposalAction::RejectProposal {
-                            subscription_id,
-                            id: id.clone(),
-                            reason: reason.into(),
-                        })
-                        .map_err(|_| anyhow!("Failed to send RejectProposal for [{}]", id))
-                }
-            },
+                    anyhow!("Failed to send decision about Agreement [{}]. {}", id, e)
+                })
+            }
+            FeedbackDecision::NoDecision(collection_type, reason) => {
+                self.no_decision(collection_type, reason);
+                Ok(())
+            }
+            FeedbackDecision::Outdated => Ok(()),
         }
         .map_err(|e| log::warn!("{}", e))
         .ok();
@@ -514,7 +906,20 @@ impl Actor for Negotiator {
                 .take()
                 .expect("Agreements collection receiver already taken on initialization."),
         );
-        Self::add_stream(select(p_channel, a_channel), ctx);
+        <Self as StreamHandler<Feedback>>::add_stream(select(p_channel, a_channel), ctx);
+
+        let reevaluation = UnboundedReceiverStream::new(
+            self.reevaluation_receiver
+                .take()
+                .expect("Re-evaluation receiver already taken on initialization."),
+        );
+        <Self as StreamHandler<ReevaluationRequest>>::add_stream(reevaluation, ctx);
+    }
+
+    fn stopped(&mut self, _: &mut Context<Self>) {
+        if self.dedicated_arbiter {
+            Arbiter::current().stop();
+        }
     }
 }
 
@@ -523,14 +928,32 @@ impl CompositeNegotiatorConfig {
         CompositeNegotiatorConfig {
             proposals: CollectionConfig {
                 collect_period: Some(Duration::from_secs(5)),
+                collect_period_jitter: None,
                 collect_amount: Some(5),
+                min_collect_time: None,
                 goal: DecideGoal::Batch(10),
+                invalid_score: InvalidScorePolicy::Reject,
+                busy_reason: BusyReasonConfig::default(),
+                score_pointer: default_score_pointer(),
             },
             agreements: CollectionConfig {
                 collect_period: Some(Duration::from_secs(20)),
+                collect_period_jitter: None,
                 collect_amount: Some(5),
+                min_collect_time: None,
                 goal: DecideGoal::Limit(1),
+                invalid_score: InvalidScorePolicy::Reject,
+                busy_reason: BusyReasonConfig::default(),
+                score_pointer: default_score_pointer(),
             },
+            min_final_score: None,
+            channel_capacity: None,
+            node_id: None,
+            cleanup_working_dirs: false,
+            require_non_empty: false,
+            reject_no_progress: false,
+            max_rounds: None,
+            explicit_accept: false,
         }
     }
 
@@ -538,14 +961,32 @@ impl CompositeNegotiatorConfig {
         CompositeNegotiatorConfig {
             proposals: CollectionConfig {
                 collect_period: Some(Duration::from_secs(5)),
+                collect_period_jitter: None,
                 collect_amount: Some(1),
+                min_collect_time: None,
                 goal: DecideGoal::Batch(10),
+                invalid_score: InvalidScorePolicy::Reject,
+                busy_reason: BusyReasonConfig::default(),
+                score_pointer: default_score_pointer(),
             },
             agreements: CollectionConfig {
                 collect_period: Some(Duration::from_secs(20)),
+                collect_period_jitter: None,
                 collect_amount: Some(1),
+                min_collect_time: None,
                 goal: DecideGoal::Limit(1),
+                invalid_score: InvalidScorePolicy::Reject,
+                busy_reason: BusyReasonConfig::default(),
+                score_pointer: default_score_pointer(),
             },
+            min_final_score: None,
+            channel_capacity: None,
+            node_id: None,
+            cleanup_working_dirs: false,
+            require_non_empty: false,
+            reject_no_progress: false,
+            max_rounds: None,
+            explicit_accept: false,
         }
     }
 }
@@ -0,0 +1,527 @@
+use std::collections::{HashMap, HashSet};
+
+use ya_client_model::market::proposal::State;
+use ya_client_model::market::Reason;
+use ya_client_model::NodeId;
+
+use crate::collection::{
+    CollectionType, DecideReason, Feedback, FeedbackAction, NoDecisionReason, ProposalScore,
+    ProposalsCollection,
+};
+use crate::component::{NegotiationResult, NegotiatorComponent, ProposalView, Score};
+use crate::composite::{
+    CompositeNegotiatorConfig, NO_PROGRESS, SCORE_BELOW_MINIMUM, SELF_NEGOTIATION, TOO_MANY_ROUNDS,
+};
+use crate::rounds::{NegotiationRounds, PrevRound};
+use crate::{AgreementAction, NegotiatorsPack, ProposalAction};
+
+use ya_negotiator_component::correlated_log;
+use ya_negotiator_component::correlation::correlate;
+use ya_negotiator_component::reason::RejectReason;
+
+/// Reaction to Proposal decided based on components results.
+pub(crate) enum ProposalDecision {
+    /// Components rejected Proposal without final flag, if `reevaluate` is set,
+    /// so it is worth to evaluate it again, when they change their mind.
+    Reject {
+        reason: Option<Reason>,
+        reevaluate: bool,
+    },
+    Counter {
+        proposal: ProposalView,
+        score: Score,
+    },
+    /// Draft Proposal, which can be promoted to Agreement.
+    Ready {
+        proposal: ProposalView,
+        score: Score,
+    },
+}
+
+/// Reaction to Agreement decided based on components results.
+pub(crate) enum AgreementDecision {
+    Reject {
+        reason: Option<Reason>,
+    },
+    Ready {
+        proposal: ProposalView,
+        score: Score,
+    },
+}
+
+/// Action decided by collection for Proposal or Agreement, which is still negotiated.
+pub(crate) enum FeedbackDecision {
+    /// Collection should choose the best of collected Proposals or Agreements.
+    Decide(CollectionType),
+    Proposal(ProposalAction),
+    Agreement(AgreementAction),
+    NoDecision(CollectionType, NoDecisionReason),
+    /// Decision concerns Proposal or Agreement, which isn't negotiated anymore.
+    Outdated,
+}
+
+/// Subscriptions and Agreements of ongoing negotiations. Shared by `Negotiator` and
+/// `NegotiatorEngine`, so both of them turn collections feedback into the same actions.
+#[derive(Default)]
+pub(crate) struct Negotiations {
+    /// Subscriptions of negotiated Proposals and collected Agreements.
+    /// Note: In theory it is possible to have conflict between Agreement and Proposal
+    /// Ids, but in practise probability is very low.
+    pub subscriptions: HashMap<String, String>,
+    /// Maps both Proposals of collected Agreement to this Agreement.
+    pub proposal_agreement: HashMap<String, String>,
+    /// Agreements approved by us, which weren't signed yet.
+    pub pending_approval: HashSet<String>,
+}
+
+/// Rules turning components results into decisions. Shared by `Negotiator` and
+/// `NegotiatorEngine`, so both of them decide the same way.
+pub(crate) struct DecisionRules {
+    min_final_score: Option<f64>,
+    node_id: Option<NodeId>,
+    reject_no_progress: bool,
+    max_rounds: Option<u32>,
+    explicit_accept: bool,
+    proposal_score_pointer: String,
+}
+
+impl DecisionRules {
+    pub fn new(config: &CompositeNegotiatorConfig) -> DecisionRules {
+        DecisionRules {
+            min_final_score: config.min_final_score,
+            node_id: config.node_id,
+            reject_no_progress: config.reject_no_progress,
+            max_rounds: config.max_rounds,
+            explicit_accept: config.explicit_accept,
+            proposal_score_pointer: config.proposals.score_pointer.clone(),
+        }
+    }
+
+    pub fn proposal_score(&self, score: &Score) -> f64 {
+        score
+            .pointer_typed(&self.proposal_score_pointer)
+            .unwrap_or(0.0)
+    }
+
+    /// Adds `Ready` Proposal or Agreement to `collection`, where it waits
+    /// for choosing the best of collected ones.
+    pub fn collect(
+        &self,
+        collection: &mut ProposalsCollection,
+        id: &str,
+        their: ProposalView,
+        our: ProposalView,
+        score: &Score,
+    ) -> anyhow::Result<()> {
+        let final_score = score
+            .pointer_typed(collection.score_pointer())
+            .unwrap_or(0.0);
+        collection.new_scored(
+            ProposalScore {
+                their,
+                our,
+                score: final_score,
+                breakdown: score.breakdown(),
+            },
+            id,
+        )
+    }
+
+    /// Lets components rescore collected batch, before choosing the best of them.
+    pub fn decide_collection(
+        &self,
+        components: &mut NegotiatorsPack,
+        collection: &mut ProposalsCollection,
+    ) -> anyhow::Result<()> {
+        collection.rescore(|batch| components.rescore_batch(batch));
+        collection.decide()
+    }
+
+    fn below_min_final_score(&self, score: &Score) -> bool {
+        match self.min_final_score {
+            Some(min_score) => self.proposal_score(score) < min_score,
+            None => false,
+        }
+    }
+
+    fn is_self_negotiation(&self, their: &ProposalView) -> bool {
+        self.node_id == Some(their.issuer)
+    }
+
+    /// Asks components about Proposal continuing negotiations from `prev` round.
+    pub fn decide_proposal(
+        &self,
+        components: &mut NegotiatorsPack,
+        their: &ProposalView,
+        prev: &PrevRound,
+        template: ProposalView,
+    ) -> anyhow::Result<ProposalDecision> {
+        if self.is_self_negotiation(their) {
+            correlated_log!(
+                log::Level::Warn,
+                "Rejecting Proposal [{}] issued by our own node.",
+                their.id
+            );
+            return Ok(ProposalDecision::Reject {
+                reason: self_negotiation_reason(),
+                reevaluate: false,
+            });
+        }
+
+        if let Some(max_rounds) = self
+            .max_rounds
+            .filter(|max_rounds| prev.round > *max_rounds)
+        {
+            correlated_log!(
+                log::Level::Warn,
+                "Rejecting Proposal [{}], because negotiations exceeded {} rounds.",
+                their.id,
+                max_rounds
+            );
+            return Ok(ProposalDecision::Reject {
+                reason: too_many_rounds_reason(max_rounds),
+                reevaluate: false,
+            });
+        }
+
+        let result = components.negotiate_step_with_history(
+            their,
+            &prev.history,
+            template.clone(),
+            prev.score.clone(),
+        )?;
+
+        let accept = matches!(result, NegotiationResult::Accept { .. });
+        Ok(match result {
+            NegotiationResult::Reject { reason, is_final } => ProposalDecision::Reject {
+                reason: reason.final_flag(is_final).into(),
+                reevaluate: !is_final,
+            },
+            NegotiationResult::Ready { score, .. } | NegotiationResult::Accept { score, .. }
+                if self.below_min_final_score(&score) =>
+            {
+                ProposalDecision::Reject {
+                    reason: below_min_score_reason(
+                        self.proposal_score(&score),
+                        self.min_final_score.unwrap_or_default(),
+                    ),
+                    reevaluate: false,
+                }
+            }
+            NegotiationResult::Ready { proposal, score }
+            | NegotiationResult::Accept { proposal, score } => match their.state {
+                // We must counter Initial Proposal even, if it is ready to promote to Agreement.
+                // ProposalsCollection should store only fully negotiated Proposals.
+                // With `explicit_accept` Draft Proposals not accepted explicitly are countered too.
+                // Such counter Proposal is usually unchanged, so it can loop the same way as
+                // `Negotiating` one.
+                State::Draft
+                    if counter_ready(their, self.explicit_accept, accept)
+                        && self.reject_no_progress
+                        && is_no_progress(their, &proposal, &template) =>
+                {
+                    no_progress_decision(their)
+                }
+                State::Initial | State::Draft
+                    if counter_ready(their, self.explicit_accept, accept) =>
+                {
+                    ProposalDecision::Counter { proposal, score }
+                }
+                State::Draft => ProposalDecision::Ready { proposal, score },
+                state => anyhow::bail!("Invalid Proposal [{}] state {:?}", their.id, state),
+            },
+            NegotiationResult::Negotiating { proposal, .. }
+                if self.reject_no_progress && is_no_progress(their, &proposal, &template) =>
+            {
+                no_progress_decision(their)
+            }
+            NegotiationResult::Negotiating { proposal, score } => {
+                ProposalDecision::Counter { proposal, score }
+            }
+        })
+    }
+
+    /// All components should be ready to sign Agreement. Otherwise negotiations
+    /// weren't finished and Agreement must be rejected.
+    pub fn decide_agreement(
+        &self,
+        components: &mut NegotiatorsPack,
+        agreement_id: &str,
+        their: &ProposalView,
+        our: ProposalView,
+    ) -> anyhow::Result<AgreementDecision> {
+        if self.is_self_negotiation(their) {
+            correlated_log!(
+                log::Level::Warn,
+                "Rejecting Agreement [{}] with our own node.",
+                agreement_id
+            );
+            return Ok(AgreementDecision::Reject {
+                reason: self_negotiation_reason(),
+            });
+        }
+
+        let decision = match components.negotiate_step(their, our, Score::default())? {
+            NegotiationResult::Ready { proposal, score }
+            | NegotiationResult::Accept { proposal, score } => {
+                AgreementDecision::Ready { proposal, score }
+            }
+            NegotiationResult::Reject { reason, is_final } => AgreementDecision::Reject {
+                reason: reason.final_flag(is_final).into(),
+            },
+            NegotiationResult::Negotiating { .. } => AgreementDecision::Reject {
+                reason: RejectReason::new("Negotiations aren't finished.")
+                    .final_flag(true)
+                    .into(),
+            },
+        };
+        Ok(decision)
+    }
+}
+
+impl Negotiations {
+    /// Agreement waits in collection. Feedback can refer to it by id of any of its Proposals.
+    pub fn collect_agreement(
+        &mut self,
+        agreement_id: &str,
+        their: &ProposalView,
+        our: &ProposalView,
+        subscription_id: String,
+    ) {
+        self.proposal_agreement
+            .insert(their.id.clone(), agreement_id.to_string());
+        self.proposal_agreement
+            .insert(our.id.clone(), agreement_id.to_string());
+        self.subscriptions
+            .insert(agreement_id.to_string(), subscription_id);
+    }
+
+    /// Agreement won't be approved anymore. Returns ids of Proposals, it was created from,
+    /// so their negotiation rounds can be forgotten too.
+    pub fn forget_agreement(&mut self, agreement_id: &str) -> Vec<String> {
+        self.subscriptions.remove(agreement_id);
+        let proposals = self
+            .proposal_agreement
+            .iter()
+            .filter(|(_, id)| id.as_str() == agreement_id)
+            .map(|(proposal_id, _)| proposal_id.clone())
+            .collect::<Vec<_>>();
+        for proposal_id in &proposals {
+            self.proposal_agreement.remove(proposal_id);
+            self.subscriptions.remove(proposal_id);
+        }
+        proposals
+    }
+
+    /// Turns collection feedback into action. Proposals and Agreements rejected by
+    /// collection are forgotten, so the caller only has to send returned action.
+    pub fn feedback(
+        &mut self,
+        feedback: Feedback,
+        rounds: &mut NegotiationRounds,
+        components: &mut NegotiatorsPack,
+    ) -> FeedbackDecision {
+        let collection_type = feedback.collection_type;
+        match feedback.action {
+            FeedbackAction::Decide(reason) => {
+                match reason {
+                    DecideReason::TimeElapsed => log::debug!(
+                        "Choosing {}s, because collect period elapsed.",
+                        collection_type
+                    ),
+                    DecideReason::GoalReached => log::debug!(
+                        "Choosing {}s, because collected expected number of them.",
+                        collection_type
+                    ),
+                };
+                FeedbackDecision::Decide(collection_type)
+            }
+            FeedbackAction::NoDecision { reason } => {
+                log::debug!(
+                    "No {}s chosen in this period. Reason: {}",
+                    collection_type,
+                    reason
+                );
+                FeedbackDecision::NoDecision(collection_type, reason)
+            }
+            FeedbackAction::Accept { id } => match collection_type {
+                CollectionType::Proposal => self.accept_proposal(id),
+                CollectionType::Agreement => self.accept_agreement(id),
+            },
+            FeedbackAction::Reject {
+                id,
+                reason,
+                is_final,
+            } => {
+                let reason: Option<Reason> = reason.final_flag(is_final).into();
+                match collection_type {
+                    CollectionType::Proposal => self.reject_proposal(id, reason, rounds),
+                    CollectionType::Agreement => {
+                        self.reject_agreement(id, reason, rounds, components)
+                    }
+                }
+            }
+        }
+    }
+
+    fn accept_proposal(&mut self, id: String) -> FeedbackDecision {
+        let _correlation = correlate(&id);
+        let subscription_id = match self.subscriptions.get(&id) {
+            Some(subscription_id) => subscription_id.clone(),
+            None => return FeedbackDecision::Outdated,
+        };
+
+        correlated_log!(log::Level::Info, "Accepting Proposal [{}]", id);
+        FeedbackDecision::Proposal(ProposalAction::AcceptProposal {
+            id,
+            subscription_id,
+        })
+    }
+
+    fn reject_proposal(
+        &mut self,
+        id: String,
+        reason: Option<Reason>,
+        rounds: &mut NegotiationRounds,
+    ) -> FeedbackDecision {
+        let _correlation = correlate(&id);
+        let subscription_id = match self.subscriptions.remove(&id) {
+            Some(subscription_id) => subscription_id,
+            None => return FeedbackDecision::Outdated,
+        };
+
+        correlated_log!(log::Level::Info, "Rejecting Proposal [{}]", id);
+        rounds.forget(&id);
+        FeedbackDecision::Proposal(ProposalAction::RejectProposal {
+            subscription_id,
+            id,
+            reason,
+        })
+    }
+
+    fn accept_agreement(&mut self, proposal_id: String) -> FeedbackDecision {
+        let agreement_id = match self.proposal_agreement.remove(&proposal_id) {
+            Some(agreement_id) => agreement_id,
+            None => {
+                log::warn!(
+                    "Accepted Proposal [{}] with no matching Agreement.",
+                    proposal_id
+                );
+                return FeedbackDecision::Outdated;
+            }
+        };
+
+        let _correlation = correlate(&agreement_id);
+        let subscription_id = match self.subscriptions.get(&agreement_id) {
+            Some(subscription_id) => subscription_id.clone(),
+            None => return FeedbackDecision::Outdated,
+        };
+
+        correlated_log!(log::Level::Info, "Accepting Agreement [{}]", agreement_id);
+        self.pending_approval.insert(agreement_id.clone());
+        FeedbackDecision::Agreement(AgreementAction::ApproveAgreement {
+            id: agreement_id,
+            subscription_id,
+        })
+    }
+
+    fn reject_agreement(
+        &mut self,
+        proposal_id: String,
+        reason: Option<Reason>,
+        rounds: &mut NegotiationRounds,
+        components: &mut NegotiatorsPack,
+    ) -> FeedbackDecision {
+        let agreement_id = match self.proposal_agreement.get(&proposal_id) {
+            Some(agreement_id) => agreement_id.clone(),
+            None => {
+                log::warn!(
+                    "Rejected Proposal [{}] with no matching Agreement.",
+                    proposal_id
+                );
+                return FeedbackDecision::Outdated;
+            }
+        };
+
+        let _correlation = correlate(&agreement_id);
+        let subscription_id = match self.subscriptions.get(&agreement_id) {
+            Some(subscription_id) => subscription_id.clone(),
+            None => return FeedbackDecision::Outdated,
+        };
+
+        correlated_log!(log::Level::Info, "Rejecting Agreement [{}]", agreement_id);
+
+        // Rejected Agreement can't be approved later, even if rejection wasn't final.
+        for id in self.forget_agreement(&agreement_id) {
+            rounds.forget(&id);
+        }
+        // Components could reserve resources for this Agreement.
+        components.on_proposal_rejected(&proposal_id).ok();
+
+        FeedbackDecision::Agreement(AgreementAction::RejectAgreement {
+            id: agreement_id,
+            subscription_id,
+            reason,
+        })
+    }
+}
+
+fn self_negotiation_reason() -> Option<Reason> {
+    RejectReason::new("Node can't negotiate with itself.")
+        .with_code(SELF_NEGOTIATION)
+        .final_flag(true)
+        .into()
+}
+
+fn no_progress_reason() -> Option<Reason> {
+    RejectReason::new("Negotiations don't progress. Our Proposal wouldn't change.")
+        .with_code(NO_PROGRESS)
+        .final_flag(true)
+        .into()
+}
+
+/// Countering Draft Proposal with the same content as our previous Proposal
+/// can't move negotiations forward.
+fn is_no_progress(their: &ProposalView, our: &ProposalView, prev: &ProposalView) -> bool {
+    their.state == State::Draft
+        && our.content.constraints == prev.content.constraints
+        && prev.changed_pointers(our).is_empty()
+}
+
+fn no_progress_decision(their: &ProposalView) -> ProposalDecision {
+    correlated_log!(
+        log::Level::Warn,
+        "Rejecting Proposal [{}], because our counter Proposal wouldn't change.",
+        their.id
+    );
+    ProposalDecision::Reject {
+        reason: no_progress_reason(),
+        reevaluate: false,
+    }
+}
+
+/// `Ready` Proposal must be countered instead of promoting it to Agreement, if it is
+/// Initial or, with `explicit_accept` enabled, if no component accepted it explicitly.
+fn counter_ready(their: &ProposalView, explicit_accept: bool, accept: bool) -> bool {
+    their.state == State::Initial || (explicit_accept && !accept)
+}
+
+fn too_many_rounds_reason(max_rounds: u32) -> Option<Reason> {
+    RejectReason::new(format!(
+        "Negotiations exceeded {} rounds without reaching agreement.",
+        max_rounds
+    ))
+    .with_code(TOO_MANY_ROUNDS)
+    .final_flag(true)
+    .into()
+}
+
+fn below_min_score_reason(score: f64, min_score: f64) -> Option<Reason> {
+    RejectReason::new(format!(
+        "Proposal score {} is below minimal score {}.",
+        score, min_score
+    ))
+    .with_code(SCORE_BELOW_MINIMUM)
+    .final_flag(true)
+    .into()
+}
@@ -0,0 +1,298 @@
+use std::convert::TryFrom;
+use std::time::Duration;
+
+use serde_json::Value;
+use tokio::sync::mpsc;
+use ya_agreement_utils::{AgreementView, OfferTemplate};
+use ya_client_model::market::{NewOffer, Proposal};
+
+use crate::collection::{CollectionType, DecideGoal, Feedback};
+use crate::component::{AgreementEvent, AgreementResult, NegotiatorComponent, ProposalView};
+use crate::composite::{template_from, CompositeNegotiatorConfig};
+use crate::decision::{
+    AgreementDecision, DecisionRules, FeedbackDecision, Negotiations, ProposalDecision,
+};
+use crate::rounds::{agreement_proposals, NegotiationRounds};
+use crate::{AgreementAction, NegotiatorsPack, ProposalAction, ProposalsCollection};
+
+/// Negotiator making decisions synchronously, without actix actor system.
+/// Intended for users driving negotiations from their own event loop.
+///
+/// Proposals and Agreements are collected and chosen the same way as in `Negotiator`,
+/// so methods return all actions decided during the call, which can be none.
+/// Collect periods aren't measured by timers, so the owner must call `poll_proposals`
+/// and `poll_agreements` periodically to get decisions made after period elapses.
+/// Proposals rejected without final flag aren't reevaluated later.
+pub struct NegotiatorEngine {
+    components: NegotiatorsPack,
+    /// State of negotiations, that we countered.
+    rounds: NegotiationRounds,
+    rules: DecisionRules,
+
+    proposals: ProposalsCollection,
+    agreements: ProposalsCollection,
+    proposals_feedback: mpsc::UnboundedReceiver<Feedback>,
+    agreements_feedback: mpsc::UnboundedReceiver<Feedback>,
+
+    /// Subscriptions of collected Proposals and Agreements.
+    negotiations: Negotiations,
+}
+
+impl NegotiatorEngine {
+    pub fn new(components: NegotiatorsPack, config: CompositeNegotiatorConfig) -> NegotiatorEngine {
+        let mut proposals =
+            ProposalsCollection::without_timers(CollectionType::Proposal, config.proposals.clone());
+        let mut agreements = ProposalsCollection::without_timers(
+            CollectionType::Agreement,
+            config.agreements.clone(),
+        );
+        let proposals_feedback = proposals.feedback_receiver.take().unwrap();
+        let agreements_feedback = agreements.feedback_receiver.take().unwrap();
+
+        NegotiatorEngine {
+            components,
+            rounds: NegotiationRounds::default(),
+            rules: DecisionRules::new(&config),
+            proposals,
+            agreements,
+            proposals_feedback,
+            agreements_feedback,
+            negotiations: Negotiations::default(),
+        }
+    }
+
+    pub fn create_offer(&mut self, template: &OfferTemplate) -> anyhow::Result<NewOffer> {
+        let offer_template = self.components.fill_template(template.clone())?;
+        self.components
+            .on_offer_published(&offer_template)
+            .map_err(|e| log::warn!("Failed to notify components about published Offer. {e}"))
+            .ok();
+        Ok(NewOffer::new(
+            offer_template.properties,
+            offer_template.constraints,
+        ))
+    }
+
+    pub fn react_to_proposal(
+        &mut self,
+        subscription_id: &str,
+        incoming_proposal: &Proposal,
+        our_prev_proposal: &Proposal,
+    ) -> anyhow::Result<Vec<ProposalAction>> {
+        let subscription_id = subscription_id.to_string();
+        for id in self.rounds.forget_expired() {
+            self.negotiations.subscriptions.remove(&id);
+        }
+        let prev = self
+            .rounds
+            .take(our_prev_proposal.prev_proposal_id.as_ref());
+
+        let their = ProposalView::try_from(incoming_proposal)?;
+        let template = template_from(our_prev_proposal.clone());
+        match self
+            .rules
+            .decide_proposal(&mut self.components, &their, &prev, template)?
+        {
+            ProposalDecision::Reject { reason, .. } => Ok(vec![ProposalAction::RejectProposal {
+                subscription_id,
+                id: their.id,
+                reason,
+            }]),
+            ProposalDecision::Counter {
+                proposal: our,
+                score,
+            } => {
+                self.rounds.countered(&their, prev, score);
+                Ok(vec![ProposalAction::CounterProposal {
+                    subscription_id,
+                    id: their.id,
+                    proposal: our.into(),
+                }])
+            }
+            ProposalDecision::Ready {
+                proposal: our,
+                score,
+            } => {
+                let id = their.id.clone();
+                self.negotiations
+                    .subscriptions
+                    .insert(id.clone(), subscription_id);
+                self.rules
+                    .collect(&mut self.proposals, &id, their, our, &score)?;
+                self.proposal_actions()
+            }
+        }
+    }
+
+    pub fn react_to_agreement(
+        &mut self,
+        subscription_id: &str,
+        agreement: &AgreementView,
+    ) -> anyhow::Result<Vec<AgreementAction>> {
+        let subscription_id = subscription_id.to_string();
+        let id = agreement.id.clone();
+        let (their, our) = agreement.as_proposal_views()?;
+
+        match self
+            .rules
+            .decide_agreement(&mut self.components, &id, &their, our.clone())?
+        {
+            AgreementDecision::Ready { proposal, score } => {
+                self.negotiations
+                    .collect_agreement(&id, &their, &our, subscription_id);
+                self.rules
+                    .collect(&mut self.agreements, &id, their, proposal, &score)?;
+                self.agreement_actions()
+            }
+            AgreementDecision::Reject { reason } => Ok(vec![AgreementAction::RejectAgreement {
+                id,
+                subscription_id,
+                reason,
+            }]),
+        }
+    }
+
+    /// Proposals decided after collect period elapsed.
+    pub fn poll_proposals(&mut self) -> anyhow::Result<Vec<ProposalAction>> {
+        self.proposals.poll_timers()?;
+        self.proposal_actions()
+    }
+
+    /// Agreements decided after collect period elapsed.
+    pub fn poll_agreements(&mut self) -> anyhow::Result<Vec<AgreementAction>> {
+        self.agreements.poll_timers()?;
+        self.agreement_actions()
+    }
+
+    /// Allows to approve `count` more Agreements.
+    pub fn request_agreements(&mut self, count: usize) {
+        self.agreements.set_goal(DecideGoal::Limit(count));
+    }
+
+    /// Returns Agreements, which were kept pending, while this one was approved.
+    pub fn agreement_signed(
+        &mut self,
+        agreement: &AgreementView,
+    ) -> anyhow::Result<Vec<AgreementAction>> {
+        if self.negotiations.pending_approval.remove(&agreement.id) && self.agreements.approved() {
+            self.decide(CollectionType::Agreement)?;
+        }
+        self.forget_agreement(&agreement.id);
+        for proposal_id in agreement_proposals(agreement) {
+            self.forget_proposal(&proposal_id);
+        }
+        self.components.on_agreement_approved(agreement)?;
+        self.agreement_actions()
+    }
+
+    /// Returns Agreement chosen in place of rejected one.
+    pub fn agreement_rejected(
+        &mut self,
+        agreement_id: &str,
+    ) -> anyhow::Result<Vec<AgreementAction>> {
+        self.forget_agreement(agreement_id);
+        self.approval_failed(agreement_id)?;
+        self.agreement_actions()
+    }
+
+    /// Returns Agreement chosen in place of broken one.
+    pub fn agreement_finalized(
+        &mut self,
+        agreement_id: &str,
+        result: &AgreementResult,
+    ) -> anyhow::Result<Vec<AgreementAction>> {
+        if matches!(
+            result,
+            AgreementResult::BrokenByUs { .. } | AgreementResult::BrokenByThem { .. }
+        ) {
+            self.approval_failed(agreement_id)?;
+        }
+        self.forget_agreement(agreement_id);
+        self.components
+            .on_agreement_terminated(agreement_id, result)?;
+        self.agreement_actions()
+    }
+
+    pub fn proposal_rejected(&mut self, proposal_id: &str) -> anyhow::Result<()> {
+        self.forget_proposal(proposal_id);
+        self.components.on_proposal_rejected(proposal_id)
+    }
+
+    pub fn post_agreement_event(
+        &mut self,
+        agreement_id: &str,
+        event: &AgreementEvent,
+    ) -> anyhow::Result<()> {
+        self.components.on_agreement_event(agreement_id, event)
+    }
+
+    pub fn control_event(&mut self, component: &str, params: Value) -> anyhow::Result<Value> {
+        self.components.control_event(component, params)
+    }
+
+    pub fn shutdown(&mut self, timeout: Duration) -> anyhow::Result<()> {
+        self.components.shutdown(timeout)
+    }
+
+    /// Agreement approved by us wasn't signed, so we can choose another
+    /// one in it's place.
+    fn approval_failed(&mut self, agreement_id: &str) -> anyhow::Result<()> {
+        if !self.negotiations.pending_approval.remove(agreement_id) {
+            return Ok(());
+        }
+        self.agreements.reconsider();
+        self.decide(CollectionType::Agreement)
+    }
+
+    fn decide(&mut self, collection_type: CollectionType) -> anyhow::Result<()> {
+        let collection = match collection_type {
+            CollectionType::Agreement => &mut self.agreements,
+            CollectionType::Proposal => &mut self.proposals,
+        };
+        self.rules
+            .decide_collection(&mut self.components, collection)
+    }
+
+    fn forget_proposal(&mut self, proposal_id: &str) {
+        self.rounds.forget(proposal_id);
+        self.negotiations.subscriptions.remove(proposal_id);
+    }
+
+    /// Neither Agreement nor Proposals, it was created from, will be negotiated anymore.
+    fn forget_agreement(&mut self, agreement_id: &str) {
+        for proposal_id in self.negotiations.forget_agreement(agreement_id) {
+            self.rounds.forget(&proposal_id);
+        }
+    }
+
+    /// Turns collection feedback into actions, the same way as `Negotiator` does.
+    fn proposal_actions(&mut self) -> anyhow::Result<Vec<ProposalAction>> {
+        let mut actions = vec![];
+        while let Ok(feedback) = self.proposals_feedback.try_recv() {
+            match self
+                .negotiations
+                .feedback(feedback, &mut self.rounds, &mut self.components)
+            {
+                FeedbackDecision::Decide(collection_type) => self.decide(collection_type)?,
+                FeedbackDecision::Proposal(action) => actions.push(action),
+                _ => {}
+            }
+        }
+        Ok(actions)
+    }
+
+    fn agreement_actions(&mut self) -> anyhow::Result<Vec<AgreementAction>> {
+        let mut actions = vec![];
+        while let Ok(feedback) = self.agreements_feedback.try_recv() {
+            match self
+                .negotiations
+                .feedback(feedback, &mut self.rounds, &mut self.components)
+            {
+                FeedbackDecision::Decide(collection_type) => self.decide(collection_type)?,
+                FeedbackDecision::Agreement(action) => actions.push(action),
+                _ => {}
+            }
+        }
+        Ok(actions)
+    }
+}
@@ -1,70 +1,423 @@
-use anyhow::bail;
+use actix::Arbiter;
+use anyhow::{bail, Context};
+use derive_more::Display;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 
 use super::negotiators::NegotiatorAddr;
 use crate::Negotiator;
 
+#[cfg(feature = "jsonrpc")]
+use ya_negotiator_jsonrpc_interface::JsonRpcComponent;
 use ya_negotiator_shared_lib_interface::SharedLibNegotiator;
 
 use ya_negotiator_component::component::NegotiatorComponent;
-use ya_negotiator_component::{static_lib::create_static_negotiator, NegotiatorsPack};
+use ya_negotiator_component::static_lib::{
+    create_static_negotiator, qualified_name, register_negotiator_if_missing, BUILTIN_LIBRARY,
+};
+use ya_negotiator_component::{unique_name, NegotiatorsPack};
 
-use crate::builtin::AcceptAll;
-use crate::builtin::LimitExpiration;
-use crate::builtin::MaxAgreements;
 pub use crate::composite::CompositeNegotiatorConfig;
 use crate::composite::NegotiatorCallbacks;
+use ya_builtin_negotiators::accept_all;
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 #[non_exhaustive]
 pub enum LoadMode {
     BuiltIn,
-    SharedLibrary { path: PathBuf },
-    StaticLib { library: String },
+    SharedLibrary {
+        path: PathBuf,
+    },
+    StaticLib {
+        library: String,
+    },
+    /// Negotiator served by external process. Requires `jsonrpc` feature.
+    JsonRpc {
+        address: String,
+        /// Time to wait for remote negotiator's response.
+        #[serde(with = "humantime_serde", default)]
+        timeout: Option<Duration>,
+    },
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct NegotiatorConfig {
     pub name: String,
     pub load_mode: LoadMode,
     pub params: serde_yaml::Value,
+    /// Disabled negotiators are kept in config, but aren't created at all.
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Negotiators are called in ascending order of priority. Negotiators with equal
+    /// priority keep the order from config. Scoring components usually want to run last,
+    /// so they can see the final shape of Proposal and scores set by other components.
+    #[serde(default)]
+    pub priority: i32,
+}
+
+fn default_enabled() -> bool {
+    true
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct NegotiatorsConfig {
     pub negotiators: Vec<NegotiatorConfig>,
     pub composite: CompositeNegotiatorConfig,
 }
 
+/// Name of file in config directory, containing `CompositeNegotiatorConfig`.
+pub const COMPOSITE_CONFIG_FILE: &str = "composite.yaml";
+
+impl NegotiatorsConfig {
+    /// Loads config from directory, where each `*.yaml` file describes single
+    /// `NegotiatorConfig` and `composite.yaml` contains `CompositeNegotiatorConfig`.
+    /// Negotiators are ordered by file names. If `composite.yaml` doesn't exist,
+    /// default provider configuration is used. The same negotiator can be configured
+    /// in multiple files. Its instances are distinguished like in `NegotiatorsPack`.
+    pub fn from_dir(path: impl AsRef<Path>) -> anyhow::Result<NegotiatorsConfig> {
+        let path = path.as_ref();
+        let mut files = fs::read_dir(path)
+            .with_context(|| format!("Can't read config directory {}", path.display()))?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        files.sort();
+
+        let mut config = NegotiatorsConfig::default();
+        let mut names = HashSet::new();
+        for file in files {
+            if !file.is_file() || file.extension().is_none_or(|ext| ext != "yaml") {
+                continue;
+            }
+
+            let content = fs::read_to_string(&file)
+                .with_context(|| format!("Can't read config file {}", file.display()))?;
+
+            if file
+                .file_name()
+                .is_some_and(|name| name == COMPOSITE_CONFIG_FILE)
+            {
+                config.composite = serde_yaml::from_str(&content)
+                    .with_context(|| format!("Invalid composite config {}", file.display()))?;
+                continue;
+            }
+
+            let negotiator: NegotiatorConfig = serde_yaml::from_str(&content)
+                .with_context(|| format!("Invalid negotiator config {}", file.display()))?;
+            // Negotiator is renamed only in the pack, since config name identifies
+            // the negotiator to create.
+            let renamed = unique_name(&negotiator.name, |candidate| names.contains(candidate));
+            if renamed != negotiator.name {
+                log::info!(
+                    "Negotiator '{}' from config file {} is configured more than once. It will be renamed to '{}'.",
+                    negotiator.name,
+                    file.display(),
+                    renamed
+                );
+            }
+            names.insert(renamed);
+            config.negotiators.push(negotiator);
+        }
+        Ok(config)
+    }
+
+    /// Checks config for common mistakes without creating negotiators. Negotiators
+    /// are checked in the order, in which they will be called. Only builtin scoring
+    /// components are recognized, since other negotiators can't be inspected offline.
+    pub fn lint(&self) -> Vec<LintWarning> {
+        let mut warnings = vec![];
+
+        let mut negotiators = self
+            .negotiators
+            .iter()
+            .filter(|negotiator| negotiator.enabled)
+            .collect::<Vec<_>>();
+        negotiators.sort_by_key(|negotiator| negotiator.priority);
+
+        if negotiators.is_empty() {
+            warnings.push(LintWarning::EmptyChain);
+        }
+
+        let mut names = HashSet::new();
+        for negotiator in &negotiators {
+            let name = &negotiator.name;
+            let renamed = unique_name(name, |candidate| names.contains(candidate));
+            names.insert(renamed.clone());
+            if &renamed != name {
+                warnings.push(LintWarning::DuplicateName {
+                    name: name.clone(),
+                    renamed,
+                });
+            }
+        }
+
+        if let Some(scorer) = negotiators
+            .iter()
+            .position(|negotiator| is_scorer(negotiator))
+        {
+            warnings.extend(
+                negotiators[scorer + 1..]
+                    .iter()
+                    .filter(|negotiator| !is_scorer(negotiator) && !is_observer(negotiator))
+                    .map(|negotiator| LintWarning::ScorerBeforeNegotiator {
+                        scorer: negotiators[scorer].name.clone(),
+                        negotiator: negotiator.name.clone(),
+                    }),
+            );
+        }
+
+        for (collection, config) in [
+            ("proposals", &self.composite.proposals),
+            ("agreements", &self.composite.agreements),
+        ] {
+            if config.collect_amount.is_none() && config.collect_period.is_none() {
+                warnings.push(LintWarning::NoDecisionTrigger {
+                    collection: collection.to_string(),
+                });
+            }
+        }
+        warnings
+    }
+}
+
+/// Possible mistake in `NegotiatorsConfig` found by `NegotiatorsConfig::lint`.
+#[derive(Clone, Debug, Display, PartialEq)]
+pub enum LintWarning {
+    /// Negotiator name is used more than once, so it will be renamed.
+    #[display(
+        fmt = "Negotiator '{}' is configured more than once. It will be renamed to '{}'.",
+        name,
+        renamed
+    )]
+    DuplicateName { name: String, renamed: String },
+    /// Scoring component is called before negotiator, which can still change or
+    /// reject Proposal, so score is computed for not final Proposal.
+    #[display(
+        fmt = "Scoring negotiator '{}' is called before '{}'. Scorers should be called last.",
+        scorer,
+        negotiator
+    )]
+    ScorerBeforeNegotiator { scorer: String, negotiator: String },
+    /// No negotiators enabled, so all Proposals and Agreements will be accepted.
+    #[display(fmt = "No negotiators enabled. All Proposals and Agreements will be accepted.")]
+    EmptyChain,
+    /// Neither `collect_amount` nor `collect_period` is set, so decisions are never
+    /// made automatically.
+    #[display(
+        fmt = "Neither collect_amount nor collect_period is set for {}. Decisions won't be triggered automatically.",
+        collection
+    )]
+    NoDecisionTrigger { collection: String },
+}
+
+/// Builtin negotiators setting `final-score`.
+fn is_scorer(negotiator: &NegotiatorConfig) -> bool {
+    negotiator.load_mode == LoadMode::BuiltIn
+        && negotiator.name == "AcceptAll"
+        && serde_yaml::from_value::<accept_all::Config>(negotiator.params.clone())
+            .is_ok_and(|config| config.score.is_some())
+}
+
+/// Builtin negotiators, which never change nor reject Proposals.
+fn is_observer(negotiator: &NegotiatorConfig) -> bool {
+    negotiator.load_mode == LoadMode::BuiltIn && negotiator.name == "Tap"
+}
+
+/// Builds `NegotiatorsConfig` without assembling `NegotiatorConfig` structures by hand.
+/// Negotiators are added in order of calling builder functions.
+#[derive(Clone, Debug, Default)]
+pub struct NegotiatorsConfigBuilder {
+    config: NegotiatorsConfig,
+}
+
+impl NegotiatorsConfigBuilder {
+    pub fn new() -> NegotiatorsConfigBuilder {
+        NegotiatorsConfigBuilder::default()
+    }
+
+    pub fn builtin(self, name: &str, params: impl Serialize) -> anyhow::Result<Self> {
+        self.negotiator(name, LoadMode::BuiltIn, params)
+    }
+
+    pub fn shared_lib(
+        self,
+        path: impl Into<PathBuf>,
+        name: &str,
+        params: impl Serialize,
+    ) -> anyhow::Result<Self> {
+        let path = path.into();
+        self.negotiator(name, LoadMode::SharedLibrary { path }, params)
+    }
+
+    pub fn static_lib(
+        self,
+        library: &str,
+        name: &str,
+        params: impl Serialize,
+    ) -> anyhow::Result<Self> {
+        let library = library.to_string();
+        self.negotiator(name, LoadMode::StaticLib { library }, params)
+    }
+
+    pub fn jsonrpc(
+        self,
+        address: &str,
+        name: &str,
+        params: impl Serialize,
+    ) -> anyhow::Result<Self> {
+        let address = address.to_string();
+        self.negotiator(
+            name,
+            LoadMode::JsonRpc {
+                address,
+                timeout: None,
+            },
+            params,
+        )
+    }
+
+    pub fn negotiator(
+        mut self,
+        name: &str,
+        load_mode: LoadMode,
+        params: impl Serialize,
+    ) -> anyhow::Result<Self> {
+        self.config.negotiators.push(NegotiatorConfig {
+            name: name.to_string(),
+            load_mode,
+            params: serde_yaml::to_value(params)?,
+            enabled: true,
+            priority: 0,
+        });
+        Ok(self)
+    }
+
+    pub fn composite(mut self, composite: CompositeNegotiatorConfig) -> Self {
+        self.config.composite = composite;
+        self
+    }
+
+    pub fn build(self) -> NegotiatorsConfig {
+        self.config
+    }
+}
+
+/// Creates Negotiator with all configured components. `agent_env` is passed
+/// to each component constructor, so components can adjust to Agent environment.
 pub fn create_negotiator(
     config: NegotiatorsConfig,
+    agent_env: serde_yaml::Value,
+    working_dir: PathBuf,
+    plugins_dir: PathBuf,
+) -> anyhow::Result<(Arc<NegotiatorAddr>, NegotiatorCallbacks)> {
+    // JSON-RPC calls block until remote negotiator responds, so they can't
+    // run on caller's Arbiter together with other actors.
+    let uses_jsonrpc = config.negotiators.iter().any(|negotiator| {
+        negotiator.enabled && matches!(negotiator.load_mode, LoadMode::JsonRpc { .. })
+    });
+    if uses_jsonrpc {
+        return create_in_arbiter(config, agent_env, working_dir, plugins_dir);
+    }
+
+    let (negotiator, callbacks) = build_negotiator(config, agent_env, working_dir, plugins_dir)?;
+    Ok((Arc::new(NegotiatorAddr::from(negotiator)), callbacks))
+}
+
+/// Starts Negotiator on new Arbiter, which is stopped together with Negotiator.
+/// Components can't be moved between threads, so they are created there as well.
+fn create_in_arbiter(
+    config: NegotiatorsConfig,
+    agent_env: serde_yaml::Value,
     working_dir: PathBuf,
     plugins_dir: PathBuf,
 ) -> anyhow::Result<(Arc<NegotiatorAddr>, NegotiatorCallbacks)> {
+    let arbiter = Arbiter::new();
+    let (sender, receiver) = std::sync::mpsc::channel();
+    arbiter.spawn_fn(move || {
+        let created = build_negotiator(config, agent_env, working_dir, plugins_dir).map(
+            |(negotiator, callbacks)| {
+                let negotiator = negotiator.with_dedicated_arbiter();
+                (NegotiatorAddr::from(negotiator), callbacks)
+            },
+        );
+        sender.send(created).ok();
+    });
+
+    match receiver.recv() {
+        Ok(Ok((negotiator, callbacks))) => Ok((Arc::new(negotiator), callbacks)),
+        Ok(Err(e)) => {
+            arbiter.stop();
+            Err(e)
+        }
+        Err(_) => {
+            arbiter.stop();
+            bail!("Negotiator Arbiter stopped before Negotiator was created.")
+        }
+    }
+}
+
+fn build_negotiator(
+    config: NegotiatorsConfig,
+    agent_env: serde_yaml::Value,
+    working_dir: PathBuf,
+    plugins_dir: PathBuf,
+) -> anyhow::Result<(Negotiator, NegotiatorCallbacks)> {
+    let mut negotiators = config.negotiators;
+    // Stable sort preserves config order of negotiators with equal priority.
+    negotiators.sort_by_key(|negotiator| negotiator.priority);
+
+    register_builtin_negotiators();
+
     let mut components = NegotiatorsPack::new();
-    for config in config.negotiators.into_iter() {
+    let mut working_dirs = vec![];
+    for config in negotiators.into_iter() {
         let name = config.name;
-        let working_dir = working_dir.join(&name);
+        if !config.enabled {
+            log::info!("Negotiator {} is disabled. Skipping.", name);
+            continue;
+        }
+
+        // Use name deduplicated by pack, so multiple instances of the same
+        // negotiator don't share working directory.
+        let working_dir = working_dir.join(components.unique_name(&name));
+        working_dirs.push(working_dir.clone());
 
         log::info!("Creating negotiator: {}", name);
 
         fs::create_dir_all(&working_dir)?;
 
         let negotiator = match config.load_mode {
-            LoadMode::BuiltIn => create_builtin(&name, config.params, working_dir)?,
+            LoadMode::BuiltIn => {
+                create_builtin(&name, config.params, agent_env.clone(), working_dir)?
+            }
             LoadMode::SharedLibrary { path } => {
                 let plugin_path = match path.is_relative() {
                     true => plugins_dir.join(path),
                     false => path,
                 };
-                create_shared_lib(&plugin_path, &name, config.params, working_dir)?
+                create_shared_lib(
+                    &plugin_path,
+                    &name,
+                    config.params,
+                    agent_env.clone(),
+                    working_dir,
+                )?
             }
             LoadMode::StaticLib { library } => create_static_negotiator(
-                &format!("{}::{}", &library, &name),
+                &qualified_name(&library, &name),
+                config.params,
+                agent_env.clone(),
+                working_dir,
+            )?,
+            LoadMode::JsonRpc { address, timeout } => create_jsonrpc(
+                &address,
+                timeout,
+                &name,
                 config.params,
+                agent_env.clone(),
                 working_dir,
             )?,
         };
@@ -72,33 +425,78 @@ pub fn create_negotiator(
         components = components.add_component(&name, negotiator);
     }
 
+    if components.is_empty() {
+        if config.composite.require_non_empty {
+            bail!("No negotiators enabled. Negotiator without components accepts everything.");
+        }
+        log::warn!("No negotiators enabled. All Proposals and Agreements will be accepted!");
+    }
+
+    if !config.composite.cleanup_working_dirs {
+        working_dirs.clear();
+    }
+
     let (negotiator, callbacks) = Negotiator::new(components, config.composite);
-    Ok((Arc::new(NegotiatorAddr::from(negotiator)), callbacks))
+    Ok((negotiator.with_cleanup_dirs(working_dirs), callbacks))
+}
+
+/// Registers builtin negotiators under `BUILTIN_LIBRARY`, so they are resolved
+/// by qualified name the same way as negotiators from static libraries.
+/// Builtins can be loaded with `LoadMode::StaticLib { library: "builtin" }` as well.
+/// Only missing builtins are registered, so they are restored after `clear_negotiators`.
+pub fn register_builtin_negotiators() {
+    ya_builtin_negotiators::register_negotiators_with(|name, constructor| {
+        register_negotiator_if_missing(BUILTIN_LIBRARY, name, constructor);
+    });
 }
 
 pub fn create_builtin(
     name: &str,
     config: serde_yaml::Value,
-    _working_dir: PathBuf,
+    agent_env: serde_yaml::Value,
+    working_dir: PathBuf,
 ) -> anyhow::Result<Box<dyn NegotiatorComponent>> {
-    let negotiator = match &name[..] {
-        "LimitAgreements" => Box::new(MaxAgreements::new(config)?) as Box<dyn NegotiatorComponent>,
-        "LimitExpiration" => {
-            Box::new(LimitExpiration::new(config)?) as Box<dyn NegotiatorComponent>
-        }
-        "AcceptAll" => Box::new(AcceptAll::new(config)?) as Box<dyn NegotiatorComponent>,
-        _ => bail!("BuiltIn negotiator {} doesn't exists.", &name),
-    };
-    Ok(negotiator)
+    register_builtin_negotiators();
+    create_static_negotiator(
+        &qualified_name(BUILTIN_LIBRARY, name),
+        config,
+        agent_env,
+        working_dir,
+    )
 }
 
 pub fn create_shared_lib(
     path: &Path,
     name: &str,
     config: serde_yaml::Value,
+    agent_env: serde_yaml::Value,
     working_dir: PathBuf,
 ) -> anyhow::Result<Box<dyn NegotiatorComponent>> {
-    SharedLibNegotiator::new(path, name, config, working_dir)
+    SharedLibNegotiator::new(path, name, config, agent_env, working_dir)
+}
+
+#[cfg(feature = "jsonrpc")]
+pub fn create_jsonrpc(
+    address: &str,
+    timeout: Option<Duration>,
+    name: &str,
+    config: serde_yaml::Value,
+    agent_env: serde_yaml::Value,
+    working_dir: PathBuf,
+) -> anyhow::Result<Box<dyn NegotiatorComponent>> {
+    JsonRpcComponent::connect(address, timeout, name, config, agent_env, working_dir)
+}
+
+#[cfg(not(feature = "jsonrpc"))]
+pub fn create_jsonrpc(
+    _address: &str,
+    _timeout: Option<Duration>,
+    name: &str,
+    _config: serde_yaml::Value,
+    _agent_env: serde_yaml::Value,
+    _working_dir: PathBuf,
+) -> anyhow::Result<Box<dyn NegotiatorComponent>> {
+    bail!("Can't create JSON-RPC negotiator {name}. Compiled without `jsonrpc` feature.")
 }
 
 #[cfg(test)]
@@ -121,14 +519,24 @@ mod tests {
             params: serde_yaml::to_value(expiration::Config {
                 min_expiration: std::time::Duration::from_secs(2),
                 max_expiration: std::time::Duration::from_secs(300),
+                max_debit_note_accept_timeout: None,
+                min_agreement_expiration: None,
             })
             .unwrap(),
+            enabled: true,
+            priority: 0,
         };
 
         let limit_conf = NegotiatorConfig {
             name: "LimitAgreements".to_string(),
             load_mode: LoadMode::BuiltIn,
-            params: serde_yaml::to_value(max_agreements::Config { max_agreements: 1 }).unwrap(),
+            params: serde_yaml::to_value(max_agreements::Config {
+                max_agreements: 1,
+                reservation_timeout: std::time::Duration::from_secs(60),
+            })
+            .unwrap(),
+            enabled: true,
+            priority: 0,
         };
 
         let config = NegotiatorsConfig {
@@ -142,11 +550,81 @@ mod tests {
         let test_dir = test_data_dir();
         create_negotiator(
             serde_yaml::from_str(&serialized).unwrap(),
+            serde_yaml::Value::Null,
             test_dir.clone(),
             test_dir,
         )
         .unwrap();
     }
+
+    #[cfg(feature = "jsonrpc")]
+    mod jsonrpc {
+        use super::*;
+        use futures::future::{select, Either};
+        use ya_agreement_utils::{OfferTemplate, ProposalView};
+        use ya_negotiator_component::component::{NegotiationResult, Score};
+        use ya_negotiator_jsonrpc_interface::run_server;
+
+        /// Remote negotiator, which responds slowly.
+        struct SlowTemplate;
+
+        impl NegotiatorComponent for SlowTemplate {
+            fn negotiate_step(
+                &mut self,
+                _their: &ProposalView,
+                template: ProposalView,
+                score: Score,
+            ) -> anyhow::Result<NegotiationResult> {
+                Ok(NegotiationResult::Ready {
+                    proposal: template,
+                    score,
+                })
+            }
+
+            fn fill_template(&mut self, template: OfferTemplate) -> anyhow::Result<OfferTemplate> {
+                std::thread::sleep(Duration::from_millis(500));
+                Ok(template)
+            }
+        }
+
+        /// Blocking JSON-RPC calls shouldn't stall caller's Arbiter.
+        #[actix_rt::test]
+        async fn test_jsonrpc_negotiator_on_dedicated_arbiter() {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            let address = listener.local_addr().unwrap();
+            std::thread::spawn(move || {
+                run_server(
+                    listener,
+                    Arc::new(|_, _, _, _| {
+                        Ok(Box::new(SlowTemplate) as Box<dyn NegotiatorComponent>)
+                    }),
+                )
+            });
+
+            let config = NegotiatorsConfigBuilder::new()
+                .jsonrpc(
+                    &address.to_string(),
+                    "SlowTemplate",
+                    serde_yaml::Value::Null,
+                )
+                .unwrap()
+                .build();
+            let test_dir = test_data_dir();
+            let (negotiator, _callbacks) =
+                create_negotiator(config, serde_yaml::Value::Null, test_dir.clone(), test_dir)
+                    .unwrap();
+
+            let template = OfferTemplate::default();
+            let offer = Box::pin(negotiator.create_offer(&template));
+            let timer = Box::pin(tokio::time::sleep(Duration::from_millis(50)));
+            match select(offer, timer).await {
+                Either::Right((_, offer)) => {
+                    offer.await.unwrap();
+                }
+                Either::Left(_) => panic!("Negotiator blocked caller's Arbiter."),
+            };
+        }
+    }
 }
 
 impl Default for NegotiatorsConfig {
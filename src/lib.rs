@@ -1,29 +1,53 @@
+mod channel;
 mod collection;
 mod composite;
+mod decision;
+mod engine;
 pub mod factory;
 mod negotiators;
+mod rounds;
 
+pub use channel::ActionReceiver;
+pub use collection::NoDecisionReason;
 pub(crate) use collection::ProposalsCollection;
-pub use composite::{Negotiator, NegotiatorCallbacks};
+pub use composite::{
+    Negotiator, NegotiatorCallbacks, NegotiatorState, NO_PROGRESS, SCORE_BELOW_MINIMUM,
+    SELF_NEGOTIATION, TOO_MANY_ROUNDS,
+};
+pub use engine::NegotiatorEngine;
 
 pub use negotiators::{
-    AgreementAction, AgreementSigned, ControlEvent, NegotiatorAddr, PostAgreementEvent,
-    ProposalAction,
+    AgreementAction, AgreementSigned, ControlEvent, DryRunProposal, NegotiationEvent,
+    NegotiatorAddr, PostAgreementEvent, ProposalAction,
 };
 
+pub use ya_negotiator_component::metrics::{LogMetrics, Metrics, NoMetrics, StepOutcome};
 pub use ya_negotiator_component::{
-    AgreementResult, NegotiationResult, NegotiatorComponent, NegotiatorsPack,
+    AgreementResult, ErrorPolicy, NegotiationResult, NegotiatorComponent, NegotiatorsPack,
+    RejectPolicy, TemplateConflictPolicy,
 };
 
 pub mod builtin {
-    pub use ya_builtin_negotiators::{AcceptAll, LimitExpiration, MaxAgreements};
+    pub use ya_builtin_negotiators::{
+        AcceptAll, AvailabilityWindow, HardwareLimits, LimitExpiration, MaxAgreements,
+        PaymentPlatform, RateLimit, Tap, TemplateEnv,
+    };
 }
 
 pub mod component {
     pub use ya_agreement_utils::ProposalView;
-    pub use ya_negotiator_component::static_lib::register_negotiator;
+    pub use ya_negotiator_component::component::{
+        diagnostics_query, is_diagnostics_query, reconfigure_request,
+    };
+    pub use ya_negotiator_component::static_lib::{
+        clear_negotiators, create_static_negotiator, deregister_negotiator, is_registered,
+        qualified_name, register_negotiator, register_negotiator_if_missing, ConstructorFunction,
+        BUILTIN_LIBRARY,
+    };
     pub use ya_negotiator_component::{
-        AgreementEvent, AgreementResult, NegotiationResult, NegotiatorComponent, NegotiatorsPack,
-        RejectReason, Score,
+        namespaced_score, AgreementEvent, AgreementResult, AnyOf, CachingComponent,
+        ComponentDescription, CounterOffer, NegotiationResult, NegotiatorComponent,
+        NegotiatorsPack, ProposalScore, ReevaluationHandle, ReevaluationRequest, RejectReason,
+        Score, ScoringAdapter, ScoringComponent, PACK_COMPONENT,
     };
 }
@@ -2,13 +2,17 @@ use actix::prelude::*;
 use actix::Actor;
 use anyhow::Result;
 use derive_more::Display;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
 
 use ya_agreement_utils::{AgreementView, OfferTemplate};
 use ya_client_model::market::{NewOffer, NewProposal, Proposal, Reason};
 
-use crate::component::AgreementResult;
-use crate::Negotiator;
+use crate::collection::NoDecisionReason;
+use crate::component::{AgreementResult, NegotiationResult};
+use crate::rounds::LatestProposals;
+use crate::{Negotiator, NegotiatorState};
 use ya_negotiator_component::component::AgreementEvent;
 
 /// Response for requestor proposals.
@@ -53,6 +57,41 @@ pub enum AgreementAction {
     },
 }
 
+/// Observational event sent to Negotiator event sink. Contrary to `ProposalAction`
+/// and `AgreementAction` no reaction is expected, so events can be forwarded
+/// to logs or UI.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum NegotiationEvent {
+    OfferPublished {
+        offer: OfferTemplate,
+    },
+    ProposalDecision(ProposalAction),
+    AgreementDecision(AgreementAction),
+    ProposalRejected {
+        proposal_id: String,
+        reason: Option<Reason>,
+    },
+    AgreementSigned {
+        agreement_id: String,
+    },
+    AgreementRejected {
+        agreement_id: String,
+    },
+    AgreementFinalized {
+        agreement_id: String,
+        result: AgreementResult,
+    },
+    PostAgreementEvent {
+        agreement_id: String,
+        event: AgreementEvent,
+    },
+    /// Collection made decision without choosing anything.
+    NoDecision {
+        collection: String,
+        reason: NoDecisionReason,
+    },
+}
+
 // =========================================== //
 // Negotiator interface
 // =========================================== //
@@ -67,7 +106,7 @@ pub struct CreateOffer {
 
 /// Reactions to events from market. These function make market decisions
 /// related to incoming Proposals.
-#[derive(Message)]
+#[derive(Message, Clone)]
 #[rtype(result = "Result<()>")]
 pub struct ReactToProposal {
     pub subscription_id: String,
@@ -77,6 +116,20 @@ pub struct ReactToProposal {
     pub our_prev_proposal: Proposal,
 }
 
+/// Evaluates Proposal the same way as `ReactToProposal`, but returns components
+/// decision instead of acting on it. Proposal isn't added to collection and
+/// components aren't notified about any lifecycle events.
+///
+/// Components are called with `dry_run_step`, so stateful components can read
+/// their internal state to make decision, but don't change it. Builtin negotiators,
+/// negotiators from shared libraries and external processes support it.
+#[derive(Message)]
+#[rtype(result = "Result<NegotiationResult>")]
+pub struct DryRunProposal {
+    pub incoming_proposal: Proposal,
+    pub our_prev_proposal: Proposal,
+}
+
 /// Reactions to events from market. These function make market decisions
 /// related to incoming Agreements.
 #[derive(Message)]
@@ -134,6 +187,34 @@ pub struct ControlEvent {
     pub params: serde_json::Value,
 }
 
+/// Collects state of whole Negotiator: components, collections, goals and
+/// recent decisions into single JSON document.
+#[derive(Message)]
+#[rtype(result = "Result<serde_json::Value>")]
+pub struct DiagnosticDump;
+
+/// Takes snapshot of negotiations state, which can be restored after restart.
+#[derive(Message)]
+#[rtype(result = "Result<NegotiatorState>")]
+pub struct Snapshot;
+
+/// Restores negotiations state saved with `Snapshot`.
+#[derive(Message)]
+#[rtype(result = "Result<()>")]
+pub struct Restore(pub NegotiatorState);
+
+/// Sets channel receiving `NegotiationEvent`s. Replaces previous sink.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct SetEventSink(pub mpsc::UnboundedSender<NegotiationEvent>);
+
+/// Negotiator will be destroyed. Components should clean up in `timeout`.
+#[derive(Message)]
+#[rtype(result = "Result<()>")]
+pub struct Shutdown {
+    pub timeout: std::time::Duration,
+}
+
 /// Negotiator should provide expected number of Agreements.
 #[derive(Message)]
 #[rtype(result = "()")]
@@ -141,7 +222,7 @@ pub struct RequestAgreements(pub usize);
 
 // TODO: Consider, if this struct is helpful at all and remove if not.
 #[derive(Clone)]
-pub struct NegotiatorAddr(pub Addr<Negotiator>);
+pub struct NegotiatorAddr(pub Addr<Negotiator>, LatestProposals);
 
 impl NegotiatorAddr {
     pub async fn create_offer(&self, template: &OfferTemplate) -> Result<NewProposal> {
@@ -158,6 +239,11 @@ impl NegotiatorAddr {
         incoming_proposal: &Proposal,
         our_proposal: &Proposal,
     ) -> Result<()> {
+        // Negotiator drops result of evaluation, if newer Proposal arrives meanwhile.
+        self.1.arrived(
+            incoming_proposal.prev_proposal_id.as_ref(),
+            &incoming_proposal.proposal_id,
+        );
         self.0
             .send(ReactToProposal {
                 subscription_id: subscription_id.to_string(),
@@ -167,6 +253,21 @@ impl NegotiatorAddr {
             .await?
     }
 
+    /// Checks, what Negotiator would decide about Proposal without sending
+    /// any response. See `DryRunProposal`.
+    pub async fn dry_run_proposal(
+        &self,
+        incoming_proposal: &Proposal,
+        our_proposal: &Proposal,
+    ) -> Result<NegotiationResult> {
+        self.0
+            .send(DryRunProposal {
+                incoming_proposal: incoming_proposal.clone(),
+                our_prev_proposal: our_proposal.clone(),
+            })
+            .await?
+    }
+
     pub async fn react_to_agreement(
         &self,
         subscription_id: &str,
@@ -248,12 +349,57 @@ impl NegotiatorAddr {
             .await?
     }
 
+    /// Typed version of `control_event`, which handles params and response
+    /// serialization.
+    pub async fn control_event_typed<T: Serialize, R: DeserializeOwned>(
+        &self,
+        component: &str,
+        params: &T,
+    ) -> Result<R> {
+        let response = self
+            .control_event(component, serde_json::to_value(params)?)
+            .await?;
+        serde_json::from_value(response).map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to deserialize control event response from '{}'. {}",
+                component,
+                e
+            )
+        })
+    }
+
+    /// Dumps state of Negotiator for diagnostic purposes. Output format isn't
+    /// stable and is meant to be read by humans.
+    pub async fn diagnostic_dump(&self) -> Result<serde_json::Value> {
+        self.0.send(DiagnosticDump).await?
+    }
+
+    pub async fn snapshot(&self) -> Result<NegotiatorState> {
+        self.0.send(Snapshot).await?
+    }
+
+    pub async fn restore(&self, state: NegotiatorState) -> Result<()> {
+        self.0.send(Restore(state)).await?
+    }
+
+    pub async fn set_event_sink(
+        &self,
+        sink: mpsc::UnboundedSender<NegotiationEvent>,
+    ) -> Result<()> {
+        Ok(self.0.send(SetEventSink(sink)).await?)
+    }
+
+    pub async fn shutdown(&self, timeout: std::time::Duration) -> Result<()> {
+        self.0.send(Shutdown { timeout }).await?
+    }
+
     pub async fn request_agreements(&self, count: usize) -> Result<()> {
         Ok(self.0.send(RequestAgreements(count)).await?)
     }
 
     pub fn from(negotiator: Negotiator) -> NegotiatorAddr {
-        NegotiatorAddr(negotiator.start())
+        let latest = negotiator.latest_proposals();
+        NegotiatorAddr(negotiator.start(), latest)
     }
 }
 
@@ -0,0 +1,154 @@
+use chrono::{Duration, Utc};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use ya_agreement_utils::{AgreementView, ProposalView};
+use ya_negotiator_component::Score;
+
+/// How long state of negotiation is kept after receiving their last Proposal,
+/// if Proposal has no expiration property.
+const MAX_ROUND_AGE_HOURS: i64 = 1;
+
+/// Pointers to ids of Proposals, from which Agreement was created.
+const PROPOSAL_ID_POINTERS: [&str; 2] = ["/demand/demandId", "/offer/offerId"];
+
+/// State of negotiations, which we countered and are waiting for next Proposal
+/// from other party. Keyed by id of their last Proposal.
+#[derive(Default)]
+pub(crate) struct NegotiationRounds {
+    /// Scores computed in previous negotiation round.
+    /// Passed to components in next round, so they can update them incrementally.
+    pub scores: HashMap<String, Score>,
+    /// Number of negotiation rounds, that we countered.
+    pub rounds: HashMap<String, u32>,
+    /// Proposals sent by other party in previous negotiation rounds, the oldest first.
+    pub histories: HashMap<String, Vec<ProposalView>>,
+}
+
+/// State of previous rounds continued by next Proposal from other party.
+pub(crate) struct PrevRound {
+    pub score: Score,
+    pub round: u32,
+    pub history: Vec<ProposalView>,
+}
+
+impl NegotiationRounds {
+    /// Takes state of negotiation continued by their new Proposal. `prev_id` is id of
+    /// their Proposal, which our previous Proposal countered.
+    pub fn take(&mut self, prev_id: Option<&String>) -> PrevRound {
+        let (score, round, history) = match prev_id {
+            Some(prev_id) => (
+                self.scores.remove(prev_id),
+                self.rounds.remove(prev_id),
+                self.histories.remove(prev_id),
+            ),
+            None => (None, None, None),
+        };
+        PrevRound {
+            score: score.unwrap_or_default(),
+            round: round.unwrap_or(0) + 1,
+            history: history.unwrap_or_default(),
+        }
+    }
+
+    /// Remembers state of negotiation after countering `their` Proposal.
+    pub fn countered(&mut self, their: &ProposalView, prev: PrevRound, score: Score) {
+        let mut history = prev.history;
+        history.push(their.clone());
+        self.scores.insert(their.id.clone(), score);
+        self.rounds.insert(their.id.clone(), prev.round);
+        self.histories.insert(their.id.clone(), history);
+    }
+
+    /// Puts back state of negotiation taken with `take`, when their new Proposal
+    /// wasn't responded to. Nothing is stored for negotiations without previous rounds.
+    pub fn put_back(&mut self, prev_id: Option<&String>, prev: PrevRound) {
+        let prev_id = match prev_id {
+            Some(prev_id) if !prev.history.is_empty() => prev_id.clone(),
+            _ => return,
+        };
+        self.scores.insert(prev_id.clone(), prev.score);
+        self.rounds.insert(prev_id.clone(), prev.round - 1);
+        self.histories.insert(prev_id, prev.history);
+    }
+
+    pub fn forget(&mut self, proposal_id: &str) {
+        self.scores.remove(proposal_id);
+        self.rounds.remove(proposal_id);
+        self.histories.remove(proposal_id);
+    }
+
+    /// Forgets negotiations, in which their last Proposal expired, so other party
+    /// won't continue them. Returns ids of forgotten Proposals.
+    pub fn forget_expired(&mut self) -> Vec<String> {
+        let now = Utc::now();
+        let expired = self
+            .histories
+            .iter()
+            .filter_map(|(id, history)| {
+                let last = history.last()?;
+                let expiration = last
+                    .expiration()
+                    .unwrap_or_else(|_| last.timestamp + Duration::hours(MAX_ROUND_AGE_HOURS));
+                (expiration < now).then(|| id.clone())
+            })
+            .collect::<Vec<_>>();
+
+        for id in &expired {
+            self.forget(id);
+        }
+        expired
+    }
+}
+
+/// Latest Proposals from other party keyed by id of our Proposal, they respond to.
+/// Shared with `NegotiatorAddr`, which updates it before Proposal is queued in
+/// Negotiator's mailbox, so Negotiator can recognize Proposals superseded while
+/// it was evaluating them.
+#[derive(Clone, Default)]
+pub(crate) struct LatestProposals(Arc<Mutex<HashMap<String, String>>>);
+
+impl LatestProposals {
+    /// Marks `their_id` as the latest response to our Proposal `our_id`.
+    /// Initial Proposals don't respond to anything, so they aren't tracked.
+    pub fn arrived(&self, our_id: Option<&String>, their_id: &str) {
+        if let Some(our_id) = our_id {
+            self.0
+                .lock()
+                .unwrap()
+                .insert(our_id.clone(), their_id.to_string());
+        }
+    }
+
+    /// Returns id of Proposal, which superseded `their_id`, if any.
+    pub fn superseded(&self, our_id: Option<&String>, their_id: &str) -> Option<String> {
+        let latest = self.0.lock().unwrap();
+        latest
+            .get(our_id?)
+            .filter(|latest_id| latest_id.as_str() != their_id)
+            .cloned()
+    }
+
+    /// Returns id of Proposal, which superseded `their_id`. Otherwise `their_id`
+    /// is still the latest response and stops being tracked.
+    pub fn finish(&self, our_id: Option<&String>, their_id: &str) -> Option<String> {
+        let our_id = our_id?;
+        let mut latest = self.0.lock().unwrap();
+        match latest.get(our_id) {
+            Some(latest_id) if latest_id != their_id => Some(latest_id.clone()),
+            Some(_) => {
+                latest.remove(our_id);
+                None
+            }
+            None => None,
+        }
+    }
+}
+
+/// Ids of Proposals, from which Agreement was created.
+pub(crate) fn agreement_proposals(agreement: &AgreementView) -> Vec<String> {
+    PROPOSAL_ID_POINTERS
+        .iter()
+        .filter_map(|pointer| agreement.pointer_typed::<String>(pointer).ok())
+        .collect()
+}
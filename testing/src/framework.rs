@@ -1,4 +1,4 @@
-use ya_agreement_utils::{AgreementView, OfferTemplate};
+use ya_agreement_utils::{unmatched_clauses, AgreementView, OfferTemplate};
 use ya_negotiators::factory::*;
 use ya_negotiators::AgreementResult;
 
@@ -6,8 +6,10 @@ use ya_client_model::market::Proposal;
 use ya_client_model::NodeId;
 
 use crate::negotiation_record::{NegotiationRecord, NegotiationRecordSync};
-use crate::node::{Node, NodeType};
+use crate::network::NetworkProfile;
+use crate::node::{seed_ids, unseed_ids, Node, NodeType};
 use crate::provider::{provider_agreements_processor, provider_proposals_processor};
+use crate::replay::Replay;
 use crate::requestor::{requestor_agreements_processor, requestor_proposals_processor};
 
 use crate::prepare_test_dir;
@@ -37,6 +39,13 @@ pub struct Framework {
 
     pub test_dir: PathBuf,
     pub test_timeout: Duration,
+    /// Number of Proposals exchanged by a single pair of nodes, after which
+    /// negotiations are considered infinite loop.
+    pub max_steps: usize,
+    /// Network conditions between nodes. Reliable network without latency by default.
+    pub network: NetworkProfile,
+    /// Ids on current thread are deterministic until Framework is dropped.
+    seeded: bool,
 }
 
 impl Framework {
@@ -48,6 +57,9 @@ impl Framework {
             providers: HashMap::new(),
             test_dir: prepare_test_dir(test_name)?,
             test_timeout: Duration::from_secs(10),
+            max_steps: 30,
+            network: NetworkProfile::default(),
+            seeded: false,
         })
     }
 
@@ -63,11 +75,30 @@ impl Framework {
         Ok(framework)
     }
 
+    /// Makes Proposal, Agreement and Node ids deterministic for given seed.
+    /// Should be called before adding nodes, otherwise their identities will be random.
+    /// Ids become random again, when Framework is dropped.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        seed_ids(seed);
+        self.seeded = true;
+        self
+    }
+
     pub fn test_timeout(mut self, timeout: Duration) -> Self {
         self.test_timeout = timeout;
         self
     }
 
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    pub fn with_network(mut self, network: NetworkProfile) -> Self {
+        self.network = network;
+        self
+    }
+
     pub fn add_provider(mut self, config: NegotiatorsConfig) -> anyhow::Result<Self> {
         let node = Node::new(config, NodeType::Provider, None, self.test_dir.clone())?;
         self.providers.insert(node.node_id, node);
@@ -110,15 +141,21 @@ impl Framework {
         Ok(self)
     }
 
+    /// Sets Agreements goal for all Providers and Requestors named `name`.
     pub async fn request_agreements(&self, name: &str, count: usize) -> anyhow::Result<()> {
-        if let Ok(node) = self.provider(name) {
-            node.request_agreements(count).await?
+        let nodes = self
+            .providers_by_name(name)
+            .into_iter()
+            .chain(self.requestors_by_name(name))
+            .collect::<Vec<_>>();
+        if nodes.is_empty() {
+            bail!("Requestor/Provider named {} not found.", name)
         }
-        if let Ok(node) = self.requestor(name) {
+
+        for node in nodes {
             node.request_agreements(count).await?
         }
-
-        bail!("Requestor/Provider named {} not found.", name)
+        Ok(())
     }
 
     pub async fn run_for_templates(
@@ -126,11 +163,40 @@ impl Framework {
         demand: OfferTemplate,
         offer: OfferTemplate,
     ) -> Result<NegotiationRecord, FrameworkError> {
-        let record = NegotiationRecordSync::new(30);
+        let offers = self
+            .providers
+            .values()
+            .map(|provider| (provider.clone(), offer.clone()))
+            .collect();
+        self.run_for_providers_templates(demand, offers).await
+    }
+
+    /// Runs negotiations, in which each Provider publishes Offer created from
+    /// his own template. Providers are identified by name. Providers not listed
+    /// here won't publish any Offer.
+    pub async fn run_for_named_templates(
+        &self,
+        demand: OfferTemplate,
+        offers: Vec<(String, OfferTemplate)>,
+    ) -> Result<NegotiationRecord, FrameworkError> {
+        let offers = offers
+            .into_iter()
+            .map(|(name, offer)| Ok((self.provider(&name)?, offer)))
+            .collect::<anyhow::Result<Vec<_>>>()
+            .map_err(|e| FrameworkError::from(e, &NegotiationRecordSync::new(self.max_steps)))?;
+        self.run_for_providers_templates(demand, offers).await
+    }
 
-        let mut offers = vec![];
-        for (_, provider) in &self.providers {
-            offers.push(
+    async fn run_for_providers_templates(
+        &self,
+        demand: OfferTemplate,
+        offers: Vec<(Arc<Node>, OfferTemplate)>,
+    ) -> Result<NegotiationRecord, FrameworkError> {
+        let record = NegotiationRecordSync::new(self.max_steps);
+
+        let mut offer_proposals = vec![];
+        for (provider, offer) in offers {
+            offer_proposals.push(
                 provider
                     .create_offer(&offer)
                     .await
@@ -149,7 +215,8 @@ impl Framework {
         }
 
         let processors_handle = self.spawn_processors(record.clone(), self.test_timeout);
-        self.init_for(offers, demands, record.clone()).await;
+        self.init_for(offer_proposals, demands, record.clone())
+            .await;
 
         processors_handle
             .await
@@ -159,7 +226,7 @@ impl Framework {
         Ok(record.clone())
     }
 
-    // Will start negotiations for all pairs of Offer/Demand.
+    // Will start negotiations for all pairs of Offer/Demand, that match each other.
     pub async fn init_for(
         &self,
         offers: Vec<Proposal>,
@@ -172,8 +239,24 @@ impl Framework {
             record.add_proposal(demand.clone());
 
             for offer in &offers {
-                //TODO: We should do Offer/Demand matching here.
                 let requestor = self.requestors.get(&demand.issuer_id).unwrap();
+                match unmatched(offer, &demand) {
+                    Ok(unmatched) if !unmatched.is_empty() => {
+                        log::info!(
+                            "Offer [{}] doesn't match Demand [{}]. Skipping negotiations.",
+                            offer.proposal_id,
+                            demand.proposal_id
+                        );
+                        record.skip(&demand, offer, unmatched);
+                        continue;
+                    }
+                    Err(e) => {
+                        record.error(requestor.node_id, offer.issuer_id, e);
+                        continue;
+                    }
+                    Ok(_) => (),
+                }
+
                 let mut p_proposal = offer.clone();
                 p_proposal.prev_proposal_id = Some(demand.proposal_id.clone());
 
@@ -195,6 +278,16 @@ impl Framework {
             .ok_or(anyhow!("Requestor {} not found.", name))?)
     }
 
+    /// All Requestors named `name`. Names don't have to be unique.
+    pub fn requestors_by_name(&self, name: &str) -> Vec<Arc<Node>> {
+        nodes_by_name(&self.requestors, name)
+    }
+
+    /// All Providers named `name`. Names don't have to be unique.
+    pub fn providers_by_name(&self, name: &str) -> Vec<Arc<Node>> {
+        nodes_by_name(&self.providers, name)
+    }
+
     pub fn provider(&self, name: &str) -> anyhow::Result<Arc<Node>> {
         Ok(self
             .providers
@@ -229,7 +322,7 @@ impl Framework {
             .await
             .map_err(|e| FrameworkError::from(e, &record))?];
 
-        let processors_handle = self.spawn_processors(record.clone(), Duration::from_secs(10));
+        let processors_handle = self.spawn_processors(record.clone(), self.test_timeout);
         self.init_for(offers, demands, record.clone()).await;
 
         processors_handle
@@ -240,6 +333,52 @@ impl Framework {
         Ok(record.clone())
     }
 
+    /// Feeds Proposals and Agreements received by Providers in recorded negotiations
+    /// into fresh Provider Negotiators created from `config`. Replayed Providers
+    /// have the same identities as recorded ones, so both records can be compared
+    /// to check how different strategy would behave for identical inputs.
+    pub async fn replay(
+        &self,
+        record: &NegotiationRecord,
+        config: NegotiatorsConfig,
+    ) -> Result<NegotiationRecord, FrameworkError> {
+        let replayed = NegotiationRecordSync::from(record);
+
+        let mut providers = self.providers.values().collect::<Vec<_>>();
+        providers.sort_by(|node1, node2| node1.name.cmp(&node2.name));
+
+        for recorded in providers {
+            let provider = Node::with_identity(
+                config.clone(),
+                NodeType::Provider,
+                recorded.node_id,
+                Some(format!("{}-replay", recorded.name)),
+                self.test_dir.clone(),
+            )
+            .map_err(|e| FrameworkError::from(e, &replayed))?;
+
+            let mut results = record
+                .results
+                .iter()
+                .filter(|(pair, _)| pair.contains(&provider.node_id))
+                .collect::<Vec<_>>();
+            results.sort_by(|(pair1, _), (pair2, _)| pair1.partial_cmp(pair2).unwrap());
+
+            let mut replay = Replay::new(provider, replayed.clone(), self.test_timeout);
+            for (_, result) in results {
+                replay
+                    .replay(record, result)
+                    .await
+                    .map_err(|e| FrameworkError::from(e, &replayed))?;
+            }
+        }
+
+        let replayed = replayed.0.lock().unwrap();
+        Ok(replayed.clone())
+    }
+
+    /// Processors are stopped, when all negotiations are finished or when `run_for`
+    /// elapses. In the second case unfinished negotiations are marked with `Timeout` stage.
     fn spawn_processors(&self, record: NegotiationRecordSync, run_for: Duration) -> JoinHandle<()> {
         tokio::spawn(
             select_all(vec![
@@ -249,6 +388,7 @@ impl Framework {
                         self.providers.clone(),
                         self.requestors.clone(),
                         record.clone(),
+                        self.network.clone(),
                     ),
                 )
                 .boxed(),
@@ -258,6 +398,7 @@ impl Framework {
                         self.providers.clone(),
                         self.requestors.clone(),
                         record.clone(),
+                        self.network.clone(),
                     ),
                 )
                 .boxed(),
@@ -267,6 +408,7 @@ impl Framework {
                         self.providers.clone(),
                         self.requestors.clone(),
                         record.clone(),
+                        self.network.clone(),
                     ),
                 )
                 .boxed(),
@@ -276,11 +418,16 @@ impl Framework {
                         self.providers.clone(),
                         self.requestors.clone(),
                         record.clone(),
+                        self.network.clone(),
                     ),
                 )
                 .boxed(),
             ])
-            .map(|_| ()),
+            .map(move |(result, _, _)| {
+                if result.is_err() {
+                    record.timeout_unfinished();
+                }
+            }),
         )
     }
 
@@ -322,6 +469,33 @@ impl Framework {
     }
 }
 
+impl Drop for Framework {
+    fn drop(&mut self) {
+        // Seed is kept in thread local, so it would leak into next tests run on this thread.
+        if self.seeded {
+            unseed_ids();
+        }
+    }
+}
+
+fn nodes_by_name(nodes: &HashMap<NodeId, Arc<Node>>, name: &str) -> Vec<Arc<Node>> {
+    let mut nodes = nodes
+        .values()
+        .filter(|node| node.name == name)
+        .cloned()
+        .collect::<Vec<_>>();
+    // HashMap order is random, so sort to make results deterministic.
+    nodes.sort_by_key(|node| node.node_id.to_string());
+    nodes
+}
+
+/// Lists constraints clauses of both sides, that aren't satisfied by other side's properties.
+fn unmatched(offer: &Proposal, demand: &Proposal) -> anyhow::Result<Vec<String>> {
+    let mut unmatched = unmatched_clauses(&demand.constraints, &offer.properties)?;
+    unmatched.extend(unmatched_clauses(&offer.constraints, &demand.properties)?);
+    Ok(unmatched)
+}
+
 trait NegotiationResponseProcessor: Future<Output = ()> + Sized + 'static {}
 
 impl FrameworkError {
@@ -1,13 +1,19 @@
 pub mod error;
 mod framework;
+mod matching;
 mod negotiation_record;
+mod network;
 mod node;
 mod provider;
+mod replay;
 mod requestor;
 mod test_directory;
 
 pub use framework::Framework;
+pub use matching::{assert_offer_matches_demand, offer_matches_demand};
 pub use negotiation_record::{
-    NegotiationRecordSync, NegotiationResult, NegotiationStage, NodePair,
+    NegotiationRecordSync, NegotiationResult, NegotiationSession, NegotiationStage, NodePair,
+    TimedStage,
 };
+pub use network::{LinkProfile, NetworkProfile};
 pub use test_directory::prepare_test_dir;
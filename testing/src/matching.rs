@@ -0,0 +1,93 @@
+use anyhow::bail;
+
+use ya_agreement_utils::{unmatched_clauses, OfferTemplate};
+
+/// Checks if Offer and Demand match each other, which means, that Offer properties
+/// satisfy Demand constraints and Demand properties satisfy Offer constraints.
+/// Returned error lists all clauses, that failed.
+pub fn offer_matches_demand(offer: &OfferTemplate, demand: &OfferTemplate) -> anyhow::Result<()> {
+    let demand_unmatched = unmatched_clauses(&demand.constraints, &offer.properties)?;
+    let offer_unmatched = unmatched_clauses(&offer.constraints, &demand.properties)?;
+
+    if demand_unmatched.is_empty() && offer_unmatched.is_empty() {
+        return Ok(());
+    }
+
+    let mut message = String::from("Offer and Demand don't match.");
+    if !demand_unmatched.is_empty() {
+        message += &format!(
+            "\nOffer properties don't satisfy Demand constraints: {}",
+            demand_unmatched.join(", ")
+        );
+    }
+    if !offer_unmatched.is_empty() {
+        message += &format!(
+            "\nDemand properties don't satisfy Offer constraints: {}",
+            offer_unmatched.join(", ")
+        );
+    }
+    message += &format!("\nOffer: {}\nDemand: {}", offer, demand);
+    bail!(message)
+}
+
+/// Panics with detailed message, if Offer and Demand don't match.
+pub fn assert_offer_matches_demand(offer: &OfferTemplate, demand: &OfferTemplate) {
+    if let Err(e) = offer_matches_demand(offer, demand) {
+        panic!("{}", e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offer() -> OfferTemplate {
+        OfferTemplate {
+            properties: serde_json::json!({
+                "golem.inf.mem.gib": 4.0,
+                "golem.node.debug.subnet": "net-1",
+            }),
+            constraints: "(golem.srv.comp.expiration>1000)".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_offer_matches_demand() {
+        let demand = OfferTemplate {
+            properties: serde_json::json!({
+                "golem.srv.comp.expiration": 2000,
+            }),
+            constraints: "(&(golem.inf.mem.gib>2)(golem.node.debug.subnet=net-1))".to_string(),
+        };
+
+        assert_offer_matches_demand(&offer(), &demand);
+    }
+
+    #[test]
+    fn test_offer_doesnt_match_demand() {
+        let demand = OfferTemplate {
+            properties: serde_json::json!({
+                "golem.srv.comp.expiration": 500,
+            }),
+            constraints: "(&(golem.inf.mem.gib>8)(golem.node.debug.subnet=net-1))".to_string(),
+        };
+
+        let message = offer_matches_demand(&offer(), &demand)
+            .unwrap_err()
+            .to_string();
+
+        assert!(message.contains("Demand constraints: (golem.inf.mem.gib>8)\n"));
+        assert!(message.contains("Offer constraints: (golem.srv.comp.expiration>1000)\n"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Offer and Demand don't match.")]
+    fn test_assert_offer_matches_demand_panics() {
+        let demand = OfferTemplate {
+            properties: serde_json::json!({}),
+            constraints: "(golem.node.debug.subnet=net-2)".to_string(),
+        };
+
+        assert_offer_matches_demand(&offer(), &demand);
+    }
+}
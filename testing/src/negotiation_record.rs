@@ -7,6 +7,7 @@ use crate::error::NegotiatorError;
 
 use anyhow::bail;
 use backtrace::Backtrace;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, DisplayFromStr};
 use std::cmp::Ordering;
@@ -47,16 +48,47 @@ pub enum NegotiationStage {
     CreateAgreement {
         id: String,
     },
+    /// Offer and Demand constraints didn't match, so negotiations weren't started.
+    Skipped {
+        offer_id: String,
+        demand_id: String,
+        unmatched: Vec<String>,
+    },
     Error(String),
-    InfiniteLoop,
+    /// Nodes exchanged more Proposals than allowed by `max_steps`.
+    InfiniteLoop {
+        pair: NodePair,
+        proposals: usize,
+    },
     Timeout,
 }
 
+/// Negotiation stage together with time, when it was recorded.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TimedStage {
+    pub stage: NegotiationStage,
+    pub at: DateTime<Utc>,
+    /// Negotiation session, to which stage belongs. See `NegotiationSession`.
+    #[serde(default)]
+    pub session: Option<String>,
+}
+
+/// Single negotiation between pair of nodes. Nodes can negotiate many times,
+/// for example after previous Agreement was terminated. Each negotiation starts
+/// from different Initial Proposal, which id is used as session id.
+#[derive(Clone, Debug)]
+pub struct NegotiationSession<'a> {
+    /// `None` for stages recorded before any Proposal was exchanged.
+    pub id: Option<&'a str>,
+    pub stages: Vec<&'a NegotiationStage>,
+}
+
 /// Artifacts and events collected from negotiations between single
 /// Provider/Requestor pair.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct NegotiationResult {
-    pub stage: Vec<NegotiationStage>,
+    /// Stages in order of recording them, so timestamps never decrease.
+    pub stage: Vec<TimedStage>,
     pub proposals: Vec<Proposal>,
     pub agreement: Option<AgreementView>,
 }
@@ -114,12 +146,52 @@ impl NegotiationRecordSync {
         let mut record = self.0.lock().unwrap();
         let negotiation = record
             .results
-            .get_mut(&NodePair(owner_node, with_node))
-            .unwrap();
+            .entry(NodePair(owner_node, with_node))
+            .or_insert(NegotiationResult::new());
 
-        negotiation
-            .stage
-            .push(NegotiationStage::Error(e.to_string()));
+        negotiation.push_stage(NegotiationStage::Error(e.to_string()));
+    }
+
+    /// Offer and Demand don't match, so negotiation between nodes won't be started.
+    pub fn skip(&self, demand: &Proposal, offer: &Proposal, unmatched: Vec<String>) {
+        let mut record = self.0.lock().unwrap();
+        let negotiation = record
+            .results
+            .entry(NodePair(demand.issuer_id, offer.issuer_id))
+            .or_insert(NegotiationResult::new());
+
+        negotiation.push_session_stage(
+            demand.proposal_id.clone(),
+            NegotiationStage::Skipped {
+                offer_id: offer.proposal_id.clone(),
+                demand_id: demand.proposal_id.clone(),
+                unmatched,
+            },
+        );
+    }
+
+    /// Node didn't respond in expected time.
+    pub fn timeout(&self, owner_node: NodeId, with_node: NodeId) {
+        let mut record = self.0.lock().unwrap();
+        record
+            .results
+            .entry(NodePair(owner_node, with_node))
+            .or_insert(NegotiationResult::new())
+            .push_stage(NegotiationStage::Timeout);
+    }
+
+    /// Test timeout elapsed. Marks all negotiations, which didn't finish yet,
+    /// so they won't look like interrupted in the middle.
+    pub fn timeout_unfinished(&self) {
+        let mut record = self.0.lock().unwrap();
+        for (pair, negotiation) in record
+            .results
+            .iter_mut()
+            .filter(|(_, negotiation)| !negotiation.is_finished())
+        {
+            log::warn!("Negotiations between [{}] timed out.", pair);
+            negotiation.push_stage(NegotiationStage::Timeout);
+        }
     }
 
     /// Node error, that cannot be assigned to any negotiation pair.
@@ -135,22 +207,25 @@ impl NegotiationRecordSync {
     pub fn accept(&self, counter_proposal: Proposal, with_node: NodeId) {
         let mut record = self.0.lock().unwrap();
         let max_steps = record.max_steps;
+        let accepted_id = counter_proposal.clone().prev_proposal_id.unwrap();
+        let session = record.session_of(&accepted_id);
 
         let negotiation = record
             .results
             .entry(NodePair(counter_proposal.issuer_id, with_node))
             .or_insert(NegotiationResult::new());
 
-        negotiation.stage.push(NegotiationStage::AcceptProposal {
-            node_id: counter_proposal.issuer_id,
-            id: counter_proposal.clone().prev_proposal_id.unwrap(),
-        });
+        negotiation.push_session_stage(
+            session,
+            NegotiationStage::AcceptProposal {
+                node_id: counter_proposal.issuer_id,
+                id: accepted_id,
+            },
+        );
 
         negotiation.proposals.push(counter_proposal.clone());
-
-        if negotiation.proposals.len() > max_steps {
-            negotiation.stage.push(NegotiationStage::InfiniteLoop);
-        }
+        negotiation
+            .detect_infinite_loop(NodePair(counter_proposal.issuer_id, with_node), max_steps);
 
         record
             .proposals
@@ -160,26 +235,29 @@ impl NegotiationRecordSync {
     pub fn counter(&self, counter_proposal: Proposal, with_node: NodeId) {
         let mut record = self.0.lock().unwrap();
         let max_steps = record.max_steps;
+        let countered_id = counter_proposal.clone().prev_proposal_id.unwrap();
+        let session = record.session_of(&countered_id);
 
         let negotiation = record
             .results
             .entry(NodePair(counter_proposal.issuer_id, with_node))
             .or_insert(NegotiationResult::new());
 
-        negotiation.stage.push(NegotiationStage::CounterProposal {
-            node_id: counter_proposal.issuer_id,
-            id: counter_proposal.clone().prev_proposal_id.unwrap(),
-            proposal: NewProposal {
-                properties: counter_proposal.properties.clone(),
-                constraints: counter_proposal.constraints.clone(),
+        negotiation.push_session_stage(
+            session,
+            NegotiationStage::CounterProposal {
+                node_id: counter_proposal.issuer_id,
+                id: countered_id,
+                proposal: NewProposal {
+                    properties: counter_proposal.properties.clone(),
+                    constraints: counter_proposal.constraints.clone(),
+                },
             },
-        });
+        );
 
         negotiation.proposals.push(counter_proposal.clone());
-
-        if negotiation.proposals.len() > max_steps {
-            negotiation.stage.push(NegotiationStage::InfiniteLoop);
-        }
+        negotiation
+            .detect_infinite_loop(NodePair(counter_proposal.issuer_id, with_node), max_steps);
 
         record
             .proposals
@@ -188,46 +266,57 @@ impl NegotiationRecordSync {
 
     pub fn reject(&self, owner_node: NodeId, rejected_proposal: Proposal, reason: Option<Reason>) {
         let mut record = self.0.lock().unwrap();
+        let rejected_id = rejected_proposal.prev_proposal_id.unwrap();
+        let session = record.session_of(&rejected_id);
+
         let negotiation = record
             .results
             .entry(NodePair(owner_node, rejected_proposal.issuer_id))
             .or_insert(NegotiationResult::new());
 
-        negotiation.stage.push(NegotiationStage::RejectProposal {
-            node_id: owner_node,
-            id: rejected_proposal.prev_proposal_id.unwrap(),
-            reason,
-        });
+        negotiation.push_session_stage(
+            session,
+            NegotiationStage::RejectProposal {
+                node_id: owner_node,
+                id: rejected_id,
+                reason,
+            },
+        );
     }
 
     pub fn approve(&self, agreement: AgreementView) {
         let mut record = self.0.lock().unwrap();
 
-        let negotiation = record.negotiation_for(&agreement);
-        negotiation.stage.push(NegotiationStage::ApproveAgreement {
-            id: agreement.id.clone(),
-        });
+        record.push_agreement_stage(
+            &agreement,
+            NegotiationStage::ApproveAgreement {
+                id: agreement.id.clone(),
+            },
+        );
     }
 
     pub fn reject_agreement(&self, agreement: AgreementView, reason: Option<Reason>) {
         let mut record = self.0.lock().unwrap();
 
-        let negotiation = record.negotiation_for(&agreement);
-        negotiation.stage.push(NegotiationStage::RejectAgreement {
-            id: agreement.id.clone(),
-            reason,
-        });
+        record.push_agreement_stage(
+            &agreement,
+            NegotiationStage::RejectAgreement {
+                id: agreement.id.clone(),
+                reason,
+            },
+        );
     }
 
     pub fn propose_agreement(&self, agreement: AgreementView) {
         let mut record = self.0.lock().unwrap();
 
-        let negotiation = record.negotiation_for(&agreement);
-
-        negotiation.agreement = Some(agreement.clone());
-        negotiation.stage.push(NegotiationStage::ProposeAgreement {
-            id: agreement.id.clone(),
-        });
+        record.negotiation_for(&agreement).agreement = Some(agreement.clone());
+        record.push_agreement_stage(
+            &agreement,
+            NegotiationStage::ProposeAgreement {
+                id: agreement.id.clone(),
+            },
+        );
     }
 
     pub fn create_agreement(&self, agreement: AgreementView) {
@@ -236,10 +325,12 @@ impl NegotiationRecordSync {
             .agreements
             .insert(agreement.id.clone(), agreement.clone());
 
-        let negotiation = record.negotiation_for(&agreement);
-        negotiation.stage.push(NegotiationStage::CreateAgreement {
-            id: agreement.id.clone(),
-        });
+        record.push_agreement_stage(
+            &agreement,
+            NegotiationStage::CreateAgreement {
+                id: agreement.id.clone(),
+            },
+        );
     }
 
     pub fn get_proposal(&self, id: &String) -> Result<Proposal, NegotiatorError> {
@@ -288,6 +379,94 @@ impl NegotiationRecord {
             })
     }
 
+    /// Stages of negotiations between pair of nodes. Empty if nodes didn't negotiate.
+    pub fn stages_for(&self, pair: &NodePair) -> Vec<&NegotiationStage> {
+        self.results
+            .get(pair)
+            .map(|result| result.stages().collect())
+            .unwrap_or_default()
+    }
+
+    /// Number of Agreements approved by Providers.
+    pub fn count_agreements(&self) -> usize {
+        self.results
+            .values()
+            .flat_map(|result| result.stages())
+            .filter(|stage| matches!(stage, NegotiationStage::ApproveAgreement { .. }))
+            .count()
+    }
+
+    /// Panics if negotiations between nodes didn't finish with Agreement.
+    pub fn assert_agreement_between(&self, node1: NodeId, node2: NodeId) {
+        let result = match self.results.get(&NodePair(node1, node2)) {
+            Some(result) => result.is_finished_with_agreement(),
+            None => Err(anyhow::anyhow!("Nodes didn't negotiate.")),
+        };
+
+        if let Err(e) = result {
+            panic!(
+                "Expected Agreement between [{}] and [{}]. {}\nNegotiation traceback: {}",
+                node1, node2, e, self
+            )
+        }
+    }
+
+    /// Panics if negotiations between nodes didn't end with Proposal or Agreement rejection.
+    /// Rejected Proposal doesn't finish negotiations, so `Timeout` recorded after it is skipped.
+    pub fn assert_rejected(&self, node1: NodeId, node2: NodeId) {
+        let stages = self.stages_for(&NodePair(node1, node2));
+        match stages
+            .into_iter()
+            .rev()
+            .find(|stage| !matches!(stage, NegotiationStage::Timeout))
+        {
+            Some(NegotiationStage::RejectProposal { .. })
+            | Some(NegotiationStage::RejectAgreement { .. }) => (),
+            stage => panic!(
+                "Expected negotiations between [{}] and [{}] to end with rejection, but last stage is: {:?}\nNegotiation traceback: {}",
+                node1, node2, stage, self
+            ),
+        }
+    }
+
+    /// Negotiation sessions between pair of nodes in order of starting them.
+    pub fn sessions_for(&self, pair: &NodePair) -> Vec<NegotiationSession<'_>> {
+        self.results
+            .get(pair)
+            .map(|result| result.sessions())
+            .unwrap_or_default()
+    }
+
+    /// Id of Initial Proposal, from which negotiations leading to Proposal started.
+    /// Proposals not present in record are treated as Initial.
+    fn session_of(&self, proposal_id: &str) -> String {
+        let mut root = proposal_id;
+        // Limit steps, so broken chain of Proposals can't hang the test.
+        for _ in 0..=self.proposals.len() {
+            match self
+                .proposals
+                .get(root)
+                .and_then(|proposal| proposal.prev_proposal_id.as_deref())
+            {
+                Some(prev) => root = prev,
+                None => break,
+            }
+        }
+        root.to_string()
+    }
+
+    /// Agreement belongs to session of Proposal, from which it was created.
+    fn push_agreement_stage(&mut self, agreement: &AgreementView, stage: NegotiationStage) {
+        let session = agreement
+            .pointer_typed::<String>("/offer/offerId")
+            .map(|offer_id| self.session_of(&offer_id));
+        let negotiation = self.negotiation_for(agreement);
+        match session {
+            Ok(session) => negotiation.push_session_stage(session, stage),
+            Err(_) => negotiation.push_stage(stage),
+        }
+    }
+
     pub fn negotiation_for(&mut self, agreement: &AgreementView) -> &mut NegotiationResult {
         self.results
             .entry(NodePair(
@@ -300,12 +479,13 @@ impl NegotiationRecord {
 
 impl NegotiationResult {
     pub fn is_finished(&self) -> bool {
-        match self.stage.last() {
+        match self.last_stage() {
             Some(stage) => match stage {
                 NegotiationStage::RejectAgreement { .. } => true,
                 NegotiationStage::ApproveAgreement { .. } => true,
+                NegotiationStage::Skipped { .. } => true,
                 NegotiationStage::Error(_) => true,
-                NegotiationStage::InfiniteLoop => true,
+                NegotiationStage::InfiniteLoop { .. } => true,
                 NegotiationStage::Timeout => true,
                 _ => false,
             },
@@ -318,7 +498,7 @@ impl NegotiationResult {
             bail!("Agreement was not created.");
         }
 
-        let provider_id = match self.stage.last() {
+        let provider_id = match self.last_stage() {
             Some(stage) => match stage {
                 NegotiationStage::ApproveAgreement { id } => id.clone(),
                 _ => bail!("Last negotiation stage is not an Agreement Approval."),
@@ -326,7 +506,7 @@ impl NegotiationResult {
             None => bail!("No negotiations."),
         };
 
-        let requestor_id = match self.stage.get(self.stage.len() - 2) {
+        let requestor_id = match self.stages().rev().nth(1) {
             Some(stage) => match stage {
                 NegotiationStage::ProposeAgreement { id } => id.clone(),
                 _ => bail!("Last negotiation stage is not an Propose Agreement."),
@@ -354,6 +534,16 @@ impl PartialOrd for NodePair {
     }
 }
 
+impl NodePair {
+    pub fn new(node1: NodeId, node2: NodeId) -> NodePair {
+        NodePair(node1, node2)
+    }
+
+    pub fn contains(&self, node_id: &NodeId) -> bool {
+        &self.0 == node_id || &self.1 == node_id
+    }
+}
+
 impl PartialEq for NodePair {
     fn eq(&self, other: &Self) -> bool {
         let ord1 = self.clone().ordered();
@@ -418,6 +608,180 @@ impl fmt::Display for NegotiationRecord {
     }
 }
 
+impl NegotiationRecord {
+    /// Renders negotiations as Graphviz DOT graph. Each Provider/Requestor pair
+    /// is drawn as separate cluster, in which nodes are Proposals and Agreements
+    /// and edges are decisions made by negotiators.
+    pub fn to_dot(&self) -> String {
+        let mut pairs = self.results.iter().collect::<Vec<_>>();
+        pairs.sort_by_key(|(pair, _)| pair.to_string());
+
+        let mut dot = String::from("digraph negotiations {\n    rankdir=LR;\n");
+        for (idx, (pair, result)) in pairs.into_iter().enumerate() {
+            dot += &format!("    subgraph cluster_{} {{\n", idx);
+            dot += &format!(
+                "        label=\"{} ({}ms)\";\n",
+                escape(&pair.to_string()),
+                result.duration().num_milliseconds()
+            );
+            dot += &self.pair_to_dot(idx, result);
+            dot += "    }\n";
+        }
+        dot += "}\n";
+        dot
+    }
+
+    fn pair_to_dot(&self, pair_idx: usize, result: &NegotiationResult) -> String {
+        let mut dot = String::new();
+        let mut last: Option<String> = None;
+        let start = result.stage.first().map(|timed| timed.at);
+
+        let node = |dot: &mut String, name: &str, label: &str, style: &str| {
+            *dot += &format!(
+                "        \"{}\" [label=\"{}\"{}];\n",
+                escape(name),
+                escape(label),
+                style
+            );
+        };
+        let edge = |dot: &mut String, from: &Option<String>, to: &str, label: &str| {
+            if let Some(from) = from {
+                *dot += &format!(
+                    "        \"{}\" -> \"{}\" [label=\"{}\"];\n",
+                    escape(from),
+                    escape(to),
+                    escape(label)
+                );
+            }
+        };
+
+        for (idx, TimedStage { stage, at, .. }) in result.stage.iter().enumerate() {
+            // Edges are labeled with time elapsed since the first stage.
+            let elapsed = start
+                .map(|start| *at - start)
+                .unwrap_or_else(chrono::Duration::zero);
+            let timed = |label: &str| format!("{} +{}ms", label, elapsed.num_milliseconds());
+            // Terminal and error nodes have no natural id, so we generate unique one.
+            let end_node = format!("{}-{}", pair_idx, idx);
+            match stage {
+                NegotiationStage::CounterProposal { id, .. }
+                | NegotiationStage::AcceptProposal { id, .. } => {
+                    let label = match stage {
+                        NegotiationStage::CounterProposal { .. } => "Counter",
+                        _ => "Accept",
+                    };
+                    let new_id = self
+                        .response_to(id, result)
+                        .unwrap_or_else(|| end_node.clone());
+
+                    node(&mut dot, id, &format!("Proposal {}", id), "");
+                    node(&mut dot, &new_id, &format!("Proposal {}", new_id), "");
+                    edge(&mut dot, &Some(id.clone()), &new_id, &timed(label));
+                    last = Some(new_id);
+                }
+                NegotiationStage::RejectProposal { id, reason, .. } => {
+                    node(&mut dot, id, &format!("Proposal {}", id), "");
+                    node(&mut dot, &end_node, "Rejected", ", shape=box");
+                    edge(
+                        &mut dot,
+                        &Some(id.clone()),
+                        &end_node,
+                        &timed(&with_reason("Reject", reason)),
+                    );
+                    last = Some(end_node);
+                }
+                NegotiationStage::ProposeAgreement { id }
+                | NegotiationStage::CreateAgreement { id } => {
+                    let label = match stage {
+                        NegotiationStage::ProposeAgreement { .. } => "Propose",
+                        _ => "Create",
+                    };
+                    node(
+                        &mut dot,
+                        id,
+                        &format!("Agreement {}", id),
+                        ", shape=doubleoctagon",
+                    );
+                    if last.as_ref() != Some(id) {
+                        edge(&mut dot, &last, id, &timed(label));
+                    }
+                    last = Some(id.clone());
+                }
+                NegotiationStage::ApproveAgreement { id } => {
+                    node(&mut dot, &end_node, "Approved", ", shape=box");
+                    edge(&mut dot, &Some(id.clone()), &end_node, &timed("Approve"));
+                    last = Some(end_node);
+                }
+                NegotiationStage::RejectAgreement { id, reason } => {
+                    node(&mut dot, &end_node, "Rejected", ", shape=box");
+                    edge(
+                        &mut dot,
+                        &Some(id.clone()),
+                        &end_node,
+                        &timed(&with_reason("Reject", reason)),
+                    );
+                    last = Some(end_node);
+                }
+                NegotiationStage::Skipped { unmatched, .. } => {
+                    node(
+                        &mut dot,
+                        &end_node,
+                        &format!("Skipped: {}", unmatched.join(" ")),
+                        ", shape=box, style=dashed",
+                    );
+                    last = Some(end_node);
+                }
+                NegotiationStage::Error(e) => {
+                    node(
+                        &mut dot,
+                        &end_node,
+                        &format!("Error: {}", e),
+                        ", shape=box, color=red",
+                    );
+                    edge(&mut dot, &last, &end_node, &timed("Error"));
+                    last = Some(end_node);
+                }
+                NegotiationStage::InfiniteLoop { .. } | NegotiationStage::Timeout => {
+                    let label = match stage {
+                        NegotiationStage::InfiniteLoop { .. } => "InfiniteLoop",
+                        _ => "Timeout",
+                    };
+                    node(
+                        &mut dot,
+                        &end_node,
+                        label,
+                        ", shape=octagon, style=filled, fillcolor=orange",
+                    );
+                    edge(&mut dot, &last, &end_node, &timed(label));
+                    last = Some(end_node);
+                }
+            }
+        }
+        dot
+    }
+
+    /// Finds Proposal sent in response to Proposal with given id.
+    fn response_to(&self, id: &str, result: &NegotiationResult) -> Option<String> {
+        result
+            .proposals
+            .iter()
+            .chain(self.proposals.values())
+            .find(|proposal| proposal.prev_proposal_id.as_deref() == Some(id))
+            .map(|proposal| proposal.proposal_id.clone())
+    }
+}
+
+fn with_reason(action: &str, reason: &Option<Reason>) -> String {
+    match reason {
+        Some(reason) => format!("{}: {}", action, reason.message),
+        None => action.to_string(),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
 impl NegotiationResult {
     pub fn new() -> NegotiationResult {
         NegotiationResult {
@@ -426,6 +790,75 @@ impl NegotiationResult {
             agreement: None,
         }
     }
+
+    /// Stage without own session continues the last recorded session.
+    fn push_stage(&mut self, stage: NegotiationStage) {
+        let session = self.stage.last().and_then(|last| last.session.clone());
+        self.stage.push(TimedStage {
+            stage,
+            at: Utc::now(),
+            session,
+        });
+    }
+
+    fn push_session_stage(&mut self, session: String, stage: NegotiationStage) {
+        self.stage.push(TimedStage {
+            stage,
+            at: Utc::now(),
+            session: Some(session),
+        });
+    }
+
+    /// Stages in order of recording them, grouped by negotiation session.
+    /// Sessions are ordered by their first stage.
+    pub fn sessions(&self) -> Vec<NegotiationSession<'_>> {
+        let mut sessions: Vec<NegotiationSession> = vec![];
+        for timed in &self.stage {
+            let id = timed.session.as_deref();
+            match sessions.iter_mut().find(|session| session.id == id) {
+                Some(session) => session.stages.push(&timed.stage),
+                None => sessions.push(NegotiationSession {
+                    id,
+                    stages: vec![&timed.stage],
+                }),
+            }
+        }
+        sessions
+    }
+
+    /// Stages without timestamps in order of recording them.
+    pub fn stages(&self) -> impl DoubleEndedIterator<Item = &NegotiationStage> {
+        self.stage.iter().map(|timed| &timed.stage)
+    }
+
+    pub fn last_stage(&self) -> Option<&NegotiationStage> {
+        self.stage.last().map(|timed| &timed.stage)
+    }
+
+    /// Time elapsed between first and last recorded stage.
+    pub fn duration(&self) -> chrono::Duration {
+        match (self.stage.first(), self.stage.last()) {
+            (Some(first), Some(last)) => last.at - first.at,
+            _ => chrono::Duration::zero(),
+        }
+    }
+
+    fn detect_infinite_loop(&mut self, pair: NodePair, max_steps: usize) {
+        // Proposals already in flight, when loop was detected, shouldn't report it again.
+        let detected = self
+            .stages()
+            .any(|stage| matches!(stage, NegotiationStage::InfiniteLoop { .. }));
+        let proposals = self.proposals.len();
+        if proposals > max_steps && !detected {
+            log::warn!(
+                "Negotiations between [{}] exchanged {} Proposals, which exceeds limit of {} steps.",
+                pair,
+                proposals,
+                max_steps
+            );
+            self.push_stage(NegotiationStage::InfiniteLoop { pair, proposals });
+        }
+    }
 }
 
 #[cfg(test)]
@@ -450,4 +883,107 @@ mod tests {
 
         assert_eq!(map.len(), 1);
     }
+
+    fn proposal(id: &str, prev: Option<&str>, issuer: NodeId) -> Proposal {
+        Proposal {
+            properties: serde_json::json!({}),
+            constraints: "".to_string(),
+            proposal_id: id.to_string(),
+            issuer_id: issuer,
+            state: State::Draft,
+            timestamp: chrono::Utc::now(),
+            prev_proposal_id: prev.map(|prev| prev.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_negotiation_record_to_dot() {
+        let provider = NodeId::from_str("0x33796f397a554a6c33675976683031774f637a37").unwrap();
+        let requestor = NodeId::from_str("0x4c684d736d3157416a6e494145776833584b4339").unwrap();
+        let other = NodeId::from_str("0x6f4a6c4d4b77304a6e4a49787a4f70753167454c").unwrap();
+
+        let record = NegotiationRecordSync::new(30);
+        record.counter(proposal("p-1", Some("d-0"), provider), requestor);
+        record.accept(proposal("d-1", Some("p-1"), requestor), provider);
+        record.reject(
+            other,
+            proposal("d-6", Some("d-5"), requestor),
+            Some("Node is \"busy\".".into()),
+        );
+        record
+            .0
+            .lock()
+            .unwrap()
+            .results
+            .get_mut(&NodePair(requestor, other))
+            .unwrap()
+            .push_stage(NegotiationStage::Timeout);
+
+        let dot = record.0.lock().unwrap().to_dot();
+
+        assert!(dot.starts_with("digraph negotiations {"));
+        assert_eq!(dot.matches('{').count(), dot.matches('}').count());
+        assert_eq!(dot.matches("subgraph cluster_").count(), 2);
+        assert!(dot.contains("\"d-0\" -> \"p-1\" [label=\"Counter +"));
+        assert!(dot.contains("\"p-1\" -> \"d-1\" [label=\"Accept +"));
+        assert!(dot.contains(r#"[label="Reject: Node is \"busy\". +"#));
+        assert!(dot.contains("[label=\"Timeout +"));
+        assert!(dot.contains("shape=octagon, style=filled, fillcolor=orange"));
+    }
+
+    #[test]
+    fn test_sequential_negotiations_recorded_as_sessions() {
+        let provider = NodeId::from_str("0x33796f397a554a6c33675976683031774f637a37").unwrap();
+        let requestor = NodeId::from_str("0x4c684d736d3157416a6e494145776833584b4339").unwrap();
+
+        let record = NegotiationRecordSync::new(30);
+        record.add_proposal(proposal("d-0", None, requestor));
+        record.counter(proposal("p-1", Some("d-0"), provider), requestor);
+        record.accept(proposal("d-1", Some("p-1"), requestor), provider);
+
+        // Negotiations restarted after previous Agreement was terminated.
+        record.add_proposal(proposal("d-5", None, requestor));
+        record.counter(proposal("p-6", Some("d-5"), provider), requestor);
+        record.counter(proposal("d-7", Some("p-6"), requestor), provider);
+        record.timeout(provider, requestor);
+
+        let record = record.0.lock().unwrap();
+        let sessions = record.sessions_for(&NodePair(requestor, provider));
+
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].id, Some("d-0"));
+        assert_eq!(sessions[0].stages.len(), 2);
+        assert!(matches!(
+            sessions[0].stages[1],
+            NegotiationStage::AcceptProposal { .. }
+        ));
+        assert_eq!(sessions[1].id, Some("d-5"));
+        assert_eq!(sessions[1].stages.len(), 3);
+        assert!(matches!(sessions[1].stages[2], NegotiationStage::Timeout));
+    }
+
+    #[test]
+    fn test_stages_carry_timestamps() {
+        let provider = NodeId::from_str("0x33796f397a554a6c33675976683031774f637a37").unwrap();
+        let requestor = NodeId::from_str("0x4c684d736d3157416a6e494145776833584b4339").unwrap();
+
+        let before = Utc::now();
+        let record = NegotiationRecordSync::new(30);
+        record.counter(proposal("p-1", Some("d-0"), provider), requestor);
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        record.accept(proposal("d-1", Some("p-1"), requestor), provider);
+        let after = Utc::now();
+
+        let record = record.0.lock().unwrap();
+        let result = record.results.get(&NodePair(provider, requestor)).unwrap();
+
+        assert_eq!(result.stage.len(), 2);
+        assert!(result
+            .stage
+            .iter()
+            .all(|timed| timed.at >= before && timed.at <= after));
+        assert!(result.stage[0].at <= result.stage[1].at);
+        assert_eq!(result.duration(), result.stage[1].at - result.stage[0].at);
+        assert!(result.duration() >= chrono::Duration::milliseconds(10));
+    }
 }
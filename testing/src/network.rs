@@ -0,0 +1,103 @@
+use rand::{thread_rng, Rng};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use ya_client_model::NodeId;
+
+use crate::negotiation_record::NodePair;
+
+/// Properties of connection between two nodes.
+#[derive(Clone, Debug)]
+pub struct LinkProfile {
+    /// Latency is drawn uniformly from range [min_latency, max_latency].
+    pub min_latency: Duration,
+    pub max_latency: Duration,
+    /// Probability in range [0.0, 1.0], that message will be lost.
+    pub drop_probability: f64,
+}
+
+/// Simulates network conditions between Providers and Requestors.
+/// Messages are delayed and can be lost according to `LinkProfile` of
+/// link between communicating nodes.
+#[derive(Clone, Debug, Default)]
+pub struct NetworkProfile {
+    default: LinkProfile,
+    links: HashMap<NodePair, LinkProfile>,
+}
+
+impl LinkProfile {
+    /// Link without any latency, that never loses messages.
+    pub fn reliable() -> LinkProfile {
+        LinkProfile {
+            min_latency: Duration::from_secs(0),
+            max_latency: Duration::from_secs(0),
+            drop_probability: 0.0,
+        }
+    }
+
+    pub fn new(latency: Duration, drop_probability: f64) -> LinkProfile {
+        LinkProfile {
+            min_latency: latency,
+            max_latency: latency,
+            drop_probability,
+        }
+    }
+
+    pub fn jitter(mut self, max_latency: Duration) -> LinkProfile {
+        self.max_latency = max_latency;
+        self
+    }
+
+    fn latency(&self) -> Duration {
+        if self.max_latency <= self.min_latency {
+            return self.min_latency;
+        }
+        thread_rng().gen_range(self.min_latency..=self.max_latency)
+    }
+
+    fn drop(&self) -> bool {
+        thread_rng().gen_bool(self.drop_probability.clamp(0.0, 1.0))
+    }
+}
+
+impl Default for LinkProfile {
+    fn default() -> Self {
+        LinkProfile::reliable()
+    }
+}
+
+impl NetworkProfile {
+    /// Network with the same profile for all links.
+    pub fn new(default: LinkProfile) -> NetworkProfile {
+        NetworkProfile {
+            default,
+            links: HashMap::new(),
+        }
+    }
+
+    /// Overrides profile of link between two nodes.
+    pub fn with_link(mut self, node1: NodeId, node2: NodeId, link: LinkProfile) -> NetworkProfile {
+        self.links.insert(NodePair::new(node1, node2), link);
+        self
+    }
+
+    /// Waits for message to travel from one node to another.
+    /// Returns false, if message was lost on the way.
+    pub async fn deliver(&self, from: NodeId, to: NodeId) -> bool {
+        let link = self
+            .links
+            .get(&NodePair::new(from, to))
+            .unwrap_or(&self.default);
+
+        let latency = link.latency();
+        if !latency.is_zero() {
+            tokio::time::sleep(latency).await;
+        }
+
+        if link.drop() {
+            log::info!("Message from [{}] to [{}] was lost.", from, to);
+            return false;
+        }
+        true
+    }
+}
@@ -1,11 +1,14 @@
 use anyhow::*;
 use chrono::{Duration, Utc};
 use rand::distributions::Alphanumeric;
-use rand::{thread_rng, Rng};
-use std::sync::Arc;
+use rand::rngs::StdRng;
+use rand::{thread_rng, Rng, SeedableRng};
+use std::cell::RefCell;
+use std::sync::{Arc, Mutex};
 use tokio::sync::broadcast;
 
 use std::path::PathBuf;
+use ya_agreement_utils::agreement::expand;
 use ya_agreement_utils::{AgreementView, OfferTemplate};
 use ya_client_model::market::agreement::State as AgreementState;
 use ya_client_model::market::proposal::State;
@@ -29,6 +32,10 @@ pub struct Node {
 
     pub agreement_sender: broadcast::Sender<AgreementAction>,
     pub proposal_sender: broadcast::Sender<ProposalAction>,
+
+    /// Last Offer (or Demand) published by this node, after all negotiators
+    /// filled their part of the template.
+    published_offer: Mutex<Option<OfferTemplate>>,
 }
 
 impl Node {
@@ -38,11 +45,27 @@ impl Node {
         name: Option<String>,
         working_dir: PathBuf,
     ) -> anyhow::Result<Arc<Node>> {
-        let node_id = generate_identity();
+        Self::with_identity(config, node_type, generate_identity(), name, working_dir)
+    }
+
+    /// Creates Node impersonating existing node. Used to replay negotiations
+    /// of recorded node using different Negotiator.
+    pub fn with_identity(
+        config: NegotiatorsConfig,
+        node_type: NodeType,
+        node_id: NodeId,
+        name: Option<String>,
+        working_dir: PathBuf,
+    ) -> anyhow::Result<Arc<Node>> {
         let name = name.unwrap_or(node_id.to_string());
         let working_dir = working_dir.join(&name);
 
-        let (negotiator, callbacks) = create_negotiator(config, working_dir.clone(), working_dir)?;
+        let (negotiator, callbacks) = create_negotiator(
+            config,
+            serde_yaml::Value::Null,
+            working_dir.clone(),
+            working_dir,
+        )?;
 
         let (agreement_sender, _) = broadcast::channel(16);
         let (proposal_sender, _) = broadcast::channel(16);
@@ -54,6 +77,7 @@ impl Node {
             proposal_sender: proposal_sender.clone(),
             agreement_sender: agreement_sender.clone(),
             name,
+            published_offer: Mutex::new(None),
         };
 
         let NegotiatorCallbacks {
@@ -90,6 +114,11 @@ impl Node {
 
     pub async fn create_offer(&self, template: &OfferTemplate) -> Result<Proposal> {
         let offer = self.negotiator.create_offer(&template).await?;
+        *self.published_offer.lock().unwrap() = Some(OfferTemplate {
+            properties: expand(offer.properties.clone()),
+            constraints: offer.constraints.clone(),
+        });
+
         let state = match self.node_type {
             NodeType::Provider => State::Initial,
             NodeType::Requestor => State::Draft,
@@ -98,6 +127,42 @@ impl Node {
         Ok(self.into_proposal(offer, state, None))
     }
 
+    /// Offer (or Demand) in the form it was published by this node, with nested
+    /// properties. `None` if node didn't publish anything yet.
+    pub fn published_offer(&self) -> Option<OfferTemplate> {
+        self.published_offer.lock().unwrap().clone()
+    }
+
+    /// Panics if published Offer doesn't have property under `pointer`
+    /// (for example `/golem/com/payment/platform`) with expected value.
+    pub fn assert_offer_has(&self, pointer: &str, value: impl Into<serde_json::Value>) {
+        let offer = self.expect_published_offer();
+        let expected = value.into();
+        match offer.pointer(pointer) {
+            Some(actual) if actual == &expected => (),
+            actual => panic!(
+                "Expected [{}] to publish Offer with `{}` = {}, but found: {:?}\nPublished Offer: {}",
+                self.name, pointer, expected, actual, offer
+            ),
+        }
+    }
+
+    /// Panics if published Offer constraints don't contain `constraint` expression.
+    pub fn assert_offer_constraint(&self, constraint: &str) {
+        let offer = self.expect_published_offer();
+        if !offer.constraints.contains(constraint) {
+            panic!(
+                "Expected [{}] to publish Offer with constraint `{}`.\nPublished Offer: {}",
+                self.name, constraint, offer
+            )
+        }
+    }
+
+    fn expect_published_offer(&self) -> OfferTemplate {
+        self.published_offer()
+            .unwrap_or_else(|| panic!("Node [{}] didn't publish any Offer.", self.name))
+    }
+
     pub async fn react_to_proposal(
         &self,
         incoming_proposal: &Proposal,
@@ -196,19 +261,71 @@ impl Node {
     }
 }
 
+thread_local! {
+    /// Generator used for ids, when deterministic sequence was requested.
+    /// If not set, ids are fully random.
+    static SEEDED_RNG: RefCell<Option<StdRng>> = const { RefCell::new(None) };
+}
+
+/// Makes `generate_id` and `generate_identity` return the same sequence of ids
+/// on current thread for the same seed. This allows to compare `NegotiationRecords`
+/// between test runs.
+pub fn seed_ids(seed: u64) {
+    SEEDED_RNG.with(|rng| *rng.borrow_mut() = Some(StdRng::seed_from_u64(seed)));
+}
+
+/// Restores fully random ids on current thread.
+pub fn unseed_ids() {
+    SEEDED_RNG.with(|rng| *rng.borrow_mut() = None);
+}
+
+fn random_string(len: usize) -> String {
+    SEEDED_RNG.with(|rng| match rng.borrow_mut().as_mut() {
+        Some(rng) => rng
+            .sample_iter(&Alphanumeric)
+            .take(len)
+            .map(char::from)
+            .collect(),
+        None => thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(len)
+            .map(char::from)
+            .collect(),
+    })
+}
+
 pub fn generate_identity() -> NodeId {
-    let random_node_id: String = thread_rng()
-        .sample_iter(&Alphanumeric)
-        .take(20)
-        .map(char::from)
-        .collect();
+    let random_node_id = random_string(20);
     NodeId::from(random_node_id.as_bytes())
 }
 
 pub fn generate_id() -> String {
-    thread_rng()
-        .sample_iter(&Alphanumeric)
-        .take(64)
-        .map(char::from)
-        .collect()
+    random_string(64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generate_sequence() -> (Vec<NodeId>, Vec<String>) {
+        let identities = (0..3).map(|_| generate_identity()).collect();
+        let ids = (0..5).map(|_| generate_id()).collect();
+        (identities, ids)
+    }
+
+    #[test]
+    fn test_seeded_ids_are_reproducible() {
+        seed_ids(1234);
+        let first = generate_sequence();
+
+        seed_ids(1234);
+        let second = generate_sequence();
+
+        assert_eq!(first, second);
+
+        seed_ids(4321);
+        let other = generate_sequence();
+
+        assert_ne!(first, other);
+    }
 }
@@ -6,6 +6,7 @@ use ya_client_model::NodeId;
 
 use crate::error::NegotiatorError;
 use crate::negotiation_record::NegotiationRecordSync;
+use crate::network::NetworkProfile;
 use crate::node::Node;
 
 use backtrace::Backtrace;
@@ -20,6 +21,7 @@ pub struct ProviderReactions {
     pub providers: HashMap<NodeId, Arc<Node>>,
     pub requestors: HashMap<NodeId, Arc<Node>>,
     pub record: NegotiationRecordSync,
+    pub network: NetworkProfile,
 }
 
 impl ProviderReactions {
@@ -55,6 +57,10 @@ impl ProviderReactions {
         // Register event.
         record.accept(prov_proposal.clone(), req_proposal.issuer_id);
 
+        if !self.network.deliver(node_id, requestor.node_id).await {
+            return Ok(());
+        }
+
         if let Err(e) = requestor
             .react_to_proposal(&prov_proposal, &req_proposal)
             .await
@@ -114,6 +120,10 @@ impl ProviderReactions {
         // Register event.
         record.counter(proposal.clone(), req_proposal.issuer_id);
 
+        if !self.network.deliver(node_id, requestor.node_id).await {
+            return Ok(());
+        }
+
         if let Err(e) = requestor.react_to_proposal(&proposal, &req_proposal).await {
             record.error(req_proposal.issuer_id, proposal.issuer_id, e.into())
         }
@@ -201,6 +211,7 @@ pub async fn provider_proposals_processor(
     providers: HashMap<NodeId, Arc<Node>>,
     requestors: HashMap<NodeId, Arc<Node>>,
     record: NegotiationRecordSync,
+    network: NetworkProfile,
 ) {
     let mut p_receivers = StreamMap::new();
 
@@ -215,6 +226,7 @@ pub async fn provider_proposals_processor(
         record: record.clone(),
         requestors,
         providers,
+        network,
     };
 
     while let Some((node_id, Ok(action))) = p_receivers.next().await {
@@ -242,6 +254,7 @@ pub async fn provider_agreements_processor(
     providers: HashMap<NodeId, Arc<Node>>,
     requestors: HashMap<NodeId, Arc<Node>>,
     record: NegotiationRecordSync,
+    network: NetworkProfile,
 ) {
     let mut p_receivers = StreamMap::new();
 
@@ -256,6 +269,7 @@ pub async fn provider_agreements_processor(
         record: record.clone(),
         requestors,
         providers,
+        network,
     };
 
     while let Some((node_id, Ok(action))) = p_receivers.next().await {
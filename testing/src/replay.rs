@@ -0,0 +1,241 @@
+use ya_agreement_utils::AgreementView;
+use ya_negotiators::{AgreementAction, ProposalAction};
+
+use ya_client_model::market::proposal::State;
+use ya_client_model::market::Proposal;
+
+use crate::negotiation_record::{
+    NegotiationRecord, NegotiationRecordSync, NegotiationResult, NegotiationStage,
+};
+use crate::node::Node;
+
+use anyhow::anyhow;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::timeout;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+
+/// Event recorded in negotiations, that will be fed to replayed Provider.
+enum Input {
+    /// Requestor countered or accepted Provider's Proposal.
+    Proposal {
+        proposal: Proposal,
+        accepted: bool,
+    },
+    CreateAgreement(AgreementView),
+    ProposeAgreement(AgreementView),
+}
+
+enum Response {
+    Proposal(ProposalAction),
+    Agreement(AgreementAction),
+}
+
+type Responses = Pin<Box<dyn Stream<Item = Response> + Send>>;
+
+/// Feeds Provider Negotiator with Requestor events from recorded negotiations
+/// and registers its responses in new record.
+pub struct Replay {
+    provider: Arc<Node>,
+    record: NegotiationRecordSync,
+    responses: Responses,
+    step_timeout: Duration,
+}
+
+impl Replay {
+    pub fn new(
+        provider: Arc<Node>,
+        record: NegotiationRecordSync,
+        step_timeout: Duration,
+    ) -> Replay {
+        let proposals = BroadcastStream::new(provider.proposal_channel())
+            .filter_map(|action| action.ok())
+            .map(Response::Proposal);
+        let agreements = BroadcastStream::new(provider.agreement_channel())
+            .filter_map(|action| action.ok())
+            .map(Response::Agreement);
+
+        Replay {
+            provider,
+            record,
+            responses: Box::pin(proposals.merge(agreements)),
+            step_timeout,
+        }
+    }
+
+    /// Replays negotiations from single Provider/Requestor pair. Replay ends on first
+    /// rejection, because following Requestor events were reactions to original
+    /// Provider decisions and make no sense anymore.
+    pub async fn replay(
+        &mut self,
+        source: &NegotiationRecord,
+        result: &NegotiationResult,
+    ) -> anyhow::Result<()> {
+        for input in self.inputs(result)? {
+            let (expected_id, with_node) = match input {
+                Input::Proposal { proposal, accepted } => {
+                    let our_prev = source.get_proposal(
+                        proposal
+                            .prev_proposal_id
+                            .as_ref()
+                            .ok_or(anyhow!("Proposal [{}] has no prev", proposal.proposal_id))?,
+                    )?;
+                    self.record.add_proposal(our_prev.clone());
+
+                    match accepted {
+                        true => self.record.accept(proposal.clone(), self.provider.node_id),
+                        false => self.record.counter(proposal.clone(), self.provider.node_id),
+                    }
+
+                    if let Err(e) = self.provider.react_to_proposal(&proposal, &our_prev).await {
+                        self.record
+                            .error(self.provider.node_id, proposal.issuer_id, e);
+                        return Ok(());
+                    }
+                    (proposal.proposal_id, proposal.issuer_id)
+                }
+                Input::CreateAgreement(agreement) => {
+                    self.record.create_agreement(agreement);
+                    continue;
+                }
+                Input::ProposeAgreement(agreement) => {
+                    self.record.propose_agreement(agreement.clone());
+
+                    let requestor_id = agreement.requestor_id()?;
+                    if let Err(e) = self.provider.react_to_agreement(&agreement).await {
+                        self.record.error(self.provider.node_id, requestor_id, e);
+                        return Ok(());
+                    }
+                    (agreement.id, requestor_id)
+                }
+            };
+
+            match timeout(self.step_timeout, self.wait_for_response(&expected_id)).await {
+                Ok(Ok(true)) => continue,
+                Ok(Ok(false)) => break,
+                Ok(Err(e)) => {
+                    self.record.error(self.provider.node_id, with_node, e);
+                    break;
+                }
+                Err(_) => {
+                    self.record.timeout(self.provider.node_id, with_node);
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Extracts events sent by Requestor to Provider in the same order they occurred.
+    fn inputs(&self, result: &NegotiationResult) -> anyhow::Result<Vec<Input>> {
+        let agreement = || {
+            result
+                .agreement
+                .clone()
+                .ok_or(anyhow!("Agreement missing in negotiation record"))
+        };
+
+        let mut proposals = result.proposals.iter();
+        let mut inputs = vec![];
+
+        for stage in result.stages() {
+            match stage {
+                NegotiationStage::CounterProposal { node_id, .. }
+                | NegotiationStage::AcceptProposal { node_id, .. } => {
+                    let proposal = proposals
+                        .next()
+                        .ok_or(anyhow!("Proposal missing in negotiation record"))?;
+                    if node_id != &self.provider.node_id {
+                        inputs.push(Input::Proposal {
+                            proposal: proposal.clone(),
+                            accepted: matches!(stage, NegotiationStage::AcceptProposal { .. }),
+                        });
+                    }
+                }
+                NegotiationStage::CreateAgreement { .. } => {
+                    inputs.push(Input::CreateAgreement(agreement()?))
+                }
+                NegotiationStage::ProposeAgreement { .. } => {
+                    inputs.push(Input::ProposeAgreement(agreement()?))
+                }
+                _ => (),
+            }
+        }
+        Ok(inputs)
+    }
+
+    /// Registers Provider responses until it reacts to event with `id`.
+    /// Returns false, if Provider rejected this event.
+    async fn wait_for_response(&mut self, id: &str) -> anyhow::Result<bool> {
+        while let Some(response) = self.responses.next().await {
+            let (response_id, proceed) = match response {
+                Response::Proposal(action) => self.proposal_response(action)?,
+                Response::Agreement(action) => self.agreement_response(action).await?,
+            };
+
+            if response_id == id {
+                return Ok(proceed);
+            }
+        }
+        Err(anyhow!(
+            "Provider [{}] channels closed.",
+            self.provider.node_id
+        ))
+    }
+
+    fn proposal_response(&self, action: ProposalAction) -> anyhow::Result<(String, bool)> {
+        let node_id = self.provider.node_id;
+        let record = &self.record;
+
+        Ok(match action {
+            ProposalAction::AcceptProposal { id, .. } => {
+                let req_proposal = record.get_proposal(&id)?;
+                let prev_prov_proposal =
+                    record.get_proposal(&req_proposal.prev_proposal_id.clone().unwrap())?;
+                let proposal = self.provider.recounter_proposal(&id, &prev_prov_proposal);
+
+                record.accept(proposal, req_proposal.issuer_id);
+                (id, true)
+            }
+            ProposalAction::CounterProposal { id, proposal, .. } => {
+                let req_proposal = record.get_proposal(&id)?;
+                let proposal =
+                    self.provider
+                        .into_proposal(proposal, State::Draft, Some(id.clone()));
+
+                record.counter(proposal, req_proposal.issuer_id);
+                (id, true)
+            }
+            ProposalAction::RejectProposal { id, reason, .. } => {
+                let req_proposal = record.get_proposal(&id)?;
+
+                record.reject(node_id, req_proposal, reason);
+                (id, false)
+            }
+        })
+    }
+
+    async fn agreement_response(&self, action: AgreementAction) -> anyhow::Result<(String, bool)> {
+        let record = &self.record;
+
+        Ok(match action {
+            AgreementAction::ApproveAgreement { id, .. } => {
+                let agreement = record.get_agreement(&id)?;
+                record.approve(agreement.clone());
+
+                if let Err(e) = self.provider.agreement_signed(&agreement).await {
+                    record.error(self.provider.node_id, agreement.requestor_id()?, e)
+                }
+                (id, true)
+            }
+            AgreementAction::RejectAgreement { id, reason, .. } => {
+                let agreement = record.get_agreement(&id)?;
+
+                record.reject_agreement(agreement, reason);
+                (id, false)
+            }
+        })
+    }
+}
@@ -7,6 +7,7 @@ use ya_client_model::NodeId;
 
 use crate::error::NegotiatorError;
 use crate::negotiation_record::NegotiationRecordSync;
+use crate::network::NetworkProfile;
 use crate::node::Node;
 
 use backtrace::Backtrace;
@@ -22,6 +23,7 @@ pub struct RequestorReactions {
     pub providers: HashMap<NodeId, Arc<Node>>,
     pub requestors: HashMap<NodeId, Arc<Node>>,
     pub record: NegotiationRecordSync,
+    pub network: NetworkProfile,
 }
 
 impl RequestorReactions {
@@ -66,6 +68,10 @@ impl RequestorReactions {
                 provider.node_id
             );
 
+            if !self.network.deliver(node_id, provider.node_id).await {
+                return Ok(());
+            }
+
             if let Err(e) = provider
                 .react_to_proposal(&req_proposal, &prov_proposal)
                 .await
@@ -151,6 +157,10 @@ impl RequestorReactions {
         // Register event.
         record.counter(proposal.clone(), prov_proposal.issuer_id);
 
+        if !self.network.deliver(node_id, provider.node_id).await {
+            return Ok(());
+        }
+
         if let Err(e) = provider.react_to_proposal(&proposal, &prov_proposal).await {
             record.error(prov_proposal.issuer_id, proposal.issuer_id, e.into());
         }
@@ -184,6 +194,10 @@ impl RequestorReactions {
             provider_id
         );
 
+        if !self.network.deliver(node_id, provider_id).await {
+            return Ok(());
+        }
+
         if let Err(e) = provider.react_to_agreement(&agreement).await {
             record.error(provider_id, node_id, e.into());
         }
@@ -234,6 +248,7 @@ pub async fn requestor_proposals_processor(
     providers: HashMap<NodeId, Arc<Node>>,
     requestors: HashMap<NodeId, Arc<Node>>,
     record: NegotiationRecordSync,
+    network: NetworkProfile,
 ) {
     let mut r_receivers = StreamMap::new();
 
@@ -248,6 +263,7 @@ pub async fn requestor_proposals_processor(
         record: record.clone(),
         requestors,
         providers,
+        network,
     };
 
     while let Some((node_id, Ok(action))) = r_receivers.next().await {
@@ -275,6 +291,7 @@ pub async fn requestor_agreements_processor(
     providers: HashMap<NodeId, Arc<Node>>,
     requestors: HashMap<NodeId, Arc<Node>>,
     record: NegotiationRecordSync,
+    network: NetworkProfile,
 ) {
     let mut r_receivers = StreamMap::new();
 
@@ -289,6 +306,7 @@ pub async fn requestor_agreements_processor(
         record: record.clone(),
         requestors,
         providers,
+        network,
     };
 
     while let Some((node_id, Ok(action))) = r_receivers.next().await {
@@ -0,0 +1,122 @@
+use chrono::Utc;
+use std::sync::Mutex;
+
+use ya_negotiators::component::{
+    NegotiationResult, NegotiatorComponent, NegotiatorsPack, ProposalView, Score,
+};
+use ya_negotiators::factory::CompositeNegotiatorConfig;
+use ya_negotiators::{Negotiator, NegotiatorAddr, ProposalAction};
+
+use ya_client_model::market::proposal::State;
+use ya_client_model::market::Proposal;
+
+/// Stores all log messages, so test can inspect them.
+struct CaptureLogger {
+    messages: Mutex<Vec<String>>,
+}
+
+impl log::Log for CaptureLogger {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        self.messages
+            .lock()
+            .unwrap()
+            .push(record.args().to_string());
+    }
+
+    fn flush(&self) {}
+}
+
+/// Negotiates Initial Proposals and accepts Drafts.
+struct CounterInitial;
+
+impl NegotiatorComponent for CounterInitial {
+    fn negotiate_step(
+        &mut self,
+        their: &ProposalView,
+        template: ProposalView,
+        score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        Ok(match their.state {
+            State::Initial => NegotiationResult::Negotiating {
+                proposal: template,
+                score,
+            },
+            _ => NegotiationResult::Ready {
+                proposal: template,
+                score,
+            },
+        })
+    }
+}
+
+fn proposal(id: &str, prev_id: Option<&str>, state: State) -> Proposal {
+    Proposal {
+        properties: serde_json::json!({
+            "golem.node.debug.subnet": "net-1",
+            "golem.srv.comp.expiration": (Utc::now() + chrono::Duration::seconds(50)).timestamp_millis(),
+        }),
+        constraints: "".to_string(),
+        proposal_id: id.to_string(),
+        issuer_id: Default::default(),
+        state,
+        timestamp: Utc::now(),
+        prev_proposal_id: prev_id.map(str::to_string),
+    }
+}
+
+/// Messages logged while processing Proposal should carry its correlation id.
+#[actix_rt::test]
+async fn test_log_correlation_id() {
+    let logger: &'static CaptureLogger = Box::leak(Box::new(CaptureLogger {
+        messages: Mutex::new(vec![]),
+    }));
+    log::set_logger(logger).unwrap();
+    log::set_max_level(log::LevelFilter::Trace);
+
+    let components =
+        NegotiatorsPack::new().add_component("CounterInitial", Box::new(CounterInitial));
+    let (negotiator, mut callbacks) =
+        Negotiator::new(components, CompositeNegotiatorConfig::default_test());
+    let negotiator = NegotiatorAddr::from(negotiator);
+
+    let our = proposal("our-0", None, State::Draft);
+    let their = proposal("their-0", Some("our-0"), State::Initial);
+    negotiator
+        .react_to_proposal("", &their, &our)
+        .await
+        .unwrap();
+    match callbacks.proposal_channel.recv().await {
+        Some(ProposalAction::CounterProposal { .. }) => {}
+        action => panic!("Unexpected action: {:?}", action),
+    }
+
+    let our = proposal("our-1", Some("their-0"), State::Draft);
+    let their = proposal("their-1", Some("our-1"), State::Draft);
+    negotiator
+        .react_to_proposal("", &their, &our)
+        .await
+        .unwrap();
+    match callbacks.proposal_channel.recv().await {
+        Some(ProposalAction::AcceptProposal { .. }) => {}
+        action => panic!("Unexpected action: {:?}", action),
+    }
+
+    let messages = logger.messages.lock().unwrap().clone();
+    for id in ["their-0", "their-1"] {
+        let related = messages
+            .iter()
+            .filter(|message| message.contains(&format!("[{}]", id)))
+            .collect::<Vec<_>>();
+        let prefix = format!("[correlation-id={}]", id);
+
+        // Composite, pack and collection should log something about each Proposal.
+        assert!(related.len() >= 2, "Messages for [{}]: {:?}", id, related);
+        for message in related {
+            assert!(message.starts_with(&prefix), "Message: {}", message);
+        }
+    }
+}
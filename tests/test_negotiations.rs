@@ -1,13 +1,32 @@
 use chrono::{DateTime, Utc};
+use futures::FutureExt;
+use std::convert::TryFrom;
+use std::sync::{Arc, Mutex};
 
-use ya_agreement_utils::{InfNodeInfo, NodeInfo, OfferDefinition, OfferTemplate, ServiceInfo};
+use ya_agreement_utils::{
+    AgreementView, InfNodeInfo, NodeInfo, OfferDefinition, OfferTemplate, ServiceInfo,
+};
 use ya_builtin_negotiators::*;
+use ya_negotiators::component::{
+    diagnostics_query, namespaced_score, reconfigure_request, register_negotiator, AgreementEvent,
+    AnyOf, CachingComponent, CounterOffer, NegotiationResult, NegotiatorComponent, ProposalScore,
+    ProposalView, RejectReason, Score, ScoringAdapter, ScoringComponent, PACK_COMPONENT,
+};
 use ya_negotiators::factory::*;
-use ya_negotiators::{NegotiatorCallbacks, ProposalAction};
+use ya_negotiators::{
+    AgreementAction, AgreementResult, ErrorPolicy, Metrics, NegotiationEvent, Negotiator,
+    NegotiatorAddr, NegotiatorCallbacks, NegotiatorEngine, NegotiatorsPack, ProposalAction,
+    RejectPolicy, StepOutcome, TemplateConflictPolicy, NO_PROGRESS, SCORE_BELOW_MINIMUM,
+    SELF_NEGOTIATION, TOO_MANY_ROUNDS,
+};
 
+use ya_client_model::market::agreement::State as AgreementState;
 use ya_client_model::market::proposal::State;
 use ya_client_model::market::NewDemand;
 use ya_client_model::market::Proposal;
+use ya_client_model::market::Reason;
+use ya_client_model::market::{Agreement, Demand, Offer};
+use ya_client_model::NodeId;
 use ya_negotiators_testing::prepare_test_dir;
 
 fn example_config() -> NegotiatorsConfig {
@@ -17,14 +36,24 @@ fn example_config() -> NegotiatorsConfig {
         params: serde_yaml::to_value(expiration::Config {
             min_expiration: std::time::Duration::from_secs(30),
             max_expiration: std::time::Duration::from_secs(300),
+            max_debit_note_accept_timeout: None,
+            min_agreement_expiration: None,
         })
         .unwrap(),
+        enabled: true,
+        priority: 0,
     };
 
     let limit_conf = NegotiatorConfig {
         name: "LimitAgreements".to_string(),
         load_mode: LoadMode::BuiltIn,
-        params: serde_yaml::to_value(max_agreements::Config { max_agreements: 1 }).unwrap(),
+        params: serde_yaml::to_value(max_agreements::Config {
+            max_agreements: 1,
+            reservation_timeout: std::time::Duration::from_secs(60),
+        })
+        .unwrap(),
+        enabled: true,
+        priority: 0,
     };
 
     NegotiatorsConfig {
@@ -86,7 +115,7 @@ async fn test_negotiation() {
             proposal_channel: mut proposals,
             agreement_channel: _agreements,
         },
-    ) = create_negotiator(config, test_dir.clone(), test_dir).unwrap();
+    ) = create_negotiator(config, serde_yaml::Value::Null, test_dir.clone(), test_dir).unwrap();
 
     let offer = negotiator.create_offer(&example_offer()).await.unwrap();
     let offer = proposal_from_demand(&offer);
@@ -118,3 +147,3495 @@ async fn test_negotiation() {
         _ => panic!("Expected reject proposal"),
     }
 }
+
+/// Counts negotiation rounds in `Score` and keeps negotiating for `rounds` steps.
+struct RoundsCounter {
+    rounds: u32,
+    seen: Arc<Mutex<Vec<u32>>>,
+}
+
+impl NegotiatorComponent for RoundsCounter {
+    fn negotiate_step(
+        &mut self,
+        _their: &ProposalView,
+        template: ProposalView,
+        mut score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        let prev_rounds = score.pointer_typed::<u32>("/rounds").unwrap_or(0);
+        self.seen.lock().unwrap().push(prev_rounds);
+
+        score.set_property("rounds", serde_json::json!(prev_rounds + 1));
+        Ok(match prev_rounds + 1 < self.rounds {
+            true => NegotiationResult::Negotiating {
+                proposal: template,
+                score,
+            },
+            false => NegotiationResult::Ready {
+                proposal: template,
+                score,
+            },
+        })
+    }
+}
+
+/// Rejection should identify component, which rejected Proposal, even
+/// if there are more components in the pack.
+#[actix_rt::test]
+async fn test_rejection_identifies_component() {
+    let test_dir = prepare_test_dir("test_rejection_identifies_component").unwrap();
+    let (negotiator, mut callbacks) = create_negotiator(
+        example_config(),
+        serde_yaml::Value::Null,
+        test_dir.clone(),
+        test_dir,
+    )
+    .unwrap();
+
+    let offer = negotiator.create_offer(&example_offer()).await.unwrap();
+    let offer = proposal_from_demand(&offer);
+
+    let demand = example_demand(Utc::now() + chrono::Duration::seconds(900), "net-1");
+    let proposal = proposal_from_demand(&demand);
+    negotiator
+        .react_to_proposal("", &proposal, &offer)
+        .await
+        .unwrap();
+
+    let reason = rejection_reason(callbacks.proposal_channel.recv().await);
+    assert_eq!(
+        reason.code.as_deref(),
+        Some(expiration::EXPIRATION_OUT_OF_RANGE)
+    );
+    assert_eq!(reason.component(), Some("LimitExpiration"));
+}
+
+/// Score computed in previous negotiation round should be passed to components
+/// in the next round of the same negotiation.
+#[actix_rt::test]
+async fn test_score_carried_across_rounds() {
+    let seen = Arc::new(Mutex::new(vec![]));
+    let components = NegotiatorsPack::new().add_component(
+        "RoundsCounter",
+        Box::new(RoundsCounter {
+            rounds: 3,
+            seen: seen.clone(),
+        }),
+    );
+
+    let (negotiator, mut callbacks) =
+        Negotiator::new(components, CompositeNegotiatorConfig::default_test());
+    let negotiator = NegotiatorAddr::from(negotiator);
+
+    let mut our = proposal_from_demand(&example_demand(
+        Utc::now() + chrono::Duration::seconds(50),
+        "net-1",
+    ));
+    our.proposal_id = "our-0".to_string();
+
+    let mut their = our.clone();
+    for round in 0..3 {
+        their.proposal_id = format!("their-{}", round);
+        their.prev_proposal_id = Some(our.proposal_id.clone());
+
+        negotiator
+            .react_to_proposal("", &their, &our)
+            .await
+            .unwrap();
+
+        match callbacks.proposal_channel.recv().await {
+            Some(ProposalAction::CounterProposal { .. }) if round < 2 => {}
+            Some(ProposalAction::AcceptProposal { .. }) if round == 2 => {}
+            action => panic!("Unexpected action in round {}: {:?}", round, action),
+        }
+
+        // Emulate our counter Proposal sent as response to their Proposal.
+        our.prev_proposal_id = Some(their.proposal_id.clone());
+        our.proposal_id = format!("our-{}", round + 1);
+    }
+
+    assert_eq!(*seen.lock().unwrap(), vec![0, 1, 2]);
+}
+
+/// Counterparty, which never converges, should be cut off after `max_rounds`.
+#[actix_rt::test]
+async fn test_max_rounds() {
+    let components = NegotiatorsPack::new().add_component(
+        "RoundsCounter",
+        Box::new(RoundsCounter {
+            rounds: 100,
+            seen: Arc::new(Mutex::new(vec![])),
+        }),
+    );
+
+    let mut config = CompositeNegotiatorConfig::default_test();
+    config.max_rounds = Some(3);
+    let (negotiator, mut callbacks) = Negotiator::new(components, config);
+    let negotiator = NegotiatorAddr::from(negotiator);
+
+    let mut our = proposal_from_demand(&example_demand(
+        Utc::now() + chrono::Duration::seconds(50),
+        "net-1",
+    ));
+    our.proposal_id = "our-0".to_string();
+
+    let mut their = our.clone();
+    for round in 0..4 {
+        their.proposal_id = format!("their-{}", round);
+        their.prev_proposal_id = Some(our.proposal_id.clone());
+
+        negotiator
+            .react_to_proposal("", &their, &our)
+            .await
+            .unwrap();
+
+        match callbacks.proposal_channel.recv().await {
+            Some(ProposalAction::CounterProposal { .. }) if round < 3 => {}
+            Some(ProposalAction::RejectProposal { reason, .. }) if round == 3 => {
+                let reason = RejectReason::from(reason.unwrap());
+                assert_eq!(reason.code.as_deref(), Some(TOO_MANY_ROUNDS));
+                assert_eq!(reason.is_final(), Some(true));
+            }
+            action => panic!("Unexpected action in round {}: {:?}", round, action),
+        }
+
+        our.prev_proposal_id = Some(their.proposal_id.clone());
+        our.proposal_id = format!("our-{}", round + 1);
+    }
+}
+
+/// Rejects Proposal, if other party lowered `test.value` in relation to their previous Proposal.
+struct DecreasingValueGuard {
+    history_lengths: Arc<Mutex<Vec<usize>>>,
+}
+
+impl NegotiatorComponent for DecreasingValueGuard {
+    fn negotiate_step_with_history(
+        &mut self,
+        their: &ProposalView,
+        history: &[ProposalView],
+        template: ProposalView,
+        score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        self.history_lengths.lock().unwrap().push(history.len());
+
+        let value = their.pointer_typed::<f64>("/test/value")?;
+        if let Some(prev) = history.last() {
+            let prev_value = prev.pointer_typed::<f64>("/test/value")?;
+            if value < prev_value {
+                return Ok(NegotiationResult::Reject {
+                    reason: RejectReason::new(format!(
+                        "Value decreased from {} to {}.",
+                        prev_value, value
+                    )),
+                    is_final: true,
+                });
+            }
+        }
+        Ok(NegotiationResult::Negotiating {
+            proposal: template,
+            score,
+        })
+    }
+}
+
+/// Components should get their Proposals from previous rounds of the same negotiation.
+#[actix_rt::test]
+async fn test_negotiation_history() {
+    let history_lengths = Arc::new(Mutex::new(vec![]));
+    let components = NegotiatorsPack::new().add_component(
+        "DecreasingValueGuard",
+        Box::new(DecreasingValueGuard {
+            history_lengths: history_lengths.clone(),
+        }),
+    );
+
+    let (negotiator, mut callbacks) =
+        Negotiator::new(components, CompositeNegotiatorConfig::default_test());
+    let negotiator = NegotiatorAddr::from(negotiator);
+
+    let mut our = proposal_from_demand(&example_demand(
+        Utc::now() + chrono::Duration::seconds(50),
+        "net-1",
+    ));
+    our.proposal_id = "our-0".to_string();
+
+    let mut their = our.clone();
+    for (round, value) in [5.0, 6.0, 4.0].iter().enumerate() {
+        their.proposal_id = format!("their-{}", round);
+        their.prev_proposal_id = Some(our.proposal_id.clone());
+        their.properties["test.value"] = serde_json::json!(value);
+
+        negotiator
+            .react_to_proposal("", &their, &our)
+            .await
+            .unwrap();
+
+        match callbacks.proposal_channel.recv().await {
+            Some(ProposalAction::CounterProposal { .. }) if round < 2 => {}
+            Some(ProposalAction::RejectProposal { reason, .. }) if round == 2 => {
+                let reason = RejectReason::from(reason.unwrap());
+                assert_eq!(reason.message, "Value decreased from 6 to 4.");
+            }
+            action => panic!("Unexpected action in round {}: {:?}", round, action),
+        }
+
+        our.prev_proposal_id = Some(their.proposal_id.clone());
+        our.proposal_id = format!("our-{}", round + 1);
+    }
+    assert_eq!(*history_lengths.lock().unwrap(), vec![0, 1, 2]);
+}
+
+/// Counters Proposals after delay, like component asking remote service.
+/// Can send newer Proposal in the same negotiation during evaluation.
+#[derive(Default)]
+struct SlowCounter {
+    evaluated: Arc<Mutex<Vec<String>>>,
+    rejected: Arc<Mutex<Vec<String>>>,
+    newer: Arc<Mutex<Option<(NegotiatorAddr, Proposal, Proposal)>>>,
+}
+
+impl NegotiatorComponent for SlowCounter {
+    fn negotiate_step(
+        &mut self,
+        their: &ProposalView,
+        template: ProposalView,
+        score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        self.evaluated.lock().unwrap().push(their.id.clone());
+
+        // Proposal is queued on first poll. We don't wait for Negotiator,
+        // which is busy evaluating this Proposal.
+        if let Some((negotiator, newer, our)) = self.newer.lock().unwrap().take() {
+            negotiator
+                .react_to_proposal("", &newer, &our)
+                .now_or_never();
+        }
+
+        Ok(NegotiationResult::Negotiating {
+            proposal: template,
+            score,
+        })
+    }
+
+    fn on_proposal_rejected(&mut self, proposal_id: &str) -> anyhow::Result<()> {
+        self.rejected.lock().unwrap().push(proposal_id.to_string());
+        Ok(())
+    }
+}
+
+/// Our Proposal and two Proposals of other party responding to it.
+fn superseding_proposals() -> (Proposal, Proposal, Proposal) {
+    let mut our = proposal_from_demand(&example_demand(
+        Utc::now() + chrono::Duration::seconds(50),
+        "net-1",
+    ));
+    our.proposal_id = "our-0".to_string();
+
+    let mut first = our.clone();
+    first.proposal_id = "their-1".to_string();
+    first.prev_proposal_id = Some(our.proposal_id.clone());
+
+    let mut second = first.clone();
+    second.proposal_id = "their-2".to_string();
+    (our, first, second)
+}
+
+/// Proposal superseded by newer Proposal in the same negotiation, before Negotiator
+/// started evaluating it, shouldn't be evaluated at all.
+#[actix_rt::test]
+async fn test_superseded_proposal_skipped() {
+    let component = SlowCounter::default();
+    let evaluated = component.evaluated.clone();
+    let components = NegotiatorsPack::new().add_component("SlowCounter", Box::new(component));
+
+    let (negotiator, mut callbacks) =
+        Negotiator::new(components, CompositeNegotiatorConfig::default_test());
+    let negotiator = NegotiatorAddr::from(negotiator);
+    let (our, first, second) = superseding_proposals();
+
+    // Both Proposals are queued, before Negotiator starts evaluating the first one.
+    let (first_result, second_result) = futures::join!(
+        negotiator.react_to_proposal("", &first, &our),
+        negotiator.react_to_proposal("", &second, &our),
+    );
+    first_result.unwrap();
+    second_result.unwrap();
+
+    assert_eq!(*evaluated.lock().unwrap(), vec!["their-2"]);
+    match callbacks.proposal_channel.recv().await {
+        Some(ProposalAction::CounterProposal { id, .. }) => assert_eq!(id, "their-2"),
+        action => panic!("Expected CounterProposal, got: {:?}", action),
+    }
+    assert!(callbacks.proposal_channel.try_recv().is_err());
+}
+
+/// Result of Proposal superseded during evaluation should be dropped and
+/// components should release anything they reserved for it.
+#[actix_rt::test]
+async fn test_superseded_proposal_result_dropped() {
+    let component = SlowCounter::default();
+    let evaluated = component.evaluated.clone();
+    let rejected = component.rejected.clone();
+    let newer = component.newer.clone();
+    let components = NegotiatorsPack::new().add_component("SlowCounter", Box::new(component));
+
+    let (negotiator, mut callbacks) =
+        Negotiator::new(components, CompositeNegotiatorConfig::default_test());
+    let negotiator = NegotiatorAddr::from(negotiator);
+    let (our, first, second) = superseding_proposals();
+
+    *newer.lock().unwrap() = Some((negotiator.clone(), second, our.clone()));
+    negotiator
+        .react_to_proposal("", &first, &our)
+        .await
+        .unwrap();
+
+    match callbacks.proposal_channel.recv().await {
+        Some(ProposalAction::CounterProposal { id, .. }) => assert_eq!(id, "their-2"),
+        action => panic!("Expected CounterProposal, got: {:?}", action),
+    }
+    assert_eq!(*evaluated.lock().unwrap(), vec!["their-1", "their-2"]);
+    assert_eq!(*rejected.lock().unwrap(), vec!["their-1"]);
+    assert!(callbacks.proposal_channel.try_recv().is_err());
+}
+
+/// Violates `NegotiationResult::Ready` contract by changing Proposal.
+struct ReadyMutator;
+
+impl NegotiatorComponent for ReadyMutator {
+    fn negotiate_step(
+        &mut self,
+        _their: &ProposalView,
+        mut template: ProposalView,
+        score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        *template.pointer_mut("/golem/node/id/name").unwrap() = serde_json::json!("changed");
+        Ok(NegotiationResult::Ready {
+            proposal: template,
+            score,
+        })
+    }
+}
+
+/// Pack should detect components, which return `Ready` despite changing Proposal.
+#[test]
+fn test_detect_ready_with_changes() {
+    let proposal = proposal_from_demand(&example_demand(
+        Utc::now() + chrono::Duration::seconds(50),
+        "net-1",
+    ));
+    let their = ProposalView::try_from(&proposal).unwrap();
+    let our = their.clone();
+
+    let mut changed = our.clone();
+    *changed.pointer_mut("/golem/node/id/name").unwrap() = serde_json::json!("changed");
+    assert_eq!(
+        our.changed_pointers(&changed),
+        vec!["/golem/node/id/name".to_string()]
+    );
+
+    let mut lenient = NegotiatorsPack::new().add_component("ReadyMutator", Box::new(ReadyMutator));
+    match lenient
+        .negotiate_step(&their, our.clone(), Score::default())
+        .unwrap()
+    {
+        NegotiationResult::Ready { .. } => {}
+        result => panic!("Expected Ready, got: {:?}", result),
+    }
+
+    let mut strict = NegotiatorsPack::new()
+        .add_component("ReadyMutator", Box::new(ReadyMutator))
+        .strict_ready(true);
+    match strict
+        .negotiate_step(&their, our, Score::default())
+        .unwrap()
+    {
+        NegotiationResult::Reject { is_final, .. } => assert!(!is_final),
+        result => panic!("Expected Reject, got: {:?}", result),
+    }
+}
+
+/// Sample scorer preferring cheaper Proposals with linear pricing model.
+struct LinearPriceScorer;
+
+impl ScoringComponent for LinearPriceScorer {
+    fn score(&self, their: &ProposalView, _our: &ProposalView) -> anyhow::Result<Score> {
+        let coeffs = their.pointer_typed::<Vec<f64>>("/golem/com/pricing/model/linear/coeffs")?;
+        let price = coeffs.iter().sum::<f64>();
+        Ok(namespaced_score("price", 1.0 / (1.0 + price)))
+    }
+}
+
+/// Sample scorer preferring Proposals with more memory.
+struct MemoryScorer;
+
+impl ScoringComponent for MemoryScorer {
+    fn score(&self, their: &ProposalView, _our: &ProposalView) -> anyhow::Result<Score> {
+        let memory = their.pointer_typed::<f64>("/golem/inf/mem/gib")?;
+        Ok(namespaced_score("memory", memory))
+    }
+}
+
+/// Scores produced by all scorers in the pack should be available in final `Score`.
+#[test]
+fn test_scores_accumulate_in_pack() {
+    let mut proposal = proposal_from_demand(&example_demand(
+        Utc::now() + chrono::Duration::seconds(50),
+        "net-1",
+    ));
+    proposal.properties["golem.com.pricing.model.linear.coeffs"] = serde_json::json!([0.5, 0.5]);
+    proposal.properties["golem.inf.mem.gib"] = serde_json::json!(8.0);
+
+    let their = ProposalView::try_from(&proposal).unwrap();
+    let our = their.clone();
+
+    let mut pack = NegotiatorsPack::new()
+        .add_component("Price", Box::new(ScoringAdapter::new(LinearPriceScorer)))
+        .add_component("Memory", Box::new(ScoringAdapter::new(MemoryScorer)));
+
+    match pack
+        .negotiate_step(&their, our.clone(), Score::default())
+        .unwrap()
+    {
+        NegotiationResult::Ready { proposal, score } => {
+            assert_eq!(proposal, our);
+            assert_eq!(score.property("price.score"), Some(&serde_json::json!(0.5)));
+            assert_eq!(
+                score.property("memory.score"),
+                Some(&serde_json::json!(8.0))
+            );
+        }
+        result => panic!("Expected Ready, got: {:?}", result),
+    }
+}
+
+/// Rejects all Proposals with its name as reason.
+struct RejectAlways {
+    name: &'static str,
+    is_final: bool,
+}
+
+impl NegotiatorComponent for RejectAlways {
+    fn negotiate_step(
+        &mut self,
+        _their: &ProposalView,
+        _template: ProposalView,
+        _score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        Ok(NegotiationResult::Reject {
+            reason: RejectReason::new(self.name),
+            is_final: self.is_final,
+        })
+    }
+}
+
+/// `AnyOf` should accept Proposal, if any child accepts it, using score of this child.
+#[test]
+fn test_any_of_accepts_via_second_child() {
+    let mut proposal = proposal_from_demand(&example_demand(
+        Utc::now() + chrono::Duration::seconds(50),
+        "net-1",
+    ));
+    proposal.properties["golem.com.pricing.model.linear.coeffs"] = serde_json::json!([0.5, 0.5]);
+    let their = ProposalView::try_from(&proposal).unwrap();
+
+    let mut any_of = AnyOf::new(vec![
+        Box::new(RejectAlways {
+            name: "policy-a",
+            is_final: true,
+        }),
+        Box::new(ScoringAdapter::new(LinearPriceScorer)),
+    ]);
+
+    match any_of
+        .negotiate_step(&their, their.clone(), Score::default())
+        .unwrap()
+    {
+        NegotiationResult::Ready { score, .. } => {
+            assert_eq!(score.property("price.score"), Some(&serde_json::json!(0.5)));
+        }
+        result => panic!("Expected Ready, got: {:?}", result),
+    }
+}
+
+/// `AnyOf` should reject Proposal only if all children reject it, combining their reasons.
+#[test]
+fn test_any_of_all_reject() {
+    let proposal = proposal_from_demand(&example_demand(
+        Utc::now() + chrono::Duration::seconds(50),
+        "net-1",
+    ));
+    let their = ProposalView::try_from(&proposal).unwrap();
+
+    let mut any_of = AnyOf::new(vec![
+        Box::new(RejectAlways {
+            name: "policy-a",
+            is_final: true,
+        }),
+        Box::new(RejectAlways {
+            name: "policy-b",
+            is_final: false,
+        }),
+    ]);
+
+    match any_of
+        .negotiate_step(&their, their.clone(), Score::default())
+        .unwrap()
+    {
+        NegotiationResult::Reject { reason, is_final } => {
+            assert!(reason.message.contains("policy-a"));
+            assert!(reason.message.contains("policy-b"));
+            assert_eq!(reason.extra["reasons"].as_array().unwrap().len(), 2);
+            assert!(!is_final);
+        }
+        result => panic!("Expected Reject, got: {:?}", result),
+    }
+}
+
+/// Fails evaluating every Proposal and echoes control events.
+struct FailAlways;
+
+impl NegotiatorComponent for FailAlways {
+    fn negotiate_step(
+        &mut self,
+        _their: &ProposalView,
+        _template: ProposalView,
+        _score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        anyhow::bail!("Evaluation failed")
+    }
+
+    fn control_event(
+        &mut self,
+        _component: &str,
+        params: serde_json::Value,
+    ) -> anyhow::Result<serde_json::Value> {
+        Ok(params)
+    }
+}
+
+/// Failing child shouldn't prevent `AnyOf` from trying other alternatives.
+/// Control events should reach all children.
+#[test]
+fn test_any_of_child_error() {
+    let mut proposal = proposal_from_demand(&example_demand(
+        Utc::now() + chrono::Duration::seconds(50),
+        "net-1",
+    ));
+    proposal.properties["golem.com.pricing.model.linear.coeffs"] = serde_json::json!([0.5, 0.5]);
+    let their = ProposalView::try_from(&proposal).unwrap();
+
+    let mut any_of = AnyOf::new(vec![
+        Box::new(FailAlways),
+        Box::new(ScoringAdapter::new(LinearPriceScorer)),
+    ]);
+    assert!(matches!(
+        any_of
+            .negotiate_step(&their, their.clone(), Score::default())
+            .unwrap(),
+        NegotiationResult::Ready { .. }
+    ));
+
+    let mut any_of = AnyOf::new(vec![Box::new(FailAlways), Box::new(FailAlways)]);
+    match any_of
+        .negotiate_step(&their, their.clone(), Score::default())
+        .unwrap()
+    {
+        NegotiationResult::Reject { reason, is_final } => {
+            assert!(reason.message.contains("Evaluation failed"));
+            assert!(!is_final);
+        }
+        result => panic!("Expected Reject, got: {:?}", result),
+    }
+
+    let response = any_of
+        .control_event("AnyOf", serde_json::json!({"ping": 1}))
+        .unwrap();
+    assert_eq!(response, serde_json::json!([{"ping": 1}, {"ping": 1}]));
+}
+
+/// Counts calls and marks Proposal as checked. Decision depends only on subnet.
+struct CountingChecker {
+    calls: Arc<Mutex<usize>>,
+}
+
+impl NegotiatorComponent for CountingChecker {
+    fn negotiate_step(
+        &mut self,
+        _their: &ProposalView,
+        template: ProposalView,
+        score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        *self.calls.lock().unwrap() += 1;
+        CounterOffer::new(template, score.patch(namespaced_score("checker", 1.0)))
+            .set("/golem/node/debug/checked", serde_json::json!(true))
+            .build()
+    }
+}
+
+/// `CachingComponent` should call wrapped component once for Proposals with the same
+/// values of cached properties and apply cached changes to each template.
+#[test]
+fn test_caching_component() {
+    let calls = Arc::new(Mutex::new(0));
+    let mut caching = CachingComponent::new(
+        Box::new(CountingChecker {
+            calls: calls.clone(),
+        }),
+        vec!["/golem/node/debug/subnet".to_string()],
+        std::time::Duration::from_secs(60),
+    );
+
+    let deadline = Utc::now() + chrono::Duration::seconds(50);
+    let mut proposal = proposal_from_demand(&example_demand(deadline, "net-1"));
+    proposal.proposal_id = "proposal-1".to_string();
+    let first = ProposalView::try_from(&proposal).unwrap();
+    proposal.proposal_id = "proposal-2".to_string();
+    let second = ProposalView::try_from(&proposal).unwrap();
+
+    caching
+        .negotiate_step(&first, first.clone(), Score::default())
+        .unwrap();
+    match caching
+        .negotiate_step(&second, second.clone(), Score::default())
+        .unwrap()
+    {
+        NegotiationResult::Negotiating { proposal, score } => {
+            assert_eq!(proposal.id, "proposal-2");
+            assert_eq!(
+                proposal.pointer("/golem/node/debug/checked"),
+                Some(&serde_json::json!(true))
+            );
+            assert_eq!(
+                score.property("checker.score"),
+                Some(&serde_json::json!(1.0))
+            );
+        }
+        result => panic!("Expected Negotiating, got: {:?}", result),
+    }
+    assert_eq!(*calls.lock().unwrap(), 1);
+
+    let other =
+        ProposalView::try_from(&proposal_from_demand(&example_demand(deadline, "net-2"))).unwrap();
+    caching
+        .negotiate_step(&other, other.clone(), Score::default())
+        .unwrap();
+    assert_eq!(*calls.lock().unwrap(), 2);
+
+    caching
+        .on_agreement_terminated("agreement-1", &AgreementResult::ClosedByUs)
+        .unwrap();
+    caching
+        .negotiate_step(&first, first.clone(), Score::default())
+        .unwrap();
+    assert_eq!(*calls.lock().unwrap(), 3);
+}
+
+fn reject_with_policy(policy: RejectPolicy) -> NegotiationResult {
+    let proposal = proposal_from_demand(&example_demand(
+        Utc::now() + chrono::Duration::seconds(50),
+        "net-1",
+    ));
+    let their = ProposalView::try_from(&proposal).unwrap();
+
+    NegotiatorsPack::new()
+        .add_component(
+            "policy-a",
+            Box::new(RejectAlways {
+                name: "reason-a",
+                is_final: true,
+            }),
+        )
+        .add_component(
+            "policy-b",
+            Box::new(RejectAlways {
+                name: "reason-b",
+                is_final: false,
+            }),
+        )
+        .reject_policy(policy)
+        .negotiate_step(&their, their.clone(), Score::default())
+        .unwrap()
+}
+
+/// By default pack stops on the first rejection.
+#[test]
+fn test_reject_policy_first_reject() {
+    match reject_with_policy(RejectPolicy::FirstReject) {
+        NegotiationResult::Reject { reason, is_final } => {
+            assert_eq!(reason.message, "reason-a");
+            assert!(is_final);
+        }
+        result => panic!("Expected Reject, got: {:?}", result),
+    }
+}
+
+/// Pack should report reasons of all rejecting components.
+#[test]
+fn test_reject_policy_collects_reasons() {
+    match reject_with_policy(RejectPolicy::CollectAnyFinal) {
+        NegotiationResult::Reject { reason, is_final } => {
+            assert!(reason.message.contains("reason-a"));
+            assert!(reason.message.contains("reason-b"));
+            assert!(is_final);
+
+            let reasons = reason.extra["reasons"].as_array().unwrap();
+            assert_eq!(reasons.len(), 2);
+            assert_eq!(reasons[0]["golem.proposal.rejection.component"], "policy-a");
+            assert_eq!(reasons[0]["message"], "reason-a");
+            assert_eq!(reasons[1]["golem.proposal.rejection.component"], "policy-b");
+            assert_eq!(reasons[1]["golem.proposal.rejection.is-final"], false);
+        }
+        result => panic!("Expected Reject, got: {:?}", result),
+    }
+
+    match reject_with_policy(RejectPolicy::CollectAllFinal) {
+        NegotiationResult::Reject { is_final, .. } => assert!(!is_final),
+        result => panic!("Expected Reject, got: {:?}", result),
+    }
+}
+
+/// Components called only to collect reasons shouldn't count rejected Proposal.
+#[test]
+fn test_reject_policy_skips_state_changes() {
+    let mut pack = NegotiatorsPack::new()
+        .add_component(
+            "policy-a",
+            Box::new(RejectAlways {
+                name: "reason-a",
+                is_final: false,
+            }),
+        )
+        .add_component(
+            "RateLimit",
+            Box::new(
+                RateLimit::new(
+                    serde_yaml::to_value(rate_limit::Config {
+                        max_per_window: 1,
+                        window: std::time::Duration::from_secs(60),
+                    })
+                    .unwrap(),
+                )
+                .unwrap(),
+            ),
+        )
+        .reject_policy(RejectPolicy::CollectAnyFinal);
+
+    let requestor = "0x0000000000000000000000000000000000000001";
+    let their = proposal_from_node(requestor, "proposal-1");
+    assert!(!is_ready(&mut pack, &their));
+    let their = proposal_from_node(requestor, "proposal-2");
+    let results = pack.negotiate_batch(vec![(their.clone(), vec![], their, Score::default())]);
+    assert!(matches!(results[0], Ok(NegotiationResult::Reject { .. })));
+
+    let state = pack
+        .control_event("RateLimit", diagnostics_query())
+        .unwrap();
+    assert_eq!(state["tracked-nodes"], serde_json::json!(0));
+}
+
+/// Appends its name to list of visited components in template.
+struct AppendName(String);
+
+impl NegotiatorComponent for AppendName {
+    fn fill_template(&mut self, mut template: OfferTemplate) -> anyhow::Result<OfferTemplate> {
+        let mut visited = template
+            .property("test.visited")
+            .cloned()
+            .unwrap_or_else(|| serde_json::json!([]));
+        visited.as_array_mut().unwrap().push(self.0.clone().into());
+        template.set_property("test.visited", visited);
+        Ok(template)
+    }
+}
+
+struct SetValue(&'static str);
+
+impl NegotiatorComponent for SetValue {
+    fn fill_template(&mut self, mut template: OfferTemplate) -> anyhow::Result<OfferTemplate> {
+        template.set_property("test.value", serde_json::json!(self.0));
+        Ok(template)
+    }
+}
+
+fn conflicting_pack() -> NegotiatorsPack {
+    NegotiatorsPack::new()
+        .add_component("SetValue", Box::new(SetValue("first")))
+        .add_component("SetValue", Box::new(SetValue("second")))
+}
+
+/// By default value written by the last component is published.
+#[test]
+fn test_fill_template_last_wins() {
+    let mut pack = conflicting_pack();
+    let template = pack.fill_template(OfferTemplate::default()).unwrap();
+    assert_eq!(
+        template.property("test.value"),
+        Some(&serde_json::json!("second"))
+    );
+}
+
+#[test]
+fn test_fill_template_error_on_conflict() {
+    let mut pack = conflicting_pack().conflict_policy(TemplateConflictPolicy::ErrorOnConflict);
+    let error = pack
+        .fill_template(OfferTemplate::default())
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("SetValue#1"), "{}", error);
+
+    // Properties from original template can be replaced by any component.
+    let mut pack = NegotiatorsPack::new()
+        .add_component("SetValue", Box::new(SetValue("first")))
+        .conflict_policy(TemplateConflictPolicy::ErrorOnConflict);
+    let mut template = OfferTemplate::default();
+    template.set_property("test.value", serde_json::json!("original"));
+    let template = pack.fill_template(template).unwrap();
+    assert_eq!(
+        template.property("test.value"),
+        Some(&serde_json::json!("first"))
+    );
+}
+
+#[test]
+fn test_fill_template_explicit_override() {
+    let mut pack = conflicting_pack()
+        .conflict_policy(TemplateConflictPolicy::ErrorOnConflict)
+        .allow_override("SetValue#1");
+    let template = pack.fill_template(OfferTemplate::default()).unwrap();
+    assert_eq!(
+        template.property("test.value"),
+        Some(&serde_json::json!("second"))
+    );
+}
+
+/// Remembers Offer published by Negotiator.
+struct RecordOffer(Arc<Mutex<Option<OfferTemplate>>>);
+
+impl NegotiatorComponent for RecordOffer {
+    fn on_offer_published(&mut self, offer: &OfferTemplate) -> anyhow::Result<()> {
+        *self.0.lock().unwrap() = Some(offer.clone());
+        Ok(())
+    }
+}
+
+/// Component should be notified about Offer including properties
+/// added by all components, also those placed after it.
+#[actix_rt::test]
+async fn test_on_offer_published() {
+    let published = Arc::new(Mutex::new(None));
+    let recorded = published.clone();
+    register_negotiator(
+        "test-offer-published",
+        "RecordOffer",
+        Box::new(move |_, _, _| Ok(Box::new(RecordOffer(recorded.clone())))),
+    );
+    register_negotiator(
+        "test-offer-published",
+        "AppendName",
+        Box::new(|_, _, _| Ok(Box::new(AppendName("appended".to_string())))),
+    );
+
+    let config = NegotiatorsConfigBuilder::new()
+        .static_lib("test-offer-published", "RecordOffer", ())
+        .unwrap()
+        .static_lib("test-offer-published", "AppendName", ())
+        .unwrap()
+        .composite(CompositeNegotiatorConfig::default_test())
+        .build();
+
+    let test_dir = prepare_test_dir("test_on_offer_published").unwrap();
+    let (negotiator, _callbacks) =
+        create_negotiator(config, serde_yaml::Value::Null, test_dir.clone(), test_dir).unwrap();
+
+    let offer = negotiator.create_offer(&example_offer()).await.unwrap();
+
+    let published = published.lock().unwrap().clone().unwrap();
+    assert_eq!(
+        published.property("test.visited"),
+        Some(&serde_json::json!(["appended"]))
+    );
+    assert_eq!(published.properties, offer.properties);
+    assert_eq!(published.constraints, offer.constraints);
+}
+
+/// Contributions of all scorers should be retrievable from collection diagnostics.
+#[actix_rt::test]
+async fn test_score_breakdown() {
+    let mut proposal = proposal_from_demand(&example_demand(
+        Utc::now() + chrono::Duration::seconds(50),
+        "net-1",
+    ));
+    proposal.proposal_id = "scored-proposal".to_string();
+    proposal.properties["golem.com.pricing.model.linear.coeffs"] = serde_json::json!([0.5, 0.5]);
+    proposal.properties["golem.inf.mem.gib"] = serde_json::json!(8.0);
+
+    let components = NegotiatorsPack::new()
+        .add_component("Price", Box::new(ScoringAdapter::new(LinearPriceScorer)))
+        .add_component("Memory", Box::new(ScoringAdapter::new(MemoryScorer)));
+    let mut config = CompositeNegotiatorConfig::default_test();
+    // Keep Proposals in collection, so we can check their scores.
+    config.proposals.collect_amount = Some(5);
+    config.proposals.collect_period = Some(std::time::Duration::from_secs(60));
+
+    let (negotiator, _callbacks) = Negotiator::new(components, config);
+    let negotiator = NegotiatorAddr::from(negotiator);
+
+    let offer = negotiator.create_offer(&example_offer()).await.unwrap();
+    let offer = proposal_from_demand(&offer);
+
+    negotiator
+        .react_to_proposal("", &proposal, &offer)
+        .await
+        .unwrap();
+
+    let dump = negotiator.diagnostic_dump().await.unwrap();
+    let awaiting = &dump["collections"]["proposals"]["awaiting"];
+    assert_eq!(awaiting[0]["id"], "scored-proposal");
+    assert_eq!(
+        awaiting[0]["breakdown"],
+        serde_json::json!({"price.score": 0.5, "memory.score": 8.0})
+    );
+}
+
+#[derive(serde::Deserialize)]
+struct LimitAgreementsState {
+    #[serde(rename = "active-agreements")]
+    active_agreements: usize,
+    #[serde(rename = "max-agreements")]
+    max_agreements: u32,
+}
+
+#[actix_rt::test]
+async fn test_control_event_typed() {
+    let test_dir = prepare_test_dir("test_control_event_typed").unwrap();
+    let (negotiator, _callbacks) = create_negotiator(
+        example_config(),
+        serde_yaml::Value::Null,
+        test_dir.clone(),
+        test_dir,
+    )
+    .unwrap();
+
+    let state: LimitAgreementsState = negotiator
+        .control_event_typed("LimitAgreements", &diagnostics_query())
+        .await
+        .unwrap();
+    assert_eq!(state.active_agreements, 0);
+    assert_eq!(state.max_agreements, 1);
+
+    // Component doesn't respond to unknown queries, so response can't be parsed.
+    assert!(negotiator
+        .control_event_typed::<_, LimitAgreementsState>(
+            "LimitAgreements",
+            &serde_json::json!({"query": "unknown"})
+        )
+        .await
+        .is_err());
+}
+
+#[test]
+fn test_config_builder() {
+    let config = NegotiatorsConfigBuilder::new()
+        .builtin(
+            "LimitExpiration",
+            expiration::Config {
+                min_expiration: std::time::Duration::from_secs(30),
+                max_expiration: std::time::Duration::from_secs(300),
+                max_debit_note_accept_timeout: None,
+                min_agreement_expiration: None,
+            },
+        )
+        .unwrap()
+        .builtin(
+            "LimitAgreements",
+            max_agreements::Config {
+                max_agreements: 1,
+                reservation_timeout: std::time::Duration::from_secs(60),
+            },
+        )
+        .unwrap()
+        .composite(CompositeNegotiatorConfig::default_test())
+        .build();
+
+    assert_eq!(config, example_config());
+}
+
+fn config_fixture_dir() -> std::path::PathBuf {
+    std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("resources")
+        .join("negotiators-config")
+}
+
+#[test]
+fn test_config_from_dir() {
+    let config = NegotiatorsConfig::from_dir(config_fixture_dir()).unwrap();
+    assert_eq!(config, example_config());
+}
+
+#[test]
+fn test_config_from_dir_duplicate_name() {
+    let test_dir = prepare_test_dir("test_config_from_dir_duplicate_name").unwrap();
+    let expiration = config_fixture_dir().join("01-limit-expiration.yaml");
+    std::fs::copy(&expiration, test_dir.join("01-limit-expiration.yaml")).unwrap();
+    std::fs::copy(&expiration, test_dir.join("02-limit-expiration.yaml")).unwrap();
+
+    let config = NegotiatorsConfig::from_dir(&test_dir).unwrap();
+    assert_eq!(config.negotiators.len(), 2);
+    assert_eq!(
+        config.lint(),
+        vec![LintWarning::DuplicateName {
+            name: "LimitExpiration".to_string(),
+            renamed: "LimitExpiration#1".to_string(),
+        }]
+    );
+}
+
+#[test]
+fn test_config_from_dir_malformed_file() {
+    let test_dir = prepare_test_dir("test_config_from_dir_malformed_file").unwrap();
+    std::fs::write(test_dir.join("01-broken.yaml"), "name: [LimitExpiration").unwrap();
+
+    let error = NegotiatorsConfig::from_dir(&test_dir).unwrap_err();
+    assert!(error.to_string().contains("01-broken.yaml"));
+}
+
+/// Agents should be able to list loaded components and their descriptions.
+#[actix_rt::test]
+async fn test_describe_components() {
+    let test_dir = prepare_test_dir("test_describe_components").unwrap();
+    let (negotiator, _callbacks) = create_negotiator(
+        example_config(),
+        serde_yaml::Value::Null,
+        test_dir.clone(),
+        test_dir,
+    )
+    .unwrap();
+
+    let response = negotiator
+        .control_event(PACK_COMPONENT, serde_json::Value::Null)
+        .await
+        .unwrap();
+    let components = response["components"].as_array().unwrap();
+
+    let names = components
+        .iter()
+        .map(|component| component["name"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["LimitExpiration", "LimitAgreements"]);
+
+    // `LimitExpiration` doesn't describe itself.
+    assert_eq!(components[0]["description"], serde_json::Value::Null);
+    assert_eq!(
+        components[1]["description"],
+        "Limits number of simultaneously running Agreements."
+    );
+    assert_eq!(
+        components[1]["config_schema"]["required"],
+        serde_json::json!(["max_agreements"])
+    );
+}
+
+/// Disabled negotiators shouldn't be created, so they can't fail on construction
+/// and they don't appear in the list of components.
+#[actix_rt::test]
+async fn test_disabled_negotiator_skipped() {
+    let test_dir = prepare_test_dir("test_disabled_negotiator_skipped").unwrap();
+    let mut config = example_config();
+    config.negotiators[1].enabled = false;
+    config.negotiators.push(NegotiatorConfig {
+        name: "NotExisting".to_string(),
+        load_mode: LoadMode::BuiltIn,
+        params: serde_yaml::Value::Null,
+        enabled: false,
+        priority: 0,
+    });
+
+    let (negotiator, _callbacks) =
+        create_negotiator(config, serde_yaml::Value::Null, test_dir.clone(), test_dir).unwrap();
+
+    let response = negotiator
+        .control_event(PACK_COMPONENT, serde_json::Value::Null)
+        .await
+        .unwrap();
+    let names = response["components"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|component| component["name"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["LimitExpiration"]);
+}
+
+/// Negotiators should be ordered by priority, keeping config order for equal priorities.
+#[actix_rt::test]
+async fn test_negotiators_priority() {
+    let test_dir = prepare_test_dir("test_negotiators_priority").unwrap();
+    let mut config = example_config();
+    let mut second_expiration = config.negotiators[0].clone();
+    config.negotiators[0].priority = 10;
+    second_expiration.priority = 10;
+    config.negotiators.push(second_expiration);
+
+    let (negotiator, _callbacks) =
+        create_negotiator(config, serde_yaml::Value::Null, test_dir.clone(), test_dir).unwrap();
+
+    let response = negotiator
+        .control_event(PACK_COMPONENT, serde_json::Value::Null)
+        .await
+        .unwrap();
+    let names = response["components"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|component| component["name"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        vec!["LimitAgreements", "LimitExpiration", "LimitExpiration#1"]
+    );
+}
+
+#[test]
+fn test_lint_valid_config() {
+    assert_eq!(example_config().lint(), vec![]);
+}
+
+#[test]
+fn test_lint_duplicate_names() {
+    let mut config = example_config();
+    config.negotiators.push(config.negotiators[0].clone());
+    config.negotiators.push(config.negotiators[0].clone());
+    assert_eq!(
+        config.lint(),
+        vec![
+            LintWarning::DuplicateName {
+                name: "LimitExpiration".to_string(),
+                renamed: "LimitExpiration#1".to_string(),
+            },
+            LintWarning::DuplicateName {
+                name: "LimitExpiration".to_string(),
+                renamed: "LimitExpiration#2".to_string(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_lint_scorer_before_negotiator() {
+    let scorer = NegotiatorsConfigBuilder::default()
+        .builtin(
+            "AcceptAll",
+            accept_all::Config {
+                score: Some(accept_all::ScoreConfig::Fixed(0.5)),
+            },
+        )
+        .unwrap()
+        .build()
+        .negotiators
+        .pop()
+        .unwrap();
+
+    let mut config = example_config();
+    config.negotiators.insert(1, scorer.clone());
+    assert_eq!(
+        config.lint(),
+        vec![LintWarning::ScorerBeforeNegotiator {
+            scorer: "AcceptAll".to_string(),
+            negotiator: "LimitAgreements".to_string(),
+        }]
+    );
+
+    // Priority moves scorer to the end of the chain.
+    config.negotiators[1].priority = 1;
+    assert_eq!(config.lint(), vec![]);
+}
+
+#[test]
+fn test_lint_empty_chain() {
+    let mut config = example_config();
+    config.negotiators[0].enabled = false;
+    config.negotiators[1].enabled = false;
+    assert_eq!(config.lint(), vec![LintWarning::EmptyChain]);
+}
+
+#[test]
+fn test_lint_no_decision_trigger() {
+    let mut config = example_config();
+    config.composite.agreements.collect_amount = None;
+    config.composite.agreements.collect_period = None;
+    assert_eq!(
+        config.lint(),
+        vec![LintWarning::NoDecisionTrigger {
+            collection: "agreements".to_string(),
+        }]
+    );
+}
+
+/// Negotiator without components would accept everything, so it is refused
+/// if `require_non_empty` is set. Otherwise it is reported by pack introspection.
+#[actix_rt::test]
+async fn test_require_non_empty() {
+    let test_dir = prepare_test_dir("test_require_non_empty").unwrap();
+    let mut config = example_config();
+    config
+        .negotiators
+        .iter_mut()
+        .for_each(|negotiator| negotiator.enabled = false);
+    config.composite.require_non_empty = true;
+
+    let error = create_negotiator(
+        config.clone(),
+        serde_yaml::Value::Null,
+        test_dir.clone(),
+        test_dir.clone(),
+    )
+    .err()
+    .unwrap();
+    assert!(error.to_string().contains("No negotiators enabled"));
+
+    config.composite.require_non_empty = false;
+    let (negotiator, _callbacks) =
+        create_negotiator(config, serde_yaml::Value::Null, test_dir.clone(), test_dir).unwrap();
+
+    let response = negotiator
+        .control_event(PACK_COMPONENT, serde_json::Value::Null)
+        .await
+        .unwrap();
+    assert_eq!(response["components"], serde_json::json!([]));
+    assert_eq!(response["empty"], serde_json::json!(true));
+}
+
+/// Score configured for `AcceptAll` should be used to order Proposals collected
+/// before making decision.
+#[actix_rt::test]
+async fn test_accept_all_score() {
+    let conf = NegotiatorConfig {
+        name: "AcceptAll".to_string(),
+        load_mode: LoadMode::BuiltIn,
+        params: serde_yaml::to_value(accept_all::Config {
+            score: Some(accept_all::ScoreConfig::Fixed(0.7)),
+        })
+        .unwrap(),
+        enabled: true,
+        priority: 0,
+    };
+    let mut config = NegotiatorsConfig {
+        negotiators: vec![conf],
+        composite: CompositeNegotiatorConfig::default_test(),
+    };
+    // Keep Proposals in collection, so we can check their scores.
+    config.composite.proposals.collect_amount = Some(5);
+    config.composite.proposals.collect_period = Some(std::time::Duration::from_secs(60));
+
+    let test_dir = prepare_test_dir("test_accept_all_score").unwrap();
+    let (negotiator, _callbacks) =
+        create_negotiator(config, serde_yaml::Value::Null, test_dir.clone(), test_dir).unwrap();
+
+    let offer = negotiator.create_offer(&example_offer()).await.unwrap();
+    let offer = proposal_from_demand(&offer);
+
+    let mut proposal = proposal_from_demand(&example_demand(
+        Utc::now() + chrono::Duration::seconds(50),
+        "net-1",
+    ));
+    proposal.proposal_id = "scored-proposal".to_string();
+
+    negotiator
+        .react_to_proposal("", &proposal, &offer)
+        .await
+        .unwrap();
+
+    let dump = negotiator.diagnostic_dump().await.unwrap();
+    assert_eq!(
+        dump["collections"]["proposals"]["awaiting"],
+        serde_json::json!([{
+            "id": "scored-proposal",
+            "score": 0.7,
+            "breakdown": {"final-score": 0.7}
+        }])
+    );
+}
+
+/// `AcceptAll` without config shouldn't set any score.
+#[test]
+fn test_accept_all_default_score() {
+    let proposal = proposal_from_demand(&example_demand(
+        Utc::now() + chrono::Duration::seconds(50),
+        "net-1",
+    ));
+    let their = ProposalView::try_from(&proposal).unwrap();
+
+    let mut component = AcceptAll::new(serde_yaml::Value::Null).unwrap();
+    match component
+        .negotiate_step(&their, their.clone(), Score::default())
+        .unwrap()
+    {
+        NegotiationResult::Ready { score, .. } => assert_eq!(score, Score::default()),
+        result => panic!("Expected Ready, got: {:?}", result),
+    }
+
+    let mut component = AcceptAll::new(serde_yaml::from_str("score: random").unwrap()).unwrap();
+    match component
+        .negotiate_step(&their, their.clone(), Score::default())
+        .unwrap()
+    {
+        NegotiationResult::Ready { score, .. } => {
+            let value = score.pointer_typed::<f64>("/final-score").unwrap();
+            assert!((0.0..1.0).contains(&value));
+        }
+        result => panic!("Expected Ready, got: {:?}", result),
+    }
+}
+
+fn limit_expiration(
+    max_debit_note_accept_timeout: Option<u64>,
+    min_agreement_expiration: Option<u64>,
+) -> LimitExpiration {
+    LimitExpiration::new(
+        serde_yaml::to_value(expiration::Config {
+            min_expiration: std::time::Duration::from_secs(30),
+            max_expiration: std::time::Duration::from_secs(300),
+            max_debit_note_accept_timeout: max_debit_note_accept_timeout
+                .map(std::time::Duration::from_secs),
+            min_agreement_expiration: min_agreement_expiration.map(std::time::Duration::from_secs),
+        })
+        .unwrap(),
+    )
+    .unwrap()
+}
+
+fn accepted_proposal(id: &str) -> ProposalView {
+    let mut proposal = proposal_from_demand(&example_demand(
+        Utc::now() + chrono::Duration::seconds(50),
+        "net-1",
+    ));
+    proposal.proposal_id = id.to_string();
+    proposal.state = State::Accepted;
+    ProposalView::try_from(&proposal).unwrap()
+}
+
+fn is_ready(component: &mut dyn NegotiatorComponent, their: &ProposalView) -> bool {
+    match component
+        .negotiate_step(their, their.clone(), Score::default())
+        .unwrap()
+    {
+        NegotiationResult::Ready { .. } => true,
+        NegotiationResult::Reject { .. } => false,
+        result => panic!("Unexpected result: {:?}", result),
+    }
+}
+
+/// Agreements accepted, but not approved yet, should occupy slots until
+/// reservation expires.
+#[test]
+fn test_max_agreements_reservations() {
+    let mut component = MaxAgreements::new(
+        serde_yaml::to_value(max_agreements::Config {
+            max_agreements: 1,
+            reservation_timeout: std::time::Duration::from_millis(200),
+        })
+        .unwrap(),
+    )
+    .unwrap();
+
+    assert!(is_ready(&mut component, &accepted_proposal("agreement-1")));
+    assert!(!is_ready(&mut component, &accepted_proposal("agreement-2")));
+
+    // Re-evaluating the same Agreement shouldn't be blocked by its own reservation.
+    assert!(is_ready(&mut component, &accepted_proposal("agreement-1")));
+
+    let mut draft = accepted_proposal("proposal-3");
+    draft.state = State::Draft;
+    assert!(!is_ready(&mut component, &draft));
+
+    // Abandoned reservation shouldn't block slots forever.
+    std::thread::sleep(std::time::Duration::from_millis(300));
+    assert!(is_ready(&mut component, &accepted_proposal("agreement-2")));
+
+    // Rejection releases reservation.
+    component.on_proposal_rejected("agreement-2").unwrap();
+    assert!(is_ready(&mut component, &draft));
+}
+
+/// Active Agreements should still occupy slots after restoring state
+/// in new component.
+#[test]
+fn test_max_agreements_restore_state() {
+    let limit_config = || {
+        serde_yaml::to_value(max_agreements::Config {
+            max_agreements: 1,
+            reservation_timeout: std::time::Duration::from_secs(60),
+        })
+        .unwrap()
+    };
+    let pack = || {
+        NegotiatorsPack::new().add_component(
+            "LimitAgreements",
+            Box::new(MaxAgreements::new(limit_config()).unwrap()),
+        )
+    };
+
+    let proposal = proposal_from_demand(&example_demand(
+        Utc::now() + chrono::Duration::seconds(50),
+        "net-1",
+    ));
+    let mut original = pack();
+    original
+        .on_agreement_approved(&agreement_from("agreement-1", &proposal, &proposal))
+        .unwrap();
+
+    let state = original.serialize_state().unwrap();
+    assert!(state.get("LimitAgreements").is_some());
+
+    let mut restored = pack();
+    restored.restore_state(state).unwrap();
+
+    let diagnostics = restored
+        .control_event("LimitAgreements", diagnostics_query())
+        .unwrap();
+    assert_eq!(diagnostics["active-agreements"], 1);
+    assert!(!is_ready(&mut restored, &accepted_proposal("agreement-2")));
+}
+
+/// Raising limit at runtime should immediately let more Agreements through.
+#[test]
+fn test_reconfigure_max_agreements() {
+    let limit_config = |max_agreements| {
+        serde_yaml::to_value(max_agreements::Config {
+            max_agreements,
+            reservation_timeout: std::time::Duration::from_secs(60),
+        })
+        .unwrap()
+    };
+    let mut pack = NegotiatorsPack::new()
+        .add_component(
+            "LimitAgreements",
+            Box::new(MaxAgreements::new(limit_config(1)).unwrap()),
+        )
+        .add_component(
+            "AcceptAll",
+            Box::new(AcceptAll::new(Default::default()).unwrap()),
+        );
+
+    assert!(is_ready(&mut pack, &accepted_proposal("agreement-1")));
+    assert!(!is_ready(&mut pack, &accepted_proposal("agreement-2")));
+
+    pack.control_event(
+        "LimitAgreements",
+        reconfigure_request(limit_config(2)).unwrap(),
+    )
+    .unwrap();
+    assert!(is_ready(&mut pack, &accepted_proposal("agreement-2")));
+    assert!(!is_ready(&mut pack, &accepted_proposal("agreement-3")));
+
+    // Components are not reconfigurable by default.
+    assert!(pack
+        .control_event(
+            "AcceptAll",
+            reconfigure_request(Default::default()).unwrap()
+        )
+        .is_err());
+}
+
+fn availability_window(timezone: &str, windows: serde_json::Value) -> AvailabilityWindow {
+    AvailabilityWindow::new(
+        serde_yaml::to_value(serde_json::json!({
+            "timezone": timezone,
+            "windows": windows,
+        }))
+        .unwrap(),
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_availability_window_negotiations() {
+    let proposal = proposal_from_demand(&example_demand(
+        Utc::now() + chrono::Duration::seconds(50),
+        "net-1",
+    ));
+    let their = ProposalView::try_from(&proposal).unwrap();
+
+    let mut component = availability_window(
+        "UTC",
+        serde_json::json!([{"start": "00:00", "end": "00:00"}]),
+    );
+    match component
+        .negotiate_step(&their, their.clone(), Score::default())
+        .unwrap()
+    {
+        NegotiationResult::Ready { .. } => (),
+        result => panic!("Expected Ready, got: {:?}", result),
+    }
+
+    // Window starting in an hour.
+    let start = Utc::now() + chrono::Duration::hours(1);
+    let end = start + chrono::Duration::minutes(30);
+    let mut component = availability_window(
+        "UTC",
+        serde_json::json!([{
+            "start": start.format("%H:%M").to_string(),
+            "end": end.format("%H:%M").to_string(),
+        }]),
+    );
+    match component
+        .negotiate_step(&their, their.clone(), Score::default())
+        .unwrap()
+    {
+        NegotiationResult::Reject { is_final, .. } => assert!(!is_final),
+        result => panic!("Expected Reject, got: {:?}", result),
+    }
+
+    // Availability is advertised in Offer.
+    let offer = component.fill_template(OfferTemplate::default()).unwrap();
+    assert_eq!(
+        offer.property("golem.node.availability.timezone"),
+        Some(&serde_json::json!("UTC"))
+    );
+}
+
+fn proposal_from_node(node_id: &str, proposal_id: &str) -> ProposalView {
+    let mut proposal = accepted_proposal(proposal_id);
+    proposal.issuer = node_id.parse().unwrap();
+    proposal.state = State::Initial;
+    proposal
+}
+
+/// `Tap` should record chosen properties without changing negotiation result.
+#[test]
+fn test_tap_passes_through() {
+    let test_dir = prepare_test_dir("test_tap_passes_through").unwrap();
+    let mut component = Tap::new(
+        serde_yaml::to_value(tap::Config {
+            pointers: vec![
+                "/golem/node/debug/subnet".to_string(),
+                "/golem/missing".to_string(),
+            ],
+            level: "debug".to_string(),
+            write_file: true,
+        })
+        .unwrap(),
+        test_dir.clone(),
+    )
+    .unwrap();
+
+    let their = accepted_proposal("tapped-proposal");
+    let template = their.clone();
+    let mut score = Score::default();
+    score.set_property("test.score", serde_json::json!(0.5));
+
+    match component
+        .negotiate_step(&their, template.clone(), score.clone())
+        .unwrap()
+    {
+        NegotiationResult::Ready {
+            proposal,
+            score: result_score,
+        } => {
+            assert_eq!(proposal, template);
+            assert_eq!(result_score, score);
+        }
+        result => panic!("Expected Ready, got: {:?}", result),
+    }
+
+    let content = std::fs::read_to_string(test_dir.join(tap::TAP_FILE)).unwrap();
+    let records = content
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0]["proposal-id"], "tapped-proposal");
+    assert_eq!(
+        records[0]["properties"],
+        serde_json::json!({
+            "/golem/node/debug/subnet": "net-1",
+            "/golem/missing": null,
+        })
+    );
+    assert_eq!(records[0]["score"], score.properties);
+}
+
+/// Rejection code and additional properties should survive conversion to `Reason`,
+/// which is sent to other party.
+#[test]
+fn test_reject_reason_code_round_trip() {
+    let reason = RejectReason::new("Node on rejection list.")
+        .with_code("NODE_BLACKLISTED")
+        .properties(serde_json::json!({"node-name": "dany"}))
+        .final_flag(true);
+
+    let market_reason: Reason = reason.clone().into();
+    assert_eq!(market_reason.message, "Node on rejection list.");
+    assert_eq!(
+        market_reason.extra["golem.proposal.rejection.code"],
+        serde_json::json!("NODE_BLACKLISTED")
+    );
+    assert_eq!(market_reason.extra["node-name"], serde_json::json!("dany"));
+
+    let serialized = serde_json::to_string(&market_reason).unwrap();
+    let deserialized: Reason = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(RejectReason::from(deserialized), reason);
+
+    // Reasons without code are converted as before.
+    let reason = RejectReason::new("No code");
+    let market_reason: Reason = reason.clone().into();
+    assert_eq!(market_reason.extra, serde_json::json!({}));
+    assert_eq!(RejectReason::from(market_reason), reason);
+}
+
+#[test]
+fn test_builtin_rejection_codes() {
+    let mut component = MaxAgreements::new(
+        serde_yaml::to_value(max_agreements::Config {
+            max_agreements: 0,
+            reservation_timeout: std::time::Duration::from_secs(60),
+        })
+        .unwrap(),
+    )
+    .unwrap();
+
+    let their = accepted_proposal("agreement-1");
+    match component
+        .negotiate_step(&their, their.clone(), Score::default())
+        .unwrap()
+    {
+        NegotiationResult::Reject { reason, .. } => {
+            let reason: Reason = reason.into();
+            assert_eq!(
+                RejectReason::from(reason).code.as_deref(),
+                Some(max_agreements::NO_CAPACITY)
+            );
+        }
+        result => panic!("Expected Reject, got: {:?}", result),
+    }
+}
+
+#[test]
+fn test_reject_reason_final_flag_round_trip() {
+    for flag in [true, false] {
+        let reason: Reason = RejectReason::new("Rejected").final_flag(flag).into();
+        assert_eq!(RejectReason::from(reason).is_final(), Some(flag));
+    }
+
+    let reason: Reason = RejectReason::new("Rejected").into();
+    assert_eq!(RejectReason::from(reason).is_final(), None);
+}
+
+fn rejection_reason(action: Option<ProposalAction>) -> RejectReason {
+    match action {
+        Some(ProposalAction::RejectProposal {
+            reason: Some(reason),
+            ..
+        }) => RejectReason::from(reason),
+        action => panic!("Expected RejectProposal with reason, got: {:?}", action),
+    }
+}
+
+/// Consumer should be able to distinguish Proposals rejected because of low score,
+/// from Proposals, that will never be accepted.
+#[actix_rt::test]
+async fn test_busy_rejection_is_not_final() {
+    let conf = NegotiatorConfig {
+        name: "AcceptAll".to_string(),
+        load_mode: LoadMode::BuiltIn,
+        params: serde_yaml::Value::Null,
+        enabled: true,
+        priority: 0,
+    };
+    let mut config = NegotiatorsConfig {
+        negotiators: vec![conf],
+        composite: CompositeNegotiatorConfig::default_test(),
+    };
+    // Choose only one of 2 collected Proposals.
+    config.composite.proposals.collect_amount = Some(2);
+    config.composite.proposals.goal =
+        serde_json::from_value(serde_json::json!({"Batch": 1})).unwrap();
+
+    let test_dir = prepare_test_dir("test_busy_rejection_is_not_final").unwrap();
+    let (
+        negotiator,
+        NegotiatorCallbacks {
+            proposal_channel: mut proposals,
+            agreement_channel: _agreements,
+        },
+    ) = create_negotiator(config, serde_yaml::Value::Null, test_dir.clone(), test_dir).unwrap();
+
+    let offer = negotiator.create_offer(&example_offer()).await.unwrap();
+    let offer = proposal_from_demand(&offer);
+
+    for id in &["proposal-1", "proposal-2"] {
+        let mut proposal = proposal_from_demand(&example_demand(
+            Utc::now() + chrono::Duration::seconds(50),
+            "net-1",
+        ));
+        proposal.proposal_id = id.to_string();
+        negotiator
+            .react_to_proposal("", &proposal, &offer)
+            .await
+            .unwrap();
+    }
+
+    let mut actions = vec![proposals.recv().await, proposals.recv().await];
+    actions.sort_by_key(|action| !matches!(action, Some(ProposalAction::AcceptProposal { .. })));
+    assert!(matches!(
+        actions[0],
+        Some(ProposalAction::AcceptProposal { .. })
+    ));
+
+    let reason = rejection_reason(actions.pop().unwrap());
+    assert_eq!(reason.is_final(), Some(false));
+    assert_eq!(reason.code.as_deref(), Some("NODE_BUSY"));
+}
+
+/// Uses memory offered in Proposal as its final score.
+struct MemoryFinalScore;
+
+impl NegotiatorComponent for MemoryFinalScore {
+    fn negotiate_step(
+        &mut self,
+        their: &ProposalView,
+        template: ProposalView,
+        mut score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        let memory = their.pointer_typed::<f64>("/golem/inf/mem/gib")?;
+        score.set_property("final-score", serde_json::json!(memory));
+        Ok(NegotiationResult::Ready {
+            proposal: template,
+            score,
+        })
+    }
+}
+
+/// Rejected Proposals should get configured reason with information how far
+/// they were from being chosen.
+#[actix_rt::test]
+async fn test_busy_reason_configured() {
+    let components = NegotiatorsPack::new().add_component("Memory", Box::new(MemoryFinalScore));
+    let mut config = CompositeNegotiatorConfig::default_test();
+    config.proposals.collect_amount = Some(2);
+    config.proposals.collect_period = Some(std::time::Duration::from_secs(60));
+    config.proposals.goal = serde_json::from_value(serde_json::json!({"Batch": 1})).unwrap();
+    config.proposals.busy_reason = serde_json::from_value(serde_json::json!({
+        "message": "Better Offer chosen.",
+        "code": "BETTER_OFFER",
+        "include_scores": true,
+    }))
+    .unwrap();
+
+    let (negotiator, mut callbacks) = Negotiator::new(components, config);
+    let negotiator = NegotiatorAddr::from(negotiator);
+
+    let offer = negotiator.create_offer(&example_offer()).await.unwrap();
+    let offer = proposal_from_demand(&offer);
+
+    for (id, memory) in &[("small-proposal", 2.0), ("big-proposal", 8.0)] {
+        let mut proposal = proposal_from_demand(&example_demand(
+            Utc::now() + chrono::Duration::seconds(50),
+            "net-1",
+        ));
+        proposal.proposal_id = id.to_string();
+        proposal.properties["golem.inf.mem.gib"] = serde_json::json!(memory);
+        negotiator
+            .react_to_proposal("", &proposal, &offer)
+            .await
+            .unwrap();
+    }
+
+    match callbacks.proposal_channel.recv().await {
+        Some(ProposalAction::AcceptProposal { id, .. }) => assert_eq!(id, "big-proposal"),
+        action => panic!("Expected AcceptProposal, got: {:?}", action),
+    }
+
+    let reason = rejection_reason(callbacks.proposal_channel.recv().await);
+    assert_eq!(reason.message, "Better Offer chosen.");
+    assert_eq!(reason.code.as_deref(), Some("BETTER_OFFER"));
+    assert_eq!(reason.is_final(), Some(false));
+    assert_eq!(reason.extra["score"], serde_json::json!(2.0));
+    assert_eq!(reason.extra["winning-score"], serde_json::json!(8.0));
+    assert_eq!(reason.extra["score-delta"], serde_json::json!(6.0));
+}
+
+/// Scores Proposals both by memory (`final-score`) and by lack of it (`small-score`).
+struct MemoryTwoScores;
+
+impl NegotiatorComponent for MemoryTwoScores {
+    fn negotiate_step(
+        &mut self,
+        their: &ProposalView,
+        template: ProposalView,
+        mut score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        let memory = their.pointer_typed::<f64>("/golem/inf/mem/gib")?;
+        score.set_property("final-score", serde_json::json!(memory));
+        score.set_property("small-score", serde_json::json!(-memory));
+        Ok(NegotiationResult::Ready {
+            proposal: template,
+            score,
+        })
+    }
+}
+
+/// Proposals selection should use configured score pointer instead of `/final-score`.
+#[actix_rt::test]
+async fn test_proposal_score_pointer() {
+    let components = NegotiatorsPack::new().add_component("Memory", Box::new(MemoryTwoScores));
+    let mut config = CompositeNegotiatorConfig::default_test();
+    config.proposals.collect_amount = Some(2);
+    config.proposals.collect_period = Some(std::time::Duration::from_secs(60));
+    config.proposals.goal = serde_json::from_value(serde_json::json!({"Batch": 1})).unwrap();
+    config.proposals.score_pointer = "/small-score".to_string();
+
+    let (negotiator, mut callbacks) = Negotiator::new(components, config);
+    let negotiator = NegotiatorAddr::from(negotiator);
+
+    let offer = negotiator.create_offer(&example_offer()).await.unwrap();
+    let offer = proposal_from_demand(&offer);
+
+    for (id, memory) in &[("small-proposal", 2.0), ("big-proposal", 8.0)] {
+        let mut proposal = proposal_from_demand(&example_demand(
+            Utc::now() + chrono::Duration::seconds(50),
+            "net-1",
+        ));
+        proposal.proposal_id = id.to_string();
+        proposal.properties["golem.inf.mem.gib"] = serde_json::json!(memory);
+        negotiator
+            .react_to_proposal("", &proposal, &offer)
+            .await
+            .unwrap();
+    }
+
+    match callbacks.proposal_channel.recv().await {
+        Some(ProposalAction::AcceptProposal { id, .. }) => assert_eq!(id, "small-proposal"),
+        action => panic!("Expected AcceptProposal, got: {:?}", action),
+    }
+}
+
+/// Negotiator shouldn't negotiate with Proposals and Agreements issued by itself.
+#[actix_rt::test]
+async fn test_self_negotiation_rejected() {
+    let node_id: NodeId = "0x33796f397a554a6c33675976683031774f637a37"
+        .parse()
+        .unwrap();
+    let mut config = example_config();
+    config.composite.node_id = Some(node_id);
+
+    let test_dir = prepare_test_dir("test_self_negotiation_rejected").unwrap();
+    let (
+        negotiator,
+        NegotiatorCallbacks {
+            proposal_channel: mut proposals,
+            agreement_channel: mut agreements,
+        },
+    ) = create_negotiator(config, serde_yaml::Value::Null, test_dir.clone(), test_dir).unwrap();
+
+    let mut offer = proposal_from_demand(&negotiator.create_offer(&example_offer()).await.unwrap());
+    offer.issuer_id = node_id;
+
+    let mut proposal = proposal_from_demand(&example_demand(
+        Utc::now() + chrono::Duration::seconds(50),
+        "net-1",
+    ));
+    proposal.proposal_id = "own-proposal".to_string();
+    proposal.issuer_id = node_id;
+
+    negotiator
+        .react_to_proposal("", &proposal, &offer)
+        .await
+        .unwrap();
+    let reason = rejection_reason(proposals.recv().await);
+    assert_eq!(reason.code.as_deref(), Some(SELF_NEGOTIATION));
+    assert_eq!(reason.is_final(), Some(true));
+
+    negotiator
+        .react_to_agreement("", &agreement_from("own-agreement", &proposal, &offer))
+        .await
+        .unwrap();
+    match agreements.recv().await {
+        Some(AgreementAction::RejectAgreement {
+            id,
+            reason: Some(reason),
+            ..
+        }) => {
+            assert_eq!(id, "own-agreement");
+            assert_eq!(
+                RejectReason::from(reason).code.as_deref(),
+                Some(SELF_NEGOTIATION)
+            );
+        }
+        action => panic!("Expected RejectAgreement, got: {:?}", action),
+    }
+}
+
+/// Negotiator with bounded channels shouldn't react to Proposals, until
+/// pending actions are consumed.
+#[actix_rt::test]
+async fn test_channel_capacity_backpressure() {
+    let conf = NegotiatorConfig {
+        name: "AcceptAll".to_string(),
+        load_mode: LoadMode::BuiltIn,
+        params: serde_yaml::Value::Null,
+        enabled: true,
+        priority: 0,
+    };
+    let mut config = NegotiatorsConfig {
+        negotiators: vec![conf],
+        composite: CompositeNegotiatorConfig::default_test(),
+    };
+    config.composite.channel_capacity = Some(1);
+
+    let test_dir = prepare_test_dir("test_channel_capacity_backpressure").unwrap();
+    let (
+        negotiator,
+        NegotiatorCallbacks {
+            proposal_channel: mut proposals,
+            agreement_channel: _agreements,
+        },
+    ) = create_negotiator(config, serde_yaml::Value::Null, test_dir.clone(), test_dir).unwrap();
+
+    let offer = negotiator.create_offer(&example_offer()).await.unwrap();
+    let offer = proposal_from_demand(&offer);
+
+    let initial_proposal = |id: &str| {
+        let mut proposal = proposal_from_demand(&example_demand(
+            Utc::now() + chrono::Duration::seconds(50),
+            "net-1",
+        ));
+        proposal.proposal_id = id.to_string();
+        proposal.state = State::Initial;
+        proposal
+    };
+
+    negotiator
+        .react_to_proposal("", &initial_proposal("proposal-1"), &offer)
+        .await
+        .unwrap();
+    // Channel is full, since nobody consumed CounterProposal yet.
+    let error = negotiator
+        .react_to_proposal("", &initial_proposal("proposal-2"), &offer)
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("overloaded"), "{}", error);
+
+    match proposals.recv().await {
+        Some(ProposalAction::CounterProposal { id, .. }) => assert_eq!(id, "proposal-1"),
+        action => panic!("Expected CounterProposal, got: {:?}", action),
+    }
+
+    negotiator
+        .react_to_proposal("", &initial_proposal("proposal-2"), &offer)
+        .await
+        .unwrap();
+    match proposals.recv().await {
+        Some(ProposalAction::CounterProposal { id, .. }) => assert_eq!(id, "proposal-2"),
+        action => panic!("Expected CounterProposal, got: {:?}", action),
+    }
+}
+
+/// Decision can produce more actions than channel capacity. None of them should be lost.
+#[actix_rt::test]
+async fn test_channel_capacity_keeps_decided_actions() {
+    let conf = NegotiatorConfig {
+        name: "AcceptAll".to_string(),
+        load_mode: LoadMode::BuiltIn,
+        params: serde_yaml::Value::Null,
+        enabled: true,
+        priority: 0,
+    };
+    let mut config = NegotiatorsConfig {
+        negotiators: vec![conf],
+        composite: CompositeNegotiatorConfig::default_test(),
+    };
+    config.composite.channel_capacity = Some(1);
+    config.composite.proposals.collect_amount = Some(3);
+
+    let test_dir = prepare_test_dir("test_channel_capacity_keeps_decided_actions").unwrap();
+    let (
+        negotiator,
+        NegotiatorCallbacks {
+            proposal_channel: mut proposals,
+            agreement_channel: _agreements,
+        },
+    ) = create_negotiator(config, serde_yaml::Value::Null, test_dir.clone(), test_dir).unwrap();
+
+    let offer = negotiator.create_offer(&example_offer()).await.unwrap();
+    let offer = proposal_from_demand(&offer);
+
+    for id in ["proposal-1", "proposal-2", "proposal-3"] {
+        let mut proposal = proposal_from_demand(&example_demand(
+            Utc::now() + chrono::Duration::seconds(50),
+            "net-1",
+        ));
+        proposal.proposal_id = id.to_string();
+        negotiator
+            .react_to_proposal("", &proposal, &offer)
+            .await
+            .unwrap();
+    }
+
+    let mut accepted = vec![];
+    for _ in 0..3 {
+        match proposals.recv().await {
+            Some(ProposalAction::AcceptProposal { id, .. }) => accepted.push(id),
+            action => panic!("Expected AcceptProposal, got: {:?}", action),
+        }
+    }
+    accepted.sort();
+    assert_eq!(accepted, vec!["proposal-1", "proposal-2", "proposal-3"]);
+    assert!(proposals.try_recv().is_err());
+}
+
+/// `Ready` Proposals scored below `min_final_score` shouldn't occupy collection capacity.
+#[actix_rt::test]
+async fn test_min_final_score_rejects_ready_proposal() {
+    let conf = NegotiatorConfig {
+        name: "AcceptAll".to_string(),
+        load_mode: LoadMode::BuiltIn,
+        params: serde_yaml::to_value(accept_all::Config {
+            score: Some(accept_all::ScoreConfig::Fixed(0.2)),
+        })
+        .unwrap(),
+        enabled: true,
+        priority: 0,
+    };
+    let mut config = NegotiatorsConfig {
+        negotiators: vec![conf],
+        composite: CompositeNegotiatorConfig::default_test(),
+    };
+    config.composite.min_final_score = Some(0.5);
+
+    let test_dir = prepare_test_dir("test_min_final_score_rejects_ready_proposal").unwrap();
+    let (
+        negotiator,
+        NegotiatorCallbacks {
+            proposal_channel: mut proposals,
+            agreement_channel: _agreements,
+        },
+    ) = create_negotiator(config, serde_yaml::Value::Null, test_dir.clone(), test_dir).unwrap();
+
+    let offer = negotiator.create_offer(&example_offer()).await.unwrap();
+    let offer = proposal_from_demand(&offer);
+
+    let mut proposal = proposal_from_demand(&example_demand(
+        Utc::now() + chrono::Duration::seconds(50),
+        "net-1",
+    ));
+    proposal.proposal_id = "low-score-proposal".to_string();
+    negotiator
+        .react_to_proposal("", &proposal, &offer)
+        .await
+        .unwrap();
+
+    let reason = rejection_reason(proposals.recv().await);
+    assert_eq!(reason.code.as_deref(), Some(SCORE_BELOW_MINIMUM));
+    // Proposal isn't reevaluated, so rejection is final.
+    assert_eq!(reason.is_final(), Some(true));
+
+    let dump = negotiator.diagnostic_dump().await.unwrap();
+    assert_eq!(
+        dump["collections"]["proposals"]["awaiting"],
+        serde_json::json!([])
+    );
+}
+
+#[actix_rt::test]
+async fn test_component_rejection_is_final() {
+    let test_dir = prepare_test_dir("test_component_rejection_is_final").unwrap();
+    let (
+        negotiator,
+        NegotiatorCallbacks {
+            proposal_channel: mut proposals,
+            agreement_channel: _agreements,
+        },
+    ) = create_negotiator(
+        example_config(),
+        serde_yaml::Value::Null,
+        test_dir.clone(),
+        test_dir,
+    )
+    .unwrap();
+
+    let offer = negotiator.create_offer(&example_offer()).await.unwrap();
+    let offer = proposal_from_demand(&offer);
+
+    let proposal = proposal_from_demand(&example_demand(
+        Utc::now() + chrono::Duration::seconds(900),
+        "net-1",
+    ));
+    negotiator
+        .react_to_proposal("", &proposal, &offer)
+        .await
+        .unwrap();
+
+    let reason = rejection_reason(proposals.recv().await);
+    assert_eq!(reason.is_final(), Some(true));
+    assert_eq!(
+        reason.code.as_deref(),
+        Some(expiration::EXPIRATION_OUT_OF_RANGE)
+    );
+}
+
+/// Rejects Proposals while any of previous Agreements is not fully paid.
+struct PaymentWatcher {
+    unpaid: Arc<Mutex<Vec<String>>>,
+}
+
+impl NegotiatorComponent for PaymentWatcher {
+    fn negotiate_step(
+        &mut self,
+        _their: &ProposalView,
+        template: ProposalView,
+        score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        let unpaid = self.unpaid.lock().unwrap();
+        Ok(match unpaid.is_empty() {
+            true => NegotiationResult::Ready {
+                proposal: template,
+                score,
+            },
+            false => NegotiationResult::Reject {
+                reason: RejectReason::new(format!("Agreements {:?} not paid.", unpaid)),
+                is_final: false,
+            },
+        })
+    }
+
+    fn on_agreement_event(
+        &mut self,
+        agreement_id: &str,
+        event: &AgreementEvent,
+    ) -> anyhow::Result<()> {
+        if let AgreementEvent::PartialPayment { amount, total } = event {
+            let mut unpaid = self.unpaid.lock().unwrap();
+            unpaid.retain(|id| id != agreement_id);
+            if amount < total {
+                unpaid.push(agreement_id.to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[actix_rt::test]
+async fn test_partial_payment_event_delivered() {
+    let unpaid = Arc::new(Mutex::new(vec![]));
+    let components = NegotiatorsPack::new().add_component(
+        "PaymentWatcher",
+        Box::new(PaymentWatcher {
+            unpaid: unpaid.clone(),
+        }),
+    );
+
+    let (negotiator, mut callbacks) =
+        Negotiator::new(components, CompositeNegotiatorConfig::default_test());
+    let negotiator = NegotiatorAddr::from(negotiator);
+
+    let mut our = proposal_from_demand(&example_demand(
+        Utc::now() + chrono::Duration::seconds(50),
+        "net-1",
+    ));
+    our.proposal_id = "our-0".to_string();
+
+    let mut their = our.clone();
+    their.proposal_id = "their-0".to_string();
+    their.prev_proposal_id = Some(our.proposal_id.clone());
+
+    negotiator
+        .react_to_proposal("", &their, &our)
+        .await
+        .unwrap();
+    match callbacks.proposal_channel.recv().await {
+        Some(ProposalAction::AcceptProposal { .. }) => {}
+        action => panic!("Unexpected action: {:?}", action),
+    }
+
+    negotiator
+        .post_agreement_event(
+            "agreement-0",
+            AgreementEvent::PartialPayment {
+                amount: 0.5,
+                total: 2.0,
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(*unpaid.lock().unwrap(), vec!["agreement-0".to_string()]);
+
+    their.proposal_id = "their-1".to_string();
+    negotiator
+        .react_to_proposal("", &their, &our)
+        .await
+        .unwrap();
+    match callbacks.proposal_channel.recv().await {
+        Some(ProposalAction::RejectProposal { .. }) => {}
+        action => panic!("Unexpected action: {:?}", action),
+    }
+}
+
+/// Payment milestone events should survive serialization used to pass them
+/// to negotiators loaded from shared libraries.
+#[test]
+fn test_payment_events_serialization() {
+    let events = vec![
+        AgreementEvent::DebitNoteAccepted { amount: 0.25 },
+        AgreementEvent::DebitNoteRejected,
+        AgreementEvent::PartialPayment {
+            amount: 1.0,
+            total: 3.5,
+        },
+    ];
+
+    for event in events {
+        let serialized = serde_json::to_string(&event).unwrap();
+        let deserialized: AgreementEvent = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(format!("{:?}", event), format!("{:?}", deserialized));
+    }
+}
+
+type BatchItem = (ProposalView, Vec<ProposalView>, ProposalView, Score);
+
+fn batch_item(expires_in: i64, price: Option<f64>, prev_value: Option<f64>) -> BatchItem {
+    let mut proposal = proposal_from_demand(&example_demand(
+        Utc::now() + chrono::Duration::seconds(expires_in),
+        "net-1",
+    ));
+    proposal.properties["golem.inf.mem.gib"] = serde_json::json!(8.0);
+    proposal.properties["test.value"] = serde_json::json!(1.0);
+    if let Some(price) = price {
+        proposal.properties["golem.com.pricing.model.linear.coeffs"] =
+            serde_json::json!([price, price]);
+    }
+
+    let history = prev_value
+        .map(|value| {
+            let mut prev = proposal.clone();
+            prev.properties["test.value"] = serde_json::json!(value);
+            ProposalView::try_from(&prev).unwrap()
+        })
+        .into_iter()
+        .collect();
+
+    let their = ProposalView::try_from(&proposal).unwrap();
+    (their.clone(), history, their, Score::default())
+}
+
+/// `negotiate_batch` should return the same results as calling `negotiate_step_with_history`
+/// for each item separately.
+#[test]
+fn test_negotiate_batch_matches_negotiate_step() {
+    let mut pack = NegotiatorsPack::new()
+        .add_component("LimitExpiration", Box::new(limit_expiration(None, None)))
+        .add_component("Price", Box::new(ScoringAdapter::new(LinearPriceScorer)))
+        .add_component("Memory", Box::new(ScoringAdapter::new(MemoryScorer)))
+        .add_component(
+            "DecreasingValueGuard",
+            Box::new(DecreasingValueGuard {
+                history_lengths: Arc::new(Mutex::new(vec![])),
+            }),
+        );
+
+    let items = vec![
+        batch_item(60, Some(0.5), None),
+        batch_item(900, Some(0.5), None),
+        batch_item(120, Some(1.5), None),
+        batch_item(60, None, None),
+        batch_item(60, Some(0.5), Some(2.0)),
+    ];
+
+    let stringify = |result: anyhow::Result<NegotiationResult>| result.map_err(|e| e.to_string());
+    let expected = items
+        .clone()
+        .into_iter()
+        .map(|(their, history, template, score)| {
+            stringify(pack.negotiate_step_with_history(&their, &history, template, score))
+        })
+        .collect::<Vec<_>>();
+    let results = pack
+        .negotiate_batch(items)
+        .into_iter()
+        .map(stringify)
+        .collect::<Vec<_>>();
+
+    assert_eq!(results, expected);
+    assert!(matches!(
+        results[0],
+        Ok(NegotiationResult::Negotiating { .. })
+    ));
+    assert!(matches!(results[1], Ok(NegotiationResult::Reject { .. })));
+    assert!(results[3].is_err());
+    // Rejected only because of history.
+    assert!(matches!(results[4], Ok(NegotiationResult::Reject { .. })));
+}
+
+/// Dry run should return components decision without putting Proposal
+/// into collection or sending any response.
+#[actix_rt::test]
+async fn test_dry_run_proposal() {
+    let mut config = example_config();
+    // Keep Proposals in collection, so we can check, if dry run added anything.
+    config.composite.proposals.collect_amount = Some(5);
+    config.composite.proposals.collect_period = Some(std::time::Duration::from_secs(60));
+
+    let test_dir = prepare_test_dir("test_dry_run_proposal").unwrap();
+    let (negotiator, mut callbacks) =
+        create_negotiator(config, serde_yaml::Value::Null, test_dir.clone(), test_dir).unwrap();
+
+    let offer = negotiator.create_offer(&example_offer()).await.unwrap();
+    let offer = proposal_from_demand(&offer);
+
+    let mut proposal = proposal_from_demand(&example_demand(
+        Utc::now() + chrono::Duration::seconds(50),
+        "net-1",
+    ));
+    proposal.proposal_id = "dry-run-proposal".to_string();
+
+    let before = negotiator.diagnostic_dump().await.unwrap();
+    match negotiator
+        .dry_run_proposal(&proposal, &offer)
+        .await
+        .unwrap()
+    {
+        NegotiationResult::Ready { .. } => {}
+        result => panic!("Expected Ready, got: {:?}", result),
+    }
+
+    let rejected = proposal_from_demand(&example_demand(
+        Utc::now() + chrono::Duration::seconds(900),
+        "net-1",
+    ));
+    match negotiator
+        .dry_run_proposal(&rejected, &offer)
+        .await
+        .unwrap()
+    {
+        NegotiationResult::Reject { reason, .. } => assert_eq!(
+            reason.code.as_deref(),
+            Some(expiration::EXPIRATION_OUT_OF_RANGE)
+        ),
+        result => panic!("Expected Reject, got: {:?}", result),
+    }
+
+    assert_eq!(negotiator.diagnostic_dump().await.unwrap(), before);
+    assert!(callbacks.proposal_channel.try_recv().is_err());
+
+    // The same Proposal negotiated for real, should land in collection.
+    negotiator
+        .react_to_proposal("", &proposal, &offer)
+        .await
+        .unwrap();
+    let dump = negotiator.diagnostic_dump().await.unwrap();
+    assert_eq!(
+        dump["collections"]["proposals"]["awaiting"][0]["id"],
+        serde_json::json!("dry-run-proposal")
+    );
+}
+
+/// Dry run shouldn't count Proposals against rate limit nor reserve Agreement slots.
+#[test]
+fn test_dry_run_stateful_builtins() {
+    let rate_limit = RateLimit::new(
+        serde_yaml::to_value(rate_limit::Config {
+            max_per_window: 1,
+            window: std::time::Duration::from_secs(60),
+        })
+        .unwrap(),
+    )
+    .unwrap();
+    let max_agreements = MaxAgreements::new(
+        serde_yaml::to_value(max_agreements::Config {
+            max_agreements: 1,
+            reservation_timeout: std::time::Duration::from_secs(60),
+        })
+        .unwrap(),
+    )
+    .unwrap();
+    let mut pack = NegotiatorsPack::new()
+        .add_component("RateLimit", Box::new(rate_limit))
+        .add_component("LimitAgreements", Box::new(max_agreements));
+
+    let requestor = "0x0000000000000000000000000000000000000001";
+    let first = proposal_from_node(requestor, "proposal-1");
+    let second = proposal_from_node(requestor, "proposal-2");
+    for their in [&first, &second, &first] {
+        let result = pack
+            .dry_run_step(their, &[], their.clone(), Score::default())
+            .unwrap();
+        assert!(
+            matches!(result, NegotiationResult::Ready { .. }),
+            "{:?}",
+            result
+        );
+    }
+    assert!(is_ready(&mut pack, &first));
+    assert!(!is_ready(&mut pack, &second));
+
+    for id in ["agreement-1", "agreement-2"] {
+        let their = accepted_proposal(id);
+        let result = pack
+            .dry_run_step(&their, &[], their.clone(), Score::default())
+            .unwrap();
+        assert!(
+            matches!(result, NegotiationResult::Ready { .. }),
+            "{:?}",
+            result
+        );
+    }
+    assert!(is_ready(&mut pack, &accepted_proposal("agreement-1")));
+    // Reservation of the first Agreement isn't released by dry run.
+    let their = accepted_proposal("agreement-2");
+    let result = pack
+        .dry_run_step(&their, &[], their.clone(), Score::default())
+        .unwrap();
+    assert!(
+        matches!(result, NegotiationResult::Reject { .. }),
+        "{:?}",
+        result
+    );
+    assert!(is_ready(&mut pack, &accepted_proposal("agreement-1")));
+}
+
+#[derive(Default)]
+struct RecordingMetrics {
+    steps: Mutex<Vec<(String, std::time::Duration, StepOutcome)>>,
+    decisions: Mutex<Vec<(String, usize, usize)>>,
+}
+
+impl Metrics for RecordingMetrics {
+    fn on_negotiate_step_done(
+        &self,
+        component: &str,
+        duration: std::time::Duration,
+        outcome: StepOutcome,
+    ) {
+        self.steps
+            .lock()
+            .unwrap()
+            .push((component.to_string(), duration, outcome));
+    }
+
+    fn on_decision(&self, collection: &str, accepted: usize, rejected: usize) {
+        self.decisions
+            .lock()
+            .unwrap()
+            .push((collection.to_string(), accepted, rejected));
+    }
+}
+
+#[actix_rt::test]
+async fn test_negotiation_metrics() {
+    let metrics = Arc::new(RecordingMetrics::default());
+    let components = NegotiatorsPack::new()
+        .add_component("LimitExpiration", Box::new(limit_expiration(None, None)));
+
+    let (negotiator, mut callbacks) = Negotiator::with_metrics(
+        components,
+        CompositeNegotiatorConfig::default_test(),
+        metrics.clone(),
+    );
+    let negotiator = NegotiatorAddr::from(negotiator);
+
+    let mut our = proposal_from_demand(&example_demand(
+        Utc::now() + chrono::Duration::seconds(50),
+        "net-1",
+    ));
+    our.proposal_id = "our-0".to_string();
+
+    let mut their = our.clone();
+    their.proposal_id = "their-0".to_string();
+    their.prev_proposal_id = Some(our.proposal_id.clone());
+
+    negotiator
+        .react_to_proposal("", &their, &our)
+        .await
+        .unwrap();
+    match callbacks.proposal_channel.recv().await {
+        Some(ProposalAction::AcceptProposal { .. }) => {}
+        action => panic!("Unexpected action: {:?}", action),
+    }
+
+    let steps = metrics.steps.lock().unwrap().clone();
+    assert_eq!(steps.len(), 1);
+    assert_eq!(steps[0].0, "LimitExpiration");
+    assert_eq!(steps[0].2, StepOutcome::Ready);
+    assert!(steps[0].1 < std::time::Duration::from_secs(1));
+
+    assert_eq!(
+        *metrics.decisions.lock().unwrap(),
+        vec![("Proposal".to_string(), 1, 0)]
+    );
+}
+
+/// Fails evaluating each Proposal.
+struct EmitErrors;
+
+impl NegotiatorComponent for EmitErrors {
+    fn negotiate_step(
+        &mut self,
+        _their: &ProposalView,
+        _template: ProposalView,
+        _score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        Err(anyhow::anyhow!("Emitted error"))
+    }
+}
+
+fn negotiate_with_policy(policy: ErrorPolicy) -> anyhow::Result<NegotiationResult> {
+    let their = ProposalView::try_from(&proposal_from_demand(&example_demand(
+        Utc::now() + chrono::Duration::seconds(50),
+        "net-1",
+    )))
+    .unwrap();
+
+    NegotiatorsPack::new()
+        .add_component("EmitErrors", Box::new(EmitErrors))
+        .add_component("LimitExpiration", Box::new(limit_expiration(None, None)))
+        .error_policy(policy)
+        .negotiate_step(&their, their.clone(), Score::default())
+}
+
+#[test]
+fn test_error_policy_fail_fast() {
+    let error = negotiate_with_policy(ErrorPolicy::FailFast).unwrap_err();
+    assert_eq!(error.to_string(), "Emitted error");
+}
+
+#[test]
+fn test_error_policy_reject_on_error() {
+    match negotiate_with_policy(ErrorPolicy::RejectOnError).unwrap() {
+        NegotiationResult::Reject { reason, is_final } => {
+            assert!(!is_final);
+            assert_eq!(reason.code.as_deref(), Some("COMPONENT_ERROR"));
+            assert!(reason.message.contains("EmitErrors"));
+            assert!(reason.message.contains("Emitted error"));
+        }
+        result => panic!("Expected Reject, got: {:?}", result),
+    }
+}
+
+#[test]
+fn test_error_policy_skip_component() {
+    match negotiate_with_policy(ErrorPolicy::SkipComponent).unwrap() {
+        NegotiationResult::Ready { .. } => {}
+        result => panic!("Expected Ready, got: {:?}", result),
+    }
+}
+
+fn agreement_from(id: &str, demand: &Proposal, offer: &Proposal) -> AgreementView {
+    let agreement = Agreement {
+        agreement_id: id.to_string(),
+        demand: Demand {
+            properties: demand.properties.clone(),
+            constraints: demand.constraints.clone(),
+            demand_id: format!("{}-demand", id),
+            requestor_id: demand.issuer_id,
+            timestamp: demand.timestamp,
+        },
+        offer: Offer {
+            properties: offer.properties.clone(),
+            constraints: offer.constraints.clone(),
+            offer_id: format!("{}-offer", id),
+            provider_id: offer.issuer_id,
+            timestamp: offer.timestamp,
+        },
+        valid_to: Utc::now() + chrono::Duration::minutes(20),
+        approved_date: None,
+        state: AgreementState::Proposal,
+        timestamp: Utc::now(),
+        app_session_id: None,
+        proposed_signature: None,
+        approved_signature: None,
+        committed_signature: None,
+    };
+    AgreementView::try_from(&agreement).unwrap()
+}
+
+/// When approved Agreement isn't signed, Negotiator should choose another
+/// candidate in it's place.
+#[actix_rt::test]
+async fn test_reconsider_agreements_after_approval_failure() {
+    let mut config = example_config();
+    // LimitAgreements would reject second Agreement before it gets to collection.
+    config
+        .negotiators
+        .retain(|negotiator| negotiator.name != "LimitAgreements");
+    config.composite.agreements.collect_amount = Some(2);
+    config.composite.agreements.collect_period = Some(std::time::Duration::from_secs(60));
+
+    let test_dir = prepare_test_dir("test_reconsider_agreements_after_approval_failure").unwrap();
+    let (negotiator, mut callbacks) =
+        create_negotiator(config, serde_yaml::Value::Null, test_dir.clone(), test_dir).unwrap();
+
+    let offer = negotiator.create_offer(&example_offer()).await.unwrap();
+    let offer = proposal_from_demand(&offer);
+    let demand = proposal_from_demand(&example_demand(
+        Utc::now() + chrono::Duration::seconds(50),
+        "net-1",
+    ));
+
+    for id in ["agreement-1", "agreement-2"] {
+        negotiator
+            .react_to_agreement("", &agreement_from(id, &demand, &offer))
+            .await
+            .unwrap();
+    }
+
+    match callbacks.agreement_channel.recv().await {
+        Some(AgreementAction::ApproveAgreement { id, .. }) => assert_eq!(id, "agreement-1"),
+        action => panic!("Unexpected action: {:?}", action),
+    }
+
+    // Second Agreement can't be rejected, while first one waits for approval.
+    negotiator.agreement_rejected("agreement-1").await.unwrap();
+
+    match callbacks.agreement_channel.recv().await {
+        Some(AgreementAction::ApproveAgreement { id, .. }) => assert_eq!(id, "agreement-2"),
+        action => panic!("Unexpected action: {:?}", action),
+    }
+}
+
+/// Agreements kept pending during approval should be rejected, when approved
+/// Agreement is signed.
+#[actix_rt::test]
+async fn test_pending_agreements_rejected_after_approval() {
+    let mut config = example_config();
+    config
+        .negotiators
+        .retain(|negotiator| negotiator.name != "LimitAgreements");
+    config.composite.agreements.collect_amount = Some(2);
+    config.composite.agreements.collect_period = Some(std::time::Duration::from_secs(60));
+
+    let test_dir = prepare_test_dir("test_pending_agreements_rejected_after_approval").unwrap();
+    let (negotiator, mut callbacks) =
+        create_negotiator(config, serde_yaml::Value::Null, test_dir.clone(), test_dir).unwrap();
+
+    let offer = negotiator.create_offer(&example_offer()).await.unwrap();
+    let offer = proposal_from_demand(&offer);
+    let demand = proposal_from_demand(&example_demand(
+        Utc::now() + chrono::Duration::seconds(50),
+        "net-1",
+    ));
+
+    for id in ["agreement-1", "agreement-2"] {
+        negotiator
+            .react_to_agreement("", &agreement_from(id, &demand, &offer))
+            .await
+            .unwrap();
+    }
+
+    match callbacks.agreement_channel.recv().await {
+        Some(AgreementAction::ApproveAgreement { id, .. }) => assert_eq!(id, "agreement-1"),
+        action => panic!("Unexpected action: {:?}", action),
+    }
+
+    negotiator
+        .agreement_signed(&agreement_from("agreement-1", &demand, &offer))
+        .await
+        .unwrap();
+
+    match callbacks.agreement_channel.recv().await {
+        Some(AgreementAction::RejectAgreement { id, .. }) => assert_eq!(id, "agreement-2"),
+        action => panic!("Unexpected action: {:?}", action),
+    }
+}
+
+/// Reservation of Agreement rejected by our collection should be released, so
+/// new Proposals can take its slot right away.
+#[actix_rt::test]
+async fn test_max_agreements_releases_slot_of_rejected_agreement() {
+    let limit_conf = NegotiatorConfig {
+        name: "LimitAgreements".to_string(),
+        load_mode: LoadMode::BuiltIn,
+        params: serde_yaml::to_value(max_agreements::Config {
+            max_agreements: 2,
+            reservation_timeout: std::time::Duration::from_secs(60),
+        })
+        .unwrap(),
+        enabled: true,
+        priority: 0,
+    };
+    let mut config = NegotiatorsConfig {
+        negotiators: vec![limit_conf],
+        composite: CompositeNegotiatorConfig::default_test(),
+    };
+    config.composite.agreements.collect_amount = Some(2);
+    config.composite.agreements.collect_period = Some(std::time::Duration::from_secs(60));
+
+    let test_dir =
+        prepare_test_dir("test_max_agreements_releases_slot_of_rejected_agreement").unwrap();
+    let (negotiator, mut callbacks) =
+        create_negotiator(config, serde_yaml::Value::Null, test_dir.clone(), test_dir).unwrap();
+
+    let offer = negotiator.create_offer(&example_offer()).await.unwrap();
+    let offer = proposal_from_demand(&offer);
+    let demand = proposal_from_demand(&example_demand(
+        Utc::now() + chrono::Duration::seconds(50),
+        "net-1",
+    ));
+
+    for id in ["agreement-1", "agreement-2"] {
+        negotiator
+            .react_to_agreement("", &agreement_from(id, &demand, &offer))
+            .await
+            .unwrap();
+    }
+
+    match callbacks.agreement_channel.recv().await {
+        Some(AgreementAction::ApproveAgreement { id, .. }) => assert_eq!(id, "agreement-1"),
+        action => panic!("Unexpected action: {:?}", action),
+    }
+    negotiator
+        .agreement_signed(&agreement_from("agreement-1", &demand, &offer))
+        .await
+        .unwrap();
+    match callbacks.agreement_channel.recv().await {
+        Some(AgreementAction::RejectAgreement { id, .. }) => assert_eq!(id, "agreement-2"),
+        action => panic!("Unexpected action: {:?}", action),
+    }
+
+    // Only signed Agreement occupies slot now.
+    let mut their = demand.clone();
+    their.proposal_id = "proposal-3".to_string();
+    their.state = State::Initial;
+    negotiator
+        .react_to_proposal("", &their, &offer)
+        .await
+        .unwrap();
+    match callbacks.proposal_channel.recv().await {
+        Some(ProposalAction::CounterProposal { id, .. }) => assert_eq!(id, "proposal-3"),
+        action => panic!("Unexpected action: {:?}", action),
+    }
+}
+
+/// Counts batches passed to `rescore_batch`.
+struct CountRescores(Arc<Mutex<usize>>);
+
+impl NegotiatorComponent for CountRescores {
+    fn rescore_batch(&mut self, _proposals: &mut [ProposalScore]) -> anyhow::Result<()> {
+        *self.0.lock().unwrap() += 1;
+        Ok(())
+    }
+}
+
+/// Agreements reconsidered after approval failure should be rescored before
+/// choosing another one.
+#[actix_rt::test]
+async fn test_reconsider_rescores_agreements() {
+    let rescores = Arc::new(Mutex::new(0));
+    let pack = NegotiatorsPack::new()
+        .add_component("CountRescores", Box::new(CountRescores(rescores.clone())));
+    let mut config = CompositeNegotiatorConfig::default_test();
+    config.agreements.collect_amount = Some(2);
+    config.agreements.collect_period = Some(std::time::Duration::from_secs(60));
+
+    let (negotiator, mut callbacks) = Negotiator::new(pack, config);
+    let negotiator = NegotiatorAddr::from(negotiator);
+
+    let offer = negotiator.create_offer(&example_offer()).await.unwrap();
+    let offer = proposal_from_demand(&offer);
+    let demand = proposal_from_demand(&example_demand(
+        Utc::now() + chrono::Duration::seconds(50),
+        "net-1",
+    ));
+    for id in ["agreement-1", "agreement-2"] {
+        negotiator
+            .react_to_agreement("", &agreement_from(id, &demand, &offer))
+            .await
+            .unwrap();
+    }
+
+    match callbacks.agreement_channel.recv().await {
+        Some(AgreementAction::ApproveAgreement { id, .. }) => assert_eq!(id, "agreement-1"),
+        action => panic!("Unexpected action: {:?}", action),
+    }
+    assert_eq!(*rescores.lock().unwrap(), 1);
+
+    negotiator.agreement_rejected("agreement-1").await.unwrap();
+    match callbacks.agreement_channel.recv().await {
+        Some(AgreementAction::ApproveAgreement { id, .. }) => assert_eq!(id, "agreement-2"),
+        action => panic!("Unexpected action: {:?}", action),
+    }
+    assert_eq!(*rescores.lock().unwrap(), 2);
+}
+
+/// `MaxAgreements` should request re-evaluation of Proposals rejected due to lack
+/// of capacity, when Agreement is terminated and slot is freed.
+#[actix_rt::test]
+async fn test_reevaluate_parked_proposal() {
+    let limit_conf = NegotiatorConfig {
+        name: "LimitAgreements".to_string(),
+        load_mode: LoadMode::BuiltIn,
+        params: serde_yaml::to_value(max_agreements::Config {
+            max_agreements: 1,
+            reservation_timeout: std::time::Duration::from_secs(60),
+        })
+        .unwrap(),
+        enabled: true,
+        priority: 0,
+    };
+    let config = NegotiatorsConfig {
+        negotiators: vec![limit_conf],
+        composite: CompositeNegotiatorConfig::default_test(),
+    };
+
+    let test_dir = prepare_test_dir("test_reevaluate_parked_proposal").unwrap();
+    let (
+        negotiator,
+        NegotiatorCallbacks {
+            proposal_channel: mut proposals,
+            agreement_channel: _agreements,
+        },
+    ) = create_negotiator(config, serde_yaml::Value::Null, test_dir.clone(), test_dir).unwrap();
+
+    let offer = negotiator.create_offer(&example_offer()).await.unwrap();
+    let offer = proposal_from_demand(&offer);
+    let mut proposal = proposal_from_demand(&example_demand(
+        Utc::now() + chrono::Duration::seconds(50),
+        "net-1",
+    ));
+    proposal.proposal_id = "parked-proposal".to_string();
+
+    // Occupy the only slot.
+    negotiator
+        .agreement_signed(&agreement_from("agreement-1", &proposal, &offer))
+        .await
+        .unwrap();
+
+    negotiator
+        .react_to_proposal("", &proposal, &offer)
+        .await
+        .unwrap();
+
+    let reason = rejection_reason(proposals.recv().await);
+    assert_eq!(reason.code.as_deref(), Some(max_agreements::NO_CAPACITY));
+
+    negotiator
+        .agreement_finalized("agreement-1", AgreementResult::ClosedByUs)
+        .await
+        .unwrap();
+
+    match proposals.recv().await {
+        Some(ProposalAction::AcceptProposal { id, .. }) => assert_eq!(id, "parked-proposal"),
+        action => panic!("Expected AcceptProposal, got: {:?}", action),
+    }
+}
+
+/// Negotiations should continue in new Negotiator restored from snapshot.
+#[actix_rt::test]
+async fn test_snapshot_restore() {
+    let mut config = example_config();
+    // Keep first Proposal in collection until the second one arrives.
+    config.composite.proposals.collect_amount = Some(2);
+    config.composite.proposals.collect_period = Some(std::time::Duration::from_secs(60));
+
+    let test_dir = prepare_test_dir("test_snapshot_restore").unwrap();
+    let (negotiator, _callbacks) = create_negotiator(
+        config.clone(),
+        serde_yaml::Value::Null,
+        test_dir.clone(),
+        test_dir.clone(),
+    )
+    .unwrap();
+
+    let offer = negotiator.create_offer(&example_offer()).await.unwrap();
+    let offer = proposal_from_demand(&offer);
+
+    let proposal = |id: &str| {
+        let mut proposal = proposal_from_demand(&example_demand(
+            Utc::now() + chrono::Duration::seconds(50),
+            "net-1",
+        ));
+        proposal.proposal_id = id.to_string();
+        proposal
+    };
+
+    negotiator
+        .react_to_proposal("subscription-1", &proposal("proposal-1"), &offer)
+        .await
+        .unwrap();
+
+    // State should survive saving to disk.
+    let state = serde_json::to_string(&negotiator.snapshot().await.unwrap()).unwrap();
+    negotiator
+        .shutdown(std::time::Duration::from_secs(1))
+        .await
+        .unwrap();
+
+    let (
+        negotiator,
+        NegotiatorCallbacks {
+            proposal_channel: mut proposals,
+            agreement_channel: mut agreements,
+        },
+    ) = create_negotiator(config, serde_yaml::Value::Null, test_dir.clone(), test_dir).unwrap();
+    negotiator
+        .restore(serde_json::from_str(&state).unwrap())
+        .await
+        .unwrap();
+    negotiator.create_offer(&example_offer()).await.unwrap();
+
+    negotiator
+        .react_to_proposal("subscription-2", &proposal("proposal-2"), &offer)
+        .await
+        .unwrap();
+
+    let mut accepted = vec![];
+    for _ in 0..2 {
+        match proposals.recv().await {
+            Some(ProposalAction::AcceptProposal {
+                id,
+                subscription_id,
+            }) => accepted.push((id, subscription_id)),
+            action => panic!("Expected AcceptProposal, got: {:?}", action),
+        }
+    }
+    accepted.sort();
+    assert_eq!(
+        accepted,
+        vec![
+            ("proposal-1".to_string(), "subscription-1".to_string()),
+            ("proposal-2".to_string(), "subscription-2".to_string()),
+        ]
+    );
+
+    negotiator
+        .react_to_agreement(
+            "subscription-1",
+            &agreement_from("agreement-1", &proposal("proposal-1"), &offer),
+        )
+        .await
+        .unwrap();
+    match agreements.recv().await {
+        Some(AgreementAction::ApproveAgreement { id, .. }) => assert_eq!(id, "agreement-1"),
+        action => panic!("Expected ApproveAgreement, got: {:?}", action),
+    }
+}
+
+/// State of countered negotiations should be dropped, when other party rejects
+/// our Proposal or their last Proposal expires.
+#[actix_rt::test]
+async fn test_negotiation_state_pruned() {
+    let (negotiator, _callbacks) = Negotiator::new(
+        NegotiatorsPack::new(),
+        CompositeNegotiatorConfig::default_test(),
+    );
+    let negotiator = NegotiatorAddr::from(negotiator);
+
+    let offer = negotiator.create_offer(&example_offer()).await.unwrap();
+    let offer = proposal_from_demand(&offer);
+    let initial = |id: &str, expiration: DateTime<Utc>| {
+        let mut proposal = proposal_from_demand(&example_demand(expiration, "net-1"));
+        proposal.proposal_id = id.to_string();
+        proposal.state = State::Initial;
+        proposal
+    };
+
+    let expiration = Utc::now() + chrono::Duration::seconds(50);
+    negotiator
+        .react_to_proposal("", &initial("rejected", expiration), &offer)
+        .await
+        .unwrap();
+    let state = negotiator.snapshot().await.unwrap();
+    assert!(state.rounds.contains_key("rejected"));
+    assert!(state.subscriptions.contains_key("rejected"));
+
+    negotiator
+        .proposal_rejected("rejected", &None)
+        .await
+        .unwrap();
+    let state = negotiator.snapshot().await.unwrap();
+    assert!(state.scores.is_empty());
+    assert!(state.rounds.is_empty());
+    assert!(state.histories.is_empty());
+    assert!(state.subscriptions.is_empty());
+
+    let expired = Utc::now() - chrono::Duration::seconds(1);
+    negotiator
+        .react_to_proposal("", &initial("expired", expired), &offer)
+        .await
+        .unwrap();
+    assert!(negotiator
+        .snapshot()
+        .await
+        .unwrap()
+        .rounds
+        .contains_key("expired"));
+
+    // Expired negotiations are swept, when next Proposal arrives.
+    negotiator
+        .react_to_proposal("", &initial("next", expiration), &offer)
+        .await
+        .unwrap();
+    let state = negotiator.snapshot().await.unwrap();
+    assert!(!state.rounds.contains_key("expired"));
+    assert!(!state.subscriptions.contains_key("expired"));
+    assert!(state.rounds.contains_key("next"));
+}
+
+/// Event sink should observe whole negotiation in order.
+#[actix_rt::test]
+async fn test_negotiation_events() {
+    let test_dir = prepare_test_dir("test_negotiation_events").unwrap();
+    let (negotiator, mut callbacks) = create_negotiator(
+        example_config(),
+        serde_yaml::Value::Null,
+        test_dir.clone(),
+        test_dir,
+    )
+    .unwrap();
+
+    let (sink, mut events) = tokio::sync::mpsc::unbounded_channel();
+    negotiator.set_event_sink(sink).await.unwrap();
+
+    let offer = negotiator.create_offer(&example_offer()).await.unwrap();
+    let offer = proposal_from_demand(&offer);
+    let mut proposal = proposal_from_demand(&example_demand(
+        Utc::now() + chrono::Duration::seconds(50),
+        "net-1",
+    ));
+    proposal.proposal_id = "proposal-1".to_string();
+
+    negotiator
+        .react_to_proposal("", &proposal, &offer)
+        .await
+        .unwrap();
+    callbacks.proposal_channel.recv().await.unwrap();
+
+    let agreement = agreement_from("agreement-1", &proposal, &offer);
+    negotiator.react_to_agreement("", &agreement).await.unwrap();
+    callbacks.agreement_channel.recv().await.unwrap();
+
+    negotiator.agreement_signed(&agreement).await.unwrap();
+    negotiator
+        .agreement_finalized("agreement-1", AgreementResult::ClosedByUs)
+        .await
+        .unwrap();
+
+    match events.recv().await {
+        Some(NegotiationEvent::OfferPublished { .. }) => (),
+        event => panic!("Expected OfferPublished, got: {:?}", event),
+    }
+    match events.recv().await {
+        Some(NegotiationEvent::ProposalDecision(ProposalAction::AcceptProposal { id, .. })) => {
+            assert_eq!(id, "proposal-1")
+        }
+        event => panic!("Expected AcceptProposal decision, got: {:?}", event),
+    }
+    match events.recv().await {
+        Some(NegotiationEvent::AgreementDecision(AgreementAction::ApproveAgreement {
+            id, ..
+        })) => assert_eq!(id, "agreement-1"),
+        event => panic!("Expected ApproveAgreement decision, got: {:?}", event),
+    }
+    match events.recv().await {
+        Some(NegotiationEvent::AgreementSigned { agreement_id }) => {
+            assert_eq!(agreement_id, "agreement-1")
+        }
+        event => panic!("Expected AgreementSigned, got: {:?}", event),
+    }
+    match events.recv().await {
+        Some(NegotiationEvent::AgreementFinalized {
+            agreement_id,
+            result,
+        }) => {
+            assert_eq!(agreement_id, "agreement-1");
+            assert_eq!(result, AgreementResult::ClosedByUs);
+        }
+        event => panic!("Expected AgreementFinalized, got: {:?}", event),
+    }
+}
+
+/// Writes its params to file in working dir, like components persisting state.
+struct PersistParams;
+
+impl NegotiatorComponent for PersistParams {}
+
+/// Multiple instances of the same negotiator should get separate working dirs,
+/// which are removed on shutdown, if configured.
+#[actix_rt::test]
+async fn test_working_dir_isolation() {
+    register_negotiator(
+        "test-working-dir",
+        "PersistParams",
+        Box::new(|params, _, working_dir| {
+            std::fs::write(
+                working_dir.join("state.yaml"),
+                serde_yaml::to_string(&params)?,
+            )?;
+            Ok(Box::new(PersistParams))
+        }),
+    );
+
+    let mut composite = CompositeNegotiatorConfig::default_test();
+    composite.cleanup_working_dirs = true;
+    let config = NegotiatorsConfigBuilder::new()
+        .static_lib("test-working-dir", "PersistParams", "first")
+        .unwrap()
+        .static_lib("test-working-dir", "PersistParams", "second")
+        .unwrap()
+        .composite(composite)
+        .build();
+
+    let test_dir = prepare_test_dir("test_working_dir_isolation").unwrap();
+    let (negotiator, _callbacks) = create_negotiator(
+        config,
+        serde_yaml::Value::Null,
+        test_dir.clone(),
+        test_dir.clone(),
+    )
+    .unwrap();
+
+    let read_state = |name: &str| -> String {
+        let content = std::fs::read_to_string(test_dir.join(name).join("state.yaml")).unwrap();
+        serde_yaml::from_str(&content).unwrap()
+    };
+    assert_eq!(read_state("PersistParams"), "first");
+    assert_eq!(read_state("PersistParams#1"), "second");
+
+    negotiator
+        .shutdown(std::time::Duration::from_secs(1))
+        .await
+        .unwrap();
+    assert!(!test_dir.join("PersistParams").exists());
+    assert!(!test_dir.join("PersistParams#1").exists());
+}
+
+/// Prefers Proposals with higher `test.rank`, but Agreements with lower one.
+struct PhaseScorer;
+
+impl NegotiatorComponent for PhaseScorer {
+    fn negotiate_step(
+        &mut self,
+        their: &ProposalView,
+        template: ProposalView,
+        mut score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        let rank = their.pointer_typed::<f64>("/test/rank")?;
+        score.set_property("final-score", serde_json::json!(rank));
+        if their.state == State::Accepted {
+            score.set_property("agreement-score", serde_json::json!(-rank));
+        }
+        Ok(NegotiationResult::Ready {
+            proposal: template,
+            score,
+        })
+    }
+}
+
+async fn chosen_agreement(score_pointer: &str, pack: NegotiatorsPack) -> String {
+    let mut config = CompositeNegotiatorConfig::default_test();
+    config.agreements.collect_amount = Some(2);
+    config.agreements.collect_period = Some(std::time::Duration::from_secs(60));
+    config.agreements.score_pointer = score_pointer.to_string();
+
+    let (negotiator, mut callbacks) = Negotiator::new(pack, config);
+    let negotiator = NegotiatorAddr::from(negotiator);
+
+    let offer = negotiator.create_offer(&example_offer()).await.unwrap();
+    let offer = proposal_from_demand(&offer);
+    for (id, rank) in [("agreement-1", 1.0), ("agreement-2", 2.0)] {
+        let mut demand = proposal_from_demand(&example_demand(
+            Utc::now() + chrono::Duration::seconds(50),
+            "net-1",
+        ));
+        demand.properties["test.rank"] = serde_json::json!(rank);
+        negotiator
+            .react_to_agreement("", &agreement_from(id, &demand, &offer))
+            .await
+            .unwrap();
+    }
+
+    match callbacks.agreement_channel.recv().await {
+        Some(AgreementAction::ApproveAgreement { id, .. }) => id,
+        action => panic!("Expected ApproveAgreement, got: {:?}", action),
+    }
+}
+
+/// Agreements can be chosen based on different score than Proposals.
+#[actix_rt::test]
+async fn test_agreement_score_pointer() {
+    let pack = || NegotiatorsPack::new().add_component("PhaseScorer", Box::new(PhaseScorer));
+    assert_eq!(
+        chosen_agreement("/final-score", pack()).await,
+        "agreement-2"
+    );
+    assert_eq!(
+        chosen_agreement("/agreement-score", pack()).await,
+        "agreement-1"
+    );
+}
+
+/// Treats `test.rank` as price. Scores are normalized to [0, 1] across
+/// the whole batch, so the cheapest Proposal gets 1.0.
+struct NormalizePrice;
+
+impl NegotiatorComponent for NormalizePrice {
+    fn rescore_batch(&mut self, proposals: &mut [ProposalScore]) -> anyhow::Result<()> {
+        let prices = proposals
+            .iter()
+            .map(|proposal| proposal.their.pointer_typed::<f64>("/test/rank"))
+            .collect::<Result<Vec<_>, _>>()?;
+        let min = prices.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max = prices.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+
+        for (proposal, price) in proposals.iter_mut().zip(prices) {
+            proposal.score = match max > min {
+                true => (max - price) / (max - min),
+                false => 1.0,
+            };
+            assert!((0.0..=1.0).contains(&proposal.score));
+        }
+        Ok(())
+    }
+}
+
+/// Scores relative to the whole batch should change which Agreement is chosen.
+#[actix_rt::test]
+async fn test_rescore_batch_changes_selection() {
+    let pack = NegotiatorsPack::new()
+        .add_component("PhaseScorer", Box::new(PhaseScorer))
+        .add_component("NormalizePrice", Box::new(NormalizePrice));
+    assert_eq!(chosen_agreement("/final-score", pack).await, "agreement-1");
+}
+
+/// `NegotiatorEngine` should make decisions without actix runtime.
+#[test]
+fn test_engine_without_runtime() {
+    let expiration = LimitExpiration::new(
+        serde_yaml::to_value(expiration::Config {
+            min_expiration: std::time::Duration::from_secs(30),
+            max_expiration: std::time::Duration::from_secs(300),
+            max_debit_note_accept_timeout: None,
+            min_agreement_expiration: None,
+        })
+        .unwrap(),
+    )
+    .unwrap();
+    let pack = NegotiatorsPack::new().add_component("LimitExpiration", Box::new(expiration));
+    let mut engine = NegotiatorEngine::new(pack, CompositeNegotiatorConfig::default_test());
+
+    let offer = engine.create_offer(&example_offer()).unwrap();
+    let offer = proposal_from_demand(&offer);
+
+    let mut proposal = proposal_from_demand(&example_demand(
+        Utc::now() + chrono::Duration::seconds(50),
+        "net-1",
+    ));
+    proposal.proposal_id = "proposal-1".to_string();
+    match engine
+        .react_to_proposal("sub", &proposal, &offer)
+        .unwrap()
+        .as_slice()
+    {
+        [ProposalAction::AcceptProposal { id, .. }] => assert_eq!(id, "proposal-1"),
+        action => panic!("Expected AcceptProposal, got: {:?}", action),
+    }
+
+    let mut expired = proposal_from_demand(&example_demand(
+        Utc::now() + chrono::Duration::seconds(10),
+        "net-1",
+    ));
+    expired.proposal_id = "proposal-2".to_string();
+    match engine
+        .react_to_proposal("sub", &expired, &offer)
+        .unwrap()
+        .as_slice()
+    {
+        [ProposalAction::RejectProposal { id, .. }] => assert_eq!(id, "proposal-2"),
+        action => panic!("Expected RejectProposal, got: {:?}", action),
+    }
+
+    let agreement = agreement_from("agreement-1", &proposal, &offer);
+    match engine
+        .react_to_agreement("sub", &agreement)
+        .unwrap()
+        .as_slice()
+    {
+        [AgreementAction::ApproveAgreement { id, .. }] => assert_eq!(id, "agreement-1"),
+        action => panic!("Expected ApproveAgreement, got: {:?}", action),
+    }
+
+    // Agreements goal is reached, so second Agreement can't be approved.
+    let mut second = proposal_from_demand(&example_demand(
+        Utc::now() + chrono::Duration::seconds(50),
+        "net-1",
+    ));
+    second.proposal_id = "proposal-3".to_string();
+    let second = agreement_from("agreement-2", &second, &offer);
+    assert!(engine
+        .react_to_agreement("sub", &second)
+        .unwrap()
+        .is_empty());
+
+    match engine.agreement_signed(&agreement).unwrap().as_slice() {
+        [AgreementAction::RejectAgreement { id, .. }] => assert_eq!(id, "agreement-2"),
+        action => panic!("Expected RejectAgreement, got: {:?}", action),
+    }
+    assert!(engine
+        .agreement_finalized("agreement-1", &AgreementResult::ClosedByUs)
+        .unwrap()
+        .is_empty());
+}
+
+/// Explicitly accepts Proposals from `net-1` subnet and is ready for others.
+struct AcceptSubnet;
+
+impl NegotiatorComponent for AcceptSubnet {
+    fn negotiate_step(
+        &mut self,
+        their: &ProposalView,
+        template: ProposalView,
+        score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        Ok(
+            match their.pointer_typed::<String>("/golem/node/debug/subnet")? == "net-1" {
+                true => NegotiationResult::Accept {
+                    proposal: template,
+                    score,
+                },
+                false => NegotiationResult::Ready {
+                    proposal: template,
+                    score,
+                },
+            },
+        )
+    }
+}
+
+/// With `explicit_accept` Draft Proposals should be promoted only, if any component
+/// returned `Accept`, and countered, if components are only `Ready`.
+#[test]
+fn test_explicit_accept() {
+    let pack = NegotiatorsPack::new()
+        .add_component(
+            "AcceptAll",
+            Box::new(AcceptAll::new(serde_yaml::Value::Null).unwrap()),
+        )
+        .add_component("AcceptSubnet", Box::new(AcceptSubnet));
+    let mut config = CompositeNegotiatorConfig::default_test();
+    config.explicit_accept = true;
+    let mut engine = NegotiatorEngine::new(pack, config);
+
+    let offer = engine.create_offer(&example_offer()).unwrap();
+    let offer = proposal_from_demand(&offer);
+    let deadline = Utc::now() + chrono::Duration::seconds(50);
+
+    let mut accepted = proposal_from_demand(&example_demand(deadline, "net-1"));
+    accepted.proposal_id = "proposal-1".to_string();
+    match engine
+        .react_to_proposal("sub", &accepted, &offer)
+        .unwrap()
+        .as_slice()
+    {
+        [ProposalAction::AcceptProposal { id, .. }] => assert_eq!(id, "proposal-1"),
+        action => panic!("Expected AcceptProposal, got: {:?}", action),
+    }
+
+    let mut ready = proposal_from_demand(&example_demand(deadline, "net-2"));
+    ready.proposal_id = "proposal-2".to_string();
+    match engine
+        .react_to_proposal("sub", &ready, &offer)
+        .unwrap()
+        .as_slice()
+    {
+        [ProposalAction::CounterProposal { id, .. }] => assert_eq!(id, "proposal-2"),
+        action => panic!("Expected CounterProposal, got: {:?}", action),
+    }
+}
+
+/// With `explicit_accept` and only `Ready` components Draft Proposals are countered
+/// with unchanged Proposals, so negotiations must be ended by `reject_no_progress`
+/// or `max_rounds`.
+#[test]
+fn test_explicit_accept_ready_chain_ends() {
+    let engine = |reject_no_progress, max_rounds| {
+        let pack = NegotiatorsPack::new().add_component(
+            "AcceptAll",
+            Box::new(AcceptAll::new(serde_yaml::Value::Null).unwrap()),
+        );
+        let mut config = CompositeNegotiatorConfig::default_test();
+        config.explicit_accept = true;
+        config.reject_no_progress = reject_no_progress;
+        config.max_rounds = max_rounds;
+        NegotiatorEngine::new(pack, config)
+    };
+    let deadline = Utc::now() + chrono::Duration::seconds(50);
+
+    for (mut engine, expected_code) in vec![
+        (engine(true, None), NO_PROGRESS),
+        (engine(false, Some(3)), TOO_MANY_ROUNDS),
+    ] {
+        let mut our = proposal_from_demand(&engine.create_offer(&example_offer()).unwrap());
+        our.proposal_id = "our-0".to_string();
+
+        let mut their = proposal_from_demand(&example_demand(deadline, "net-1"));
+        their.state = State::Initial;
+
+        let mut ended = false;
+        for round in 0..10 {
+            their.proposal_id = format!("their-{}", round);
+            their.prev_proposal_id = Some(our.proposal_id.clone());
+
+            match engine
+                .react_to_proposal("sub", &their, &our)
+                .unwrap()
+                .as_slice()
+            {
+                [ProposalAction::CounterProposal { proposal, .. }] => {
+                    our.properties = proposal.properties.clone();
+                }
+                [ProposalAction::RejectProposal { reason, .. }] => {
+                    let reason = RejectReason::from(reason.clone().unwrap());
+                    assert_eq!(reason.code.as_deref(), Some(expected_code));
+                    ended = true;
+                    break;
+                }
+                action => panic!("Unexpected action in round {}: {:?}", round, action),
+            }
+
+            their.state = State::Draft;
+            their.properties = our.properties.clone();
+            our.prev_proposal_id = Some(their.proposal_id.clone());
+            our.proposal_id = format!("our-{}", round + 1);
+        }
+        assert!(
+            ended,
+            "Negotiations ending with {} didn't end.",
+            expected_code
+        );
+    }
+}
+
+/// Pack should return `Accept`, if all components are ready and any of them accepted
+/// Proposal explicitly, but negotiating component still takes precedence.
+#[test]
+fn test_pack_combines_accept() {
+    let their = ProposalView::try_from(&proposal_from_demand(&example_demand(
+        Utc::now() + chrono::Duration::seconds(50),
+        "net-1",
+    )))
+    .unwrap();
+
+    let mut pack = NegotiatorsPack::new()
+        .add_component(
+            "AcceptAll",
+            Box::new(AcceptAll::new(serde_yaml::Value::Null).unwrap()),
+        )
+        .add_component("AcceptSubnet", Box::new(AcceptSubnet));
+    assert!(matches!(
+        pack.negotiate_step(&their, their.clone(), Score::default())
+            .unwrap(),
+        NegotiationResult::Accept { .. }
+    ));
+
+    let calls = Arc::new(Mutex::new(0));
+    let mut pack = NegotiatorsPack::new()
+        .add_component("AcceptSubnet", Box::new(AcceptSubnet))
+        .add_component("Checker", Box::new(CountingChecker { calls }));
+    assert!(matches!(
+        pack.negotiate_step(&their, their.clone(), Score::default())
+            .unwrap(),
+        NegotiationResult::Negotiating { .. }
+    ));
+}
@@ -2,9 +2,12 @@ use chrono::{DateTime, Duration, Utc};
 
 use ya_agreement_utils::{InfNodeInfo, NodeInfo, OfferDefinition, OfferTemplate, ServiceInfo};
 use ya_builtin_negotiators::*;
+use ya_negotiators::component::{
+    register_negotiator, NegotiationResult, NegotiatorComponent, ProposalView, Score,
+};
 use ya_negotiators::factory::*;
-use ya_negotiators::AgreementResult;
-use ya_negotiators_testing::Framework;
+use ya_negotiators::{AgreementResult, NO_PROGRESS};
+use ya_negotiators_testing::{Framework, LinkProfile, NegotiationStage, NetworkProfile, NodePair};
 
 fn example_config() -> NegotiatorsConfig {
     let expiration_conf = NegotiatorConfig {
@@ -13,8 +16,12 @@ fn example_config() -> NegotiatorsConfig {
         params: serde_yaml::to_value(expiration::Config {
             min_expiration: std::time::Duration::from_secs(30),
             max_expiration: std::time::Duration::from_secs(300),
+            max_debit_note_accept_timeout: None,
+            min_agreement_expiration: None,
         })
         .unwrap(),
+        enabled: true,
+        priority: 0,
     };
 
     NegotiatorsConfig {
@@ -28,6 +35,8 @@ fn req_example_config() -> NegotiatorsConfig {
         name: "AcceptAll".to_string(),
         load_mode: LoadMode::BuiltIn,
         params: serde_yaml::Value::Null,
+        enabled: true,
+        priority: 0,
     };
 
     NegotiatorsConfig {
@@ -198,3 +207,572 @@ async fn test_negotiations_collect_period() {
         1
     );
 }
+
+/// Negotiations shouldn't be started for Offers, which don't match Demand constraints.
+#[actix_rt::test]
+async fn test_negotiations_only_for_matching_offers() {
+    let framework = Framework::new_empty("test_negotiations_only_for_matching_offers")
+        .unwrap()
+        .add_named_provider(example_config(), "mem-2")
+        .unwrap()
+        .add_named_provider(example_config(), "mem-8")
+        .unwrap()
+        .add_requestor(req_example_config())
+        .unwrap();
+
+    let offer = |mem: f64| OfferTemplate {
+        properties: serde_json::json!({
+            "golem.node.id.name": "dany",
+            "golem.inf.mem.gib": mem,
+        }),
+        constraints: "".to_string(),
+    };
+
+    let mut demand = example_demand(Utc::now() + chrono::Duration::seconds(150));
+    demand.constraints = "(golem.inf.mem.gib>4)".to_string();
+
+    let record = framework
+        .run_for_named_templates(
+            demand,
+            vec![
+                ("mem-2".to_string(), offer(2.0)),
+                ("mem-8".to_string(), offer(8.0)),
+            ],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(record.results.len(), 2);
+    assert_eq!(
+        record
+            .results
+            .iter()
+            .filter(|(_, result)| matches!(
+                result.last_stage(),
+                Some(NegotiationStage::Skipped { .. })
+            ))
+            .count(),
+        1
+    );
+
+    assert_eq!(record.agreements.len(), 1);
+    let agreement = record.agreements.values().next().unwrap();
+    assert_eq!(
+        agreement.provider_id().unwrap(),
+        framework.provider("mem-8").unwrap().node_id
+    );
+}
+
+#[actix_rt::test]
+async fn test_diagnostic_dump() {
+    let framework = Framework::new(
+        "test_diagnostic_dump",
+        example_config(),
+        req_example_config(),
+    )
+    .unwrap();
+    let record = framework
+        .run_for_templates(
+            example_demand(Utc::now() + chrono::Duration::seconds(150)),
+            example_offer(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(record.agreements.len(), 1);
+
+    let provider = framework.providers.values().next().unwrap();
+    let dump = provider.negotiator.diagnostic_dump().await.unwrap();
+
+    for section in &[
+        "components",
+        "goals",
+        "collections",
+        "components-state",
+        "recent-decisions",
+    ] {
+        assert!(dump.get(section).is_some(), "Missing section: {}", section);
+    }
+
+    assert_eq!(dump["components"], serde_json::json!(["LimitExpiration"]));
+    assert!(dump["collections"]["proposals"]["awaiting"].is_array());
+    assert!(!dump["recent-decisions"].as_array().unwrap().is_empty());
+}
+
+#[actix_rt::test]
+async fn test_record_assertions_agreement() {
+    let framework = Framework::new(
+        "test_record_assertions_agreement",
+        example_config(),
+        req_example_config(),
+    )
+    .unwrap();
+    let record = framework
+        .run_for_templates(
+            example_demand(Utc::now() + chrono::Duration::seconds(150)),
+            example_offer(),
+        )
+        .await
+        .unwrap();
+
+    let provider = framework.providers.values().next().unwrap().node_id;
+    let requestor = framework.requestors.values().next().unwrap().node_id;
+
+    record.assert_agreement_between(provider, requestor);
+    assert_eq!(record.count_agreements(), 1);
+    assert!(matches!(
+        record
+            .stages_for(&NodePair::new(requestor, provider))
+            .last(),
+        Some(NegotiationStage::ApproveAgreement { .. })
+    ));
+}
+
+/// Provider should reject Demand with expiration exceeding his limits.
+#[actix_rt::test]
+async fn test_record_assertions_rejection() {
+    let framework = Framework::new_empty("test_record_assertions_rejection")
+        .unwrap()
+        .test_timeout(std::time::Duration::from_secs(3))
+        .add_provider(example_config())
+        .unwrap()
+        .add_requestor(req_example_config())
+        .unwrap();
+    let record = framework
+        .run_for_templates(
+            example_demand(Utc::now() + chrono::Duration::seconds(900)),
+            example_offer(),
+        )
+        .await
+        .unwrap();
+
+    let provider = framework.providers.values().next().unwrap().node_id;
+    let requestor = framework.requestors.values().next().unwrap().node_id;
+
+    record.assert_rejected(provider, requestor);
+    assert_eq!(record.count_agreements(), 0);
+    assert!(!record
+        .stages_for(&NodePair::new(provider, requestor))
+        .is_empty());
+}
+
+/// Negotiations over network losing all messages should finish after test timeout.
+#[actix_rt::test]
+async fn test_negotiations_with_message_loss() {
+    let framework = Framework::new_empty("test_negotiations_with_message_loss")
+        .unwrap()
+        .test_timeout(std::time::Duration::from_secs(3))
+        .with_network(NetworkProfile::new(LinkProfile::new(
+            std::time::Duration::from_millis(50),
+            1.0,
+        )))
+        .add_provider(example_config())
+        .unwrap()
+        .add_requestor(req_example_config())
+        .unwrap();
+
+    let before = Utc::now();
+    let record = framework
+        .run_for_templates(
+            example_demand(Utc::now() + chrono::Duration::seconds(150)),
+            example_offer(),
+        )
+        .await
+        .unwrap();
+
+    let diff = Utc::now() - before;
+    assert!(diff >= Duration::seconds(3));
+    assert!(diff < Duration::seconds(10));
+
+    assert_eq!(record.count_agreements(), 0);
+    assert!(record.agreements.is_empty());
+}
+
+/// Replaying recorded negotiations with stricter Provider config should
+/// result in rejection, where original Provider signed Agreement.
+#[actix_rt::test]
+async fn test_replay_with_different_config() {
+    let framework = Framework::new(
+        "test_replay_with_different_config",
+        example_config(),
+        req_example_config(),
+    )
+    .unwrap();
+    let record = framework
+        .run_for_templates(
+            example_demand(Utc::now() + chrono::Duration::seconds(150)),
+            example_offer(),
+        )
+        .await
+        .unwrap();
+
+    let provider_id = *framework.providers.keys().next().unwrap();
+    let requestor_id = *framework.requestors.keys().next().unwrap();
+
+    record.assert_agreement_between(provider_id, requestor_id);
+
+    let mut strict_config = example_config();
+    strict_config.negotiators[0].params = serde_yaml::to_value(expiration::Config {
+        min_expiration: std::time::Duration::from_secs(30),
+        max_expiration: std::time::Duration::from_secs(60),
+        max_debit_note_accept_timeout: None,
+        min_agreement_expiration: None,
+    })
+    .unwrap();
+
+    let replayed = framework.replay(&record, strict_config).await.unwrap();
+
+    replayed.assert_rejected(provider_id, requestor_id);
+    assert_eq!(replayed.count_agreements(), 0);
+}
+
+/// Provider negotiating with many Requestors at the same time shouldn't accept
+/// more Agreements than allowed by `LimitAgreements`.
+#[actix_rt::test]
+async fn test_agreements_oversubscription() {
+    let mut prov_config = example_config();
+    prov_config.negotiators.push(NegotiatorConfig {
+        name: "LimitAgreements".to_string(),
+        load_mode: LoadMode::BuiltIn,
+        params: serde_yaml::to_value(max_agreements::Config {
+            max_agreements: 1,
+            reservation_timeout: std::time::Duration::from_secs(60),
+        })
+        .unwrap(),
+        enabled: true,
+        priority: 0,
+    });
+
+    let framework = Framework::new_empty("test_agreements_oversubscription")
+        .unwrap()
+        .test_timeout(std::time::Duration::from_secs(5))
+        .add_provider(prov_config)
+        .unwrap()
+        .add_requestor(req_example_config())
+        .unwrap()
+        .add_requestor(req_example_config())
+        .unwrap()
+        .add_requestor(req_example_config())
+        .unwrap();
+
+    let record = framework
+        .run_for_templates(
+            example_demand(Utc::now() + chrono::Duration::seconds(150)),
+            example_offer(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(record.count_agreements(), 1);
+
+    // Provider would fail on approving Agreement above the limit.
+    let errors = record
+        .results
+        .values()
+        .flat_map(|result| result.stages())
+        .filter(|stage| matches!(stage, NegotiationStage::Error(_)))
+        .count();
+    assert_eq!(errors, 0, "{}", record);
+    assert!(record.errors.is_empty());
+}
+
+/// Counters every Proposal, so negotiations never end.
+struct PingPong;
+
+impl NegotiatorComponent for PingPong {
+    fn negotiate_step(
+        &mut self,
+        _their: &ProposalView,
+        template: ProposalView,
+        score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        Ok(NegotiationResult::Negotiating {
+            proposal: template,
+            score,
+        })
+    }
+}
+
+fn ping_pong_config() -> NegotiatorsConfig {
+    register_negotiator(
+        "test-negotiators",
+        "PingPong",
+        Box::new(|_, _, _| Ok(Box::new(PingPong) as Box<dyn NegotiatorComponent>)),
+    );
+
+    NegotiatorsConfig {
+        negotiators: vec![NegotiatorConfig {
+            name: "PingPong".to_string(),
+            load_mode: LoadMode::StaticLib {
+                library: "test-negotiators".to_string(),
+            },
+            params: serde_yaml::Value::Null,
+            enabled: true,
+            priority: 0,
+        }],
+        composite: CompositeNegotiatorConfig::default_test(),
+    }
+}
+
+#[actix_rt::test]
+async fn test_infinite_loop_detection() {
+    let framework = Framework::new(
+        "test_infinite_loop_detection",
+        ping_pong_config(),
+        ping_pong_config(),
+    )
+    .unwrap()
+    .with_max_steps(6);
+
+    let record = framework
+        .run_for_templates(
+            example_demand(Utc::now() + chrono::Duration::seconds(150)),
+            example_offer(),
+        )
+        .await
+        .unwrap();
+
+    let provider = framework.providers.values().next().unwrap().node_id;
+    let requestor = framework.requestors.values().next().unwrap().node_id;
+    let looped = NodePair::new(provider, requestor);
+
+    // Proposals in flight can still be recorded after detecting loop.
+    match record
+        .stages_for(&looped)
+        .into_iter()
+        .find(|stage| matches!(stage, NegotiationStage::InfiniteLoop { .. }))
+    {
+        Some(NegotiationStage::InfiniteLoop { pair, proposals }) => {
+            assert_eq!(pair, &looped);
+            assert_eq!(*proposals, 7);
+        }
+        stage => panic!("Expected InfiniteLoop, got: {:?}\n{}", stage, record),
+    }
+}
+
+/// The same `PingPong` negotiators shouldn't loop, if they refuse to counter
+/// Draft Proposals with unchanged Proposal.
+#[actix_rt::test]
+async fn test_reject_no_progress_prevents_loop() {
+    let mut config = ping_pong_config();
+    config.composite.reject_no_progress = true;
+
+    let framework = Framework::new(
+        "test_reject_no_progress_prevents_loop",
+        config.clone(),
+        config,
+    )
+    .unwrap()
+    .test_timeout(std::time::Duration::from_secs(2))
+    .with_max_steps(6);
+
+    let record = framework
+        .run_for_templates(
+            example_demand(Utc::now() + chrono::Duration::seconds(150)),
+            example_offer(),
+        )
+        .await
+        .unwrap();
+
+    let provider = framework.providers.values().next().unwrap().node_id;
+    let requestor = framework.requestors.values().next().unwrap().node_id;
+    let stages = record.stages_for(&NodePair::new(provider, requestor));
+
+    assert!(
+        !stages
+            .iter()
+            .any(|stage| matches!(stage, NegotiationStage::InfiniteLoop { .. })),
+        "{}",
+        record
+    );
+    let rejected = stages.iter().any(|stage| match stage {
+        NegotiationStage::RejectProposal {
+            reason: Some(reason),
+            ..
+        } => reason.extra["golem.proposal.rejection.code"] == NO_PROGRESS,
+        _ => false,
+    });
+    assert!(rejected, "{}", record);
+}
+
+/// Requestor, which collects Proposals for longer than test lasts, stalls
+/// negotiations. Such negotiations should be marked as timed out.
+#[actix_rt::test]
+async fn test_timeout_stage_for_stalled_negotiations() {
+    let mut req_config = req_example_config();
+    req_config.composite.proposals.collect_amount = Some(10);
+    req_config.composite.proposals.collect_period = Some(std::time::Duration::from_secs(60));
+
+    let framework = Framework::new_empty("test_timeout_stage_for_stalled_negotiations")
+        .unwrap()
+        .test_timeout(std::time::Duration::from_secs(2))
+        .add_provider(example_config())
+        .unwrap()
+        .add_requestor(req_config)
+        .unwrap();
+
+    let record = framework
+        .run_for_templates(
+            example_demand(Utc::now() + chrono::Duration::seconds(150)),
+            example_offer(),
+        )
+        .await
+        .unwrap();
+
+    let provider = framework.providers.values().next().unwrap().node_id;
+    let requestor = framework.requestors.values().next().unwrap().node_id;
+
+    assert!(matches!(
+        record
+            .stages_for(&NodePair::new(provider, requestor))
+            .last(),
+        Some(NegotiationStage::Timeout)
+    ));
+    assert!(record.results.values().all(|result| result.is_finished()));
+}
+
+#[actix_rt::test]
+async fn test_request_agreements_for_named_nodes() {
+    let framework = Framework::new_empty("test_request_agreements_for_named_nodes")
+        .unwrap()
+        .add_named_provider(example_config(), "twin")
+        .unwrap()
+        .add_named_provider(example_config(), "twin")
+        .unwrap()
+        .add_named_provider(example_config(), "other")
+        .unwrap()
+        .add_named_requestor(req_example_config(), "requestor")
+        .unwrap();
+
+    assert_eq!(framework.providers_by_name("twin").len(), 2);
+    assert_eq!(framework.providers_by_name("other").len(), 1);
+    assert!(framework.requestors_by_name("twin").is_empty());
+    assert!(framework.providers_by_name("missing").is_empty());
+
+    framework.request_agreements("twin", 3).await.unwrap();
+    framework.request_agreements("requestor", 2).await.unwrap();
+    assert!(framework.request_agreements("missing", 1).await.is_err());
+}
+
+#[actix_rt::test]
+async fn test_request_agreements_sets_goal() {
+    let framework = Framework::new_empty("test_request_agreements_sets_goal")
+        .unwrap()
+        .add_named_provider(example_config(), "provider")
+        .unwrap();
+
+    framework.request_agreements("provider", 4).await.unwrap();
+
+    let provider = framework.provider("provider").unwrap();
+    let dump = provider.negotiator.diagnostic_dump().await.unwrap();
+    // Requested Agreements are added to `Limit(1)` from config.
+    assert_eq!(
+        dump["goals"]["agreements"],
+        serde_json::json!({ "Limit": 5 })
+    );
+}
+
+/// Offer published by Provider should contain properties and constraints
+/// filled by all negotiators in the chain.
+#[actix_rt::test]
+async fn test_published_offer_assertions() {
+    ya_builtin_negotiators::register_negotiators();
+
+    let mut config = example_config();
+    config.negotiators.push(NegotiatorConfig {
+        name: "TemplateEnv".to_string(),
+        load_mode: LoadMode::BuiltIn,
+        params: serde_yaml::from_str("env: {subnet: net-1}").unwrap(),
+        enabled: true,
+        priority: 0,
+    });
+    config.negotiators.push(NegotiatorConfig {
+        name: "PaymentPlatform".to_string(),
+        load_mode: LoadMode::StaticLib {
+            library: "golem-negotiators".to_string(),
+        },
+        params: serde_yaml::from_str("platforms: [erc20-polygon-glm]").unwrap(),
+        enabled: true,
+        priority: 0,
+    });
+
+    let framework = Framework::new(
+        "test_published_offer_assertions",
+        config,
+        req_example_config(),
+    )
+    .unwrap();
+
+    let mut offer = example_offer();
+    offer.set_property("golem.node.debug.subnet", "${env.subnet}".into());
+    let mut demand = example_demand(Utc::now() + chrono::Duration::seconds(150));
+    demand.set_property(
+        "golem.com.payment.chosen-platform",
+        "erc20-polygon-glm".into(),
+    );
+
+    framework.run_for_templates(demand, offer).await.unwrap();
+
+    let provider = framework.providers.values().next().unwrap();
+    provider.assert_offer_has("/golem/node/id/name", "dany");
+    provider.assert_offer_has("/golem/node/debug/subnet", "net-1");
+    provider.assert_offer_has(
+        "/golem/com/payment/accepted-platforms",
+        serde_json::json!(["erc20-polygon-glm"]),
+    );
+    provider.assert_offer_constraint("golem.com.payment.platform.erc20-polygon-glm.address");
+
+    let requestor = framework.requestors.values().next().unwrap();
+    requestor.assert_offer_has("/golem/com/payment/chosen-platform", "erc20-polygon-glm");
+    assert!(requestor.published_offer().unwrap().constraints.is_empty());
+}
+
+/// Sorted Node, Proposal and Agreement ids generated during negotiations.
+async fn seeded_run_ids(seed: Option<u64>) -> (Vec<String>, Vec<String>, Vec<String>) {
+    let mut framework = Framework::new_empty("test_seeded_runs_are_reproducible").unwrap();
+    if let Some(seed) = seed {
+        framework = framework.with_seed(seed);
+    }
+    let framework = framework
+        .add_provider(example_config())
+        .unwrap()
+        .add_requestor(req_example_config())
+        .unwrap();
+
+    let record = framework
+        .run_for_templates(
+            example_demand(Utc::now() + chrono::Duration::seconds(150)),
+            example_offer(),
+        )
+        .await
+        .unwrap();
+
+    // Negotiations are processed concurrently, so ids can be generated in
+    // different order. Sets of ids should be the same anyway.
+    let mut nodes = framework
+        .providers
+        .keys()
+        .chain(framework.requestors.keys())
+        .map(|node_id| node_id.to_string())
+        .collect::<Vec<_>>();
+    let mut proposals = record.proposals.keys().cloned().collect::<Vec<_>>();
+    let mut agreements = record.agreements.keys().cloned().collect::<Vec<_>>();
+    nodes.sort();
+    proposals.sort();
+    agreements.sort();
+    (nodes, proposals, agreements)
+}
+
+/// Records of runs with the same seed should be comparable by ids. Seed shouldn't
+/// outlive Framework, since next tests run on the same thread.
+#[actix_rt::test]
+async fn test_seeded_runs_are_reproducible() {
+    let first = seeded_run_ids(Some(1234)).await;
+    let second = seeded_run_ids(Some(1234)).await;
+
+    assert!(!first.1.is_empty());
+    assert!(!first.2.is_empty());
+    assert_eq!(first, second);
+
+    let unseeded = seeded_run_ids(None).await;
+    assert_ne!(first.0, unseeded.0);
+}
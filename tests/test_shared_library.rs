@@ -1,8 +1,10 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
 use std::path::PathBuf;
 
 use ya_agreement_utils::{InfNodeInfo, NodeInfo, OfferDefinition, OfferTemplate, ServiceInfo};
+use ya_negotiators::component::{NegotiationResult, ProposalView, Score};
 use ya_negotiators::factory::*;
 use ya_negotiators::{NegotiatorCallbacks, ProposalAction};
 
@@ -38,6 +40,8 @@ fn example_config() -> NegotiatorsConfig {
             names: vec!["dany".to_string()],
         })
         .unwrap(),
+        enabled: true,
+        priority: 0,
     };
 
     NegotiatorsConfig {
@@ -46,6 +50,27 @@ fn example_config() -> NegotiatorsConfig {
     }
 }
 
+#[test]
+fn test_config_builder_shared_lib() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("target")
+        .join(debug_or_release())
+        .join("libdll_negotiator.so");
+    let config = NegotiatorsConfigBuilder::new()
+        .shared_lib(
+            path,
+            "FilterNodes",
+            FilterNodesConfig {
+                names: vec!["dany".to_string()],
+            },
+        )
+        .unwrap()
+        .composite(CompositeNegotiatorConfig::default_test())
+        .build();
+
+    assert_eq!(config, example_config());
+}
+
 fn example_offer_definition() -> OfferTemplate {
     OfferDefinition {
         node_info: NodeInfo::with_name("blabla"),
@@ -96,7 +121,7 @@ async fn test_shared_library() {
             proposal_channel: mut proposals,
             agreement_channel: _agreements,
         },
-    ) = create_negotiator(config, test_dir.clone(), test_dir).unwrap();
+    ) = create_negotiator(config, serde_yaml::Value::Null, test_dir.clone(), test_dir).unwrap();
 
     let offer = negotiator
         .create_offer(&example_offer_definition())
@@ -131,3 +156,232 @@ async fn test_shared_library() {
         _ => panic!("Expected AcceptProposal"),
     }
 }
+
+/// `Score` should pass through shared library boundary unchanged.
+#[test]
+fn test_shared_library_score_round_trip() {
+    let test_dir = prepare_test_dir("test_shared_library_score_round_trip").unwrap();
+    let config = example_config().negotiators.remove(0);
+    let path = match config.load_mode {
+        LoadMode::SharedLibrary { path } => path,
+        _ => panic!("Expected shared library config."),
+    };
+
+    let mut negotiator = create_shared_lib(
+        &path,
+        &config.name,
+        config.params,
+        serde_yaml::Value::Null,
+        test_dir,
+    )
+    .unwrap();
+
+    let demand = example_demand(Utc::now() + chrono::Duration::seconds(50), "node-1");
+    let their = ProposalView::try_from(&proposal_from_demand(&demand)).unwrap();
+    let our = their.clone();
+
+    let mut score = Score::default();
+    score.set_property("final-score", serde_json::json!(0.75));
+    score.set_property("sample.score", serde_json::json!({"nested": [1, 2, 3]}));
+
+    match negotiator
+        .negotiate_step(&their, our.clone(), score.clone())
+        .unwrap()
+    {
+        NegotiationResult::Ready {
+            proposal,
+            score: returned,
+        } => {
+            assert_eq!(proposal, our);
+            assert_eq!(returned, score);
+        }
+        result => panic!("Expected Ready, got: {:?}", result),
+    }
+}
+
+#[actix_rt::test]
+async fn test_shared_library_shutdown() {
+    let test_dir = prepare_test_dir("test_shared_library_shutdown").unwrap();
+    let (negotiator, _callbacks) = create_negotiator(
+        example_config(),
+        serde_yaml::Value::Null,
+        test_dir.clone(),
+        test_dir.clone(),
+    )
+    .unwrap();
+
+    let marker = test_dir.join("FilterNodes").join("shutdown");
+    assert!(!marker.exists());
+
+    negotiator
+        .shutdown(std::time::Duration::from_secs(5))
+        .await
+        .unwrap();
+    assert!(marker.exists());
+}
+
+/// `FilterNodes` should extend rejection list with nodes from Agent environment.
+#[test]
+fn test_shared_library_agent_env() {
+    let test_dir = prepare_test_dir("test_shared_library_agent_env").unwrap();
+    let config = example_config().negotiators.remove(0);
+    let path = match config.load_mode {
+        LoadMode::SharedLibrary { path } => path,
+        _ => panic!("Expected shared library config."),
+    };
+    let agent_env = serde_yaml::from_str("rejected-nodes: [node-1]").unwrap();
+
+    let mut negotiator =
+        create_shared_lib(&path, &config.name, config.params, agent_env, test_dir).unwrap();
+
+    let demand = example_demand(Utc::now() + chrono::Duration::seconds(50), "node-1");
+    let their = ProposalView::try_from(&proposal_from_demand(&demand)).unwrap();
+
+    match negotiator
+        .negotiate_step(&their, their.clone(), Score::default())
+        .unwrap()
+    {
+        NegotiationResult::Reject { reason, .. } => {
+            assert_eq!(reason.code, Some("NODE_BLACKLISTED".to_string()))
+        }
+        result => panic!("Expected Reject, got: {:?}", result),
+    }
+}
+
+fn price_counter_config() -> NegotiatorConfig {
+    let mut config = example_config().negotiators.remove(0);
+    config.name = "PriceCounter".to_string();
+    config.params = serde_yaml::to_value(serde_json::json!({
+        "pointer": "/golem/com/pricing/price",
+        "max_price": 0.5,
+    }))
+    .unwrap();
+    config
+}
+
+fn proposal_with_price(price: f64) -> ProposalView {
+    let mut demand = example_demand(Utc::now() + chrono::Duration::seconds(50), "node-1");
+    demand.properties["golem.com.pricing.price"] = serde_json::json!(price);
+    ProposalView::try_from(&proposal_from_demand(&demand)).unwrap()
+}
+
+/// Countered Proposal should carry price proposed by negotiator.
+#[test]
+fn test_shared_library_price_counter() {
+    let test_dir = prepare_test_dir("test_shared_library_price_counter").unwrap();
+    let config = price_counter_config();
+    let path = match config.load_mode {
+        LoadMode::SharedLibrary { path } => path,
+        _ => panic!("Expected shared library config."),
+    };
+
+    let mut negotiator = create_shared_lib(
+        &path,
+        &config.name,
+        config.params,
+        serde_yaml::Value::Null,
+        test_dir,
+    )
+    .unwrap();
+
+    let their = proposal_with_price(1.0);
+    match negotiator
+        .negotiate_step(&their, their.clone(), Score::default())
+        .unwrap()
+    {
+        NegotiationResult::Negotiating { proposal, .. } => {
+            assert_eq!(
+                proposal
+                    .pointer_typed::<f64>("/golem/com/pricing/price")
+                    .unwrap(),
+                0.5
+            );
+        }
+        result => panic!("Expected Negotiating, got: {:?}", result),
+    }
+
+    let their = proposal_with_price(0.4);
+    match negotiator
+        .negotiate_step(&their, their.clone(), Score::default())
+        .unwrap()
+    {
+        NegotiationResult::Ready { proposal, .. } => assert_eq!(proposal, their),
+        result => panic!("Expected Ready, got: {:?}", result),
+    }
+}
+
+/// Dry run should cross library boundary and give the same result as negotiating.
+#[test]
+fn test_shared_library_dry_run() {
+    let test_dir = prepare_test_dir("test_shared_library_dry_run").unwrap();
+    let config = price_counter_config();
+    let path = match config.load_mode {
+        LoadMode::SharedLibrary { path } => path,
+        _ => panic!("Expected shared library config."),
+    };
+
+    let mut negotiator = create_shared_lib(
+        &path,
+        &config.name,
+        config.params,
+        serde_yaml::Value::Null,
+        test_dir,
+    )
+    .unwrap();
+
+    let their = proposal_with_price(1.0);
+    let dry_run = negotiator
+        .dry_run_step(&their, &[], their.clone(), Score::default())
+        .unwrap();
+    let negotiated = negotiator
+        .negotiate_step(&their, their.clone(), Score::default())
+        .unwrap();
+    assert_eq!(dry_run, negotiated);
+}
+
+/// Batch evaluated by shared library should give the same results as
+/// evaluating Proposals one by one.
+#[test]
+fn test_shared_library_negotiate_batch() {
+    let test_dir = prepare_test_dir("test_shared_library_negotiate_batch").unwrap();
+    let config = example_config().negotiators.remove(0);
+    let path = match config.load_mode {
+        LoadMode::SharedLibrary { path } => path,
+        _ => panic!("Expected shared library config."),
+    };
+
+    let mut negotiator = create_shared_lib(
+        &path,
+        &config.name,
+        config.params,
+        serde_yaml::Value::Null,
+        test_dir,
+    )
+    .unwrap();
+
+    let items = ["node-1", "dany", "node-2"]
+        .iter()
+        .map(|name| {
+            let demand = example_demand(Utc::now() + chrono::Duration::seconds(50), name);
+            let their = ProposalView::try_from(&proposal_from_demand(&demand)).unwrap();
+            (their.clone(), vec![], their, Score::default())
+        })
+        .collect::<Vec<_>>();
+
+    let expected = items
+        .clone()
+        .into_iter()
+        .map(|(their, history, template, score)| {
+            negotiator.negotiate_step_with_history(&their, &history, template, score)
+        })
+        .collect::<anyhow::Result<Vec<_>>>()
+        .unwrap();
+    let results = negotiator
+        .negotiate_batch(items)
+        .into_iter()
+        .collect::<anyhow::Result<Vec<_>>>()
+        .unwrap();
+
+    assert_eq!(results, expected);
+    assert!(matches!(results[1], NegotiationResult::Reject { .. }));
+}
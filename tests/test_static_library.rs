@@ -1,7 +1,13 @@
 use chrono::{DateTime, Utc};
+use std::convert::TryFrom;
 
 use ya_agreement_utils::{InfNodeInfo, NodeInfo, OfferDefinition, OfferTemplate, ServiceInfo};
 use ya_builtin_negotiators::*;
+use ya_negotiators::component::{
+    create_static_negotiator, deregister_negotiator, is_registered, qualified_name,
+    register_negotiator, ConstructorFunction, NegotiationResult, NegotiatorComponent, ProposalView,
+    RejectReason, Score, BUILTIN_LIBRARY,
+};
 use ya_negotiators::factory::*;
 use ya_negotiators::{NegotiatorCallbacks, ProposalAction};
 
@@ -19,8 +25,12 @@ fn example_config() -> NegotiatorsConfig {
         params: serde_yaml::to_value(expiration::Config {
             min_expiration: std::time::Duration::from_secs(30),
             max_expiration: std::time::Duration::from_secs(300),
+            max_debit_note_accept_timeout: None,
+            min_agreement_expiration: None,
         })
         .unwrap(),
+        enabled: true,
+        priority: 0,
     };
 
     let limit_conf = NegotiatorConfig {
@@ -28,7 +38,13 @@ fn example_config() -> NegotiatorsConfig {
         load_mode: LoadMode::StaticLib {
             library: "golem-negotiators".to_string(),
         },
-        params: serde_yaml::to_value(max_agreements::Config { max_agreements: 1 }).unwrap(),
+        params: serde_yaml::to_value(max_agreements::Config {
+            max_agreements: 1,
+            reservation_timeout: std::time::Duration::from_secs(60),
+        })
+        .unwrap(),
+        enabled: true,
+        priority: 0,
     };
 
     NegotiatorsConfig {
@@ -37,6 +53,35 @@ fn example_config() -> NegotiatorsConfig {
     }
 }
 
+#[test]
+fn test_config_builder_static_lib() {
+    let config = NegotiatorsConfigBuilder::new()
+        .static_lib(
+            "golem-negotiators",
+            "LimitExpiration",
+            expiration::Config {
+                min_expiration: std::time::Duration::from_secs(30),
+                max_expiration: std::time::Duration::from_secs(300),
+                max_debit_note_accept_timeout: None,
+                min_agreement_expiration: None,
+            },
+        )
+        .unwrap()
+        .static_lib(
+            "golem-negotiators",
+            "LimitAgreements",
+            max_agreements::Config {
+                max_agreements: 1,
+                reservation_timeout: std::time::Duration::from_secs(60),
+            },
+        )
+        .unwrap()
+        .composite(CompositeNegotiatorConfig::default_test())
+        .build();
+
+    assert_eq!(config, example_config());
+}
+
 fn example_offer() -> OfferTemplate {
     OfferDefinition {
         node_info: NodeInfo::with_name("dany"),
@@ -90,7 +135,7 @@ async fn test_static_library() {
             proposal_channel: mut proposals,
             agreement_channel: _agreements,
         },
-    ) = create_negotiator(config, test_dir.clone(), test_dir).unwrap();
+    ) = create_negotiator(config, serde_yaml::Value::Null, test_dir.clone(), test_dir).unwrap();
 
     let offer = negotiator.create_offer(&example_offer()).await.unwrap();
     let offer = proposal_from_demand(&offer);
@@ -122,3 +167,148 @@ async fn test_static_library() {
         _ => panic!("Expected reject proposal"),
     }
 }
+
+/// Negotiators created by static library should get Agent environment.
+#[actix_rt::test]
+async fn test_static_library_agent_env() {
+    ya_builtin_negotiators::register_negotiators();
+
+    let mut config = example_config();
+    config.negotiators.push(NegotiatorConfig {
+        name: "TemplateEnv".to_string(),
+        load_mode: LoadMode::StaticLib {
+            library: "golem-negotiators".to_string(),
+        },
+        params: serde_yaml::Value::Null,
+        enabled: true,
+        priority: 0,
+    });
+    let agent_env = serde_yaml::from_str("subnet: net-1").unwrap();
+
+    let test_dir = prepare_test_dir("test_static_library_agent_env").unwrap();
+    let (negotiator, _callbacks) =
+        create_negotiator(config, agent_env, test_dir.clone(), test_dir).unwrap();
+
+    let mut template = example_offer();
+    template.set_property("golem.node.debug.subnet", "${env.subnet}".into());
+
+    let offer = negotiator.create_offer(&template).await.unwrap();
+    let offer = OfferTemplate::new(offer.properties);
+    assert_eq!(
+        offer.property("golem.node.debug.subnet"),
+        Some(&serde_json::json!("net-1"))
+    );
+}
+
+/// Static library negotiator named the same as builtin `AcceptAll`.
+struct RejectAll;
+
+impl NegotiatorComponent for RejectAll {
+    fn negotiate_step(
+        &mut self,
+        _their: &ProposalView,
+        _template: ProposalView,
+        _score: Score,
+    ) -> anyhow::Result<NegotiationResult> {
+        Ok(NegotiationResult::Reject {
+            reason: RejectReason::new("Rejecting all."),
+            is_final: true,
+        })
+    }
+}
+
+/// Builtin and static library negotiator with the same name should be both
+/// resolvable by their qualified names.
+#[test]
+fn test_builtin_and_static_lib_with_the_same_name() {
+    register_negotiator(
+        "test-library",
+        "AcceptAll",
+        Box::new(|_, _, _| Ok(Box::new(RejectAll) as Box<dyn NegotiatorComponent>)),
+    );
+
+    let test_dir = prepare_test_dir("test_builtin_and_static_lib_with_the_same_name").unwrap();
+    let their = ProposalView::try_from(&proposal_from_demand(&example_demand(
+        Utc::now() + chrono::Duration::seconds(50),
+        "net-1",
+    )))
+    .unwrap();
+
+    let mut builtin = create_builtin(
+        "AcceptAll",
+        serde_yaml::Value::Null,
+        serde_yaml::Value::Null,
+        test_dir.clone(),
+    )
+    .unwrap();
+    assert!(matches!(
+        builtin
+            .negotiate_step(&their, their.clone(), Score::default())
+            .unwrap(),
+        NegotiationResult::Ready { .. }
+    ));
+    // All negotiators from builtin crate are available under `builtin` library.
+    assert!(is_registered(BUILTIN_LIBRARY, "RateLimit"));
+    assert!(is_registered(BUILTIN_LIBRARY, "HardwareLimits"));
+
+    let mut qualified = create_static_negotiator(
+        &qualified_name(BUILTIN_LIBRARY, "AcceptAll"),
+        serde_yaml::Value::Null,
+        serde_yaml::Value::Null,
+        test_dir.clone(),
+    )
+    .unwrap();
+    assert!(matches!(
+        qualified
+            .negotiate_step(&their, their.clone(), Score::default())
+            .unwrap(),
+        NegotiationResult::Ready { .. }
+    ));
+
+    let mut static_lib = create_static_negotiator(
+        &qualified_name("test-library", "AcceptAll"),
+        serde_yaml::Value::Null,
+        serde_yaml::Value::Null,
+        test_dir,
+    )
+    .unwrap();
+    assert!(matches!(
+        static_lib
+            .negotiate_step(&their, their.clone(), Score::default())
+            .unwrap(),
+        NegotiationResult::Reject { .. }
+    ));
+}
+
+fn reject_all_constructor() -> ConstructorFunction {
+    Box::new(|_, _, _| Ok(Box::new(RejectAll) as Box<dyn NegotiatorComponent>))
+}
+
+/// Deregistered negotiator can't be created anymore.
+#[test]
+fn test_deregister_negotiator() {
+    assert!(!register_negotiator(
+        "deregister-library",
+        "RejectAll",
+        reject_all_constructor()
+    ));
+    assert!(register_negotiator(
+        "deregister-library",
+        "RejectAll",
+        reject_all_constructor()
+    ));
+
+    assert!(deregister_negotiator("deregister-library", "RejectAll"));
+    assert!(!deregister_negotiator("deregister-library", "RejectAll"));
+
+    let test_dir = prepare_test_dir("test_deregister_negotiator").unwrap();
+    let error = create_static_negotiator(
+        &qualified_name("deregister-library", "RejectAll"),
+        serde_yaml::Value::Null,
+        serde_yaml::Value::Null,
+        test_dir,
+    )
+    .err()
+    .unwrap();
+    assert!(error.to_string().contains("not found."));
+}